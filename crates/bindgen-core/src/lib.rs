@@ -1,12 +1,14 @@
 use anyhow::Result;
-use std::collections::{btree_map::Entry, BTreeMap, HashMap};
+use std::collections::{btree_map::Entry, BTreeMap, HashMap, HashSet};
 use std::fmt::{self, Write};
 use std::ops::Deref;
 use std::path::Path;
 use wit_parser::*;
 
 pub use wit_parser;
+pub mod lint;
 mod ns;
+pub mod registry;
 
 pub use ns::Ns;
 
@@ -41,6 +43,14 @@ pub enum Direction {
 }
 
 pub trait Generator {
+    /// The name of this generator, as reported in [`UnsupportedFeature`]
+    /// errors, which is also its name in a [`Registry`](registry::Registry).
+    ///
+    /// Defaults to the name of the type implementing `Generator`.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
     fn preprocess_all(&mut self, imports: &[Interface], exports: &[Interface]) {
         drop((imports, exports));
     }
@@ -89,6 +99,94 @@ pub trait Generator {
     fn type_list(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs);
     fn type_builtin(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs);
 
+    /// Whether this generator supports resources and handles to them.
+    ///
+    /// Generators which return `true` here must implement `type_resource`
+    /// and `type_handle` and accept resource functions in `import`/`export`.
+    fn supports_resources(&self) -> bool {
+        false
+    }
+
+    /// Generates the type for the resource `name`, whose functions are
+    /// later passed to `import`/`export` along with the interface's other
+    /// functions.
+    fn type_resource(&mut self, _iface: &Interface, _id: TypeId, name: &str, _docs: &Docs) {
+        unreachable!("resource `{name}` is unsupported")
+    }
+    fn type_handle(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        _handle: &Handle,
+        _docs: &Docs,
+    ) {
+        unreachable!("handle `{name}` is unsupported")
+    }
+
+    /// Whether this generator supports `future` and `stream` types.
+    ///
+    /// Generators which return `true` here must implement `type_future` and
+    /// `type_stream` and handle the `Future*` and `Stream*` ABI instructions.
+    fn supports_futures_and_streams(&self) -> bool {
+        false
+    }
+    fn type_future(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        _payload: &Option<Type>,
+        _docs: &Docs,
+    ) {
+        unreachable!("future `{name}` is unsupported")
+    }
+    fn type_stream(
+        &mut self,
+        _iface: &Interface,
+        _id: TypeId,
+        name: &str,
+        _stream: &Stream,
+        _docs: &Docs,
+    ) {
+        unreachable!("stream `{name}` is unsupported")
+    }
+
+    /// Generates the constant `constant`, whose type is always a primitive
+    /// type or an alias of one.
+    ///
+    /// By default constants are left out of the generated bindings.
+    fn constant(&mut self, _iface: &Interface, _constant: &Constant) {}
+
+    /// Whether this generator supports recursive types, which refer to
+    /// themselves through a `list` or `option`.
+    fn supports_recursive_types(&self) -> bool {
+        false
+    }
+
+    /// Returns a description of what this generator doesn't support about
+    /// the type `id`, if anything, for limitations beyond those covered by
+    /// the `supports_*` methods.
+    fn unsupported_type(&self, _iface: &Interface, _id: TypeId) -> Option<String> {
+        None
+    }
+
+    /// Checks that `iface` only uses constructs supported by this generator,
+    /// returning an [`UnsupportedFeature`] error for the first which isn't.
+    ///
+    /// This is called by `generate_one` so that unsupported interfaces are
+    /// reported as an error rather than a panic partway through generation.
+    fn validate(&self, iface: &Interface) -> Result<()> {
+        Support {
+            generator: self.name(),
+            resources: self.supports_resources(),
+            futures_and_streams: self.supports_futures_and_streams(),
+            recursive_types: self.supports_recursive_types(),
+            unsupported_type: &|iface, id| self.unsupported_type(iface, id),
+        }
+        .validate(iface)
+    }
+
     fn preprocess_functions(&mut self, iface: &Interface, dir: Direction) {
         drop((iface, dir));
     }
@@ -104,7 +202,8 @@ pub trait Generator {
         drop(files);
     }
 
-    fn generate_one(&mut self, iface: &Interface, dir: Direction, files: &mut Files) {
+    fn generate_one(&mut self, iface: &Interface, dir: Direction, files: &mut Files) -> Result<()> {
+        self.validate(iface)?;
         self.preprocess_one(iface, dir);
 
         for (id, ty) in iface.types.iter() {
@@ -126,11 +225,17 @@ pub trait Generator {
                 TypeDefKind::Union(u) => self.type_union(iface, id, name, u, &ty.docs),
                 TypeDefKind::List(t) => self.type_list(iface, id, name, t, &ty.docs),
                 TypeDefKind::Type(t) => self.type_alias(iface, id, name, t, &ty.docs),
-                TypeDefKind::Future(_) => todo!("generate for future"),
-                TypeDefKind::Stream(_) => todo!("generate for stream"),
+                TypeDefKind::Future(t) => self.type_future(iface, id, name, t, &ty.docs),
+                TypeDefKind::Stream(s) => self.type_stream(iface, id, name, s, &ty.docs),
+                TypeDefKind::Resource => self.type_resource(iface, id, name, &ty.docs),
+                TypeDefKind::Handle(h) => self.type_handle(iface, id, name, h, &ty.docs),
             }
         }
 
+        for constant in iface.constants.iter() {
+            self.constant(iface, constant);
+        }
+
         self.preprocess_functions(iface, dir);
 
         for f in iface.functions.iter() {
@@ -142,30 +247,174 @@ pub trait Generator {
 
         self.finish_functions(iface, dir);
 
-        self.finish_one(iface, files)
+        self.finish_one(iface, files);
+        Ok(())
     }
 
-    fn generate_all(&mut self, imports: &[Interface], exports: &[Interface], files: &mut Files) {
+    fn generate_all(
+        &mut self,
+        imports: &[Interface],
+        exports: &[Interface],
+        files: &mut Files,
+    ) -> Result<()> {
         self.preprocess_all(imports, exports);
 
         for imp in imports {
-            self.generate_one(imp, Direction::Import, files);
+            self.generate_one(imp, Direction::Import, files)?;
         }
 
         for exp in exports {
-            self.generate_one(exp, Direction::Export, files);
+            self.generate_one(exp, Direction::Export, files)?;
         }
 
         self.finish_all(files);
+        Ok(())
+    }
+
+    /// Generates bindings for all of the imports and exports of `world`.
+    ///
+    /// The default export, if any, is generated before the named exports.
+    fn generate_world(&mut self, world: &World, files: &mut Files) -> Result<()> {
+        let imports = world.imports.values().cloned().collect::<Vec<_>>();
+        let exports = world
+            .default
+            .iter()
+            .chain(world.exports.values())
+            .cloned()
+            .collect::<Vec<_>>();
+        self.generate_all(&imports, &exports, files)
+    }
+}
+
+/// The constructs a generator supports, gathered from either a [`Generator`]
+/// or an [`InterfaceGenerator`] so that both validate interfaces the same way.
+struct Support<'a> {
+    generator: &'a str,
+    resources: bool,
+    futures_and_streams: bool,
+    recursive_types: bool,
+    unsupported_type: &'a dyn Fn(&Interface, TypeId) -> Option<String>,
+}
+
+impl Support<'_> {
+    fn validate(&self, iface: &Interface) -> Result<()> {
+        let unsupported = |construct: String, span: Option<Span>| UnsupportedFeature {
+            generator: self.generator.to_string(),
+            construct,
+            span,
+        };
+        for (id, ty) in iface.types.iter() {
+            if ty.name.is_none() {
+                continue;
+            }
+            if let Some(construct) = self.unsupported_construct(iface, id) {
+                return Err(unsupported(construct, ty.span).into());
+            }
+        }
+        // Anonymous types are only reached through the signatures of the
+        // functions which use them.
+        for func in iface.functions.iter() {
+            if func.resource().is_some() && !self.resources {
+                return Err(unsupported("resources".to_string(), func.span).into());
+            }
+            let types = func
+                .params
+                .iter()
+                .map(|(_, ty)| ty)
+                .chain(func.results.iter_types());
+            for ty in types {
+                if let Type::Id(id) = ty {
+                    if let Some(construct) = self.unsupported_construct(iface, *id) {
+                        return Err(unsupported(construct, func.span).into());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns what isn't supported about the type `id` or the anonymous
+    /// types within it.
+    ///
+    /// Named types within `id` aren't visited, as they're checked on their
+    /// own, which also keeps this from looping forever on recursive types.
+    fn unsupported_construct(&self, iface: &Interface, id: TypeId) -> Option<String> {
+        let ty = &iface.types[id];
+        let construct = match &ty.kind {
+            TypeDefKind::Future(_) | TypeDefKind::Stream(_) if !self.futures_and_streams => {
+                Some("`future` and `stream` types")
+            }
+            TypeDefKind::Resource | TypeDefKind::Handle(_) if !self.resources => Some("resources"),
+            _ if ty.name.is_some() && !self.recursive_types && iface.is_recursive(id) => {
+                Some("recursive types")
+            }
+            _ => None,
+        };
+        if let Some(construct) = construct {
+            return Some(construct.to_string());
+        }
+        if let Some(construct) = (self.unsupported_type)(iface, id) {
+            return Some(construct);
+        }
+        let nested: Vec<&Type> = match &ty.kind {
+            TypeDefKind::Record(r) => r.fields.iter().map(|f| &f.ty).collect(),
+            TypeDefKind::Tuple(t) => t.types.iter().collect(),
+            TypeDefKind::Variant(v) => v.cases.iter().filter_map(|c| c.ty.as_ref()).collect(),
+            TypeDefKind::Union(u) => u.cases.iter().map(|c| &c.ty).collect(),
+            TypeDefKind::Option(t) | TypeDefKind::List(t) | TypeDefKind::Type(t) => vec![t],
+            TypeDefKind::Result(r) => r.ok.iter().chain(&r.err).collect(),
+            TypeDefKind::Future(t) => t.iter().collect(),
+            TypeDefKind::Stream(s) => s.element.iter().chain(&s.end).collect(),
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Handle(_) => Vec::new(),
+        };
+        nested.into_iter().find_map(|ty| match ty {
+            Type::Id(id) if iface.types[*id].name.is_none() => {
+                self.unsupported_construct(iface, *id)
+            }
+            _ => None,
+        })
     }
 }
 
+/// The error for an interface which uses a construct that a generator can't
+/// generate bindings for, as returned by [`Generator::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedFeature {
+    /// The name of the generator, see [`Generator::name`].
+    pub generator: String,
+    /// What isn't supported, such as "resources".
+    pub construct: String,
+    /// Where the construct is used within the source of the interface, if
+    /// known, see `TypeDef::span`.
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for UnsupportedFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the `{}` generator doesn't support {}",
+            self.generator, self.construct
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedFeature {}
+
 #[derive(Default)]
 pub struct Types {
     type_info: HashMap<TypeId, TypeInfo>,
+    // Every anonymous type mapped to the first anonymous type in its interface
+    // with the same structure, which is the one generators emit.
+    canonical: HashMap<TypeId, TypeId>,
+    // The names of the types which are their own canonical type.
+    anonymous_names: HashMap<TypeId, String>,
 }
 
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
 pub struct TypeInfo {
     /// Whether or not this type is ever used (transitively) within the
     /// parameter of a function.
@@ -177,6 +426,13 @@ pub struct TypeInfo {
 
     /// Whether or not this type (transitively) has a list.
     pub has_list: bool,
+
+    /// Whether or not this type (transitively) has a handle to a resource.
+    pub has_handle: bool,
+
+    /// Whether or not this type (transitively) has a boxed option, which is
+    /// an option that's part of a recursive type.
+    pub has_box: bool,
 }
 
 impl std::ops::BitOrAssign for TypeInfo {
@@ -184,6 +440,8 @@ impl std::ops::BitOrAssign for TypeInfo {
         self.param |= rhs.param;
         self.result |= rhs.result;
         self.has_list |= rhs.has_list;
+        self.has_handle |= rhs.has_handle;
+        self.has_box |= rhs.has_box;
     }
 }
 
@@ -192,6 +450,19 @@ impl Types {
         for (t, _) in iface.types.iter() {
             self.type_id_info(iface, t);
         }
+        // Types which are part of a cycle were computed while their own info
+        // was still a placeholder, so recompute until nothing changes.
+        loop {
+            let mut changed = false;
+            for (t, _) in iface.types.iter() {
+                let info = self.compute_type_id_info(iface, t);
+                let prev = self.type_info.insert(t, info).unwrap();
+                changed |= prev != info;
+            }
+            if !changed {
+                break;
+            }
+        }
         for f in iface.functions.iter() {
             for (_, ty) in f.params.iter() {
                 self.set_param_result_ty(iface, ty, true, false);
@@ -200,16 +471,59 @@ impl Types {
                 self.set_param_result_ty(iface, ty, false, true);
             }
         }
+
+        let mut anonymous = Anonymous {
+            iface,
+            types: self,
+            shapes: HashMap::new(),
+            names: iface
+                .types
+                .iter()
+                .filter_map(|(_, ty)| ty.name.clone())
+                .collect(),
+        };
+        for (id, _) in iface.types.iter() {
+            anonymous.id(id);
+        }
     }
 
     pub fn get(&self, id: TypeId) -> TypeInfo {
         self.type_info[&id]
     }
 
+    /// Returns the type which `id` is emitted as.
+    ///
+    /// Structurally identical anonymous types, such as an `option<u32>` copied
+    /// from a dependency and one written locally, may have different ids. All
+    /// of them map to the first one in the interface, and any other type maps
+    /// to itself.
+    pub fn canonical(&self, id: TypeId) -> TypeId {
+        self.canonical.get(&id).copied().unwrap_or(id)
+    }
+
+    /// Returns the name of the anonymous type `id`, such as `option-u32`,
+    /// `tuple2-u8-string` or `result-void-error`.
+    ///
+    /// Names are kebab-cased like wit identifiers so generators can convert
+    /// them to their own case. They're the same for structurally identical
+    /// types and never clash with the name of another type in the interface.
+    pub fn anonymous_name(&self, id: TypeId) -> &str {
+        &self.anonymous_names[&self.canonical(id)]
+    }
+
     pub fn type_id_info(&mut self, iface: &Interface, ty: TypeId) -> TypeInfo {
         if let Some(info) = self.type_info.get(&ty) {
             return *info;
         }
+        // Insert a placeholder first so recursive types don't recurse
+        // forever, see `analyze`.
+        self.type_info.insert(ty, TypeInfo::default());
+        let info = self.compute_type_id_info(iface, ty);
+        self.type_info.insert(ty, info);
+        info
+    }
+
+    fn compute_type_id_info(&mut self, iface: &Interface, ty: TypeId) -> TypeInfo {
         let mut info = TypeInfo::default();
         match &iface.types[ty].kind {
             TypeDefKind::Record(r) => {
@@ -236,8 +550,9 @@ impl Types {
             TypeDefKind::Type(ty) => {
                 info = self.type_info(iface, ty);
             }
-            TypeDefKind::Option(ty) => {
-                info = self.type_info(iface, ty);
+            TypeDefKind::Option(t) => {
+                info = self.type_info(iface, t);
+                info.has_box |= iface.is_recursive(ty);
             }
             TypeDefKind::Result(r) => {
                 info = self.optional_type_info(iface, r.ok.as_ref());
@@ -255,9 +570,10 @@ impl Types {
                 info = self.optional_type_info(iface, stream.element.as_ref());
                 info |= self.optional_type_info(iface, stream.end.as_ref());
             }
+            TypeDefKind::Resource => {}
+            TypeDefKind::Handle(_) => info.has_handle = true,
         }
-        self.type_info.insert(ty, info);
-        return info;
+        info
     }
 
     pub fn type_info(&mut self, iface: &Interface, ty: &Type) -> TypeInfo {
@@ -315,6 +631,10 @@ impl Types {
                 self.set_param_result_optional_ty(iface, stream.element.as_ref(), param, result);
                 self.set_param_result_optional_ty(iface, stream.end.as_ref(), param, result);
             }
+            TypeDefKind::Resource => {}
+            TypeDefKind::Handle(h) => {
+                self.set_param_result_ty(iface, &Type::Id(h.resource()), param, result)
+            }
         }
     }
 
@@ -373,6 +693,12 @@ pub fn load(path: impl AsRef<Path>) -> Result<Interface> {
     Interface::parse_file(path)
 }
 
+/// Loads the interface at `path`, reading it and its dependencies from
+/// `options.sources`, which may be files held in memory.
+pub fn load_with(path: impl AsRef<Path>, options: &ParseOptions) -> Result<Interface> {
+    Ok(Interface::parse_file_with(path, options)?.0)
+}
+
 #[derive(Default)]
 pub struct Source {
     s: String,
@@ -475,9 +801,153 @@ macro_rules! uwriteln {
     };
 }
 
+/// The structure of an anonymous type, with the anonymous types within it
+/// replaced by their canonical types.
+#[derive(PartialEq, Eq, Hash)]
+enum Shape {
+    Tuple(Vec<Type>),
+    Option(Type),
+    Result(Option<Type>, Option<Type>),
+    List(Type),
+    Future(Option<Type>),
+    Stream(Option<Type>, Option<Type>),
+    Own(TypeId),
+    Borrow(TypeId),
+}
+
+/// Fills in `Types::canonical` and `Types::anonymous_names` for an interface.
+struct Anonymous<'a> {
+    iface: &'a Interface,
+    types: &'a mut Types,
+    shapes: HashMap<Shape, TypeId>,
+    // Names which are taken, starting with those of all named types.
+    names: HashSet<String>,
+}
+
+impl Anonymous<'_> {
+    /// Returns the canonical type of `id`, or `None` if it's not anonymous.
+    fn id(&mut self, id: TypeId) -> Option<TypeId> {
+        if let Some(canonical) = self.types.canonical.get(&id) {
+            return Some(*canonical);
+        }
+        let iface = self.iface;
+        let ty = &iface.types[id];
+        if ty.name.is_some() {
+            return None;
+        }
+        let shape = match &ty.kind {
+            TypeDefKind::Tuple(t) => Shape::Tuple(t.types.iter().map(|t| self.ty(t)).collect()),
+            TypeDefKind::Option(t) => Shape::Option(self.ty(t)),
+            TypeDefKind::Result(r) => Shape::Result(
+                self.optional_ty(r.ok.as_ref()),
+                self.optional_ty(r.err.as_ref()),
+            ),
+            TypeDefKind::List(t) => Shape::List(self.ty(t)),
+            TypeDefKind::Future(t) => Shape::Future(self.optional_ty(t.as_ref())),
+            TypeDefKind::Stream(s) => Shape::Stream(
+                self.optional_ty(s.element.as_ref()),
+                self.optional_ty(s.end.as_ref()),
+            ),
+            TypeDefKind::Handle(Handle::Own(r)) => Shape::Own(*r),
+            TypeDefKind::Handle(Handle::Borrow(r)) => Shape::Borrow(*r),
+            // Anonymous aliases are looked through by `ty`, and no other kind
+            // of type can be anonymous.
+            _ => return None,
+        };
+        let canonical = match self.shapes.get(&shape) {
+            Some(canonical) => *canonical,
+            None => {
+                let base = self.shape_name(&shape);
+                let mut name = base.clone();
+                let mut n = 2;
+                while !self.names.insert(name.clone()) {
+                    name = format!("{base}-{n}");
+                    n += 1;
+                }
+                self.types.anonymous_names.insert(id, name);
+                self.shapes.insert(shape, id);
+                id
+            }
+        };
+        self.types.canonical.insert(id, canonical);
+        Some(canonical)
+    }
+
+    fn ty(&mut self, ty: &Type) -> Type {
+        let id = match ty {
+            Type::Id(id) => *id,
+            _ => return *ty,
+        };
+        let iface = self.iface;
+        let def = &iface.types[id];
+        match (&def.name, &def.kind) {
+            (None, TypeDefKind::Type(t)) => self.ty(t),
+            _ => self.id(id).map(Type::Id).unwrap_or(*ty),
+        }
+    }
+
+    fn optional_ty(&mut self, ty: Option<&Type>) -> Option<Type> {
+        ty.map(|ty| self.ty(ty))
+    }
+
+    fn shape_name(&self, shape: &Shape) -> String {
+        match shape {
+            Shape::Tuple(types) => {
+                let mut name = format!("tuple{}", types.len());
+                for ty in types {
+                    name.push('-');
+                    name.push_str(&self.name(Some(ty)));
+                }
+                name
+            }
+            Shape::Option(t) => format!("option-{}", self.name(Some(t))),
+            Shape::Result(ok, err) => format!(
+                "result-{}-{}",
+                self.name(ok.as_ref()),
+                self.name(err.as_ref())
+            ),
+            Shape::List(t) => format!("list-{}", self.name(Some(t))),
+            Shape::Future(t) => format!("future-{}", self.name(t.as_ref())),
+            Shape::Stream(element, end) => format!(
+                "stream-{}-{}",
+                self.name(element.as_ref()),
+                self.name(end.as_ref())
+            ),
+            Shape::Own(r) => format!("own-{}", self.name(Some(&Type::Id(*r)))),
+            Shape::Borrow(r) => format!("borrow-{}", self.name(Some(&Type::Id(*r)))),
+        }
+    }
+
+    /// Returns the name of a type within a shape, which is already canonical.
+    fn name(&self, ty: Option<&Type>) -> String {
+        let name = match ty {
+            None => "void",
+            Some(Type::Bool) => "bool",
+            Some(Type::U8) => "u8",
+            Some(Type::U16) => "u16",
+            Some(Type::U32) => "u32",
+            Some(Type::U64) => "u64",
+            Some(Type::S8) => "s8",
+            Some(Type::S16) => "s16",
+            Some(Type::S32) => "s32",
+            Some(Type::S64) => "s64",
+            Some(Type::Float32) => "float32",
+            Some(Type::Float64) => "float64",
+            Some(Type::Char) => "char",
+            Some(Type::String) => "string",
+            Some(Type::Id(id)) => match &self.iface.types[*id].name {
+                Some(name) => name,
+                None => &self.types.anonymous_names[id],
+            },
+        };
+        name.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Generator, Source};
+    use super::{Files, Generator, Source, Types, UnsupportedFeature};
+    use wit_parser::*;
 
     #[test]
     fn simple_append() {
@@ -529,6 +999,110 @@ mod tests {
     fn generator_is_object_safe() {
         fn _assert(_: &dyn Generator) {}
     }
+
+    /// A generator which supports nothing optional and generates nothing.
+    struct Plain;
+
+    #[rustfmt::skip]
+    impl Generator for Plain {
+        fn type_record(&mut self, _: &Interface, _: TypeId, _: &str, _: &Record, _: &Docs) {}
+        fn type_flags(&mut self, _: &Interface, _: TypeId, _: &str, _: &Flags, _: &Docs) {}
+        fn type_tuple(&mut self, _: &Interface, _: TypeId, _: &str, _: &Tuple, _: &Docs) {}
+        fn type_variant(&mut self, _: &Interface, _: TypeId, _: &str, _: &Variant, _: &Docs) {}
+        fn type_option(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+        fn type_result(&mut self, _: &Interface, _: TypeId, _: &str, _: &Result_, _: &Docs) {}
+        fn type_union(&mut self, _: &Interface, _: TypeId, _: &str, _: &Union, _: &Docs) {}
+        fn type_enum(&mut self, _: &Interface, _: TypeId, _: &str, _: &Enum, _: &Docs) {}
+        fn type_alias(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+        fn type_list(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+        fn type_builtin(&mut self, _: &Interface, _: TypeId, _: &str, _: &Type, _: &Docs) {}
+        fn import(&mut self, _: &Interface, _: &Function) {}
+        fn export(&mut self, _: &Interface, _: &Function) {}
+        fn finish_one(&mut self, _: &Interface, _: &mut Files) {}
+    }
+
+    #[test]
+    fn default_name() {
+        assert_eq!(Plain.name(), "wit_bindgen_core::tests::Plain");
+    }
+
+    fn unsupported(wit: &str) -> Option<(String, String)> {
+        let iface = Interface::parse("test", wit).unwrap();
+        let mut files = Files::default();
+        let err = Plain.generate_all(&[iface], &[], &mut files).err()?;
+        let err = err.downcast::<UnsupportedFeature>().unwrap();
+        let span = err.span.unwrap();
+        Some((
+            err.construct,
+            wit[span.start as usize..span.end as usize].to_string(),
+        ))
+    }
+
+    #[test]
+    fn unsupported_features() {
+        let found = |construct: &str, at: &str| Some((construct.to_string(), at.to_string()));
+        assert_eq!(unsupported("f: func(a: list<u8>)"), None);
+        assert_eq!(
+            unsupported("type s = stream<u8>"),
+            found("`future` and `stream` types", "s")
+        );
+        assert_eq!(
+            unsupported("f: func() -> option<future<u8>>"),
+            found("`future` and `stream` types", "f")
+        );
+        assert_eq!(
+            unsupported("resource r\nf: func(x: own<r>)"),
+            found("resources", "r")
+        );
+        assert_eq!(
+            unsupported("record tree { children: list<tree> }"),
+            found("recursive types", "tree")
+        );
+    }
+
+    #[test]
+    fn anonymous_names() {
+        let sources = [
+            ("dep.wit", "record r { a: option<u32> }"),
+            (
+                "root.wit",
+                "
+                    use { r } from dep
+                    type option-u32 = u32
+                    f: func(a: option<u32>, b: r) -> result<_, list<tuple<u8, string>>>
+                ",
+            ),
+        ]
+        .into_iter()
+        .collect::<WitSources>();
+        let options = ParseOptions {
+            sources,
+            ..ParseOptions::default()
+        };
+        let (iface, _) = Interface::parse_file_with("root.wit", &options).unwrap();
+        let mut types = Types::default();
+        types.analyze(&iface);
+
+        let id = |ty: &Type| match ty {
+            Type::Id(id) => *id,
+            _ => unreachable!(),
+        };
+        let f = &iface.functions[0];
+        let local = id(&f.params[0].1);
+        let copied = match &iface.types[id(&f.params[1].1)].kind {
+            TypeDefKind::Record(r) => id(&r.fields[0].ty),
+            _ => unreachable!(),
+        };
+        assert_ne!(local, copied);
+        assert_eq!(types.canonical(local), types.canonical(copied));
+        assert_eq!(types.anonymous_name(copied), "option-u32-2");
+
+        let result = id(f.results.iter_types().next().unwrap());
+        assert_eq!(
+            types.anonymous_name(result),
+            "result-void-list-tuple2-u8-string"
+        );
+    }
 }
 
 /// This is a possible replacement for the `Generator` trait above, currently
@@ -554,6 +1128,57 @@ pub trait InterfaceGenerator<'a> {
     fn type_list(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs);
     fn type_builtin(&mut self, id: TypeId, name: &str, ty: &Type, docs: &Docs);
 
+    /// The name of this generator, used in the errors returned by `validate`.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Whether this generator supports resources, see
+    /// [`Generator::supports_resources`].
+    fn supports_resources(&self) -> bool {
+        false
+    }
+    fn type_resource(&mut self, _id: TypeId, name: &str, _docs: &Docs) {
+        unreachable!("resource `{name}` is unsupported")
+    }
+    fn type_handle(&mut self, _id: TypeId, name: &str, _handle: &Handle, _docs: &Docs) {
+        unreachable!("handle `{name}` is unsupported")
+    }
+
+    /// Whether this generator supports `future` and `stream` types, see
+    /// [`Generator::supports_futures_and_streams`].
+    fn supports_futures_and_streams(&self) -> bool {
+        false
+    }
+    fn type_future(&mut self, _id: TypeId, name: &str, _payload: &Option<Type>, _docs: &Docs) {
+        unreachable!("future `{name}` is unsupported")
+    }
+    fn type_stream(&mut self, _id: TypeId, name: &str, _stream: &Stream, _docs: &Docs) {
+        unreachable!("stream `{name}` is unsupported")
+    }
+
+    /// Whether this generator supports recursive types, see
+    /// [`Generator::supports_recursive_types`].
+    fn supports_recursive_types(&self) -> bool {
+        false
+    }
+
+    /// Checks that `iface()` only uses constructs supported by this
+    /// generator, like [`Generator::validate`], which callers should do
+    /// before `types`.
+    fn validate(&self) -> Result<()> {
+        Support {
+            generator: self.name(),
+            resources: self.supports_resources(),
+            futures_and_streams: self.supports_futures_and_streams(),
+            recursive_types: self.supports_recursive_types(),
+            unsupported_type: &|_, _| None,
+        }
+        .validate(self.iface())
+    }
+
+    fn constant(&mut self, constant: &Constant);
+
     fn types(&mut self) {
         for (id, ty) in self.iface().types.iter() {
             let name = match &ty.name {
@@ -571,9 +1196,17 @@ pub trait InterfaceGenerator<'a> {
                 TypeDefKind::Union(u) => self.type_union(id, name, u, &ty.docs),
                 TypeDefKind::List(t) => self.type_list(id, name, t, &ty.docs),
                 TypeDefKind::Type(t) => self.type_alias(id, name, t, &ty.docs),
-                TypeDefKind::Future(_) => todo!("generate for future"),
-                TypeDefKind::Stream(_) => todo!("generate for stream"),
+                TypeDefKind::Future(t) => self.type_future(id, name, t, &ty.docs),
+                TypeDefKind::Stream(s) => self.type_stream(id, name, s, &ty.docs),
+                TypeDefKind::Resource => self.type_resource(id, name, &ty.docs),
+                TypeDefKind::Handle(h) => self.type_handle(id, name, h, &ty.docs),
             }
         }
     }
+
+    fn constants(&mut self) {
+        for constant in self.iface().constants.iter() {
+            self.constant(constant);
+        }
+    }
 }
@@ -0,0 +1,392 @@
+//! Checking, before generating any bindings, how the names in an interface
+//! will be converted to identifiers in each target language.
+//!
+//! Every name is valid in wit, but once a generator converts it to its
+//! language's conventions it may be a reserved word, may be renamed by the
+//! generator to avoid one, or may end up identical to another name declared
+//! alongside it. Each generator describes its conventions with a [`Naming`]
+//! and [`lint`] reports everything which doesn't survive the conversion
+//! unchanged.
+
+use std::collections::{hash_map::Entry, HashMap};
+use std::fmt;
+use wit_parser::{FunctionKind, Interface, TypeDefKind};
+
+/// Something declared in an interface which generators give a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Item<'a> {
+    Type(&'a str),
+    Field {
+        ty: &'a str,
+        name: &'a str,
+    },
+    /// A case of a `variant`.
+    Case {
+        ty: &'a str,
+        name: &'a str,
+    },
+    /// A case of an `enum`.
+    EnumCase {
+        ty: &'a str,
+        name: &'a str,
+    },
+    Flag {
+        ty: &'a str,
+        name: &'a str,
+    },
+    /// A function, or a method or static function of `resource`.
+    Function {
+        resource: Option<&'a str>,
+        name: &'a str,
+    },
+    /// A parameter of the function whose full name is `function`.
+    Param {
+        function: &'a str,
+        name: &'a str,
+    },
+    Constant(&'a str),
+}
+
+impl<'a> Item<'a> {
+    /// The wit name of this item.
+    pub fn name(&self) -> &'a str {
+        match *self {
+            Item::Type(name) | Item::Constant(name) => name,
+            Item::Field { name, .. }
+            | Item::Case { name, .. }
+            | Item::EnumCase { name, .. }
+            | Item::Flag { name, .. }
+            | Item::Function { name, .. }
+            | Item::Param { name, .. } => name,
+        }
+    }
+
+    /// The namespace this item is declared in by most languages.
+    pub fn namespace(&self) -> Namespace<'a> {
+        match *self {
+            Item::Type(_) | Item::Constant(_) | Item::Function { resource: None, .. } => {
+                Namespace::Interface
+            }
+            Item::Field { ty, .. }
+            | Item::Case { ty, .. }
+            | Item::EnumCase { ty, .. }
+            | Item::Flag { ty, .. }
+            | Item::Function {
+                resource: Some(ty), ..
+            } => Namespace::Type(ty),
+            Item::Param { function, .. } => Namespace::Function(function),
+        }
+    }
+}
+
+impl fmt::Display for Item<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Type(name) => write!(f, "type `{name}`"),
+            Item::Field { ty, name } => write!(f, "field `{name}` of type `{ty}`"),
+            Item::Case { ty, name } | Item::EnumCase { ty, name } => {
+                write!(f, "case `{name}` of type `{ty}`")
+            }
+            Item::Flag { ty, name } => write!(f, "flag `{name}` of type `{ty}`"),
+            Item::Function {
+                resource: None,
+                name,
+            } => write!(f, "function `{name}`"),
+            Item::Function {
+                resource: Some(resource),
+                name,
+            } => write!(f, "function `{name}` of resource `{resource}`"),
+            Item::Param { function, name } => {
+                write!(f, "parameter `{name}` of function `{function}`")
+            }
+            Item::Constant(name) => write!(f, "constant `{name}`"),
+        }
+    }
+}
+
+/// A scope in generated bindings. Two items declared in the same namespace
+/// with the same identifier collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace<'a> {
+    /// The top level of the bindings for an interface.
+    Interface,
+    /// The members of the type or resource with this name.
+    Type(&'a str),
+    /// The parameters of the function with this full name.
+    Function(&'a str),
+}
+
+/// How a generator turns the names in an interface into identifiers.
+pub trait Naming {
+    /// The name of the target language, as shown in lints.
+    fn language(&self) -> &'static str;
+
+    /// Returns the identifier `item` is converted to before any escaping,
+    /// or `None` if `item` doesn't become an identifier in this language.
+    fn convert(&self, item: &Item<'_>) -> Option<String>;
+
+    /// Returns the identifier generated bindings use in place of `ident`
+    /// for `item` if they rename it, such as to avoid a keyword.
+    fn escape(&self, item: &Item<'_>, ident: &str) -> Option<String> {
+        let _ = (item, ident);
+        None
+    }
+
+    /// Returns whether `ident` can't be used as the identifier for `item`.
+    fn is_reserved(&self, item: &Item<'_>, ident: &str) -> bool;
+
+    /// Returns the namespace `item` is declared in.
+    fn namespace<'a>(&self, item: &Item<'a>) -> Namespace<'a> {
+        item.namespace()
+    }
+}
+
+/// Whether a [`Lint`] prevents generating working bindings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// A name which doesn't convert cleanly to an identifier in a language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub severity: Severity,
+    pub language: &'static str,
+    /// The item which is named, such as ``function `foo` ``.
+    pub item: String,
+    pub message: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}: {}",
+            self.severity.as_str(),
+            self.language,
+            self.item,
+            self.message
+        )
+    }
+}
+
+/// Everything reported by [`lint`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lints {
+    pub lints: Vec<Lint>,
+}
+
+impl Lints {
+    pub fn has_errors(&self) -> bool {
+        self.lints.iter().any(|l| l.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for Lints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for lint in self.lints.iter() {
+            writeln!(f, "{}", lint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the names in `iface` against each of `languages`.
+///
+/// Names which generators rename are reported as warnings, while names which
+/// become a reserved word or the same identifier as another item in the same
+/// namespace are reported as errors.
+pub fn lint(iface: &Interface, languages: &[&dyn Naming]) -> Lints {
+    let items = items(iface);
+    let mut lints = Vec::new();
+    for lang in languages {
+        let mut declared = HashMap::new();
+        for item in items.iter() {
+            let mut push = |severity, message| {
+                lints.push(Lint {
+                    severity,
+                    language: lang.language(),
+                    item: item.to_string(),
+                    message,
+                })
+            };
+            let ident = match lang.convert(item) {
+                Some(ident) => ident,
+                None => continue,
+            };
+            let ident = match lang.escape(item, &ident) {
+                Some(escaped) => {
+                    push(
+                        Severity::Warning,
+                        format!("renamed from `{ident}` to `{escaped}`"),
+                    );
+                    escaped
+                }
+                None => ident,
+            };
+            if lang.is_reserved(item, &ident) {
+                push(
+                    Severity::Error,
+                    format!("becomes `{ident}`, which is reserved"),
+                );
+            }
+            match declared.entry((lang.namespace(item), ident)) {
+                Entry::Occupied(prev) => push(
+                    Severity::Error,
+                    format!("becomes `{}`, the same as {}", prev.key().1, prev.get()),
+                ),
+                Entry::Vacant(entry) => {
+                    entry.insert(*item);
+                }
+            }
+        }
+    }
+    Lints { lints }
+}
+
+/// Returns every named item of `iface`, in the order they're declared.
+fn items(iface: &Interface) -> Vec<Item<'_>> {
+    let mut items = Vec::new();
+    for (_, ty) in iface.types.iter() {
+        let ty_name = match &ty.name {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
+        items.push(Item::Type(ty_name));
+        match &ty.kind {
+            TypeDefKind::Record(r) => {
+                for field in r.fields.iter() {
+                    items.push(Item::Field {
+                        ty: ty_name,
+                        name: &field.name,
+                    });
+                }
+            }
+            TypeDefKind::Variant(v) => {
+                for case in v.cases.iter() {
+                    items.push(Item::Case {
+                        ty: ty_name,
+                        name: &case.name,
+                    });
+                }
+            }
+            TypeDefKind::Enum(e) => {
+                for case in e.cases.iter() {
+                    items.push(Item::EnumCase {
+                        ty: ty_name,
+                        name: &case.name,
+                    });
+                }
+            }
+            TypeDefKind::Flags(f) => {
+                for flag in f.flags.iter() {
+                    items.push(Item::Flag {
+                        ty: ty_name,
+                        name: &flag.name,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    for func in iface.functions.iter() {
+        // Constructors are named by the language rather than by the
+        // interface, and the `self` parameter of methods is implicit.
+        let params = match &func.kind {
+            FunctionKind::Constructor { .. } => &func.params[..],
+            FunctionKind::Method { .. } => &func.params[1..],
+            FunctionKind::Freestanding | FunctionKind::Static { .. } => {
+                items.push(Item::Function {
+                    resource: func.resource().and_then(|r| iface.types[r].name.as_deref()),
+                    name: func.item_name(),
+                });
+                &func.params[..]
+            }
+        };
+        for (name, _) in params {
+            items.push(Item::Param {
+                function: &func.name,
+                name,
+            });
+        }
+    }
+    for constant in iface.constants.iter() {
+        items.push(Item::Constant(&constant.name));
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A language which drops the hyphens from names and escapes `type`.
+    struct Squash;
+
+    impl Naming for Squash {
+        fn language(&self) -> &'static str {
+            "squash"
+        }
+
+        fn convert(&self, item: &Item<'_>) -> Option<String> {
+            Some(item.name().replace('-', ""))
+        }
+
+        fn escape(&self, _item: &Item<'_>, ident: &str) -> Option<String> {
+            if ident == "type" {
+                Some("type_".to_string())
+            } else {
+                None
+            }
+        }
+
+        fn is_reserved(&self, _item: &Item<'_>, ident: &str) -> bool {
+            ident == "class"
+        }
+    }
+
+    fn lint_squash(wit: &str) -> Vec<String> {
+        let iface = Interface::parse("test", wit).unwrap();
+        let lints = lint(&iface, &[&Squash]);
+        lints.lints.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn renamed_and_reserved() {
+        assert_eq!(
+            lint_squash("record %type { class: u32, x: u32 }"),
+            [
+                "warning: squash: type `type`: renamed from `type` to `type_`",
+                "error: squash: field `class` of type `type`: becomes `class`, which is reserved",
+            ]
+        );
+    }
+
+    #[test]
+    fn collisions() {
+        assert_eq!(
+            lint_squash(
+                "
+                    record x-y { a-b: u32, ab: u32 }
+                    type xy = u32
+                    f: func(a-b: u32, ab: u32, xy: x-y)
+                "
+            ),
+            [
+                "error: squash: field `ab` of type `x-y`: becomes `ab`, the same as field `a-b` of type `x-y`",
+                "error: squash: type `xy`: becomes `xy`, the same as type `x-y`",
+                "error: squash: parameter `ab` of function `f`: becomes `ab`, the same as parameter `a-b` of function `f`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Looking up generators by name, for tools such as the `wit-bindgen` CLI
+//! which offer a choice of generators.
+//!
+//! Each generator crate describes itself with a [`Registration`]: its name,
+//! a description, the options it accepts and how to build it from them. A
+//! tool collects the registrations it knows about in a [`Registry`], to
+//! which generators maintained out of tree may be added as well.
+
+use crate::Generator;
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A set of generators, keyed by name.
+#[derive(Default)]
+pub struct Registry {
+    generators: BTreeMap<String, Registration>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Adds `registration`, replacing any generator previously registered
+    /// with the same name.
+    pub fn register(&mut self, registration: Registration) {
+        self.generators
+            .insert(registration.name.clone(), registration);
+    }
+
+    /// Returns the generator named `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&Registration> {
+        self.generators.get(name)
+    }
+
+    /// Returns every registered generator, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Registration> + '_ {
+        self.generators.values()
+    }
+}
+
+/// How a generator is described and built.
+pub struct Registration {
+    name: String,
+    description: String,
+    options: Vec<OptionSpec>,
+    build: Box<BuildFn>,
+}
+
+type BuildFn = dyn Fn(&Options) -> Box<dyn Generator>;
+
+impl Registration {
+    /// Creates the registration of the generator `name`, which `build`
+    /// creates from the values of its options.
+    ///
+    /// The options themselves are declared with [`Registration::flag`] and
+    /// [`Registration::string`].
+    pub fn new(
+        name: &str,
+        description: &str,
+        build: impl Fn(&Options) -> Box<dyn Generator> + 'static,
+    ) -> Registration {
+        Registration {
+            name: name.to_string(),
+            description: description.to_string(),
+            options: Vec::new(),
+            build: Box::new(build),
+        }
+    }
+
+    /// Declares the option `name` which is either set or not.
+    pub fn flag(mut self, name: &str, description: &str) -> Registration {
+        self.options.push(OptionSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            kind: OptionKind::Flag,
+        });
+        self
+    }
+
+    /// Declares the option `name` which takes a string.
+    pub fn string(mut self, name: &str, description: &str) -> Registration {
+        self.options.push(OptionSpec {
+            name: name.to_string(),
+            description: description.to_string(),
+            kind: OptionKind::String,
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the options this generator accepts, in the order they were
+    /// declared.
+    pub fn options(&self) -> &[OptionSpec] {
+        &self.options
+    }
+
+    /// Builds the generator, after checking `options` against the options
+    /// it accepts.
+    pub fn build(&self, options: &Options) -> Result<Box<dyn Generator>> {
+        for (name, value) in options.values.iter() {
+            let spec = self
+                .options
+                .iter()
+                .find(|o| o.name == *name)
+                .ok_or_else(|| anyhow!("generator `{}` has no option `{}`", self.name, name))?;
+            match (spec.kind, value) {
+                (OptionKind::Flag, Some(_)) => {
+                    bail!("option `{}` is a flag and doesn't take a value", name)
+                }
+                (OptionKind::String, None) => bail!("option `{}` requires a value", name),
+                _ => {}
+            }
+        }
+        Ok((self.build)(options))
+    }
+}
+
+/// An option accepted by a generator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionSpec {
+    pub name: String,
+    pub description: String,
+    pub kind: OptionKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Flag,
+    String,
+}
+
+impl fmt::Display for OptionSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            OptionKind::Flag => write!(f, "{}", self.name),
+            OptionKind::String => write!(f, "{}=<string>", self.name),
+        }
+    }
+}
+
+/// The values given to the options of a generator.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    values: BTreeMap<String, Option<String>>,
+}
+
+impl Options {
+    /// Parses options written as `name` for flags or `name=value` otherwise.
+    pub fn parse<'a>(args: impl IntoIterator<Item = &'a str>) -> Options {
+        let mut options = Options::default();
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) => options.set(name, value),
+                None => options.set_flag(arg),
+            }
+        }
+        options
+    }
+
+    pub fn set_flag(&mut self, name: &str) {
+        self.values.insert(name.to_string(), None);
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.values
+            .insert(name.to_string(), Some(value.to_string()));
+    }
+
+    /// Returns whether the flag `name` is set.
+    pub fn flag(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// Returns the value of the option `name`, if it's set.
+    pub fn string(&self, name: &str) -> Option<&str> {
+        self.values.get(name).and_then(|v| v.as_deref())
+    }
+
+    /// Returns every option which is set along with its value, which is
+    /// `None` for flags.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> + '_ {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_deref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Options, Registration};
+
+    fn registration() -> Registration {
+        Registration::new("test", "A test generator.", |_| unreachable!())
+            .flag("fast", "Goes fast.")
+            .string("prefix", "A prefix.")
+    }
+
+    #[test]
+    fn parse() {
+        let options = Options::parse(["fast", "prefix=a=b"]);
+        assert!(options.flag("fast"));
+        assert!(!options.flag("slow"));
+        assert_eq!(options.string("prefix"), Some("a=b"));
+        assert_eq!(options.string("fast"), None);
+    }
+
+    #[test]
+    fn validate() {
+        let err = |args: &[&str]| {
+            let options = Options::parse(args.iter().copied());
+            registration().build(&options).err().unwrap().to_string()
+        };
+        assert_eq!(err(&["slow"]), "generator `test` has no option `slow`");
+        assert_eq!(
+            err(&["fast=yes"]),
+            "option `fast` is a flag and doesn't take a value"
+        );
+        assert_eq!(err(&["prefix"]), "option `prefix` requires a value");
+    }
+}
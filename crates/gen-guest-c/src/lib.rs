@@ -4,10 +4,11 @@ use heck::*;
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Write;
 use std::mem;
+use wit_bindgen_core::registry::Registration;
 use wit_bindgen_core::wit_parser::abi::{
     AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
 };
-use wit_bindgen_core::{uwrite, uwriteln, wit_parser::*, Direction, Files, Generator, Ns};
+use wit_bindgen_core::{uwrite, uwriteln, wit_parser::*, Direction, Files, Generator, Ns, Types};
 
 #[derive(Default)]
 pub struct C {
@@ -19,10 +20,12 @@ pub struct C {
     return_pointer_area_align: usize,
     sizes: SizeAlign,
     names: Ns,
+    info: Types,
 
     // The set of types that are considered public (aka need to be in the
     // header file) which are anonymous and we're effectively monomorphizing.
-    // This is discovered lazily when printing type names.
+    // This is discovered lazily when printing type names, and only contains
+    // canonical types so structurally identical types are printed once.
     public_anonymous_types: BTreeSet<TypeId>,
 
     // This is similar to `public_anonymous_types` where it's discovered
@@ -35,6 +38,9 @@ pub struct C {
     // at the end.
     types: HashMap<TypeId, wit_bindgen_core::Source>,
 
+    // `#define`s for the interface's constants, printed before any types.
+    constants: wit_bindgen_core::Source,
+
     needs_string: bool,
 
     direction: Direction,
@@ -58,6 +64,15 @@ impl Opts {
     }
 }
 
+/// Describes this generator for a [`Registry`](wit_bindgen_core::registry::Registry).
+pub fn registration() -> Registration {
+    Registration::new(
+        "guest-c",
+        "Generates bindings for C/CPP guest modules.",
+        |_| Box::new(Opts::default().build()),
+    )
+}
+
 #[derive(Debug)]
 struct Return {
     return_multiple: bool,
@@ -187,8 +202,10 @@ impl C {
                 TypeDefKind::Enum(_) => false,
                 TypeDefKind::Flags(_) => false,
                 TypeDefKind::Tuple(_) | TypeDefKind::Record(_) | TypeDefKind::List(_) => true,
-                TypeDefKind::Future(_) => todo!("is_arg_by_pointer for future"),
-                TypeDefKind::Stream(_) => todo!("is_arg_by_pointer for stream"),
+                TypeDefKind::Future(_) => unreachable!("futures are unsupported"),
+                TypeDefKind::Stream(_) => unreachable!("streams are unsupported"),
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
+                TypeDefKind::Handle(_) => unreachable!("resources are unsupported"),
             },
             Type::String => true,
             _ => false,
@@ -258,10 +275,11 @@ impl C {
                     None => match &ty.kind {
                         TypeDefKind::Type(t) => self.print_ty(iface, t),
                         _ => {
-                            self.public_anonymous_types.insert(*id);
-                            self.private_anonymous_types.remove(id);
+                            let id = self.info.canonical(*id);
+                            self.public_anonymous_types.insert(id);
+                            self.private_anonymous_types.remove(&id);
                             self.print_namespace(iface);
-                            self.print_ty_name(iface, &Type::Id(*id));
+                            self.print_ty_name(iface, &Type::Id(id));
                             self.src.h("_t");
                         }
                     },
@@ -292,57 +310,15 @@ impl C {
                 }
                 match &ty.kind {
                     TypeDefKind::Type(t) => self.print_ty_name(iface, t),
-                    TypeDefKind::Record(_)
-                    | TypeDefKind::Flags(_)
-                    | TypeDefKind::Enum(_)
-                    | TypeDefKind::Variant(_)
-                    | TypeDefKind::Union(_) => {
-                        unimplemented!()
-                    }
-                    TypeDefKind::Tuple(t) => {
-                        self.src.h("tuple");
-                        self.src.h(&t.types.len().to_string());
-                        for ty in t.types.iter() {
-                            self.src.h("_");
-                            self.print_ty_name(iface, ty);
-                        }
-                    }
-                    TypeDefKind::Option(ty) => {
-                        self.src.h("option_");
-                        self.print_ty_name(iface, ty);
-                    }
-                    TypeDefKind::Result(r) => {
-                        self.src.h("result_");
-                        self.print_optional_ty_name(iface, r.ok.as_ref());
-                        self.src.h("_");
-                        self.print_optional_ty_name(iface, r.err.as_ref());
-                    }
-                    TypeDefKind::List(t) => {
-                        self.src.h("list_");
-                        self.print_ty_name(iface, t);
-                    }
-                    TypeDefKind::Future(t) => {
-                        self.src.h("future_");
-                        self.print_optional_ty_name(iface, t.as_ref());
-                    }
-                    TypeDefKind::Stream(s) => {
-                        self.src.h("stream_");
-                        self.print_optional_ty_name(iface, s.element.as_ref());
-                        self.src.h("_");
-                        self.print_optional_ty_name(iface, s.end.as_ref());
+                    _ => {
+                        let name = self.info.anonymous_name(*id).to_snake_case();
+                        self.src.h(&name);
                     }
                 }
             }
         }
     }
 
-    fn print_optional_ty_name(&mut self, iface: &Interface, ty: Option<&Type>) {
-        match ty {
-            Some(ty) => self.print_ty_name(iface, ty),
-            None => self.src.h("void"),
-        }
-    }
-
     fn print_anonymous_type(&mut self, iface: &Interface, ty: TypeId) {
         let prev = mem::take(&mut self.src.h);
         self.src.h("typedef ");
@@ -396,8 +372,10 @@ impl C {
                 self.src.h("size_t len;\n");
                 self.src.h("}");
             }
-            TypeDefKind::Future(_) => todo!("print_anonymous_type for future"),
-            TypeDefKind::Stream(_) => todo!("print_anonymous_type for stream"),
+            TypeDefKind::Future(_) => unreachable!("futures are unsupported"),
+            TypeDefKind::Stream(_) => unreachable!("streams are unsupported"),
+            TypeDefKind::Resource => unreachable!("resources are used through handles"),
+            TypeDefKind::Handle(_) => unreachable!("resources are unsupported"),
         }
         self.src.h(" ");
         self.print_namespace(iface);
@@ -567,8 +545,10 @@ impl C {
                 }
                 self.src.c("}\n");
             }
-            TypeDefKind::Future(_) => todo!("print_dtor for future"),
-            TypeDefKind::Stream(_) => todo!("print_dtor for stream"),
+            TypeDefKind::Future(_) => unreachable!("futures are unsupported"),
+            TypeDefKind::Stream(_) => unreachable!("streams are unsupported"),
+            TypeDefKind::Resource => unreachable!("resources are used through handles"),
+            TypeDefKind::Handle(_) => unreachable!("resources are unsupported"),
         }
         self.src.c("}\n");
     }
@@ -599,8 +579,10 @@ impl C {
                 self.optional_owns_anything(iface, r.ok.as_ref())
                     || self.optional_owns_anything(iface, r.err.as_ref())
             }
-            TypeDefKind::Future(_) => todo!("owns_anything for future"),
-            TypeDefKind::Stream(_) => todo!("owns_anything for stream"),
+            TypeDefKind::Future(_) => unreachable!("futures are unsupported"),
+            TypeDefKind::Stream(_) => unreachable!("streams are unsupported"),
+            TypeDefKind::Resource => unreachable!("resources are used through handles"),
+            TypeDefKind::Handle(_) => unreachable!("resources are unsupported"),
         }
     }
 
@@ -694,8 +676,10 @@ impl Return {
             | TypeDefKind::Variant(_)
             | TypeDefKind::Union(_) => {}
 
-            TypeDefKind::Future(_) => todo!("return_single for future"),
-            TypeDefKind::Stream(_) => todo!("return_single for stream"),
+            TypeDefKind::Future(_) => unreachable!("futures are unsupported"),
+            TypeDefKind::Stream(_) => unreachable!("streams are unsupported"),
+            TypeDefKind::Resource => unreachable!("resources are used through handles"),
+            TypeDefKind::Handle(_) => unreachable!("resources are unsupported"),
         }
 
         self.retptrs.push(*orig_ty);
@@ -703,10 +687,15 @@ impl Return {
 }
 
 impl Generator for C {
+    fn name(&self) -> &str {
+        "guest-c"
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
         self.direction = dir;
         let variant = Self::abi_variant(dir);
         self.sizes.fill(iface);
+        self.info.analyze(iface);
         self.in_import = variant == AbiVariant::GuestImport;
     }
 
@@ -973,6 +962,33 @@ impl Generator for C {
         drop((iface, _id, name, ty, docs));
     }
 
+    fn constant(&mut self, iface: &Interface, constant: &Constant) {
+        let prev = mem::take(&mut self.src.h);
+        self.docs(&constant.docs);
+        let value = match (&constant.value, iface.unalias(&constant.ty)) {
+            (Literal::Integer(i), Type::U32) => format!("{i}u"),
+            (Literal::Integer(i), Type::U64) => format!("{i}ull"),
+            // `-9223372036854775808ll` would overflow before being negated.
+            (Literal::Integer(i), Type::S64) if *i == i64::MIN as i128 => {
+                format!("({}ll - 1)", i + 1)
+            }
+            (Literal::Integer(i), Type::S64) => format!("{i}ll"),
+            (Literal::Float(f), Type::Float32) => format!("{f:?}f"),
+            (Literal::Char(c), _) => format!("{}u", u32::from(*c)),
+            (Literal::String(s), _) => c_string_literal(s),
+            (other, _) => other.to_string(),
+        };
+        uwriteln!(
+            self.src.h,
+            "#define {}_{} {}",
+            iface.name.to_shouty_snake_case(),
+            constant.name.to_shouty_snake_case(),
+            value,
+        );
+        let src = mem::replace(&mut self.src.h, prev);
+        self.constants.push_str(&src);
+    }
+
     fn import(&mut self, iface: &Interface, func: &Function) {
         let prev = mem::take(&mut self.src);
         let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
@@ -1253,9 +1269,18 @@ impl Generator for C {
             );
         }
 
+        let constants = mem::take(&mut self.constants);
+        self.src.h(&constants);
+
         // Afterwards print all types. Note that this print must be in a
-        // topological order, so we
+        // topological order, where each anonymous type is printed as its
+        // canonical type in place of the first type which shares it.
+        let mut printed = HashSet::new();
         for id in iface.topological_types() {
+            let id = self.info.canonical(id);
+            if !printed.insert(id) {
+                continue;
+            }
             if let Some(ty) = self.types.get(&id) {
                 if private_types.contains(&id) {
                     self.src.c(ty);
@@ -1916,6 +1941,9 @@ impl Bindgen for FunctionBindgen<'_> {
             Instruction::EnumLower { .. } => results.push(format!("(int32_t) {}", operands[0])),
             Instruction::EnumLift { .. } => results.push(operands.pop().unwrap()),
 
+            // TODO: check refinements
+            Instruction::RefinementCheck { .. } => results.push(operands.pop().unwrap()),
+
             Instruction::ListCanonLower { .. } | Instruction::StringLower { .. } => {
                 results.push(format!("(int32_t) ({}).ptr", operands[0]));
                 results.push(format!("(int32_t) ({}).len", operands[0]));
@@ -2205,7 +2233,27 @@ impl Bindgen for FunctionBindgen<'_> {
                 uwriteln!(self.src, "}}");
             }
 
-            i => unimplemented!("{:?}", i),
+            Instruction::HandleLower { .. } | Instruction::HandleLift { .. } => {
+                unreachable!("resources are rejected by `validate`")
+            }
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("futures and streams are rejected by `validate`")
+            }
+
+            Instruction::BoxedOptionLower { .. }
+            | Instruction::BoxedOptionLift { .. }
+            | Instruction::RecursiveStore { .. }
+            | Instruction::RecursiveLoad { .. }
+            | Instruction::RecursiveReturn { .. }
+            | Instruction::GuestDeallocateBoxedOption { .. }
+            | Instruction::GuestDeallocateRecursive { .. } => {
+                unreachable!("recursive types are rejected by `validate`")
+            }
+
+            Instruction::Malloc { .. } => unreachable!("only hosts allocate arguments"),
         }
     }
 }
@@ -2225,6 +2273,25 @@ impl Source {
     }
 }
 
+fn c_string_literal(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' => ret.push_str("\\\""),
+            b'\\' => ret.push_str("\\\\"),
+            b'\n' => ret.push_str("\\n"),
+            b'\r' => ret.push_str("\\r"),
+            b'\t' => ret.push_str("\\t"),
+            0x20..=0x7e => ret.push(b as char),
+            // Octal escapes are at most three digits, unlike hex escapes which
+            // would swallow any hex digit that follows.
+            _ => ret.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
 fn wasm_type(ty: WasmType) -> &'static str {
     match ty {
         WasmType::I32 => "int32_t",
@@ -51,3 +51,12 @@ fn verify(dir: &Path, name: &str) {
 
     test_helpers::run_command(&mut cmd);
 }
+
+#[test]
+fn unsupported() {
+    test_helpers::assert_unsupported(
+        wit_bindgen_gen_guest_c::Opts::default().build(),
+        "variant tree { leaf(u32), node(list<tree>) }",
+        "recursive types",
+    );
+}
@@ -1,12 +1,16 @@
 use heck::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::io::{Read, Write};
 use std::mem;
 use std::process::{Command, Stdio};
-use wit_bindgen_core::wit_parser::abi::{AbiVariant, Bindgen, Instruction, LiftLower, WasmType};
+use wit_bindgen_core::registry::Registration;
+use wit_bindgen_core::wit_parser::abi::{
+    AbiVariant, Bindgen, Instruction, LiftLower, RecursiveHelper, WasmType,
+};
 use wit_bindgen_core::{wit_parser::*, Direction, Files, Generator, Source, TypeInfo, Types};
 use wit_bindgen_gen_rust_lib::{
-    int_repr, wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator, RustGenerator, TypeMode,
+    int_repr, resource_fn_name, rust_refinement_check, to_rust_ident, unsupported_handle,
+    wasm_type, FnSig, RustFlagsRepr, RustFunctionGenerator, RustGenerator, TypeMode,
 };
 
 #[derive(Default)]
@@ -21,6 +25,7 @@ pub struct RustWasm {
     return_pointer_area_size: usize,
     return_pointer_area_align: usize,
     sizes: SizeAlign,
+    recursive_helpers: Vec<(TypeId, RecursiveHelper)>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -35,7 +40,8 @@ pub struct Opts {
     pub multi_module: bool,
 
     /// Whether or not the bindings assume interface values are always
-    /// well-formed or whether checks are performed.
+    /// well-formed or whether checks are performed, including those of
+    /// `where` refinements. Values which fail a check trap the guest.
     #[cfg_attr(feature = "clap", arg(long))]
     pub unchecked: bool,
 
@@ -69,6 +75,7 @@ pub struct Opts {
 #[derive(Default)]
 struct Trait {
     methods: Vec<String>,
+    is_async: bool,
 }
 
 impl Opts {
@@ -79,6 +86,41 @@ impl Opts {
     }
 }
 
+/// Describes this generator for a [`Registry`](wit_bindgen_core::registry::Registry).
+pub fn registration() -> Registration {
+    Registration::new("guest-rust", "Generates bindings for Rust guest modules.", |options| {
+        let opts = Opts {
+            rustfmt: options.flag("rustfmt"),
+            multi_module: options.flag("multi-module"),
+            unchecked: options.flag("unchecked"),
+            no_std: options.flag("no-std"),
+            raw_strings: options.flag("raw-strings"),
+            ..Opts::default()
+        };
+        Box::new(opts.build())
+    })
+    .flag(
+        "rustfmt",
+        "Whether or not `rustfmt` is executed to format generated code.",
+    )
+    .flag(
+        "multi-module",
+        "Adds the wit module name into import binding names when enabled.",
+    )
+    .flag(
+        "unchecked",
+        "Whether or not the bindings assume interface values are always well-formed or whether checks are performed, including those of `where` refinements.",
+    )
+    .flag(
+        "no-std",
+        "If true, code generation should avoid any features that depend on `std`.",
+    )
+    .flag(
+        "raw-strings",
+        "If true, code generation should pass borrowed string arguments as `&[u8]` instead of `&str`.",
+    )
+}
+
 impl RustWasm {
     pub fn new() -> RustWasm {
         RustWasm::default()
@@ -92,6 +134,28 @@ impl RustWasm {
         }
     }
 
+    /// Returns the symbol the wasm function implementing the export `name` is
+    /// exported as.
+    fn export_name(&self, iface: &Interface, name: &str) -> String {
+        match &iface.module {
+            Some(module) => format!("{module}#{name}"),
+            None => format!("{}{name}", self.opts.symbol_namespace),
+        }
+    }
+
+    /// Returns the name of the trait which `func` is a method of when it's
+    /// exported, which is the resource's trait for resource functions.
+    fn export_trait_name(iface: &Interface, func: &Function) -> String {
+        match func.resource() {
+            Some(resource) => iface.types[resource]
+                .name
+                .as_ref()
+                .unwrap()
+                .to_upper_camel_case(),
+            None => iface.name.to_upper_camel_case(),
+        }
+    }
+
     fn ret_area_type_name(iface: &Interface) -> String {
         format!("__{}RetArea", iface.name.to_upper_camel_case())
     }
@@ -99,6 +163,100 @@ impl RustWasm {
     fn ret_area_name(iface: &Interface) -> String {
         format!("__{}_RET_AREA", iface.name.to_shouty_snake_case())
     }
+
+    fn recursive_helper_name(iface: &Interface, id: TypeId, helper: RecursiveHelper) -> String {
+        let prefix = match helper {
+            RecursiveHelper::Store => "store",
+            RecursiveHelper::Load => "load",
+            RecursiveHelper::Deallocate => "dealloc",
+        };
+        let name = iface.types[id].name.as_ref().unwrap();
+        format!("__{prefix}_{}", name.to_snake_case())
+    }
+
+    /// Generates the helpers which read, write and deallocate values of
+    /// recursive types, including those the helpers themselves call.
+    fn print_recursive_helpers(&mut self, iface: &Interface) {
+        let (variant, lift_lower) = if self.in_import {
+            (AbiVariant::GuestImport, LiftLower::LowerArgsLiftResults)
+        } else {
+            (AbiVariant::GuestExport, LiftLower::LiftArgsLowerResults)
+        };
+        let mut generated = HashSet::new();
+        while let Some((id, helper)) = self.recursive_helpers.pop() {
+            if !generated.insert((id, helper)) {
+                continue;
+            }
+            let params = match helper {
+                RecursiveHelper::Store => vec!["value".to_string(), "base".to_string()],
+                RecursiveHelper::Load | RecursiveHelper::Deallocate => vec!["base".to_string()],
+            };
+            let mut f = FunctionBindgen::new(self, params);
+            f.in_helper = true;
+            iface.recursive_helper(variant, lift_lower, id, helper, &mut f);
+            let sig = match helper {
+                // Imports borrow their arguments, and allocations made while
+                // lowering them are freed by the caller after the call.
+                RecursiveHelper::Store if f.gen.in_import => format!(
+                    "(value: &{}, base: i32, cleanup_list: &mut Vec<(*mut u8, alloc::Layout)>)",
+                    f.typename_lower(iface, id)
+                ),
+                RecursiveHelper::Store => {
+                    format!("(value: {}, base: i32)", f.typename_lower(iface, id))
+                }
+                RecursiveHelper::Load => format!("(base: i32) -> {}", f.typename_lift(iface, id)),
+                RecursiveHelper::Deallocate => "(base: i32)".to_string(),
+            };
+            let src = mem::take(&mut f.src);
+            let name = Self::recursive_helper_name(iface, id, helper);
+            self.src.push_str(&format!("unsafe fn {name}{sig} {{\n"));
+            if self.opts.standalone && !self.in_import {
+                self.src
+                    .push_str("#[allow(unused_imports)]\nuse wit_bindgen_guest_rust;\nuse ");
+                self.src.push_str(&iface.name.to_snake_case());
+                self.src.push_str("::*;\n");
+            }
+            self.src.push_str(&String::from(src));
+            self.src.push_str("}\n");
+        }
+    }
+
+    fn print_component_type(&mut self, iface: &Interface, dir: Direction) {
+        self.src.push_str("#[cfg(target_arch = \"wasm32\")]\n");
+
+        // The custom section name here must start with "component-type" but
+        // otherwise is attempted to be unique here to ensure that this doesn't get
+        // concatenated to other custom sections by LLD by accident since LLD will
+        // concatenate custom sections of the same name.
+        let direction = match dir {
+            Direction::Import => "import",
+            Direction::Export => "export",
+        };
+        let iface_name = &iface.name;
+        self.src.push_str(&format!(
+            "#[link_section = \"component-type:{direction}:{iface_name}\"]\n"
+        ));
+
+        let mut encoder = wit_component::ComponentEncoder::default();
+        encoder = match dir {
+            Direction::Import => encoder.imports([iface.clone()]).unwrap(),
+            // Interfaces with a module name are named exports of a world,
+            // anything else is the default export.
+            Direction::Export if iface.module.is_some() => {
+                encoder.exports([iface.clone()]).unwrap()
+            }
+            Direction::Export => encoder.interface(iface.clone()).unwrap(),
+        };
+        let component_type = encoder.types_only(true).encode().expect(&format!(
+            "encoding interface {} as a component type",
+            iface.name
+        ));
+        self.src.push_str(&format!(
+            "pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; {}] = ",
+            component_type.len()
+        ));
+        self.src.push_str(&format!("{:?};\n", component_type));
+    }
 }
 
 impl RustGenerator for RustWasm {
@@ -145,6 +303,16 @@ impl RustGenerator for RustWasm {
         self.print_rust_slice(iface, mutbl, ty, lifetime);
     }
 
+    fn print_resource(&mut self, iface: &Interface, id: TypeId) {
+        // Exported resources are implemented by the type of the same name in
+        // the parent module.
+        if !self.in_import {
+            self.push_str("super::");
+        }
+        let name = iface.types[id].name.as_ref().unwrap();
+        self.push_str(&name.to_upper_camel_case());
+    }
+
     fn print_borrowed_str(&mut self, lifetime: &'static str) {
         self.push_str("&");
         if lifetime != "'_" {
@@ -160,6 +328,24 @@ impl RustGenerator for RustWasm {
 }
 
 impl Generator for RustWasm {
+    fn name(&self) -> &str {
+        "guest-rust"
+    }
+
+    fn supports_recursive_types(&self) -> bool {
+        true
+    }
+
+    // Exported resources are implemented by types in the parent module of
+    // the bindings, which standalone bindings don't have.
+    fn supports_resources(&self) -> bool {
+        !self.opts.standalone
+    }
+
+    fn unsupported_type(&self, iface: &Interface, id: TypeId) -> Option<String> {
+        unsupported_handle(iface, id)
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
         let variant = Self::abi_variant(dir);
         self.in_import = variant == AbiVariant::GuestImport;
@@ -168,14 +354,15 @@ impl Generator for RustWasm {
 
         if !self.opts.standalone {
             self.src.push_str(&format!(
-                "#[allow(clippy::all)]\nmod {} {{\n",
+                "#[allow(clippy::all, deprecated)]\nmod {} {{\n",
                 iface.name.to_snake_case(),
             ));
         }
 
         self.src.push_str("#[allow(unused_imports)]");
-        self.src
-            .push_str("use wit_bindgen_guest_rust::rt::{alloc, vec::Vec, string::String};");
+        self.src.push_str(
+            "use wit_bindgen_guest_rust::rt::{alloc, boxed::Box, vec::Vec, string::String};",
+        );
 
         self.sizes.fill(iface);
     }
@@ -290,8 +477,8 @@ impl Generator for RustWasm {
         self.print_typedef_result(iface, id, result, docs);
     }
 
-    fn type_enum(&mut self, _iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
-        self.print_typedef_enum(id, name, enum_, docs, &[], Box::new(|_| String::new()));
+    fn type_enum(&mut self, iface: &Interface, id: TypeId, _name: &str, enum_: &Enum, docs: &Docs) {
+        self.print_typedef_enum(iface, id, enum_, docs, &[], Box::new(|_| String::new()));
     }
 
     fn type_alias(&mut self, iface: &Interface, id: TypeId, _name: &str, ty: &Type, docs: &Docs) {
@@ -311,6 +498,69 @@ impl Generator for RustWasm {
         self.src.push_str(";\n");
     }
 
+    fn type_resource(&mut self, iface: &Interface, id: TypeId, name: &str, docs: &Docs) {
+        let camel = name.to_upper_camel_case();
+        let destructor = iface.resource_drop(id);
+        if !self.in_import {
+            // The guest implements the resource as `super::{camel}`, which
+            // owned handles are boxed pointers to.
+            let export_name = self.export_name(iface, &destructor.name);
+            self.src.push_str(&format!(
+                "#[export_name = \"{export_name}\"]
+                unsafe extern \"C\" fn __wit_bindgen_{}_{}(arg0: i32) {{
+                    core::mem::drop(Box::from_raw(arg0 as u32 as usize as *mut super::{camel}));
+                }}
+                ",
+                iface.name.to_snake_case(),
+                destructor.name.to_snake_case(),
+            ));
+            return;
+        }
+
+        self.rustdoc(docs);
+        self.src.push_str(&format!(
+            "#[derive(Debug)]
+            #[repr(transparent)]
+            pub struct {camel}(i32);
+
+            impl {camel} {{
+                /// Returns the index of the handle to this resource, transferring
+                /// ownership of the resource to the caller.
+                pub fn into_raw(self) -> i32 {{
+                    let handle = self.0;
+                    core::mem::forget(self);
+                    handle
+                }}
+
+                /// Creates a `{camel}` from the index of a handle to the resource,
+                /// taking ownership of it.
+                ///
+                /// # Safety
+                ///
+                /// `handle` must be an owned handle to a `{name}` which isn't
+                /// used anywhere else.
+                pub unsafe fn from_raw(handle: i32) -> Self {{
+                    Self(handle)
+                }}
+            }}
+
+            impl Drop for {camel} {{
+                fn drop(&mut self) {{
+                    unsafe {{
+            "
+        ));
+        let mut f = FunctionBindgen::new(self, Vec::new());
+        let import = f.declare_import(iface, &destructor.name, &[WasmType::I32], &[]);
+        f.push_str(&format!("{import}(self.0);\n"));
+        let src = mem::take(&mut f.src);
+        self.src.push_str(&String::from(src));
+        self.src.push_str("}\n}\n}\n");
+    }
+
+    fn constant(&mut self, iface: &Interface, constant: &Constant) {
+        self.print_constant(iface, constant);
+    }
+
     fn preprocess_functions(&mut self, _iface: &Interface, dir: Direction) {
         if self.opts.standalone && dir == Direction::Export {
             self.src.push_str(
@@ -322,10 +572,18 @@ impl Generator for RustWasm {
     }
 
     fn import(&mut self, iface: &Interface, func: &Function) {
-        let sig = FnSig::default();
+        let mut sig = FnSig::default();
         let param_mode = TypeMode::AllBorrowed("'_");
-        match &func.kind {
-            FunctionKind::Freestanding => {}
+        // Resource functions are associated functions of the resource's type.
+        if let Some(resource) = func.resource() {
+            sig.use_item_name = true;
+            if let FunctionKind::Method { .. } = func.kind {
+                sig.self_arg = Some("&self".to_string());
+                sig.self_is_first_param = true;
+            }
+            self.src.push_str("impl ");
+            self.print_resource(iface, resource);
+            self.src.push_str(" {\n");
         }
         let params = self.print_signature(iface, func, param_mode, &sig);
         self.src.push_str("{\n");
@@ -352,8 +610,8 @@ impl Generator for RustWasm {
         self.src.push_str("}\n");
         self.src.push_str("}\n");
 
-        match &func.kind {
-            FunctionKind::Freestanding => {}
+        if func.resource().is_some() {
+            self.src.push_str("}\n");
         }
     }
 
@@ -361,13 +619,9 @@ impl Generator for RustWasm {
         let iface_name = iface.name.to_snake_case();
 
         let name_snake = func.name.to_snake_case();
-        let name = match &iface.module {
-            Some(module) => {
-                format!("{module}#{}", func.name)
-            }
-            None => format!("{}{}", self.opts.symbol_namespace, func.name),
-        };
+        let name = self.export_name(iface, &func.name);
 
+        self.src.push_str("#[allow(deprecated)]\n");
         self.src.push_str(&format!("#[export_name = \"{name}\"]\n"));
         self.src.push_str("unsafe extern \"C\" fn __wit_bindgen_");
         self.src.push_str(&iface_name);
@@ -456,21 +710,31 @@ impl Generator for RustWasm {
         let prev = mem::take(&mut self.src);
         self.in_trait = true;
         let mut sig = FnSig::default();
+        sig.async_ = func.is_async;
         sig.private = true;
+        if func.resource().is_some() {
+            sig.use_item_name = true;
+            if let FunctionKind::Method { .. } = func.kind {
+                sig.self_arg = Some("&self".to_string());
+                sig.self_is_first_param = true;
+            }
+        }
         self.print_signature(iface, func, TypeMode::Owned, &sig);
         self.src.push_str(";");
         self.in_trait = false;
         let trait_ = self
             .traits
-            .entry(iface.name.to_upper_camel_case())
+            .entry(Self::export_trait_name(iface, func))
             .or_insert(Trait::default());
-        let dst = match &func.kind {
-            FunctionKind::Freestanding => &mut trait_.methods,
-        };
-        dst.push(mem::replace(&mut self.src, prev).into());
+        trait_.is_async |= func.is_async;
+        trait_
+            .methods
+            .push(mem::replace(&mut self.src, prev).into());
     }
 
     fn finish_functions(&mut self, iface: &Interface, dir: Direction) {
+        self.print_recursive_helpers(iface);
+
         if !self.in_import && self.return_pointer_area_align > 0 {
             self.src.push_str(&format!(
                 "
@@ -485,35 +749,12 @@ impl Generator for RustWasm {
             ));
         }
 
-        self.src.push_str("#[cfg(target_arch = \"wasm32\")]\n");
-
-        // The custom section name here must start with "component-type" but
-        // otherwise is attempted to be unique here to ensure that this doesn't get
-        // concatenated to other custom sections by LLD by accident since LLD will
-        // concatenate custom sections of the same name.
-        let direction = match dir {
-            Direction::Import => "import",
-            Direction::Export => "export",
-        };
-        let iface_name = &iface.name;
-        self.src.push_str(&format!(
-            "#[link_section = \"component-type:{direction}:{iface_name}\"]\n"
-        ));
-
-        let mut encoder = wit_component::ComponentEncoder::default();
-        encoder = match dir {
-            Direction::Import => encoder.imports([iface.clone()]).unwrap(),
-            Direction::Export => encoder.interface(iface.clone()).unwrap(),
-        };
-        let component_type = encoder.types_only(true).encode().expect(&format!(
-            "encoding interface {} as a component type",
-            iface.name
-        ));
-        self.src.push_str(&format!(
-            "pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; {}] = ",
-            component_type.len()
-        ));
-        self.src.push_str(&format!("{:?};\n", component_type));
+        // Recursive types have no encoding in the component model, so modules
+        // using them can't be made into components and have no component
+        // type to embed.
+        if !iface.types.iter().any(|(id, _)| iface.is_recursive(id)) {
+            self.print_component_type(iface, dir);
+        }
 
         // For standalone generation, close the export! macro
         if self.opts.standalone && dir == Direction::Export {
@@ -525,6 +766,9 @@ impl Generator for RustWasm {
         let mut src = mem::take(&mut self.src);
 
         for (name, trait_) in self.traits.iter() {
+            if trait_.is_async {
+                src.push_str("#[wit_bindgen_guest_rust::async_trait(?Send)]\n");
+            }
             src.push_str("pub trait ");
             src.push_str(&name);
             src.push_str(" {\n");
@@ -576,6 +820,9 @@ struct FunctionBindgen<'a> {
     tmp: usize,
     needs_cleanup_list: bool,
     cleanup: Vec<(String, String)>,
+    /// Whether this is the body of a helper for a recursive type, where
+    /// `cleanup_list` is a parameter rather than a local.
+    in_helper: bool,
 }
 
 impl FunctionBindgen<'_> {
@@ -589,6 +836,7 @@ impl FunctionBindgen<'_> {
             tmp: 0,
             needs_cleanup_list: false,
             cleanup: Vec::new(),
+            in_helper: false,
         }
     }
 
@@ -609,6 +857,23 @@ impl FunctionBindgen<'_> {
         }
     }
 
+    /// Moves the pending cleanup into `cleanup_list`, to be deallocated once
+    /// the import has been called.
+    fn defer_cleanup(&mut self) {
+        if self.cleanup.len() > 0 {
+            self.needs_cleanup_list = true;
+            self.push_str("cleanup_list.extend_from_slice(&[");
+            for (ptr, layout) in mem::take(&mut self.cleanup) {
+                self.push_str("(");
+                self.push_str(&ptr);
+                self.push_str(", ");
+                self.push_str(&layout);
+                self.push_str("),");
+            }
+            self.push_str("]);\n");
+        }
+    }
+
     fn declare_import(
         &mut self,
         iface: &Interface,
@@ -692,18 +957,7 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 
     fn finish_block(&mut self, operands: &mut Vec<String>) {
-        if self.cleanup.len() > 0 {
-            self.needs_cleanup_list = true;
-            self.push_str("cleanup_list.extend_from_slice(&[");
-            for (ptr, layout) in mem::take(&mut self.cleanup) {
-                self.push_str("(");
-                self.push_str(&ptr);
-                self.push_str(", ");
-                self.push_str(&layout);
-                self.push_str("),");
-            }
-            self.push_str("]);\n");
-        }
+        self.defer_cleanup();
         let (prev_src, prev_cleanup) = self.block_storage.pop().unwrap();
         let src = mem::replace(&mut self.src, prev_src);
         self.cleanup = prev_cleanup;
@@ -1055,6 +1309,69 @@ impl Bindgen for FunctionBindgen<'_> {
                 ));
             }
 
+            Instruction::BoxedOptionLower {
+                payload, realloc, ..
+            } => {
+                let body = self.blocks.pop().unwrap();
+                let tmp = self.tmp();
+                let result = format!("result{tmp}");
+                let ptr = format!("ptr{tmp}");
+                let layout = format!("layout{tmp}");
+                let size = self.gen.sizes.size(payload);
+                let align = self.gen.sizes.align(payload);
+                let operand = &operands[0];
+                // Imports are lowered from borrowed values, which may
+                // themselves be behind a reference.
+                let (operand, unbox) = if realloc.is_none() {
+                    (format!("{operand}.as_ref()"), "&**e")
+                } else {
+                    (operand.clone(), "*e")
+                };
+                self.push_str(&format!(
+                    "let {result} = match {operand} {{
+                        Some(e) => {{
+                            let e = {unbox};
+                            let {layout} = alloc::Layout::from_size_align_unchecked({size}, {align});
+                            let {ptr} = alloc::alloc({layout});
+                            if {ptr}.is_null() {{
+                                alloc::handle_alloc_error({layout});
+                            }}
+                            let base = {ptr} as i32;
+                            {body}
+                "
+                ));
+                if realloc.is_none() {
+                    // The callee doesn't take ownership of the box, so it's
+                    // deallocated once the call has returned.
+                    self.needs_cleanup_list = true;
+                    self.push_str(&format!("cleanup_list.push(({ptr}, {layout}));\n"));
+                }
+                self.push_str(
+                    "base
+                        }
+                        None => 0,
+                    };\n",
+                );
+                results.push(result);
+            }
+
+            Instruction::BoxedOptionLift { payload, .. } => {
+                let body = self.blocks.pop().unwrap();
+                let size = self.gen.sizes.size(payload);
+                let align = self.gen.sizes.align(payload);
+                let operand = &operands[0];
+                results.push(format!(
+                    "match {operand} {{
+                        0 => None,
+                        base => {{
+                            let value = {body};
+                            wit_bindgen_guest_rust::rt::dealloc(base, {size}, {align});
+                            Some(Box::new(value))
+                        }}
+                    }}"
+                ));
+            }
+
             Instruction::ResultLower {
                 results: result_types,
                 result,
@@ -1116,6 +1433,25 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(result);
             }
 
+            Instruction::RefinementCheck { .. } if unchecked => {
+                results.push(operands.pop().unwrap());
+            }
+
+            Instruction::RefinementCheck {
+                refinement, name, ..
+            } => {
+                let tmp = self.tmp();
+                let value = format!("refined{tmp}");
+                let check = rust_refinement_check(refinement, &value);
+                self.push_str(&format!("let {value} = {};\n", operands[0]));
+                self.push_str(&format!(
+                    "if !{check} {{
+                        wit_bindgen_guest_rust::rt::refinement_failed({name:?}, \"{refinement}\");
+                    }}\n"
+                ));
+                results.push(value);
+            }
+
             Instruction::EnumLift { enum_, name, .. } => {
                 let mut result = format!("match ");
                 result.push_str(&operands[0]);
@@ -1298,6 +1634,11 @@ impl Bindgen for FunctionBindgen<'_> {
 
             Instruction::CallInterface { module, func } => {
                 self.let_results(func.results.len(), results);
+                // There's no executor in the guest, so async exports are
+                // driven to completion before returning to the host.
+                if func.is_async {
+                    self.push_str("wit_bindgen_guest_rust::rt::block_on(");
+                }
                 match &func.kind {
                     FunctionKind::Freestanding => {
                         if self.gen.opts.standalone {
@@ -1315,10 +1656,22 @@ impl Bindgen for FunctionBindgen<'_> {
                             ));
                         }
                     }
+                    FunctionKind::Method { .. }
+                    | FunctionKind::Static { .. }
+                    | FunctionKind::Constructor { .. } => {
+                        self.push_str(&format!(
+                            "<super::{t} as {t}>::{}",
+                            to_rust_ident(resource_fn_name(func)),
+                            t = RustWasm::export_trait_name(iface, func),
+                        ));
+                    }
                 }
                 self.push_str("(");
                 self.push_str(&operands.join(", "));
                 self.push_str(")");
+                if func.is_async {
+                    self.push_str(")");
+                }
                 self.push_str(";\n");
             }
 
@@ -1338,6 +1691,51 @@ impl Bindgen for FunctionBindgen<'_> {
                 }
             }
 
+            Instruction::RecursiveStore {
+                ty,
+                realloc,
+                offset,
+                ..
+            } => {
+                let name = RustWasm::recursive_helper_name(iface, *ty, RecursiveHelper::Store);
+                self.gen
+                    .recursive_helpers
+                    .push((*ty, RecursiveHelper::Store));
+                let (value, addr) = (&operands[0], &operands[1]);
+                if realloc.is_some() {
+                    self.push_str(&format!("{name}({value}, {addr} + {offset});\n"));
+                } else {
+                    self.needs_cleanup_list = true;
+                    let cleanup_list = if self.in_helper {
+                        "cleanup_list"
+                    } else {
+                        "&mut cleanup_list"
+                    };
+                    self.push_str(&format!(
+                        "{name}(&{value}, {addr} + {offset}, {cleanup_list});\n"
+                    ));
+                }
+            }
+
+            Instruction::RecursiveLoad { ty, offset, .. } => {
+                let name = RustWasm::recursive_helper_name(iface, *ty, RecursiveHelper::Load);
+                self.gen
+                    .recursive_helpers
+                    .push((*ty, RecursiveHelper::Load));
+                results.push(format!("{name}({} + {offset})", operands[0]));
+            }
+
+            Instruction::RecursiveReturn { amt } => {
+                self.defer_cleanup();
+                match amt {
+                    0 => {}
+                    _ => {
+                        self.push_str(&operands.join(", "));
+                        self.push_str("\n");
+                    }
+                }
+            }
+
             Instruction::I32Load { offset } => {
                 results.push(format!("*(({} + {}) as *const i32)", operands[0], offset));
             }
@@ -1413,6 +1811,43 @@ impl Bindgen for FunctionBindgen<'_> {
 
             Instruction::Malloc { .. } => unimplemented!(),
 
+            // Imported resources are owned by the host, which refers to them by
+            // the index of their handle. Exported resources are owned by the
+            // guest, whose handles are pointers to the boxed resource.
+            Instruction::HandleLower { handle, .. } => {
+                let op = &operands[0];
+                results.push(match (self.gen.in_import, handle) {
+                    (true, Handle::Own(_)) => format!("({op}).into_raw()"),
+                    (true, Handle::Borrow(_)) => format!("({op}).0"),
+                    (false, Handle::Own(_)) => format!("Box::into_raw(Box::new({op})) as i32"),
+                    (false, Handle::Borrow(_)) => {
+                        unreachable!("borrowed handles can't be returned")
+                    }
+                });
+            }
+            Instruction::HandleLift { handle, name, .. } => {
+                let op = &operands[0];
+                let camel = name.to_upper_camel_case();
+                results.push(match (self.gen.in_import, handle) {
+                    (true, Handle::Own(_)) => format!("{camel}({op})"),
+                    (true, Handle::Borrow(_)) => {
+                        unreachable!("borrowed handles can't be returned")
+                    }
+                    (false, Handle::Own(_)) => {
+                        format!("*Box::from_raw({op} as u32 as usize as *mut super::{camel})")
+                    }
+                    (false, Handle::Borrow(_)) => {
+                        format!("&*({op} as u32 as usize as *const super::{camel})")
+                    }
+                });
+            }
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("futures and streams are rejected by `validate`")
+            }
+
             Instruction::GuestDeallocate { size, align } => {
                 self.push_str(&format!(
                     "wit_bindgen_guest_rust::rt::dealloc({}, {}, {});\n",
@@ -1446,6 +1881,30 @@ impl Bindgen for FunctionBindgen<'_> {
                 self.src.push_str("}\n");
             }
 
+            Instruction::GuestDeallocateBoxedOption { payload } => {
+                let body = self.blocks.pop().unwrap();
+                let size = self.gen.sizes.size(payload);
+                let align = self.gen.sizes.align(payload);
+                let operand = &operands[0];
+                self.push_str(&format!(
+                    "match {operand} {{
+                        0 => {{}}
+                        base => {{
+                            {body}
+                            wit_bindgen_guest_rust::rt::dealloc(base, {size}, {align});
+                        }}
+                    }}\n"
+                ));
+            }
+
+            Instruction::GuestDeallocateRecursive { ty, offset, .. } => {
+                let name = RustWasm::recursive_helper_name(iface, *ty, RecursiveHelper::Deallocate);
+                self.gen
+                    .recursive_helpers
+                    .push((*ty, RecursiveHelper::Deallocate));
+                self.push_str(&format!("{name}({} + {offset});\n", operands[0]));
+            }
+
             Instruction::GuestDeallocateList { element } => {
                 let body = self.blocks.pop().unwrap();
                 let tmp = self.tmp();
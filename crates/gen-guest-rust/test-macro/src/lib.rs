@@ -10,9 +10,19 @@ pub fn gen_dummy_export(input: TokenStream) -> TokenStream {
     let input = input.to_string();
     let input = input.trim_matches('"');
     let iface = &Interface::parse_file(&input).unwrap();
+    match dummy_export(iface) {
+        Ok(ret) => ret.into(),
+        Err(msg) => {
+            let msg = format!("cannot generate a dummy export for `{}`: {}", input, msg);
+            quote::quote!(compile_error!(#msg);).into()
+        }
+    }
+}
+
+fn dummy_export(iface: &Interface) -> Result<proc_macro2::TokenStream, String> {
     let mut ret = quote::quote!();
     if iface.functions.len() == 0 {
-        return ret.into();
+        return Ok(ret);
     }
 
     let snake = quote::format_ident!("{}", iface.name.to_snake_case());
@@ -26,12 +36,12 @@ pub fn gen_dummy_export(input: TokenStream) -> TokenStream {
             .params
             .iter()
             .map(|(_, t)| quote_ty(true, iface, t))
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
         let rets = f
             .results
             .iter_types()
             .map(|t| quote_ty(false, iface, t))
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
         let ret = match rets.len() {
             0 => quote::quote!(()),
             1 => rets[0].clone(),
@@ -44,6 +54,11 @@ pub fn gen_dummy_export(input: TokenStream) -> TokenStream {
         };
         match &f.kind {
             FunctionKind::Freestanding => methods.push(method),
+            FunctionKind::Method { .. }
+            | FunctionKind::Static { .. }
+            | FunctionKind::Constructor { .. } => {
+                return Err(format!("resource function `{}` is unsupported", f.name))
+            }
         }
     }
     ret.extend(quote::quote! {
@@ -54,11 +69,11 @@ pub fn gen_dummy_export(input: TokenStream) -> TokenStream {
         }
     });
 
-    ret.into()
+    Ok(ret)
 }
 
-fn quote_ty(param: bool, iface: &Interface, ty: &Type) -> proc_macro2::TokenStream {
-    match *ty {
+fn quote_ty(param: bool, iface: &Interface, ty: &Type) -> Result<proc_macro2::TokenStream, String> {
+    Ok(match *ty {
         Type::Bool => quote::quote! { bool },
         Type::U8 => quote::quote! { u8 },
         Type::S8 => quote::quote! { i8 },
@@ -72,21 +87,25 @@ fn quote_ty(param: bool, iface: &Interface, ty: &Type) -> proc_macro2::TokenStre
         Type::Float64 => quote::quote! { f64 },
         Type::Char => quote::quote! { char },
         Type::String => quote::quote! { String },
-        Type::Id(id) => quote_id(param, iface, id),
-    }
+        Type::Id(id) => quote_id(param, iface, id)?,
+    })
 }
 
-fn quote_id(param: bool, iface: &Interface, id: TypeId) -> proc_macro2::TokenStream {
+fn quote_id(
+    param: bool,
+    iface: &Interface,
+    id: TypeId,
+) -> Result<proc_macro2::TokenStream, String> {
     let ty = &iface.types[id];
     if let Some(name) = &ty.name {
         let name = quote::format_ident!("{}", name.to_upper_camel_case());
         let module = quote::format_ident!("{}", iface.name.to_snake_case());
-        return quote::quote! { #module::#name };
+        return Ok(quote::quote! { #module::#name });
     }
-    match &ty.kind {
-        TypeDefKind::Type(t) => quote_ty(param, iface, t),
+    Ok(match &ty.kind {
+        TypeDefKind::Type(t) => quote_ty(param, iface, t)?,
         TypeDefKind::List(t) => {
-            let t = quote_ty(param, iface, t);
+            let t = quote_ty(param, iface, t)?;
             quote::quote! { Vec<#t> }
         }
         TypeDefKind::Flags(_) => panic!("unknown flags"),
@@ -95,25 +114,33 @@ fn quote_id(param: bool, iface: &Interface, id: TypeId) -> proc_macro2::TokenStr
         TypeDefKind::Variant(_) => panic!("unknown variant"),
         TypeDefKind::Union(_) => panic!("unknown union"),
         TypeDefKind::Tuple(t) => {
-            let fields = t.types.iter().map(|ty| quote_ty(param, iface, ty));
+            let fields = t
+                .types
+                .iter()
+                .map(|ty| quote_ty(param, iface, ty))
+                .collect::<Result<Vec<_>, _>>()?;
             quote::quote! { (#(#fields,)*) }
         }
         TypeDefKind::Option(ty) => {
-            let ty = quote_ty(param, iface, ty);
+            let ty = quote_ty(param, iface, ty)?;
             quote::quote! { Option<#ty> }
         }
         TypeDefKind::Result(r) => {
             let ok = match &r.ok {
-                Some(t) => quote_ty(param, iface, t),
+                Some(t) => quote_ty(param, iface, t)?,
                 None => quote::quote!(()),
             };
             let err = match &r.err {
-                Some(t) => quote_ty(param, iface, t),
+                Some(t) => quote_ty(param, iface, t)?,
                 None => quote::quote!(()),
             };
             quote::quote! { Result<#ok, #err> }
         }
-        TypeDefKind::Future(_) => todo!("unknown future"),
-        TypeDefKind::Stream(_) => todo!("unknown stream"),
-    }
+        TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+            return Err("futures and streams are unsupported".to_string())
+        }
+        TypeDefKind::Resource | TypeDefKind::Handle(_) => {
+            return Err("resources are unsupported".to_string())
+        }
+    })
 }
@@ -76,3 +76,12 @@ mod raw_strings {
         let _t: Vec<u8> = cat::bar();
     }
 }
+
+#[test]
+fn unsupported() {
+    test_helpers::assert_unsupported(
+        wit_bindgen_gen_guest_rust::Opts::default().build(),
+        "f: func(x: future<u32>) -> stream<u8>",
+        "`future` and `stream` types",
+    );
+}
@@ -1,13 +1,15 @@
 use heck::{ToLowerCamelCase, ToShoutySnakeCase, ToSnakeCase, ToUpperCamelCase};
 use std::{collections::HashSet, fmt::Write, iter, mem, ops::Deref};
+use wit_bindgen_core::registry::Registration;
 use wit_bindgen_core::{
+    lint::{Item, Naming},
     uwrite, uwriteln,
     wit_parser::{
         abi::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType},
-        Case, Docs, Enum, Flags, FlagsRepr, Function, FunctionKind, Int, Interface, Record,
-        Result_, SizeAlign, Tuple, Type, TypeDefKind, TypeId, Union, Variant,
+        Case, Constant, Docs, Enum, Flags, FlagsRepr, Function, FunctionKind, Int, Interface,
+        Literal, Record, Result_, SizeAlign, Tuple, Type, TypeDefKind, TypeId, Union, Variant,
     },
-    Direction, Files, Generator, Ns,
+    Direction, Files, Generator, Ns, Types,
 };
 
 #[derive(Default)]
@@ -16,6 +18,7 @@ pub struct TeaVmJava {
     src: String,
     stub: String,
     sizes: SizeAlign,
+    types: Types,
     tuple_counts: HashSet<usize>,
     return_area_size: usize,
     return_area_align: usize,
@@ -40,6 +43,24 @@ impl Opts {
     }
 }
 
+/// Describes this generator for a [`Registry`](wit_bindgen_core::registry::Registry).
+pub fn registration() -> Registration {
+    Registration::new(
+        "guest-teavm-java",
+        "Generates bindings for TeaVM-based Java guest modules.",
+        |options| {
+            let opts = Opts {
+                generate_stub: options.flag("generate-stub"),
+            };
+            Box::new(opts.build())
+        },
+    )
+    .flag(
+        "generate-stub",
+        "Whether or not to generate a stub class for exported functions",
+    )
+}
+
 impl TeaVmJava {
     fn type_name(&mut self, iface: &Interface, ty: &Type) -> String {
         self.type_name_with_qualifier(iface, ty, None)
@@ -109,11 +130,11 @@ impl TeaVmJava {
                         format!("{}Result<{ok}, {err}>", qualifier.unwrap_or(""))
                     }
                     _ => {
-                        if let Some(name) = &ty.name {
-                            format!("{}{}", qualifier.unwrap_or(""), name.to_upper_camel_case())
-                        } else {
-                            unreachable!()
-                        }
+                        let name = match &ty.name {
+                            Some(name) => name.as_str(),
+                            None => self.types.anonymous_name(*id),
+                        };
+                        format!("{}{}", qualifier.unwrap_or(""), name.to_upper_camel_case())
                     }
                 }
             }
@@ -141,10 +162,26 @@ impl TeaVmJava {
     }
 
     fn print_docs(&mut self, docs: &Docs) {
-        if let Some(docs) = &docs.contents {
-            let lines = docs
-                .trim()
-                .lines()
+        let mut lines = Vec::new();
+        if let Some(contents) = &docs.contents {
+            lines.extend(contents.trim().lines().map(|line| line.to_string()));
+        }
+        if let Some(since) = &docs.since {
+            lines.push(format!("@since {since}"));
+        }
+        if let Some(deprecated) = &docs.deprecated {
+            let mut tag = "@deprecated".to_string();
+            if let Some(since) = &deprecated.since {
+                tag.push_str(&format!(" Since {since}."));
+            }
+            if let Some(note) = &deprecated.note {
+                tag.push_str(&format!(" {note}"));
+            }
+            lines.push(tag);
+        }
+        if !lines.is_empty() {
+            let lines = lines
+                .iter()
                 .map(|line| format!("* {line}"))
                 .collect::<Vec<_>>()
                 .join("\n");
@@ -158,6 +195,7 @@ impl TeaVmJava {
                 "
             )
         }
+        self.src.push_str(deprecated_annotation(docs));
     }
 
     fn non_empty_type<'a>(&self, iface: &'a Interface, ty: Option<&'a Type>) -> Option<&'a Type> {
@@ -222,6 +260,20 @@ impl TeaVmJava {
 }
 
 impl Generator for TeaVmJava {
+    fn name(&self) -> &str {
+        "guest-teavm-java"
+    }
+
+    // Flags are represented as a single integer of at most 64 bits.
+    fn unsupported_type(&self, iface: &Interface, id: TypeId) -> Option<String> {
+        match &iface.types[id].kind {
+            TypeDefKind::Flags(flags) if flags.flags.len() > 64 => {
+                Some("flags with more than 64 members".to_string())
+            }
+            _ => None,
+        }
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, _dir: Direction) {
         let package = format!("wit_{}", iface.name.to_snake_case());
         let name = iface.name.to_upper_camel_case();
@@ -256,6 +308,7 @@ impl Generator for TeaVmJava {
         }
 
         self.sizes.fill(iface);
+        self.types.analyze(iface);
     }
 
     fn type_record(
@@ -298,7 +351,8 @@ impl Generator for TeaVmJava {
             .iter()
             .map(|field| {
                 format!(
-                    "public final {} {};",
+                    "{}public final {} {};",
+                    deprecated_annotation(&field.docs),
                     self.type_name(iface, &field.ty),
                     field.name.to_lower_camel_case()
                 )
@@ -337,7 +391,7 @@ impl Generator for TeaVmJava {
             FlagsRepr::U16 => "short",
             FlagsRepr::U32(1) => "int",
             FlagsRepr::U32(2) => "long",
-            repr => todo!("flags {repr:?}"),
+            FlagsRepr::U32(_) => unreachable!("flags with more than 64 members are unsupported"),
         };
 
         let flags = flags
@@ -351,8 +405,9 @@ impl Generator for TeaVmJava {
                 } else {
                     ""
                 };
+                let deprecated = deprecated_annotation(&flag.docs);
                 format!(
-                    "public static final {name} {flag_name} = new {name}(({ty}) (1{suffix} << {i}));"
+                    "{deprecated}public static final {name} {flag_name} = new {name}(({ty}) (1{suffix} << {i}));"
                 )
             })
             .collect::<Vec<_>>()
@@ -414,8 +469,9 @@ impl Generator for TeaVmJava {
                         (String::new(), "null")
                     };
 
+                let deprecated = deprecated_annotation(&case.docs);
                 format!(
-                    "public static {name} {case_name}({parameter}) {{
+                    "{deprecated}public static {name} {case_name}({parameter}) {{
                          return new {name}({tag}, {argument});
                      }}
                     "
@@ -519,6 +575,7 @@ impl Generator for TeaVmJava {
                     .enumerate()
                     .map(|(i, case)| Case {
                         docs: case.docs.clone(),
+                        attrs: case.attrs.clone(),
                         name: format!("f{i}"),
                         ty: Some(case.ty),
                     })
@@ -543,7 +600,13 @@ impl Generator for TeaVmJava {
         let cases = enum_
             .cases
             .iter()
-            .map(|case| case.name.to_shouty_snake_case())
+            .map(|case| {
+                format!(
+                    "{}{}",
+                    deprecated_annotation(&case.docs),
+                    case.name.to_shouty_snake_case()
+                )
+            })
             .collect::<Vec<_>>()
             .join(", ");
 
@@ -576,9 +639,35 @@ impl Generator for TeaVmJava {
         unimplemented!();
     }
 
+    fn constant(&mut self, iface: &Interface, constant: &Constant) {
+        self.print_docs(&constant.docs);
+
+        let ty = iface.unalias(&constant.ty);
+        let ty_name = self.type_name(iface, &ty);
+        let name = constant.name.to_shouty_snake_case();
+        let value = match (ty, &constant.value) {
+            // Java's integers are all signed, so unsigned values are written
+            // with the same bits as their signed counterparts.
+            (Type::U8 | Type::S8, Literal::Integer(i)) => format!("(byte) {}", *i as i8),
+            (Type::U16 | Type::S16, Literal::Integer(i)) => format!("(short) {}", *i as i16),
+            (Type::U32 | Type::S32, Literal::Integer(i)) => format!("{}", *i as i32),
+            (Type::U64 | Type::S64, Literal::Integer(i)) => format!("{}L", *i as i64),
+            (Type::Float32, Literal::Float(f)) => format!("{:?}f", f),
+            (Type::Char, Literal::Char(c)) => format!("{}", u32::from(*c)),
+            (_, value) => value.to_string(),
+        };
+
+        uwrite!(
+            self.src,
+            "
+            public static final {ty_name} {name} = {value};
+            "
+        );
+    }
+
     fn import(&mut self, iface: &Interface, func: &Function) {
         if func.kind != FunctionKind::Freestanding {
-            todo!("resources");
+            unreachable!("resources are unsupported");
         }
 
         let mut bindgen = FunctionBindgen::new(
@@ -634,8 +723,14 @@ impl Generator for TeaVmJava {
             self.src,
             r#"@Import(name = "{name}", module = "{module}")
                private static native {result_type} wasmImport{camel_name}({params});
+            "#
+        );
 
-               {sig} {{
+        self.print_docs(&func.docs);
+
+        uwrite!(
+            self.src,
+            r#"{sig} {{
                    {cleanup_list} {src}
                }}
             "#
@@ -730,11 +825,12 @@ impl Generator for TeaVmJava {
         if self.opts.generate_stub {
             let class = iface.name.to_upper_camel_case();
             let sig = self.sig_string(iface, func, Some(&format!("{class}.")));
+            let deprecated = deprecated_annotation(&func.docs);
 
             uwrite!(
                 self.stub,
                 r#"
-                {sig} {{
+                {deprecated}{sig} {{
                     throw new RuntimeException("todo");
                 }}
                 "#
@@ -879,6 +975,90 @@ impl Generator for TeaVmJava {
     }
 }
 
+/// Java's keywords along with the `true`, `false` and `null` literals.
+const JAVA_KEYWORDS: &[&str] = &[
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "try",
+    "void",
+    "volatile",
+    "while",
+    "true",
+    "false",
+    "null",
+];
+
+/// The [`Naming`] of generated Java bindings.
+pub struct JavaNaming;
+
+impl Naming for JavaNaming {
+    fn language(&self) -> &'static str {
+        "java"
+    }
+
+    fn convert(&self, item: &Item<'_>) -> Option<String> {
+        let name = item.name();
+        Some(match item {
+            Item::Type(_) => name.to_upper_camel_case(),
+            // Each case of a variant has a static method to create it.
+            Item::Case { .. } | Item::Field { .. } | Item::Function { .. } | Item::Param { .. } => {
+                name.to_lower_camel_case()
+            }
+            Item::EnumCase { .. } | Item::Flag { .. } | Item::Constant(_) => {
+                name.to_shouty_snake_case()
+            }
+        })
+    }
+
+    fn is_reserved(&self, _item: &Item<'_>, ident: &str) -> bool {
+        JAVA_KEYWORDS.contains(&ident)
+    }
+}
+
 struct Block {
     body: String,
     results: Vec<String>,
@@ -1406,6 +1586,9 @@ impl Bindgen for FunctionBindgen<'_> {
                 operands[0]
             )),
 
+            // TODO: check refinements
+            Instruction::RefinementCheck { .. } => results.push(operands[0].clone()),
+
             Instruction::ListCanonLower { element, realloc } => {
                 let op = &operands[0];
                 let (size, ty) = list_element_info(element);
@@ -1781,6 +1964,26 @@ impl Bindgen for FunctionBindgen<'_> {
 
             Instruction::Malloc { .. } => unimplemented!(),
 
+            Instruction::HandleLower { .. } | Instruction::HandleLift { .. } => {
+                unreachable!("resources are unsupported")
+            }
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("futures and streams are rejected by `validate`")
+            }
+
+            Instruction::BoxedOptionLower { .. }
+            | Instruction::BoxedOptionLift { .. }
+            | Instruction::RecursiveStore { .. }
+            | Instruction::RecursiveLoad { .. }
+            | Instruction::RecursiveReturn { .. }
+            | Instruction::GuestDeallocateBoxedOption { .. }
+            | Instruction::GuestDeallocateRecursive { .. } => {
+                unreachable!("recursive types are unsupported")
+            }
+
             Instruction::GuestDeallocate { size, align } => {
                 uwriteln!(
                     self.src,
@@ -1921,6 +2124,16 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 }
 
+/// Returns the `@Deprecated` annotation to put before an item, if it's
+/// deprecated.
+fn deprecated_annotation(docs: &Docs) -> &'static str {
+    if docs.deprecated.is_some() {
+        "@Deprecated\n"
+    } else {
+        ""
+    }
+}
+
 fn int_type(int: Int) -> &'static str {
     match int {
         Int::U8 => "byte",
@@ -40,6 +40,15 @@ mod imports {
     test_helpers::codegen_tests!("*.wit");
 }
 
+#[test]
+fn unsupported() {
+    test_helpers::assert_unsupported(
+        wit_bindgen_gen_guest_teavm_java::Opts::default().build(),
+        "f: func(x: future<u32>) -> stream<u8>",
+        "`future` and `stream` types",
+    );
+}
+
 fn verify(dir: &Path, name: &str) {
     let java_dir = &dir.join("src/main/java");
     let package_dir = &java_dir.join(format!("wit_{}", name.to_snake_case()));
@@ -11,10 +11,11 @@ use wasmtime_environ::component::{
     StringEncoding, Translator,
 };
 use wasmtime_environ::{EntityIndex, ModuleTranslation, PrimaryMap, ScopeVec, Tunables};
+use wit_bindgen_core::lint::{Item, Namespace, Naming};
 use wit_bindgen_core::wit_parser::abi::{
     AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
 };
-use wit_bindgen_core::{uwrite, uwriteln, wit_parser::*, Files, InterfaceGenerator};
+use wit_bindgen_core::{uwrite, uwriteln, wit_parser::*, Files, InterfaceGenerator, Types};
 use wit_component::ComponentInterfaces;
 
 #[derive(Default)]
@@ -48,6 +49,7 @@ struct JsInterface<'a> {
     src: Source,
     gen: &'a mut Js,
     iface: &'a Interface,
+    types: Types,
     needs_ty_option: bool,
     needs_ty_result: bool,
 }
@@ -137,9 +139,7 @@ impl Opts {
         // `wit-bindgen` to generate bindings for JS.
         let mut gen = Js::default();
         gen.opts = self;
-        gen.generate(name, &component, &modules, &interfaces, files);
-
-        Ok(())
+        gen.generate(name, &component, &modules, &interfaces, files)
     }
 }
 
@@ -174,7 +174,11 @@ enum Intrinsic {
     Utf8Decoder,
     Utf8Encode,
     Utf8EncodedLen,
+    /// The length of a string once encoded as UTF-8, which is what the `len`
+    /// refinement of strings constrains.
+    Utf8Length,
     ThrowInvalidBool,
+    RefinementError,
 }
 
 impl Intrinsic {
@@ -200,7 +204,9 @@ impl Intrinsic {
             Intrinsic::Utf8Decoder => "UTF8_DECODER",
             Intrinsic::Utf8Encode => "utf8_encode",
             Intrinsic::Utf8EncodedLen => "UTF8_ENCODED_LEN",
+            Intrinsic::Utf8Length => "utf8_length",
             Intrinsic::ThrowInvalidBool => "throw_invalid_bool",
+            Intrinsic::RefinementError => "RefinementError",
         }
     }
 }
@@ -213,7 +219,7 @@ impl Js {
         modules: &PrimaryMap<StaticModuleIndex, ModuleTranslation<'_>>,
         interfaces: &ComponentInterfaces<'_>,
         files: &mut Files,
-    ) {
+    ) -> Result<()> {
         // Generate a TypeScript description of all interfaces found within the
         // component. Each interface gets its own TypeScript file to prevent
         // name clashes between them.
@@ -231,12 +237,15 @@ impl Js {
             let camel = name.to_upper_camel_case();
             assert_eq!(iface.name, *name);
             let mut gen = self.js_interface(iface);
+            gen.validate()?;
             gen.types();
+            gen.constants();
             gen.post_types();
 
             uwriteln!(gen.src.ts, "export interface {camel} {{");
             for func in iface.functions.iter() {
-                gen.ts_func(func);
+                let promise = !is_import && func.is_async;
+                gen.ts_func(func, promise);
             }
             uwriteln!(gen.src.ts, "}}");
 
@@ -311,7 +320,8 @@ impl Js {
             Some(iface) => {
                 let mut gen = self.js_interface(iface);
                 for func in iface.functions.iter() {
-                    gen.ts_func(func);
+                    let promise = func.is_async;
+                    gen.ts_func(func, promise);
                 }
                 gen.gen.src.ts(&mem::take(&mut gen.src.ts));
                 uwriteln!(gen.gen.src.ts, "}}");
@@ -319,6 +329,7 @@ impl Js {
                 // After the default interface has its function definitions
                 // inlined the rest of the types are generated here as well.
                 gen.types();
+                gen.constants();
                 gen.post_types();
                 gen.gen.src.ts(&mem::take(&mut gen.src.ts));
             }
@@ -349,10 +360,13 @@ impl Js {
     }
 
     fn js_interface<'a>(&'a mut self, iface: &'a Interface) -> JsInterface<'a> {
+        let mut types = Types::default();
+        types.analyze(iface);
         JsInterface {
             src: Source::default(),
             gen: self,
             iface,
+            types,
             needs_ty_option: false,
             needs_ty_result: false,
         }
@@ -556,11 +570,45 @@ impl Js {
                 }
             "),
 
+            Intrinsic::Utf8Length => self.src.js("
+                function utf8_length(s) {
+                    let len = 0;
+                    for (const c of s) {
+                        const p = c.codePointAt(0);
+                        len += p < 0x80 ? 1 : p < 0x800 ? 2 : p < 0x10000 ? 3 : 4;
+                    }
+                    return len;
+                }
+            "),
+
             Intrinsic::ThrowInvalidBool => self.src.js("
                 function throw_invalid_bool() {
                     throw new RangeError(\"invalid variant discriminant for bool\");
                 }
             "),
+
+            Intrinsic::RefinementError => {
+                self.src.js("
+                    export class RefinementError extends RangeError {
+                        constructor(type, refinement) {
+                            super(`value of \\`${type}\\` does not satisfy \\`where ${refinement}\\``);
+                            this.name = 'RefinementError';
+                            this.type = type;
+                            this.refinement = refinement;
+                        }
+                    }
+                ");
+                self.src.ts("
+                    /**
+                    * Thrown when a value doesn't satisfy the `where` refinement
+                    * of its type.
+                    */
+                    export class RefinementError extends RangeError {
+                        type: string;
+                        refinement: string;
+                    }
+                ");
+            }
         }
 
         name
@@ -905,10 +953,15 @@ impl Instantiator<'_> {
                     options,
                 } => {
                     assert!(depth < 2);
-                    uwrite!(self.src.js, "{camel}");
                     let callee = self.core_def(func);
                     let iface = iface.unwrap();
                     let func = iface.functions.iter().find(|f| f.name == *name).unwrap();
+                    // Async exports are still called synchronously, but are
+                    // exposed as `async` methods which return a `Promise`.
+                    if func.is_async {
+                        self.src.js("async ");
+                    }
+                    uwrite!(self.src.js, "{camel}");
                     self.bindgen(
                         func.params.len(),
                         callee,
@@ -947,10 +1000,42 @@ impl<'a> JsInterface<'a> {
     }
 
     fn docs(&mut self, docs: &Docs) {
-        match &docs.contents {
-            Some(docs) => self.docs_raw(docs),
-            None => return,
+        self.docs_with_defaults(docs, &[]);
+    }
+
+    /// Prints `docs` followed by a line for each of `defaults`, which
+    /// describe default values that TypeScript has no way to express, and
+    /// then the `@since` and `@deprecated` tags of the item.
+    fn docs_with_defaults(&mut self, docs: &Docs, defaults: &[String]) {
+        let mut paragraphs = Vec::new();
+        if let Some(contents) = &docs.contents {
+            paragraphs.push(contents.trim_end().to_string());
+        }
+        if !defaults.is_empty() {
+            paragraphs.push(defaults.join("\n"));
+        }
+        let mut tags = Vec::new();
+        if let Some(since) = &docs.since {
+            tags.push(format!("@since {}", since));
+        }
+        if let Some(deprecated) = &docs.deprecated {
+            let mut tag = "@deprecated".to_string();
+            if let Some(since) = &deprecated.since {
+                tag.push_str(&format!(" Since {}.", since));
+            }
+            if let Some(note) = &deprecated.note {
+                tag.push(' ');
+                tag.push_str(note);
+            }
+            tags.push(tag);
+        }
+        if !tags.is_empty() {
+            paragraphs.push(tags.join("\n"));
+        }
+        if paragraphs.is_empty() {
+            return;
         }
+        self.docs_raw(&paragraphs.join("\n\n"));
     }
 
     fn array_ty(&self, ty: &Type) -> Option<&'static str> {
@@ -979,10 +1064,6 @@ impl<'a> JsInterface<'a> {
                 match &ty.kind {
                     TypeDefKind::Type(t) => self.print_ty(t),
                     TypeDefKind::Tuple(t) => self.print_tuple(t),
-                    TypeDefKind::Record(_) => panic!("anonymous record"),
-                    TypeDefKind::Flags(_) => panic!("anonymous flags"),
-                    TypeDefKind::Enum(_) => panic!("anonymous enum"),
-                    TypeDefKind::Union(_) => panic!("anonymous union"),
                     TypeDefKind::Option(t) => {
                         if self.maybe_null(t) {
                             self.needs_ty_option = true;
@@ -1002,10 +1083,13 @@ impl<'a> JsInterface<'a> {
                         self.print_optional_ty(r.err.as_ref());
                         self.src.ts(">");
                     }
-                    TypeDefKind::Variant(_) => panic!("anonymous variant"),
                     TypeDefKind::List(v) => self.print_list(v),
-                    TypeDefKind::Future(_) => todo!("anonymous future"),
-                    TypeDefKind::Stream(_) => todo!("anonymous stream"),
+                    // Anything else has no TypeScript spelling of its own, so
+                    // it's referred to by its canonical name.
+                    _ => {
+                        let name = self.types.anonymous_name(*id).to_upper_camel_case();
+                        self.src.ts(&name);
+                    }
                 }
             }
         }
@@ -1039,16 +1123,30 @@ impl<'a> JsInterface<'a> {
         self.src.ts("]");
     }
 
-    fn ts_func(&mut self, func: &Function) {
-        self.docs(&func.docs);
+    fn ts_func(&mut self, func: &Function, promise: bool) {
+        let param_start = match &func.kind {
+            FunctionKind::Freestanding
+            | FunctionKind::Static { .. }
+            | FunctionKind::Constructor { .. } => 0,
+            FunctionKind::Method { .. } => 1,
+        };
+
+        let defaults = func.params[param_start..]
+            .iter()
+            .zip(&func.defaults[param_start..])
+            .filter_map(|((name, _), default)| {
+                Some(format!(
+                    "`{}` defaults to `{}`.",
+                    to_js_ident(&name.to_lower_camel_case()),
+                    js_literal(default.as_ref()?),
+                ))
+            })
+            .collect::<Vec<_>>();
+        self.docs_with_defaults(&func.docs, &defaults);
 
         self.src.ts(&func.item_name().to_lower_camel_case());
         self.src.ts("(");
 
-        let param_start = match &func.kind {
-            FunctionKind::Freestanding => 0,
-        };
-
         for (i, (name, ty)) in func.params[param_start..].iter().enumerate() {
             if i > 0 {
                 self.src.ts(", ");
@@ -1058,6 +1156,9 @@ impl<'a> JsInterface<'a> {
             self.print_ty(ty);
         }
         self.src.ts("): ");
+        if promise {
+            self.src.ts("Promise<");
+        }
         match func.results.len() {
             0 => self.src.ts("void"),
             1 => self.print_ty(func.results.iter_types().next().unwrap()),
@@ -1072,6 +1173,9 @@ impl<'a> JsInterface<'a> {
                 self.src.ts("]");
             }
         }
+        if promise {
+            self.src.ts(">");
+        }
         self.src.ts(";\n");
     }
 
@@ -1104,6 +1208,10 @@ impl<'a> InterfaceGenerator<'a> for JsInterface<'a> {
         self.iface
     }
 
+    fn name(&self) -> &str {
+        "host-js"
+    }
+
     fn type_record(&mut self, _id: TypeId, name: &str, record: &Record, docs: &Docs) {
         self.docs(docs);
         self.src.ts(&format!(
@@ -1111,7 +1219,12 @@ impl<'a> InterfaceGenerator<'a> for JsInterface<'a> {
             name.to_upper_camel_case()
         ));
         for field in record.fields.iter() {
-            self.docs(&field.docs);
+            let defaults = field
+                .default
+                .iter()
+                .map(|default| format!("Defaults to `{}`.", js_literal(default)))
+                .collect::<Vec<_>>();
+            self.docs_with_defaults(&field.docs, &defaults);
             let (option_str, ty) = self
                 .as_nullable(&field.ty)
                 .map_or(("", &field.ty), |ty| ("?", ty));
@@ -1281,6 +1394,19 @@ impl<'a> InterfaceGenerator<'a> for JsInterface<'a> {
     fn type_builtin(&mut self, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
         drop((_id, name, ty, docs));
     }
+
+    fn constant(&mut self, constant: &Constant) {
+        self.docs(&constant.docs);
+        let value = match (&constant.value, self.iface.unalias(&constant.ty)) {
+            (Literal::Integer(i), Type::U64 | Type::S64) => format!("{i}n"),
+            (other, _) => js_literal(other),
+        };
+        self.src.ts(&format!(
+            "export const {} = {};\n",
+            constant.name.to_shouty_snake_case(),
+            value
+        ));
+    }
 }
 
 struct FunctionBindgen<'a> {
@@ -1373,7 +1499,7 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 
     fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
-        unimplemented!()
+        unreachable!("only guests provide return pointers")
     }
 
     fn is_list_canonical(&self, iface: &Interface, ty: &Type) -> bool {
@@ -2046,6 +2172,46 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(format!("enum{tmp}"));
             }
 
+            Instruction::RefinementCheck {
+                refinement,
+                name,
+                ty,
+            } => {
+                let tmp = self.tmp();
+                let value = format!("refined{tmp}");
+                let check = match refinement {
+                    Refinement::Range(range) => {
+                        // 64-bit integers are lifted as `BigInt`s which can't
+                        // be compared with plain numbers.
+                        let suffix = match iface.unalias(&Type::Id(*ty)) {
+                            Type::U64 | Type::S64 => "n",
+                            _ => "",
+                        };
+                        js_range_check(range, &value, suffix)
+                    }
+                    Refinement::Length(range) => {
+                        let len = match iface.unalias(&Type::Id(*ty)) {
+                            Type::String => {
+                                let utf8_length = self.gen.intrinsic(Intrinsic::Utf8Length);
+                                format!("{utf8_length}({value})")
+                            }
+                            _ => format!("{value}.length"),
+                        };
+                        js_range_check(range, &len, "")
+                    }
+                };
+                let error = self.gen.intrinsic(Intrinsic::RefinementError);
+                uwriteln!(
+                    self.src.js,
+                    "const {value} = {};
+                    if (!({check})) {{
+                        throw new {error}(\"{name}\", \"{refinement}\");
+                    }}",
+                    operands[0],
+                );
+                results.push(value);
+            }
+
             Instruction::EnumLift { name, enum_, .. } => {
                 let tmp = self.tmp();
 
@@ -2252,11 +2418,60 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(ptr);
             }
 
-            i => unimplemented!("{:?}", i),
+            Instruction::HandleLower { .. } | Instruction::HandleLift { .. } => {
+                unreachable!("resources are rejected by `validate`")
+            }
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("futures and streams are rejected by `validate`")
+            }
+
+            Instruction::BoxedOptionLower { .. }
+            | Instruction::BoxedOptionLift { .. }
+            | Instruction::RecursiveStore { .. }
+            | Instruction::RecursiveLoad { .. }
+            | Instruction::RecursiveReturn { .. } => {
+                unreachable!("recursive types are rejected by `validate`")
+            }
+
+            Instruction::GuestDeallocate { .. }
+            | Instruction::GuestDeallocateString
+            | Instruction::GuestDeallocateList { .. }
+            | Instruction::GuestDeallocateVariant { .. }
+            | Instruction::GuestDeallocateBoxedOption { .. }
+            | Instruction::GuestDeallocateRecursive { .. } => {
+                unreachable!("hosts don't deallocate")
+            }
         }
     }
 }
 
+/// Returns a JS expression which is `true` if `value` lies within `range`,
+/// with `suffix` appended to each bound.
+fn js_range_check(range: &Range, value: &str, suffix: &str) -> String {
+    let mut checks = Vec::new();
+    if let Some(start) = &range.start {
+        checks.push(format!("{start}{suffix} <= {value}"));
+    }
+    if let Some(end) = &range.end {
+        let op = if range.inclusive { "<=" } else { "<" };
+        checks.push(format!("{value} {op} {end}{suffix}"));
+    }
+    checks.join(" && ")
+}
+
+fn js_literal(literal: &Literal) -> String {
+    match literal {
+        // Rust's escapes for strings, including `\u{...}`, are valid in
+        // JavaScript as well.
+        Literal::Char(c) => format!("{:?}", c.to_string()),
+        Literal::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
+
 fn to_js_ident(name: &str) -> &str {
     match name {
         "in" => "in_",
@@ -2265,6 +2480,109 @@ fn to_js_ident(name: &str) -> &str {
     }
 }
 
+/// JavaScript's reserved words in strict mode, which can't name parameters.
+const JS_RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// The [`Naming`] of generated JavaScript and TypeScript bindings.
+pub struct JsNaming;
+
+impl Naming for JsNaming {
+    fn language(&self) -> &'static str {
+        "js"
+    }
+
+    fn convert(&self, item: &Item<'_>) -> Option<String> {
+        let name = item.name();
+        Some(match item {
+            Item::Type(_) => name.to_upper_camel_case(),
+            Item::Case { ty, name } => format!("{ty}-{name}").to_upper_camel_case(),
+            // Enum cases are represented as strings.
+            Item::EnumCase { .. } => return None,
+            Item::Field { .. } | Item::Flag { .. } | Item::Function { .. } | Item::Param { .. } => {
+                name.to_lower_camel_case()
+            }
+            Item::Constant(_) => name.to_shouty_snake_case(),
+        })
+    }
+
+    fn escape(&self, item: &Item<'_>, ident: &str) -> Option<String> {
+        match item {
+            Item::Param { .. } if to_js_ident(ident) != ident => {
+                Some(to_js_ident(ident).to_string())
+            }
+            _ => None,
+        }
+    }
+
+    fn is_reserved(&self, item: &Item<'_>, ident: &str) -> bool {
+        // Fields, flags and functions are properties, which may be named
+        // anything, while types share a module with these helpers.
+        match item {
+            Item::Param { .. } => JS_RESERVED.contains(&ident),
+            Item::Type(_) | Item::Case { .. } => ["Option", "Result"].contains(&ident),
+            _ => false,
+        }
+    }
+
+    fn namespace<'a>(&self, item: &Item<'a>) -> Namespace<'a> {
+        match item {
+            // Each case of a variant is its own TypeScript type alongside the
+            // variant.
+            Item::Case { .. } => Namespace::Interface,
+            _ => item.namespace(),
+        }
+    }
+}
+
 #[derive(Default)]
 struct Source {
     js: wit_bindgen_core::Source,
@@ -2279,3 +2597,35 @@ impl Source {
         self.ts.push_str(s);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{InterfaceGenerator, Js};
+    use wit_bindgen_core::wit_parser::Interface;
+    use wit_bindgen_core::UnsupportedFeature;
+
+    // Components can't describe futures, streams, resources or recursive
+    // types, so this checks the interface directly rather than going through
+    // `Opts::generate`.
+    #[test]
+    fn unsupported() {
+        let iface =
+            Interface::parse("unsupported", "f: func(x: future<u32>) -> stream<u8>").unwrap();
+        let mut js = Js::default();
+        let err = js.js_interface(&iface).validate().unwrap_err();
+        let err = err.downcast::<UnsupportedFeature>().unwrap();
+        assert_eq!(err.generator, "host-js");
+        assert_eq!(err.construct, "`future` and `stream` types");
+    }
+
+    #[test]
+    fn recursive() {
+        let iface =
+            Interface::parse("recursive", "variant tree { leaf(u32), node(list<tree>) }").unwrap();
+        let mut js = Js::default();
+        let err = js.js_interface(&iface).validate().unwrap_err();
+        let err = err.downcast::<UnsupportedFeature>().unwrap();
+        assert_eq!(err.generator, "host-js");
+        assert_eq!(err.construct, "recursive types");
+    }
+}
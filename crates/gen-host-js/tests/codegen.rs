@@ -51,3 +51,56 @@ fn verify(dir: &Path, name: &str) {
             .arg(dir.join(&format!("{}.js", name))),
     );
 }
+
+/// Generates bindings for a component whose default interface is `wit`,
+/// returning the generated JS and TypeScript.
+fn generate_default(wit: &str) -> (String, String) {
+    let iface = wit_bindgen_core::wit_parser::Interface::parse("test", wit).unwrap();
+    let module = test_helpers::dummy_module(&[], &[], Some(&iface));
+    let component = wit_component::ComponentEncoder::default()
+        .interface(iface)
+        .unwrap()
+        .module(&module)
+        .unwrap()
+        .validate(true)
+        .encode()
+        .unwrap();
+    let mut files = wit_bindgen_core::Files::default();
+    wit_bindgen_gen_host_js::Opts::default()
+        .generate("test", &component, &mut files)
+        .unwrap();
+    let file = |name| {
+        let (_, contents) = files.iter().find(|(path, _)| *path == name).unwrap();
+        String::from_utf8(contents.to_vec()).unwrap()
+    };
+    (file("test.js"), file("test.d.ts"))
+}
+
+#[test]
+fn async_exports() {
+    let (js, ts) = generate_default("f: async func(a: u32) -> string\ng: func()");
+    assert!(js.contains("async f("), "{js}");
+    assert!(ts.contains("f(a: number): Promise<string>;"), "{ts}");
+    assert!(ts.contains("g(): void;"), "{ts}");
+}
+
+#[test]
+fn deprecated() {
+    let (_, ts) = generate_default(include_str!("../../../tests/codegen/deprecated.wit"));
+    assert!(ts.contains(" * @since 1.3.0\n"), "{ts}");
+    assert!(
+        ts.contains(" * @deprecated Since 1.3.0. use `distance2` instead\n"),
+        "{ts}"
+    );
+    assert!(ts.contains(" * @deprecated use `x` instead\n"), "{ts}");
+}
+
+#[test]
+fn refinements() {
+    let (js, _) = generate_default("type port = u16 where 1..=65535\nf: func() -> port");
+    assert!(js.contains("if (!(1 <= refined"), "{js}");
+    assert!(
+        js.contains("throw new RefinementError(\"port\", \"1..=65535\");"),
+        "{js}"
+    );
+}
@@ -1,10 +1,12 @@
 use heck::*;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::mem;
+use wit_bindgen_core::lint::{Item, Namespace, Naming};
+use wit_bindgen_core::registry::Registration;
 use wit_bindgen_core::wit_parser::abi::{
-    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmType,
+    AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, RecursiveHelper, WasmType,
 };
-use wit_bindgen_core::{wit_parser::*, Direction, Files, Generator, Ns};
+use wit_bindgen_core::{wit_parser::*, Direction, Files, Generator, Ns, Types};
 
 pub mod dependencies;
 pub mod source;
@@ -20,6 +22,7 @@ pub struct WasmtimePy {
     guest_imports: HashMap<String, Imports>,
     guest_exports: HashMap<String, Exports>,
     sizes: SizeAlign,
+    types: Types,
     /// Tracks the intrinsics and Python imports needed
     deps: Dependencies,
     /// Whether the Python Union being emited will wrap its cases with dataclasses
@@ -72,6 +75,15 @@ impl Opts {
     }
 }
 
+/// Describes this generator for a [`Registry`](wit_bindgen_core::registry::Registry).
+pub fn registration() -> Registration {
+    Registration::new(
+        "host-wasmtime-py",
+        "Generates bindings for Python hosts using the Wasmtime engine.",
+        |_| Box::new(Opts::default().build()),
+    )
+}
+
 impl WasmtimePy {
     pub fn new() -> WasmtimePy {
         WasmtimePy::default()
@@ -96,6 +108,123 @@ impl WasmtimePy {
     fn intrinsics(&mut self, _iface: &Interface) -> Source {
         self.deps.intrinsics()
     }
+
+    /// Generates the helpers called by `f` to read and write values of
+    /// recursive types, along with any helpers those call in turn.
+    ///
+    /// Helpers are nested functions so they can use the same `memory`,
+    /// `realloc` and `caller` as the function calling them.
+    fn recursive_helpers(
+        iface: &Interface,
+        variant: AbiVariant,
+        lift_lower: LiftLower,
+        f: &mut FunctionBindgen<'_>,
+    ) -> Source {
+        let mut src = Source::default();
+        let mut generated = BTreeSet::new();
+        while let Some((id, helper)) = f.helpers.pop() {
+            if !generated.insert((id, helper)) {
+                continue;
+            }
+            let params = match helper {
+                RecursiveHelper::Store => vec!["value".to_string(), "addr".to_string()],
+                RecursiveHelper::Load => vec!["addr".to_string()],
+                RecursiveHelper::Deallocate => unreachable!("hosts don't deallocate"),
+            };
+            let mut helper_f = FunctionBindgen::new(f.gen, params);
+            iface.recursive_helper(variant, lift_lower, id, helper, &mut helper_f);
+            let FunctionBindgen {
+                src: body,
+                needs_memory,
+                needs_realloc,
+                helpers,
+                ..
+            } = helper_f;
+            f.needs_memory |= needs_memory;
+            if needs_realloc.is_some() {
+                f.needs_realloc = needs_realloc;
+            }
+            f.helpers.extend(helpers);
+
+            let mut builder = src.builder(&mut f.gen.deps, iface, &f.gen.types);
+            let name = recursive_helper_name(iface, id, helper);
+            match helper {
+                RecursiveHelper::Store => {
+                    builder.push_str(&format!("def {name}(value: "));
+                    builder.print_ty(&Type::Id(id), true);
+                    builder.push_str(", addr: int) -> None:\n");
+                }
+                _ => {
+                    builder.push_str(&format!("def {name}(addr: int) -> "));
+                    builder.print_ty(&Type::Id(id), true);
+                    builder.push_str(":\n");
+                }
+            }
+            builder.indent();
+            builder.push_str(&body);
+            builder.dedent();
+        }
+        src
+    }
+}
+
+fn recursive_helper_name(iface: &Interface, id: TypeId, helper: RecursiveHelper) -> String {
+    let prefix = match helper {
+        RecursiveHelper::Store => "store",
+        RecursiveHelper::Load => "load",
+        RecursiveHelper::Deallocate => "dealloc",
+    };
+    let name = iface.types[id].name.as_ref().unwrap();
+    format!("_{prefix}_{}", name.to_snake_case())
+}
+
+/// Python's keywords, including the `None`, `True` and `False` constants.
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// The [`Naming`] of generated Python bindings.
+pub struct PyNaming;
+
+impl Naming for PyNaming {
+    fn language(&self) -> &'static str {
+        "python"
+    }
+
+    fn convert(&self, item: &Item<'_>) -> Option<String> {
+        let name = item.name();
+        Some(match item {
+            Item::Type(_) => name.to_upper_camel_case(),
+            Item::Case { ty, name } => format!("{ty}-{name}").to_upper_camel_case(),
+            Item::EnumCase { .. } | Item::Flag { .. } | Item::Constant(_) => {
+                name.to_shouty_snake_case()
+            }
+            Item::Field { .. } | Item::Function { .. } | Item::Param { .. } => name.to_snake_case(),
+        })
+    }
+
+    fn is_reserved(&self, item: &Item<'_>, ident: &str) -> bool {
+        // Parameters share their function's scope with `self`, the `caller`
+        // and the locals which `FunctionBindgen` always uses.
+        let local = match item {
+            Item::Param { .. } => {
+                ["self", "caller", "len", "base", "i", "memory", "realloc"].contains(&ident)
+            }
+            _ => false,
+        };
+        local || PYTHON_KEYWORDS.contains(&ident)
+    }
+
+    fn namespace<'a>(&self, item: &Item<'a>) -> Namespace<'a> {
+        match item {
+            // Each case of a variant is its own class alongside the variant.
+            Item::Case { .. } => Namespace::Interface,
+            _ => item.namespace(),
+        }
+    }
 }
 
 fn array_ty(iface: &Interface, ty: &Type) -> Option<&'static str> {
@@ -121,9 +250,18 @@ fn array_ty(iface: &Interface, ty: &Type) -> Option<&'static str> {
 }
 
 impl Generator for WasmtimePy {
+    fn name(&self) -> &str {
+        "host-wasmtime-py"
+    }
+
+    fn supports_recursive_types(&self) -> bool {
+        true
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
         let variant = Self::abi_variant(dir);
         self.sizes.fill(iface);
+        self.types.analyze(iface);
         self.in_import = variant == AbiVariant::GuestImport;
     }
 
@@ -135,17 +273,26 @@ impl Generator for WasmtimePy {
         record: &Record,
         docs: &Docs,
     ) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.pyimport("dataclasses", "dataclass");
         builder.push_str("@dataclass\n");
         builder.push_str(&format!("class {}:\n", name.to_upper_camel_case()));
         builder.indent();
         builder.docstring(docs);
-        for field in record.fields.iter() {
+        let defaults = record
+            .fields
+            .iter()
+            .map(|f| f.default.as_ref())
+            .collect::<Vec<_>>();
+        let first_default = first_default(&defaults);
+        for (i, field) in record.fields.iter().enumerate() {
             builder.comment(&field.docs);
             let field_name = field.name.to_snake_case();
             builder.push_str(&format!("{field_name}: "));
             builder.print_ty(&field.ty, true);
+            if let Some(default) = field.default.as_ref().filter(|_| i >= first_default) {
+                builder.push_str(&format!(" = {}", py_literal(default)));
+            }
             builder.push_str("\n");
         }
         if record.fields.is_empty() {
@@ -163,7 +310,7 @@ impl Generator for WasmtimePy {
         tuple: &Tuple,
         docs: &Docs,
     ) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.comment(docs);
         builder.push_str(&format!("{} = ", name.to_upper_camel_case()));
         builder.print_tuple(tuple);
@@ -178,7 +325,7 @@ impl Generator for WasmtimePy {
         flags: &Flags,
         docs: &Docs,
     ) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.pyimport("enum", "Flag");
         builder.pyimport("enum", "auto");
         builder.push_str(&format!("class {}(Flag):\n", name.to_upper_camel_case()));
@@ -204,7 +351,7 @@ impl Generator for WasmtimePy {
         variant: &Variant,
         docs: &Docs,
     ) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.pyimport("dataclasses", "dataclass");
         let mut cases = Vec::new();
         for case in variant.cases.iter() {
@@ -255,7 +402,7 @@ impl Generator for WasmtimePy {
             py_type_classes.insert(py_type_class_of(&case.ty));
         }
 
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         if py_type_classes.len() != union.cases.len() {
             // Some of the cases are not distinguishable
             self.union_representation
@@ -277,7 +424,7 @@ impl Generator for WasmtimePy {
         payload: &Type,
         docs: &Docs,
     ) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.pyimport("typing", "Optional");
         builder.comment(docs);
         builder.push_str(&name.to_upper_camel_case());
@@ -296,7 +443,7 @@ impl Generator for WasmtimePy {
     ) {
         self.deps.needs_result = true;
 
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.comment(docs);
         builder.push_str(&format!("{} = Result[", name.to_upper_camel_case()));
         builder.print_optional_ty(result.ok.as_ref(), true);
@@ -306,7 +453,7 @@ impl Generator for WasmtimePy {
     }
 
     fn type_enum(&mut self, iface: &Interface, _id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.pyimport("enum", "Enum");
         builder.push_str(&format!("class {}(Enum):\n", name.to_upper_camel_case()));
         builder.indent();
@@ -330,7 +477,7 @@ impl Generator for WasmtimePy {
     }
 
     fn type_alias(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.comment(docs);
         builder.push_str(&format!("{} = ", name.to_upper_camel_case()));
         builder.print_ty(ty, false);
@@ -338,7 +485,7 @@ impl Generator for WasmtimePy {
     }
 
     fn type_list(&mut self, iface: &Interface, _id: TypeId, name: &str, ty: &Type, docs: &Docs) {
-        let mut builder = self.src.builder(&mut self.deps, iface);
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
         builder.comment(docs);
         builder.push_str(&format!("{} = ", name.to_upper_camel_case()));
         builder.print_list(ty);
@@ -349,17 +496,25 @@ impl Generator for WasmtimePy {
         self.type_alias(iface, id, name, ty, docs);
     }
 
+    fn constant(&mut self, iface: &Interface, constant: &Constant) {
+        let mut builder = self.src.builder(&mut self.deps, iface, &self.types);
+        builder.comment(&constant.docs);
+        builder.push_str(&format!("{}: ", constant.name.to_shouty_snake_case()));
+        builder.print_ty(&constant.ty, false);
+        builder.push_str(&format!(" = {}\n", py_literal(&constant.value)));
+    }
+
     // As with `abi_variant` above, we're generating host-side bindings here
     // so a user "export" uses the "guest import" ABI variant on the inside of
     // this `Generator` implementation.
     fn export(&mut self, iface: &Interface, func: &Function) {
         let mut pysig = Source::default();
-        let mut builder = pysig.builder(&mut self.deps, iface);
+        let mut builder = pysig.builder(&mut self.deps, iface, &self.types);
         builder.print_sig(func, self.in_import);
         let pysig = pysig.to_string();
 
         let mut func_body = Source::default();
-        let mut builder = func_body.builder(&mut self.deps, iface);
+        let mut builder = func_body.builder(&mut self.deps, iface, &self.types);
 
         let sig = iface.wasm_signature(AbiVariant::GuestImport, func);
         builder.push_str(&format!(
@@ -392,6 +547,12 @@ impl Generator for WasmtimePy {
             func,
             &mut f,
         );
+        let helpers = Self::recursive_helpers(
+            iface,
+            AbiVariant::GuestImport,
+            LiftLower::LiftArgsLowerResults,
+            &mut f,
+        );
 
         let FunctionBindgen {
             src,
@@ -401,7 +562,7 @@ impl Generator for WasmtimePy {
             ..
         } = f;
 
-        let mut builder = func_body.builder(&mut self.deps, iface);
+        let mut builder = func_body.builder(&mut self.deps, iface, &self.types);
         if needs_memory {
             // TODO: hardcoding "memory"
             builder.push_str("m = caller[\"memory\"]\n");
@@ -417,6 +578,7 @@ impl Generator for WasmtimePy {
             locals.insert("realloc").unwrap();
         }
 
+        builder.push_str(&helpers);
         builder.push_str(&src);
         builder.dedent();
 
@@ -449,6 +611,9 @@ impl Generator for WasmtimePy {
             .or_insert(Imports::default());
         let dst = match &func.kind {
             FunctionKind::Freestanding => &mut imports.freestanding_funcs,
+            FunctionKind::Method { .. }
+            | FunctionKind::Static { .. }
+            | FunctionKind::Constructor { .. } => unreachable!("resources are unsupported"),
         };
         dst.push(import);
     }
@@ -458,7 +623,7 @@ impl Generator for WasmtimePy {
     // this `Generator` implementation.
     fn import(&mut self, iface: &Interface, func: &Function) {
         let mut func_body = Source::default();
-        let mut builder = func_body.builder(&mut self.deps, iface);
+        let mut builder = func_body.builder(&mut self.deps, iface, &self.types);
 
         // Print the function signature
         let params = builder.print_sig(func, self.in_import);
@@ -469,6 +634,9 @@ impl Generator for WasmtimePy {
         // Use FunctionBindgen call
         let src_object = match &func.kind {
             FunctionKind::Freestanding => "self".to_string(),
+            FunctionKind::Method { .. }
+            | FunctionKind::Static { .. }
+            | FunctionKind::Constructor { .. } => unreachable!("resources are unsupported"),
         };
         let mut f = FunctionBindgen::new(self, params);
         f.src_object = src_object;
@@ -478,6 +646,12 @@ impl Generator for WasmtimePy {
             func,
             &mut f,
         );
+        let helpers = Self::recursive_helpers(
+            iface,
+            AbiVariant::GuestExport,
+            LiftLower::LowerArgsLiftResults,
+            &mut f,
+        );
         let FunctionBindgen {
             src,
             needs_memory,
@@ -485,7 +659,7 @@ impl Generator for WasmtimePy {
             src_object,
             ..
         } = f;
-        let mut builder = func_body.builder(&mut self.deps, iface);
+        let mut builder = func_body.builder(&mut self.deps, iface, &self.types);
         if needs_memory {
             // TODO: hardcoding "memory"
             builder.push_str(&format!("memory = {}._memory;\n", src_object));
@@ -499,6 +673,7 @@ impl Generator for WasmtimePy {
             ));
         }
 
+        builder.push_str(&helpers);
         builder.push_str(&src);
         builder.dedent();
 
@@ -534,6 +709,9 @@ impl Generator for WasmtimePy {
 
         let dst = match &func.kind {
             FunctionKind::Freestanding => &mut exports.freestanding_funcs,
+            FunctionKind::Method { .. }
+            | FunctionKind::Static { .. }
+            | FunctionKind::Constructor { .. } => unreachable!("resources are unsupported"),
         };
         dst.push(func_body);
     }
@@ -679,6 +857,7 @@ struct FunctionBindgen<'a> {
     params: Vec<String>,
     payloads: Vec<String>,
     src_object: String,
+    helpers: Vec<(TypeId, RecursiveHelper)>,
 }
 
 impl FunctionBindgen<'_> {
@@ -701,6 +880,7 @@ impl FunctionBindgen<'_> {
             params,
             payloads: Vec::new(),
             src_object: "self".to_string(),
+            helpers: Vec::new(),
         }
     }
 
@@ -752,7 +932,7 @@ impl Bindgen for FunctionBindgen<'_> {
     }
 
     fn return_pointer(&mut self, _iface: &Interface, _size: usize, _align: usize) -> String {
-        unimplemented!()
+        unreachable!("only guests provide return pointers")
     }
 
     fn is_list_canonical(&self, iface: &Interface, ty: &Type) -> bool {
@@ -766,7 +946,7 @@ impl Bindgen for FunctionBindgen<'_> {
         operands: &mut Vec<String>,
         results: &mut Vec<String>,
     ) {
-        let mut builder = self.src.builder(&mut self.gen.deps, iface);
+        let mut builder = self.src.builder(&mut self.gen.deps, iface, &self.gen.types);
         match inst {
             Instruction::GetArg { nth } => results.push(self.params[*nth].clone()),
             Instruction::I32Const { val } => results.push(val.to_string()),
@@ -1250,6 +1430,59 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(result);
             }
 
+            Instruction::BoxedOptionLower {
+                payload, realloc, ..
+            } => {
+                let base = self.payloads.pop().unwrap();
+                let e = self.payloads.pop().unwrap();
+                let realloc = realloc.unwrap();
+                let (body, body_results) = self.blocks.pop().unwrap();
+                assert!(body_results.is_empty());
+                self.needs_realloc = Some(realloc.to_string());
+                let size = self.gen.sizes.size(payload);
+                let align = self.gen.sizes.align(payload);
+                let result = self.locals.tmp("result");
+
+                let op0 = &operands[0];
+                builder.push_str(&format!("if {op0} is None:\n"));
+                builder.indent();
+                builder.push_str(&format!("{result} = 0\n"));
+                builder.dedent();
+                builder.push_str("else:\n");
+                builder.indent();
+                builder.push_str(&format!("{e} = {op0}\n"));
+                builder.push_str(&format!(
+                    "{base} = realloc(caller, 0, 0, {align}, {size})\n"
+                ));
+                builder.push_str(&format!("assert(isinstance({base}, int))\n"));
+                builder.push_str(&body);
+                builder.push_str(&format!("{result} = {base}\n"));
+                builder.dedent();
+
+                results.push(result);
+            }
+
+            Instruction::BoxedOptionLift { ty, .. } => {
+                let (body, body_results) = self.blocks.pop().unwrap();
+                let base = self.payloads.pop().unwrap();
+                let result = self.locals.tmp("option");
+                builder.print_var_declaration(&result, &Type::Id(*ty));
+
+                let op0 = &operands[0];
+                builder.push_str(&format!("if {op0} == 0:\n"));
+                builder.indent();
+                builder.push_str(&format!("{result} = None\n"));
+                builder.dedent();
+                builder.push_str("else:\n");
+                builder.indent();
+                builder.push_str(&format!("{base} = {op0}\n"));
+                builder.push_str(&body);
+                builder.push_str(&format!("{result} = {}\n", body_results[0]));
+                builder.dedent();
+
+                results.push(result);
+            }
+
             Instruction::ResultLower {
                 results: result_types,
                 ..
@@ -1326,6 +1559,9 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(format!("{}({})", name.to_upper_camel_case(), operands[0]));
             }
 
+            // TODO: check refinements
+            Instruction::RefinementCheck { .. } => results.push(operands[0].clone()),
+
             Instruction::ListCanonLower { element, realloc } => {
                 // Lowering only happens when we're passing lists into wasm,
                 // which forces us to always allocate, so this should always be
@@ -1530,6 +1766,9 @@ impl Bindgen for FunctionBindgen<'_> {
                             operands.join(", "),
                         ));
                     }
+                    FunctionKind::Method { .. }
+                    | FunctionKind::Static { .. }
+                    | FunctionKind::Constructor { .. } => unreachable!("resources are unsupported"),
                 }
                 builder.push_str("\n");
             }
@@ -1562,6 +1801,26 @@ impl Bindgen for FunctionBindgen<'_> {
                 }
             }
 
+            Instruction::RecursiveStore { ty, offset, .. } => {
+                let name = recursive_helper_name(iface, *ty, RecursiveHelper::Store);
+                self.helpers.push((*ty, RecursiveHelper::Store));
+                builder.push_str(&format!(
+                    "{name}({}, {} + {offset})\n",
+                    operands[0], operands[1]
+                ));
+            }
+            Instruction::RecursiveLoad { ty, offset, .. } => {
+                let name = recursive_helper_name(iface, *ty, RecursiveHelper::Load);
+                self.helpers.push((*ty, RecursiveHelper::Load));
+                let result = self.locals.tmp("value");
+                builder.push_str(&format!("{result} = {name}({} + {offset})\n", operands[0]));
+                results.push(result);
+            }
+            Instruction::RecursiveReturn { amt } => match amt {
+                0 => {}
+                _ => builder.push_str(&format!("return {}\n", operands.join(", "))),
+            },
+
             Instruction::I32Load { offset } => self.load("c_int32", *offset, operands, results),
             Instruction::I64Load { offset } => self.load("c_int64", *offset, operands, results),
             Instruction::F32Load { offset } => self.load("c_float", *offset, operands, results),
@@ -1593,7 +1852,24 @@ impl Bindgen for FunctionBindgen<'_> {
                 results.push(ptr);
             }
 
-            i => unimplemented!("{:?}", i),
+            Instruction::HandleLower { .. } | Instruction::HandleLift { .. } => {
+                unreachable!("resources are rejected by `validate`")
+            }
+            Instruction::FutureLower { .. }
+            | Instruction::FutureLift { .. }
+            | Instruction::StreamLower { .. }
+            | Instruction::StreamLift { .. } => {
+                unreachable!("futures and streams are rejected by `validate`")
+            }
+
+            Instruction::GuestDeallocate { .. }
+            | Instruction::GuestDeallocateString
+            | Instruction::GuestDeallocateList { .. }
+            | Instruction::GuestDeallocateVariant { .. }
+            | Instruction::GuestDeallocateBoxedOption { .. }
+            | Instruction::GuestDeallocateRecursive { .. } => {
+                unreachable!("hosts don't deallocate")
+            }
         }
     }
 }
@@ -1623,6 +1899,21 @@ enum PyTypeClass {
     Custom,
 }
 
+/// Returns `literal` written as a Python expression.
+fn py_literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Bool(true) => "True".to_string(),
+        Literal::Bool(false) => "False".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Returns the index of the first of `defaults` from which every value has a
+/// default, as Python only allows defaults on trailing parameters and fields.
+fn first_default(defaults: &[Option<&Literal>]) -> usize {
+    defaults.len() - defaults.iter().rev().take_while(|d| d.is_some()).count()
+}
+
 fn wasm_ty_ctor(ty: WasmType) -> &'static str {
     match ty {
         WasmType::I32 => "wasmtime.ValType.i32()",
@@ -1,7 +1,9 @@
 use heck::*;
 use wit_bindgen_core::wit_parser::*;
+use wit_bindgen_core::Types;
 
 use crate::dependencies::Dependencies;
+use crate::{first_default, py_literal};
 
 /// A [Source] represents some unit of Python code
 /// and keeps track of its indent.
@@ -132,18 +134,21 @@ impl From<Source> for String {
 /// ```
 /// # use wit_bindgen_gen_host_wasmtime_py::dependencies::Dependencies;
 /// # use wit_bindgen_core::wit_parser::{Interface, Type};
+/// # use wit_bindgen_core::Types;
 /// # use wit_bindgen_gen_host_wasmtime_py::source::Source;
 /// # let mut deps = Dependencies::default();
 /// # let mut interface = Interface::default();
 /// # let iface = &interface;
+/// # let types = Types::default();
 /// let mut source = Source::default();
-/// let mut builder = source.builder(&mut deps, iface);
+/// let mut builder = source.builder(&mut deps, iface, &types);
 /// builder.print_ty(&Type::Bool, false);
 /// ```
 pub struct SourceBuilder<'s, 'd, 'i> {
     source: &'s mut Source,
     pub deps: &'d mut Dependencies,
     iface: &'i Interface,
+    types: &'i Types,
 }
 
 impl<'s, 'd, 'i> Source {
@@ -152,11 +157,13 @@ impl<'s, 'd, 'i> Source {
         &'s mut self,
         deps: &'d mut Dependencies,
         iface: &'i Interface,
+        types: &'i Types,
     ) -> SourceBuilder<'s, 'd, 'i> {
         SourceBuilder {
             source: self,
             deps,
             iface,
+            types,
         }
     }
 }
@@ -188,19 +195,19 @@ impl<'s, 'd, 'i> SourceBuilder<'s, 'd, 'i> {
             Type::Id(id) => {
                 let ty = &self.iface.types[*id];
                 if let Some(name) = &ty.name {
-                    self.push_str(&name.to_upper_camel_case());
+                    // Recursive types may be referred to before they're
+                    // defined, so they're the ones which need forward
+                    // references.
+                    if forward_ref && self.iface.is_recursive(*id) {
+                        self.push_str(&format!("'{}'", name.to_upper_camel_case()));
+                    } else {
+                        self.push_str(&name.to_upper_camel_case());
+                    }
                     return;
                 }
                 match &ty.kind {
                     TypeDefKind::Type(t) => self.print_ty(t, forward_ref),
                     TypeDefKind::Tuple(t) => self.print_tuple(t),
-                    TypeDefKind::Record(_)
-                    | TypeDefKind::Flags(_)
-                    | TypeDefKind::Enum(_)
-                    | TypeDefKind::Variant(_)
-                    | TypeDefKind::Union(_) => {
-                        unreachable!()
-                    }
                     TypeDefKind::Option(t) => {
                         self.deps.pyimport("typing", "Optional");
                         self.push_str("Optional[");
@@ -216,17 +223,11 @@ impl<'s, 'd, 'i> SourceBuilder<'s, 'd, 'i> {
                         self.push_str("]");
                     }
                     TypeDefKind::List(t) => self.print_list(t),
-                    TypeDefKind::Future(t) => {
-                        self.push_str("Future[");
-                        self.print_optional_ty(t.as_ref(), true);
-                        self.push_str("]");
-                    }
-                    TypeDefKind::Stream(s) => {
-                        self.push_str("Stream[");
-                        self.print_optional_ty(s.element.as_ref(), true);
-                        self.push_str(", ");
-                        self.print_optional_ty(s.end.as_ref(), true);
-                        self.push_str("]");
+                    // Anything else has no Python spelling of its own, so it's
+                    // referred to by its canonical name.
+                    _ => {
+                        let name = self.types.anonymous_name(*id).to_upper_camel_case();
+                        self.push_str(&name);
                     }
                 }
             }
@@ -323,12 +324,18 @@ impl<'s, 'd, 'i> SourceBuilder<'s, 'd, 'i> {
             self.source.push_str("(self, caller: wasmtime.Store");
         }
         let mut params = Vec::new();
-        for (param, ty) in func.params.iter() {
+        let defaults = func.defaults.iter().map(|d| d.as_ref()).collect::<Vec<_>>();
+        let first_default = first_default(&defaults);
+        for (i, ((param, ty), default)) in func.params.iter().zip(&defaults).enumerate() {
             self.source.push_str(", ");
             self.source.push_str(&param.to_snake_case());
             params.push(param.to_snake_case());
             self.source.push_str(": ");
             self.print_ty(ty, true);
+            if let Some(default) = default.filter(|_| i >= first_default) {
+                self.source.push_str(" = ");
+                self.source.push_str(&py_literal(default));
+            }
         }
         self.source.push_str(") -> ");
         match func.results.len() {
@@ -453,8 +460,9 @@ mod tests {
         // If the element type is u8, it is interpreted as `bytes`
         let mut deps = Dependencies::default();
         let iface = Interface::default();
+        let types = Types::default();
         let mut source = Source::default();
-        let mut builder = source.builder(&mut deps, &iface);
+        let mut builder = source.builder(&mut deps, &iface, &types);
         builder.print_list(&Type::U8);
         drop(builder);
         assert_eq!(source.s, "bytes");
@@ -466,8 +474,9 @@ mod tests {
         // If the element type is u8, it is interpreted as `bytes`
         let mut deps = Dependencies::default();
         let iface = Interface::default();
+        let types = Types::default();
         let mut source = Source::default();
-        let mut builder = source.builder(&mut deps, &iface);
+        let mut builder = source.builder(&mut deps, &iface, &types);
         builder.print_list(&Type::Float32);
         drop(builder);
         assert_eq!(source.s, "List[float]");
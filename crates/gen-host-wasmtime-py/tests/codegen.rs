@@ -43,3 +43,12 @@ fn verify(dir: &Path, _name: &str) {
             .arg("mypy.ini"),
     );
 }
+
+#[test]
+fn unsupported() {
+    test_helpers::assert_unsupported(
+        wit_bindgen_gen_host_wasmtime_py::Opts::default().build(),
+        "f: func(x: future<u32>) -> stream<u8>",
+        "`future` and `stream` types",
+    );
+}
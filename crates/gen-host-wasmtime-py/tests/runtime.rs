@@ -19,7 +19,8 @@ fn execute(name: &str, wasm: &Path, py: &Path, imports: &Path, exports: &Path) {
     let mut files = Default::default();
     wit_bindgen_gen_host_wasmtime_py::Opts::default()
         .build()
-        .generate_all(&[], &[iface], &mut files);
+        .generate_all(&[], &[iface], &mut files)
+        .unwrap();
     for (file, contents) in files.iter() {
         fs::write(dir.join("imports").join(file), contents).unwrap();
     }
@@ -29,7 +30,8 @@ fn execute(name: &str, wasm: &Path, py: &Path, imports: &Path, exports: &Path) {
     let mut files = Default::default();
     wit_bindgen_gen_host_wasmtime_py::Opts::default()
         .build()
-        .generate_all(&[iface], &[], &mut files);
+        .generate_all(&[iface], &[], &mut files)
+        .unwrap();
     for (file, contents) in files.iter() {
         fs::write(dir.join("exports").join(file), contents).unwrap();
     }
@@ -4,11 +4,15 @@ use std::fmt::Write as _;
 use std::io::{Read, Write};
 use std::mem;
 use std::process::{Command, Stdio};
+use wit_bindgen_core::registry::Registration;
 use wit_bindgen_core::wit_parser::abi::AbiVariant;
 use wit_bindgen_core::{
     uwrite, uwriteln, wit_parser::*, Direction, Files, Generator, Source, TypeInfo, Types,
 };
-use wit_bindgen_gen_rust_lib::{to_rust_ident, FnSig, RustGenerator, TypeMode};
+use wit_bindgen_gen_rust_lib::{
+    resource_fn_name, rust_refinement_check, to_rust_ident, unsupported_handle, FnSig,
+    RustGenerator, TypeMode,
+};
 
 #[derive(Default)]
 pub struct Wasmtime {
@@ -21,10 +25,13 @@ pub struct Wasmtime {
     in_trait: bool,
     trait_name: String,
     sizes: SizeAlign,
+    // Whether wasm is called asynchronously, see `Opts::async_`.
+    async_: bool,
 }
 
 struct Import {
     name: String,
+    is_async: bool,
     trait_signature: String,
     closure: String,
 }
@@ -45,6 +52,15 @@ pub struct Opts {
     /// Whether or not to emit `tracing` macro calls on function entry/exit.
     #[cfg_attr(feature = "clap", arg(long))]
     pub tracing: bool,
+
+    /// Whether or not to instantiate components and call all of their exports
+    /// asynchronously, as required by engines with `Config::async_support`.
+    ///
+    /// This is implied if any function being generated is `async`. It's only
+    /// needed to call the exports of an interface without `async` functions
+    /// from an engine which other bindings need to be async.
+    #[cfg_attr(feature = "clap", arg(long = "async"))]
+    pub async_: bool,
 }
 
 impl Opts {
@@ -55,6 +71,34 @@ impl Opts {
     }
 }
 
+/// Describes this generator for a [`Registry`](wit_bindgen_core::registry::Registry).
+pub fn registration() -> Registration {
+    Registration::new(
+        "host-wasmtime-rust",
+        "Generates bindings for Rust hosts using the Wasmtime engine.",
+        |options| {
+            let opts = Opts {
+                rustfmt: options.flag("rustfmt"),
+                tracing: options.flag("tracing"),
+                async_: options.flag("async"),
+            };
+            Box::new(opts.build())
+        },
+    )
+    .flag(
+        "rustfmt",
+        "Whether or not `rustfmt` is executed to format generated code.",
+    )
+    .flag(
+        "tracing",
+        "Whether or not to emit `tracing` macro calls on function entry/exit.",
+    )
+    .flag(
+        "async",
+        "Whether or not to instantiate components and call their exports asynchronously.",
+    )
+}
+
 impl Wasmtime {
     pub fn new() -> Wasmtime {
         Wasmtime::default()
@@ -75,6 +119,158 @@ impl Wasmtime {
         }
     }
 
+    /// Emits a `span` for a call to `func`, entering it immediately for
+    /// synchronous calls. Async calls instead attach the span to the future
+    /// being awaited, see `instrumented`.
+    fn print_tracing_span(&mut self, kind: &str, iface: &Interface, func: &Function, async_: bool) {
+        self.src.push_str(&format!(
+            "
+                let span = wit_bindgen_host_wasmtime_rust::tracing::span!(
+                    wit_bindgen_host_wasmtime_rust::tracing::Level::TRACE,
+                    \"{}\",
+                    module = \"{}\",
+                    function = \"{}\",
+                );
+            ",
+            kind, iface.name, func.name,
+        ));
+        if !async_ {
+            self.src.push_str("let _enter = span.enter();\n");
+        }
+    }
+
+    /// Wraps the future produced by `call` in the current tracing span, if
+    /// tracing is enabled.
+    fn instrumented(&self, call: String) -> String {
+        if self.opts.tracing {
+            format!("wit_bindgen_host_wasmtime_rust::tracing::Instrument::instrument({call}, span)")
+        } else {
+            call
+        }
+    }
+
+    /// Emits statements which return a `RefinementError` from the current
+    /// function if any refined type within `value`, an owned place of type
+    /// `ty`, doesn't satisfy its refinement.
+    ///
+    /// Wasmtime lifts values itself so the checks can't be done during
+    /// lifting as in other generators, and instead walk the lifted value.
+    fn print_refinement_checks(&mut self, iface: &Interface, ty: &Type, value: &str, depth: usize) {
+        if !iface.has_refinements(ty) {
+            return;
+        }
+        let id = match ty {
+            Type::Id(id) => *id,
+            _ => unreachable!(),
+        };
+        let e = format!("e{depth}");
+        let inner = format!("(*{e})");
+        let depth = depth + 1;
+        let ty = &iface.types[id];
+        if let Some(refinement) = &ty.refinement {
+            uwriteln!(
+                self.src,
+                "if !{} {{
+                    return Err(wit_bindgen_host_wasmtime_rust::RefinementError {{
+                        ty: {:?},
+                        refinement: \"{refinement}\",
+                    }}.into());
+                }}",
+                rust_refinement_check(refinement, value),
+                ty.name.as_deref().unwrap_or(""),
+            );
+        }
+        match &ty.kind {
+            TypeDefKind::Type(t) => self.print_refinement_checks(iface, t, value, depth),
+            TypeDefKind::List(t) => {
+                if iface.has_refinements(t) {
+                    uwriteln!(self.src, "for {e} in {value}.iter() {{");
+                    self.print_refinement_checks(iface, t, &inner, depth);
+                    self.src.push_str("}\n");
+                }
+            }
+            TypeDefKind::Option(t) => {
+                uwriteln!(self.src, "if let Some({e}) = &{value} {{");
+                self.print_refinement_checks(iface, t, &inner, depth);
+                self.src.push_str("}\n");
+            }
+            TypeDefKind::Result(r) => {
+                for (case, t) in [("Ok", &r.ok), ("Err", &r.err)] {
+                    if let Some(t) = t {
+                        if iface.has_refinements(t) {
+                            uwriteln!(self.src, "if let {case}({e}) = &{value} {{");
+                            self.print_refinement_checks(iface, t, &inner, depth);
+                            self.src.push_str("}\n");
+                        }
+                    }
+                }
+            }
+            TypeDefKind::Tuple(t) => {
+                for (i, t) in t.types.iter().enumerate() {
+                    self.print_refinement_checks(iface, t, &format!("{value}.{i}"), depth);
+                }
+            }
+            TypeDefKind::Record(r) => {
+                for field in r.fields.iter() {
+                    let field_value = format!("{value}.{}", to_rust_ident(&field.name));
+                    self.print_refinement_checks(iface, &field.ty, &field_value, depth);
+                }
+            }
+            TypeDefKind::Variant(v) => {
+                let name = self.result_name(iface, id);
+                for case in v.cases.iter() {
+                    if let Some(t) = &case.ty {
+                        if iface.has_refinements(t) {
+                            let case_name = case.name.to_upper_camel_case();
+                            uwriteln!(self.src, "if let {name}::{case_name}({e}) = &{value} {{");
+                            self.print_refinement_checks(iface, t, &inner, depth);
+                            self.src.push_str("}\n");
+                        }
+                    }
+                }
+            }
+            TypeDefKind::Union(u) => {
+                let name = self.result_name(iface, id);
+                let case_names = self.union_case_names(iface, u);
+                for (case, case_name) in u.cases.iter().zip(case_names) {
+                    if iface.has_refinements(&case.ty) {
+                        uwriteln!(self.src, "if let {name}::{case_name}({e}) = &{value} {{");
+                        self.print_refinement_checks(iface, &case.ty, &inner, depth);
+                        self.src.push_str("}\n");
+                    }
+                }
+            }
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Future(_)
+            | TypeDefKind::Stream(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Handle(_) => {}
+        }
+    }
+
+    /// Returns the name of the Rust method for `func`, which for resource
+    /// functions is prefixed with the resource's name.
+    fn method_name(iface: &Interface, func: &Function) -> String {
+        match func.resource() {
+            Some(resource) => format!(
+                "{}_{}",
+                iface.types[resource].name.as_ref().unwrap().to_snake_case(),
+                resource_fn_name(func).to_snake_case()
+            ),
+            None => func.name.to_snake_case(),
+        }
+    }
+
+    /// Prints `ty` as it's passed to and from Wasmtime, where handles are
+    /// the `u32` indices which represent them.
+    fn print_abi_ty(&mut self, iface: &Interface, ty: &Type, mode: TypeMode) {
+        match handle(iface, ty) {
+            Some(_) => self.push_str("u32"),
+            None => self.print_ty(iface, ty, mode),
+        }
+    }
+
     fn print_result_ty(&mut self, iface: &Interface, results: &Results, mode: TypeMode) {
         match results {
             Results::Named(rs) => match rs.len() {
@@ -135,16 +331,55 @@ impl RustGenerator for Wasmtime {
         }
         self.push_str(" str");
     }
+
+    fn print_resource(&mut self, iface: &Interface, id: TypeId) {
+        // Resources implemented by the host are associated types of the
+        // trait it implements.
+        if self.in_import {
+            self.push_str("Self::");
+        }
+        let name = iface.types[id].name.as_ref().unwrap();
+        self.push_str(&name.to_upper_camel_case());
+    }
 }
 
 impl Generator for Wasmtime {
+    fn name(&self) -> &str {
+        "host-wasmtime-rust"
+    }
+
+    // Values are passed through wasmtime's implementation of the component
+    // model, which has no recursive types.
+    fn supports_recursive_types(&self) -> bool {
+        false
+    }
+
+    fn supports_resources(&self) -> bool {
+        true
+    }
+
+    fn unsupported_type(&self, iface: &Interface, id: TypeId) -> Option<String> {
+        unsupported_handle(iface, id)
+    }
+
+    fn preprocess_all(&mut self, imports: &[Interface], exports: &[Interface]) {
+        // An `async` function anywhere requires `Config::async_support`, with
+        // which Wasmtime panics on synchronous calls into wasm.
+        self.async_ = self.opts.async_
+            || imports
+                .iter()
+                .chain(exports)
+                .flat_map(|iface| iface.functions.iter())
+                .any(|func| func.is_async);
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, dir: Direction) {
         let variant = Self::abi_variant(dir);
         self.types.analyze(iface);
         self.in_import = variant == AbiVariant::GuestImport;
         self.trait_name = iface.name.to_upper_camel_case();
         self.src.push_str(&format!(
-            "#[allow(clippy::all)]\npub mod {} {{\n",
+            "#[allow(clippy::all, deprecated)]\npub mod {} {{\n",
             iface.name.to_snake_case(),
         ));
         self.src.push_str(
@@ -183,7 +418,9 @@ impl Generator for Wasmtime {
         flags: &Flags,
         docs: &Docs,
     ) {
-        self.rustdoc(docs);
+        // TODO wasmtime-component-macro doesnt support `#[deprecated]` on
+        // flags rn
+        self.rustdoc_contents(docs);
         self.src.push_str("wasmtime::component::flags!(\n");
         self.src
             .push_str(&format!("{} {{\n", name.to_upper_camel_case()));
@@ -244,8 +481,8 @@ impl Generator for Wasmtime {
         self.print_typedef_result(iface, id, result, docs);
     }
 
-    fn type_enum(&mut self, _iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
-        self.print_typedef_enum(id, name, enum_, docs,
+    fn type_enum(&mut self, iface: &Interface, id: TypeId, _name: &str, enum_: &Enum, docs: &Docs) {
+        self.print_typedef_enum(iface, id, enum_, docs,
             &["#[derive(wasmtime::component::ComponentType, wasmtime::component::Lift, wasmtime::component::Lower)]".to_owned(),
             "#[component(enum)]".to_owned()],
             Box::new(|case| format!("#[component(name = \"{}\")]", case.name))
@@ -269,6 +506,72 @@ impl Generator for Wasmtime {
         self.src.push_str(";\n");
     }
 
+    fn type_resource(&mut self, iface: &Interface, id: TypeId, name: &str, docs: &Docs) {
+        // Resources implemented by the host are associated types of its
+        // trait, see `finish_one`.
+        if self.in_import {
+            return;
+        }
+
+        let camel = name.to_upper_camel_case();
+        let snake = name.to_snake_case();
+        self.rustdoc(docs);
+        uwriteln!(
+            self.src,
+            "///
+            /// This is a handle to a resource owned by the guest, which is
+            /// destroyed by passing it to `{snake}_drop`.
+            #[derive(Debug)]
+            pub struct {camel}(u32);"
+        );
+
+        let destructor = iface.resource_drop(id);
+        let (async_, call, post_return) = if self.async_ {
+            ("async ", "call_async", "post_return_async")
+        } else {
+            ("", "call", "post_return")
+        };
+        let (generics, store, await_) = if self.async_ {
+            (
+                "<T: Send>",
+                "impl wasmtime::AsContextMut<Data = T>",
+                ".await",
+            )
+        } else {
+            ("", "impl wasmtime::AsContextMut", "")
+        };
+        let func = format!(
+            "
+                /// Destroys the guest's `{name}` which `handle` refers to.
+                pub {async_}fn {snake}_drop{generics}(&self, mut store: {store}, handle: {camel}) -> anyhow::Result<()> {{
+                    let callee = unsafe {{
+                        wasmtime::component::TypedFunc::<(u32,), ()>::new_unchecked(self.{snake}_drop)
+                    }};
+                    callee.{call}(store.as_context_mut(), (handle.0,)){await_}?;
+                    callee.{post_return}(store.as_context_mut()){await_}?;
+                    Ok(())
+                }}
+            "
+        );
+        let getter = format!(
+            "*instance.get_typed_func::<(u32,), (), _>(&mut store, \"{}\")?.func()",
+            destructor.name
+        );
+        let exports = self
+            .guest_exports
+            .entry(iface.name.to_string())
+            .or_insert_with(Exports::default);
+        exports.funcs.push(func);
+        exports.fields.insert(
+            format!("{snake}_drop"),
+            ("wasmtime::component::Func".to_string(), getter),
+        );
+    }
+
+    fn constant(&mut self, iface: &Interface, constant: &Constant) {
+        self.print_constant(iface, constant);
+    }
+
     // As with `abi_variant` above, we're generating host-side bindings here
     // so a user "export" uses the "guest import" ABI variant on the inside of
     // this `Generator` implementation.
@@ -280,8 +583,10 @@ impl Generator for Wasmtime {
         self.in_trait = true;
 
         let mut fnsig = FnSig::default();
+        fnsig.async_ = func.is_async;
         fnsig.private = true;
         fnsig.self_arg = Some(self_arg);
+        fnsig.name = Some(Self::method_name(iface, func));
 
         // These trait method args used to be TypeMode::LeafBorrowed, but wasmtime
         // Lift is not impled for borrowed types, so I don't think we can
@@ -293,46 +598,109 @@ impl Generator for Wasmtime {
         let trait_signature = mem::take(&mut self.src).into();
 
         // Generate the closure that's passed to a `Linker`, the final piece of
-        // codegen here.
+        // codegen here. Async closures, used with `func_wrap_async`, take
+        // their parameters as a tuple and return a boxed future.
         self.src
             .push_str("move |mut caller: wasmtime::StoreContextMut<'_, T>");
-        for (i, param) in func.params.iter().enumerate() {
-            uwrite!(self.src, ", arg{} :", i);
-            // Lift is required to be impled for this type, so we can't use
-            // a borrowed type:
-            self.print_ty(iface, &param.1, TypeMode::Owned);
+        if func.is_async {
+            self.src.push_str(", (");
+            for (i, _) in func.params.iter().enumerate() {
+                uwrite!(self.src, "arg{},", i);
+            }
+            self.src.push_str("): (");
+            for (_, ty) in func.params.iter() {
+                self.print_abi_ty(iface, ty, TypeMode::Owned);
+                self.src.push_str(",");
+            }
+            self.src.push_str(")| Box::new(async move {\n");
+        } else {
+            for (i, param) in func.params.iter().enumerate() {
+                uwrite!(self.src, ", arg{} :", i);
+                // Lift is required to be impled for this type, so we can't use
+                // a borrowed type:
+                self.print_abi_ty(iface, &param.1, TypeMode::Owned);
+            }
+            self.src.push_str("| {\n");
         }
-        self.src.push_str("| {\n");
 
         if self.opts.tracing {
-            self.src.push_str(&format!(
-                "
-                    let span = wit_bindgen_host_wasmtime_rust::tracing::span!(
-                        wit_bindgen_host_wasmtime_rust::tracing::Level::TRACE,
-                        \"wit-bindgen export\",
-                        module = \"{}\",
-                        function = \"{}\",
-                    );
-                    let _enter = span.enter();
-                ",
-                iface.name, func.name,
-            ));
+            self.print_tracing_span("wit-bindgen export", iface, func, func.is_async);
+        }
+
+        for (i, (_, ty)) in func.params.iter().enumerate() {
+            self.print_refinement_checks(iface, ty, &format!("arg{i}"), 0);
+        }
+        let uses_handles = func
+            .params
+            .iter()
+            .map(|(_, ty)| ty)
+            .chain(func.results.iter_types())
+            .any(|ty| handle(iface, ty).is_some());
+        if uses_handles {
+            self.src
+                .push_str("let (host, tables) = get(caller.data_mut());\n");
+        } else if has_resources(iface) {
+            self.src.push_str("let host = get(caller.data_mut()).0;\n");
+        } else {
+            self.src.push_str("let host = get(caller.data_mut());\n");
         }
 
-        self.src.push_str("let host = get(caller.data_mut());\n");
+        // Handles are looked up in the table of their resource, which owned
+        // handles are removed from.
+        for (i, (_, ty)) in func.params.iter().enumerate() {
+            match handle(iface, ty) {
+                Some(Handle::Own(r)) => {
+                    let table = table_name(iface, *r);
+                    uwriteln!(self.src, "let arg{i} = tables.{table}.remove(arg{i})?;");
+                }
+                Some(Handle::Borrow(r)) => {
+                    let table = table_name(iface, *r);
+                    uwriteln!(self.src, "let arg{i} = tables.{table}.get(arg{i})?;");
+                }
+                None => {}
+            }
+        }
 
-        uwrite!(self.src, "let r = host.{}(", func.name.to_snake_case());
+        let mut call = format!("host.{}(", Self::method_name(iface, func));
         for (i, _) in func.params.iter().enumerate() {
-            uwrite!(self.src, "arg{},", i);
+            uwrite!(call, "arg{},", i);
         }
-        uwrite!(self.src, ");\n");
-        if func.results.iter_types().len() == 1 {
+        call.push(')');
+        if func.is_async {
+            call = self.instrumented(call);
+            call.push_str(".await");
+        }
+        uwrite!(self.src, "let r = {call};\n");
+        let results = func.results.iter_types().collect::<Vec<_>>();
+        if results.iter().any(|ty| handle(iface, ty).is_some()) {
+            // Owned handles which are returned are added to their table.
+            self.src.push_str("Ok((");
+            for (i, ty) in results.iter().enumerate() {
+                let value = if results.len() == 1 {
+                    "r".to_string()
+                } else {
+                    format!("r.{i}")
+                };
+                match handle(iface, ty) {
+                    Some(h) => {
+                        let table = table_name(iface, h.resource());
+                        uwrite!(self.src, "tables.{table}.insert({value}),");
+                    }
+                    None => uwrite!(self.src, "{value},"),
+                }
+            }
+            self.src.push_str("))\n");
+        } else if results.len() == 1 {
             uwrite!(self.src, "Ok((r,))\n");
         } else {
             uwrite!(self.src, "Ok(r)\n");
         }
 
-        self.src.push_str("}");
+        if func.is_async {
+            self.src.push_str("})");
+        } else {
+            self.src.push_str("}");
+        }
         let closure = mem::replace(&mut self.src, prev).into();
 
         self.guest_imports
@@ -340,6 +708,7 @@ impl Generator for Wasmtime {
             .or_insert(Vec::new())
             .push(Import {
                 name: func.name.to_string(),
+                is_async: func.is_async,
                 closure,
                 trait_signature,
             });
@@ -350,11 +719,19 @@ impl Generator for Wasmtime {
     // this `Generator` implementation.
     fn import(&mut self, iface: &Interface, func: &Function) {
         let prev = mem::take(&mut self.src);
-        uwrite!(
-            self.src,
-            "pub fn {}(&self, mut store: impl wasmtime::AsContextMut, ",
-            func.name.to_snake_case(),
-        );
+        if self.async_ {
+            uwrite!(
+                self.src,
+                "pub async fn {}<T: Send>(&self, mut store: impl wasmtime::AsContextMut<Data = T>, ",
+                Self::method_name(iface, func),
+            );
+        } else {
+            uwrite!(
+                self.src,
+                "pub fn {}(&self, mut store: impl wasmtime::AsContextMut, ",
+                Self::method_name(iface, func),
+            );
+        }
         for (i, param) in func.params.iter().enumerate() {
             uwrite!(self.src, "arg{}: ", i);
             self.print_ty(iface, &param.1, TypeMode::AllBorrowed("'_"));
@@ -365,48 +742,67 @@ impl Generator for Wasmtime {
         self.src.push_str("> {\n");
 
         if self.opts.tracing {
-            self.src.push_str(&format!(
-                "
-                    let span = wit_bindgen_host_wasmtime_rust::tracing::span!(
-                        wit_bindgen_host_wasmtime_rust::tracing::Level::TRACE,
-                        \"wit-bindgen import\",
-                        module = \"{}\",
-                        function = \"{}\",
-                    );
-                    let _enter = span.enter();
-                ",
-                iface.name, func.name,
-            ));
+            self.print_tracing_span("wit-bindgen import", iface, func, self.async_);
         }
 
         self.src.push_str("let callee = unsafe {\n");
         self.src.push_str("wasmtime::component::TypedFunc::<(");
         for (_, ty) in func.params.iter() {
-            self.print_ty(iface, ty, TypeMode::AllBorrowed("'_"));
+            self.print_abi_ty(iface, ty, TypeMode::AllBorrowed("'_"));
             self.push_str(", ");
         }
         self.src.push_str("), (");
         for ty in func.results.iter_types() {
-            self.print_ty(iface, ty, TypeMode::Owned);
+            self.print_abi_ty(iface, ty, TypeMode::Owned);
             self.push_str(", ");
         }
         uwriteln!(
             self.src,
             ")>::new_unchecked(self.{})",
-            func.name.to_snake_case()
+            Self::method_name(iface, func)
         );
         self.src.push_str("};\n");
         self.src.push_str("let (");
         for (i, _) in func.results.iter_types().enumerate() {
             uwrite!(self.src, "ret{},", i);
         }
-        uwrite!(self.src, ") = callee.call(store.as_context_mut(), (");
-        for (i, _) in func.params.iter().enumerate() {
-            uwrite!(self.src, "arg{}, ", i);
+        let mut call = String::from("callee.call");
+        if self.async_ {
+            call.push_str("_async");
+        }
+        call.push_str("(store.as_context_mut(), (");
+        for (i, (_, ty)) in func.params.iter().enumerate() {
+            match handle(iface, ty) {
+                Some(_) => uwrite!(call, "arg{}.0, ", i),
+                None => uwrite!(call, "arg{}, ", i),
+            }
         }
-        uwriteln!(self.src, "))?;");
+        call.push_str("))");
+        if self.async_ {
+            call = self.instrumented(call);
+            call.push_str(".await");
+        }
+        uwriteln!(self.src, ") = {call}?;");
 
-        uwriteln!(self.src, "callee.post_return(store.as_context_mut())?;");
+        if self.async_ {
+            uwriteln!(
+                self.src,
+                "callee.post_return_async(store.as_context_mut()).await?;"
+            );
+        } else {
+            uwriteln!(self.src, "callee.post_return(store.as_context_mut())?;");
+        }
+        for (i, ty) in func.results.iter_types().enumerate() {
+            self.print_refinement_checks(iface, ty, &format!("ret{i}"), 0);
+            if let Some(h) = handle(iface, ty) {
+                let camel = iface.types[h.resource()]
+                    .name
+                    .as_ref()
+                    .unwrap()
+                    .to_upper_camel_case();
+                uwriteln!(self.src, "let ret{i} = {camel}(ret{i});");
+            }
+        }
 
         self.src.push_str("Ok(");
         if func.results.iter_types().len() == 1 {
@@ -428,13 +824,13 @@ impl Generator for Wasmtime {
 
         self.src.push_str("*instance.get_typed_func::<(");
         for (_, ty) in func.params.iter() {
-            self.print_ty(iface, ty, TypeMode::AllBorrowed("'_"));
+            self.print_abi_ty(iface, ty, TypeMode::AllBorrowed("'_"));
             self.push_str(", ");
         }
 
         self.src.push_str("), (");
         for ty in func.results.iter_types() {
-            self.print_ty(iface, ty, TypeMode::Owned);
+            self.print_abi_ty(iface, ty, TypeMode::Owned);
             self.push_str(", ");
         }
 
@@ -449,42 +845,134 @@ impl Generator for Wasmtime {
             .or_insert_with(Exports::default);
         exports.funcs.push(pub_func);
         exports.fields.insert(
-            to_rust_ident(&func.name),
+            Self::method_name(iface, func),
             ("wasmtime::component::Func".to_string(), getter),
         );
     }
 
-    fn finish_one(&mut self, _iface: &Interface, files: &mut Files) {
-        for (module, funcs) in sorted_iter(&self.guest_imports) {
+    fn finish_one(&mut self, iface: &Interface, files: &mut Files) {
+        let resources = iface
+            .types
+            .iter()
+            .filter(|(_, ty)| ty.kind == TypeDefKind::Resource)
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        let guest_imports = mem::take(&mut self.guest_imports);
+        for (module, funcs) in sorted_iter(&guest_imports) {
             let module_camel = module.to_upper_camel_case();
+            let is_async = funcs.iter().any(|f| f.is_async);
+            if is_async {
+                self.src
+                    .push_str("#[wit_bindgen_host_wasmtime_rust::async_trait]\n");
+            }
             self.src.push_str("pub trait ");
             self.src.push_str(&module_camel);
             self.src.push_str(": Sized ");
+            if is_async {
+                self.src.push_str("+ Send ");
+            }
             self.src.push_str("{\n");
+            for id in resources.iter() {
+                let ty = &iface.types[*id];
+                let name = ty.name.as_ref().unwrap();
+                self.rustdoc(&ty.docs);
+                uwrite!(self.src, "type {}", name.to_upper_camel_case());
+                if is_async {
+                    self.src.push_str(": Send + Sync");
+                }
+                self.src.push_str(";\n\n");
+                uwriteln!(
+                    self.src,
+                    "/// Destroys a `{name}` once the guest drops its handle.
+                    fn {snake}_drop(&mut self, state: Self::{camel}) {{
+                        drop(state);
+                    }}
+                    ",
+                    snake = name.to_snake_case(),
+                    camel = name.to_upper_camel_case(),
+                );
+            }
             for f in funcs {
                 self.src.push_str(&f.trait_signature);
                 self.src.push_str(";\n\n");
             }
             self.src.push_str("}\n");
+
+            if !resources.is_empty() {
+                uwriteln!(
+                    self.src,
+                    "/// The resources which the guest has handles to.
+                    pub struct {module_camel}Tables<U: {module_camel}> {{"
+                );
+                for id in resources.iter() {
+                    let camel = iface.types[*id]
+                        .name
+                        .as_ref()
+                        .unwrap()
+                        .to_upper_camel_case();
+                    uwriteln!(
+                        self.src,
+                        "{}: wit_bindgen_host_wasmtime_rust::Table<U::{camel}>,",
+                        table_name(iface, *id)
+                    );
+                }
+                self.src.push_str("}\n");
+                uwriteln!(
+                    self.src,
+                    "impl<U: {module_camel}> Default for {module_camel}Tables<U> {{
+                        fn default() -> Self {{
+                            Self {{"
+                );
+                for id in resources.iter() {
+                    uwriteln!(self.src, "{}: Default::default(),", table_name(iface, *id));
+                }
+                self.src.push_str("}\n}\n}\n");
+            }
         }
 
-        for (module, funcs) in mem::take(&mut self.guest_imports) {
+        for (module, funcs) in guest_imports {
             let module_camel = module.to_upper_camel_case();
+            let is_async = funcs.iter().any(|f| f.is_async);
             self.push_str(
                 "\npub fn add_to_linker<T, U>(linker: &mut wasmtime::component::Linker<T>",
             );
             self.push_str(", get: impl Fn(&mut T) -> ");
-            self.push_str("&mut U");
+            if resources.is_empty() {
+                self.push_str("&mut U");
+            } else {
+                uwrite!(self.src, "(&mut U, &mut {module_camel}Tables<U>)");
+            }
             self.push_str("+ Send + Sync + Copy + 'static) -> anyhow::Result<()> \n");
             self.push_str("where U: ");
             self.push_str(&module_camel);
+            if is_async {
+                self.push_str(", T: Send");
+            }
             self.push_str("\n{\n");
             self.push_str(&format!("let mut inst = linker.instance(\"{}\")?;", module,));
             for f in funcs {
-                self.push_str(&format!(
-                    "inst.func_wrap(\"{}\", {})?;\n",
-                    f.name, f.closure,
-                ));
+                let wrap = if f.is_async {
+                    "func_wrap_async"
+                } else {
+                    "func_wrap"
+                };
+                self.push_str(&format!("inst.{}(\"{}\", {})?;\n", wrap, f.name, f.closure,));
+            }
+            for id in resources.iter() {
+                let destructor = iface.resource_drop(*id);
+                uwriteln!(
+                    self.src,
+                    "inst.func_wrap(\"{}\", move |mut caller: wasmtime::StoreContextMut<'_, T>, handle: u32| {{
+                        let (host, tables) = get(caller.data_mut());
+                        let state = tables.{}.remove(handle)?;
+                        host.{}_drop(state);
+                        Ok(())
+                    }})?;",
+                    destructor.name,
+                    table_name(iface, *id),
+                    iface.types[*id].name.as_ref().unwrap().to_snake_case(),
+                );
             }
             self.push_str("Ok(())\n}\n");
         }
@@ -502,21 +990,41 @@ impl Generator for Wasmtime {
             self.push_str("}\n");
             uwrite!(self.src, "impl {} {{\n", name);
 
-            self.push_str(&format!(
-                "
-                    /// Instantiates the provided `module` using the specified
-                    /// parameters, wrapping up the result in a structure that
-                    /// translates between wasm and the host.
-                    pub fn instantiate<T>(
-                        mut store: impl wasmtime::AsContextMut<Data = T>,
-                        component: &wasmtime::component::Component,
-                        linker: &wasmtime::component::Linker<T>,
-                    ) -> anyhow::Result<(Self, wasmtime::component::Instance)> {{
-                        let instance = linker.instantiate(&mut store, component)?;
-                        Ok((Self::new(store, &instance)?, instance))
-                    }}
-                ",
-            ));
+            if self.async_ {
+                self.push_str(
+                    "
+                        /// Instantiates the provided `module` using the specified
+                        /// parameters, wrapping up the result in a structure that
+                        /// translates between wasm and the host.
+                        ///
+                        /// The store's engine must have `Config::async_support`.
+                        pub async fn instantiate_async<T: Send>(
+                            mut store: impl wasmtime::AsContextMut<Data = T>,
+                            component: &wasmtime::component::Component,
+                            linker: &wasmtime::component::Linker<T>,
+                        ) -> anyhow::Result<(Self, wasmtime::component::Instance)> {
+                            let instance = linker.instantiate_async(&mut store, component).await?;
+                            Ok((Self::new(store, &instance)?, instance))
+                        }
+                    ",
+                );
+            } else {
+                self.push_str(
+                    "
+                        /// Instantiates the provided `module` using the specified
+                        /// parameters, wrapping up the result in a structure that
+                        /// translates between wasm and the host.
+                        pub fn instantiate<T>(
+                            mut store: impl wasmtime::AsContextMut<Data = T>,
+                            component: &wasmtime::component::Component,
+                            linker: &wasmtime::component::Linker<T>,
+                        ) -> anyhow::Result<(Self, wasmtime::component::Instance)> {
+                            let instance = linker.instantiate(&mut store, component)?;
+                            Ok((Self::new(store, &instance)?, instance))
+                        }
+                    ",
+                );
+            }
 
             self.push_str(&format!(
                 "
@@ -591,6 +1099,34 @@ impl Generator for Wasmtime {
     }
 }
 
+/// Returns whether `iface` defines any resources.
+fn has_resources(iface: &Interface) -> bool {
+    iface
+        .types
+        .iter()
+        .any(|(_, ty)| ty.kind == TypeDefKind::Resource)
+}
+
+/// Returns the handle `ty` is, if it's a handle.
+fn handle<'a>(iface: &'a Interface, ty: &Type) -> Option<&'a Handle> {
+    match ty {
+        Type::Id(id) => match &iface.types[*id].kind {
+            TypeDefKind::Handle(h) => Some(h),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the name of the field of the host's tables which holds the
+/// resources of type `id`.
+fn table_name(iface: &Interface, id: TypeId) -> String {
+    format!(
+        "{}_table",
+        iface.types[id].name.as_ref().unwrap().to_snake_case()
+    )
+}
+
 fn sorted_iter<K: Ord, V>(map: &HashMap<K, V>) -> impl Iterator<Item = (&K, &V)> {
     let mut list = map.into_iter().collect::<Vec<_>>();
     list.sort_by_key(|p| p.0);
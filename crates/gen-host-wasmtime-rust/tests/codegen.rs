@@ -53,3 +53,38 @@ mod imports {
         test_helpers::codegen_tests!("*.wit");
     }
 }
+
+#[test]
+fn unsupported() {
+    test_helpers::assert_unsupported(
+        wit_bindgen_gen_host_wasmtime_rust::Opts::default().build(),
+        "f: func(x: future<u32>) -> stream<u8>",
+        "`future` and `stream` types",
+    );
+}
+
+// Union cases are named after their types, and those names are public, so
+// anonymous types keep the names they've always had.
+#[test]
+fn union_case_names() {
+    use wit_bindgen_core::{wit_parser::Interface, Files, Generator};
+
+    let iface = Interface::parse(
+        "unions",
+        "
+            union anonymous { option<u32>, list<u8>, tuple<u8, string> }
+            f: func(x: anonymous)
+        ",
+    )
+    .unwrap();
+    let mut files = Files::default();
+    wit_bindgen_gen_host_wasmtime_rust::Opts::default()
+        .build()
+        .generate_all(&[iface], &[], &mut files)
+        .unwrap();
+    let (_, src) = files.iter().next().unwrap();
+    let src = std::str::from_utf8(src).unwrap();
+    for case in ["OptionalU32(", "U8List(", "Tuple("] {
+        assert!(src.contains(case), "missing `{case}` in:\n{src}");
+    }
+}
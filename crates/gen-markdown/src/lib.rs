@@ -1,9 +1,12 @@
 use heck::*;
 use pulldown_cmark::{html, Event, LinkType, Parser, Tag};
 use std::collections::HashMap;
+use wit_bindgen_core::registry::Registration;
 use wit_bindgen_core::{wit_parser, Direction, Files, Generator, Source};
 use wit_parser::*;
 
+mod wit;
+
 #[derive(Default)]
 pub struct Markdown {
     src: Source,
@@ -12,6 +15,7 @@ pub struct Markdown {
     hrefs: HashMap<String, String>,
     funcs: usize,
     types: usize,
+    constants: usize,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -28,6 +32,15 @@ impl Opts {
     }
 }
 
+/// Describes this generator for a [`Registry`](wit_bindgen_core::registry::Registry).
+pub fn registration() -> Registration {
+    Registration::new(
+        "markdown",
+        "Generates a Markdown file describing an interface.",
+        |_| Box::new(Opts::default().build()),
+    )
+}
+
 impl Markdown {
     pub fn new() -> Markdown {
         Markdown::default()
@@ -143,20 +156,83 @@ impl Markdown {
                             self.src.push_str("stream");
                         }
                     },
+                    TypeDefKind::Handle(h) => {
+                        let (kind, resource) = match h {
+                            Handle::Own(r) => ("own", r),
+                            Handle::Borrow(r) => ("borrow", r),
+                        };
+                        self.src.push_str(kind);
+                        self.src.push_str("<");
+                        self.print_ty(iface, &Type::Id(*resource), false);
+                        self.src.push_str(">");
+                    }
+                    TypeDefKind::Resource => unreachable!(),
                 }
             }
         }
     }
 
     fn docs(&mut self, docs: &Docs) {
-        let docs = match &docs.contents {
-            Some(docs) => docs,
-            None => return,
-        };
-        for line in docs.lines() {
-            self.src.push_str(line.trim());
+        self.deprecated(docs);
+        self.contents(docs);
+        if let Some(since) = &docs.since {
+            if docs.contents.is_some() {
+                self.src.push_str("\n");
+            }
+            self.since(since);
+        }
+    }
+
+    /// Documents a top-level item, whose definition is `wit`.
+    ///
+    /// The definition is written in a ```` ```wit ```` code block right after
+    /// the item's docs, which makes the generated markdown a literate
+    /// interface: parsing it again gives back the same items with the same
+    /// docs.
+    fn definition(&mut self, docs: &Docs, wit: &str) {
+        if docs.contents.is_some() {
+            self.contents(docs);
             self.src.push_str("\n");
         }
+        let src = self.src.as_mut_string();
+        src.push_str("```wit\n");
+        src.push_str(wit);
+        src.push_str("```\n");
+        if docs.deprecated.is_some() || docs.since.is_some() {
+            self.src.push_str("\n");
+        }
+        self.deprecated(docs);
+        if let Some(since) = &docs.since {
+            self.since(since);
+        }
+    }
+
+    fn deprecated(&mut self, docs: &Docs) {
+        if let Some(deprecated) = &docs.deprecated {
+            self.src.push_str("**Deprecated**");
+            if let Some(since) = &deprecated.since {
+                self.src.push_str(&format!(" since `{}`", since));
+            }
+            if let Some(note) = &deprecated.note {
+                self.src.push_str(": ");
+                self.src.push_str(note);
+            }
+            self.src.push_str("\n\n");
+        }
+    }
+
+    fn contents(&mut self, docs: &Docs) {
+        if let Some(contents) = &docs.contents {
+            for line in contents.lines() {
+                self.src.push_str(line.trim());
+                self.src.push_str("\n");
+            }
+        }
+    }
+
+    fn since(&mut self, since: &str) {
+        self.src
+            .push_str(&format!("*Available since `{}`.*\n", since));
     }
 
     fn print_type_header(&mut self, name: &str) {
@@ -173,8 +249,8 @@ impl Markdown {
             .insert(name.to_string(), format!("#{}", name.to_snake_case()));
     }
 
-    fn print_type_info(&mut self, ty: TypeId, docs: &Docs) {
-        self.docs(docs);
+    fn print_type_info(&mut self, iface: &Interface, ty: TypeId, docs: &Docs) {
+        self.definition(docs, &wit::type_def(iface, ty));
         self.src.push_str("\n");
         self.src
             .push_str(&format!("Size: {}, ", self.sizes.size(&Type::Id(ty))));
@@ -184,6 +260,14 @@ impl Markdown {
 }
 
 impl Generator for Markdown {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn supports_recursive_types(&self) -> bool {
+        true
+    }
+
     fn preprocess_one(&mut self, iface: &Interface, _dir: Direction) {
         self.sizes.fill(iface);
     }
@@ -198,7 +282,7 @@ impl Generator for Markdown {
     ) {
         self.print_type_header(name);
         self.src.push_str("record\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n### Record Fields\n\n");
         for field in record.fields.iter() {
             self.src.push_str(&format!(
@@ -212,6 +296,9 @@ impl Generator for Markdown {
                 format!("#{}.{}", name.to_snake_case(), field.name.to_snake_case()),
             );
             self.print_ty(iface, &field.ty, false);
+            if let Some(default) = &field.default {
+                self.src.push_str(&format!(" = `{}`", default));
+            }
             self.src.indent(1);
             self.src.push_str("\n\n");
             self.docs(&field.docs);
@@ -230,7 +317,7 @@ impl Generator for Markdown {
     ) {
         self.print_type_header(name);
         self.src.push_str("tuple\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n### Tuple Fields\n\n");
         for (i, ty) in tuple.types.iter().enumerate() {
             self.src.push_str(&format!(
@@ -250,7 +337,7 @@ impl Generator for Markdown {
 
     fn type_flags(
         &mut self,
-        _iface: &Interface,
+        iface: &Interface,
         id: TypeId,
         name: &str,
         flags: &Flags,
@@ -258,7 +345,7 @@ impl Generator for Markdown {
     ) {
         self.print_type_header(name);
         self.src.push_str("record\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n### Record Fields\n\n");
         for (i, flag) in flags.flags.iter().enumerate() {
             self.src.push_str(&format!(
@@ -290,7 +377,7 @@ impl Generator for Markdown {
     ) {
         self.print_type_header(name);
         self.src.push_str("variant\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n### Variant Cases\n\n");
         for case in variant.cases.iter() {
             self.src.push_str(&format!(
@@ -325,7 +412,7 @@ impl Generator for Markdown {
     ) {
         self.print_type_header(name);
         self.src.push_str("union\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n### Union Cases\n\n");
         let snake = name.to_snake_case();
         for (i, case) in union.cases.iter().enumerate() {
@@ -344,10 +431,10 @@ impl Generator for Markdown {
         }
     }
 
-    fn type_enum(&mut self, _iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
+    fn type_enum(&mut self, iface: &Interface, id: TypeId, name: &str, enum_: &Enum, docs: &Docs) {
         self.print_type_header(name);
         self.src.push_str("enum\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n### Enum Cases\n\n");
         for case in enum_.cases.iter() {
             self.src.push_str(&format!(
@@ -379,8 +466,8 @@ impl Generator for Markdown {
         self.print_type_header(name);
         self.src.push_str("option<");
         self.print_ty(iface, payload, false);
-        self.src.push_str(">");
-        self.print_type_info(id, docs);
+        self.src.push_str(">\n\n");
+        self.print_type_info(iface, id, docs);
     }
 
     fn type_result(
@@ -414,17 +501,69 @@ impl Generator for Markdown {
                 self.src.push_str("result");
             }
         }
-        self.print_type_info(id, docs);
+        self.src.push_str("\n\n");
+        self.print_type_info(iface, id, docs);
     }
 
     fn type_alias(&mut self, iface: &Interface, id: TypeId, name: &str, ty: &Type, docs: &Docs) {
         self.print_type_header(name);
         self.print_ty(iface, ty, true);
+        if let Some(refinement) = &iface.types[id].refinement {
+            self.src.push_str(&format!(" where `{}`", refinement));
+        }
         self.src.push_str("\n\n");
-        self.print_type_info(id, docs);
+        self.print_type_info(iface, id, docs);
         self.src.push_str("\n");
     }
 
+    fn supports_resources(&self) -> bool {
+        true
+    }
+
+    fn type_resource(&mut self, iface: &Interface, id: TypeId, name: &str, docs: &Docs) {
+        self.print_type_header(name);
+        self.src.push_str("resource\n\n");
+        self.definition(docs, &wit::type_def(iface, id));
+        self.src.push_str("\n");
+    }
+
+    fn type_handle(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _handle: &Handle,
+        docs: &Docs,
+    ) {
+        self.type_alias(iface, id, name, &Type::Id(id), docs);
+    }
+
+    fn supports_futures_and_streams(&self) -> bool {
+        true
+    }
+
+    fn type_future(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _payload: &Option<Type>,
+        docs: &Docs,
+    ) {
+        self.type_alias(iface, id, name, &Type::Id(id), docs);
+    }
+
+    fn type_stream(
+        &mut self,
+        iface: &Interface,
+        id: TypeId,
+        name: &str,
+        _stream: &Stream,
+        docs: &Docs,
+    ) {
+        self.type_alias(iface, id, name, &Type::Id(id), docs);
+    }
+
     fn type_list(&mut self, iface: &Interface, id: TypeId, name: &str, _ty: &Type, docs: &Docs) {
         self.type_alias(iface, id, name, &Type::Id(id), docs);
     }
@@ -433,6 +572,27 @@ impl Generator for Markdown {
         self.type_alias(iface, id, name, ty, docs)
     }
 
+    fn constant(&mut self, iface: &Interface, constant: &Constant) {
+        if self.constants == 0 {
+            self.src.push_str("# Constants\n\n");
+        }
+        self.constants += 1;
+        self.src.push_str(&format!(
+            "## <a href=\"#{}\" name=\"{0}\"></a> `{}`: ",
+            constant.name.to_snake_case(),
+            constant.name,
+        ));
+        self.hrefs.insert(
+            constant.name.clone(),
+            format!("#{}", constant.name.to_snake_case()),
+        );
+        self.print_ty(iface, &constant.ty, false);
+        self.src.push_str(&format!(" = `{}`", constant.value));
+        self.src.push_str("\n\n");
+        self.definition(&constant.docs, &wit::constant(iface, constant));
+        self.src.push_str("\n");
+    }
+
     fn import(&mut self, iface: &Interface, func: &Function) {
         if self.funcs == 0 {
             self.src.push_str("# Functions\n\n");
@@ -449,11 +609,15 @@ impl Generator for Markdown {
         self.src.push_str(&func.name);
         self.src.push_str("` ");
         self.src.push_str("\n\n");
-        self.docs(&func.docs);
+        match func.kind {
+            FunctionKind::Freestanding => self.definition(&func.docs, &wit::function(iface, func)),
+            // Functions of resources are defined along with the resource.
+            _ => self.docs(&func.docs),
+        }
 
         if func.params.len() > 0 {
             self.src.push_str("##### Params\n\n");
-            for (name, ty) in func.params.iter() {
+            for ((name, ty), default) in func.params.iter().zip(&func.defaults) {
                 self.src.push_str(&format!(
                     "- <a href=\"#{f}.{p}\" name=\"{f}.{p}\"></a> `{}`: ",
                     name,
@@ -461,6 +625,9 @@ impl Generator for Markdown {
                     p = name.to_snake_case(),
                 ));
                 self.print_ty(iface, ty, false);
+                if let Some(default) = default {
+                    self.src.push_str(&format!(" = `{}`", default));
+                }
                 self.src.push_str("\n");
             }
         }
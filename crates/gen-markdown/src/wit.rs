@@ -0,0 +1,248 @@
+//! Printing the definitions of items as wit, for the ```` ```wit ```` code
+//! blocks which make generated markdown a literate interface.
+//!
+//! The documentation of the item itself is left to the prose before the
+//! block, while everything nested within it, such as the docs of fields, is
+//! printed as comments.
+
+use wit_bindgen_core::wit_parser::*;
+
+/// Prints the definition of the named type `id`, including the functions of
+/// resources.
+pub fn type_def(iface: &Interface, id: TypeId) -> String {
+    let ty = &iface.types[id];
+    let name = ident(ty.name.as_deref().unwrap());
+    let mut out = String::new();
+    attrs(&mut out, &ty.attrs, "");
+    match &ty.kind {
+        TypeDefKind::Record(r) => {
+            let fields = r.fields.iter().map(|f| {
+                let mut field = format!("{}: {}", ident(&f.name), type_name(iface, &f.ty));
+                if let Some(default) = &f.default {
+                    field.push_str(&format!(" = {}", default));
+                }
+                (&f.docs, &f.attrs, field)
+            });
+            block(&mut out, &format!("record {}", name), fields, ",");
+        }
+        TypeDefKind::Flags(f) => {
+            let flags = f.flags.iter().map(|f| (&f.docs, &f.attrs, ident(&f.name)));
+            block(&mut out, &format!("flags {}", name), flags, ",");
+        }
+        TypeDefKind::Variant(v) => {
+            let cases = v.cases.iter().map(|c| {
+                let case = match &c.ty {
+                    Some(ty) => format!("{}({})", ident(&c.name), type_name(iface, ty)),
+                    None => ident(&c.name),
+                };
+                (&c.docs, &c.attrs, case)
+            });
+            block(&mut out, &format!("variant {}", name), cases, ",");
+        }
+        TypeDefKind::Enum(e) => {
+            let cases = e.cases.iter().map(|c| (&c.docs, &c.attrs, ident(&c.name)));
+            block(&mut out, &format!("enum {}", name), cases, ",");
+        }
+        TypeDefKind::Union(u) => {
+            let cases = u
+                .cases
+                .iter()
+                .map(|c| (&c.docs, &c.attrs, type_name(iface, &c.ty)));
+            block(&mut out, &format!("union {}", name), cases, ",");
+        }
+        TypeDefKind::Resource => {
+            let funcs = iface
+                .functions
+                .iter()
+                .filter(|f| f.resource() == Some(id))
+                .map(|f| (&f.docs, &f.attrs, signature(iface, f)))
+                .collect::<Vec<_>>();
+            if funcs.is_empty() {
+                out.push_str(&format!("resource {}\n", name));
+            } else {
+                block(
+                    &mut out,
+                    &format!("resource {}", name),
+                    funcs.into_iter(),
+                    "",
+                );
+            }
+        }
+        kind => {
+            out.push_str(&format!("type {} = {}", name, kind_name(iface, kind)));
+            if let Some(refinement) = &ty.refinement {
+                out.push_str(&format!(" where {}", refinement));
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Prints the declaration of a freestanding function.
+pub fn function(iface: &Interface, func: &Function) -> String {
+    let mut out = String::new();
+    attrs(&mut out, &func.attrs, "");
+    out.push_str(&signature(iface, func));
+    out.push('\n');
+    out
+}
+
+pub fn constant(iface: &Interface, constant: &Constant) -> String {
+    format!(
+        "const {}: {} = {}\n",
+        ident(&constant.name),
+        type_name(iface, &constant.ty),
+        constant.value
+    )
+}
+
+/// Prints `header { ... }` with each of `items` on its own line, preceded by
+/// its docs and attributes and followed by `sep`.
+fn block<'a>(
+    out: &mut String,
+    header: &str,
+    items: impl Iterator<Item = (&'a Docs, &'a Attributes, String)>,
+    sep: &str,
+) {
+    out.push_str(header);
+    out.push_str(" {\n");
+    for (item_docs, item_attrs, item) in items {
+        docs(out, item_docs, "  ");
+        attrs(out, item_attrs, "  ");
+        out.push_str(&format!("  {}{}\n", item, sep));
+    }
+    out.push_str("}\n");
+}
+
+fn docs(out: &mut String, docs: &Docs, indent: &str) {
+    for line in docs.contents.iter().flat_map(|c| c.lines()) {
+        if line.is_empty() {
+            out.push_str(&format!("{}///\n", indent));
+        } else {
+            out.push_str(&format!("{}/// {}\n", indent, line));
+        }
+    }
+}
+
+fn attrs(out: &mut String, attrs: &Attributes, indent: &str) {
+    for attr in attrs.attrs.iter() {
+        out.push_str(&format!("{}{}\n", indent, attr));
+    }
+}
+
+/// Prints how `func` is declared, either on its own or within its resource.
+fn signature(iface: &Interface, func: &Function) -> String {
+    let mut params = &func.params[..];
+    let mut defaults = &func.defaults[..];
+    let mut ret = match &func.kind {
+        FunctionKind::Freestanding => format!("{}: ", ident(&func.name)),
+        FunctionKind::Method { name, .. } => {
+            // Skip `self`, which is implicit.
+            params = &params[1..];
+            defaults = &defaults[1..];
+            format!("{}: ", ident(name))
+        }
+        FunctionKind::Static { name, .. } => format!("{}: static ", ident(name)),
+        FunctionKind::Constructor { .. } => "constructor".to_string(),
+    };
+    if !matches!(func.kind, FunctionKind::Constructor { .. }) {
+        if func.is_async {
+            ret.push_str("async ");
+        }
+        ret.push_str("func");
+    }
+    ret.push('(');
+    for (i, ((name, ty), default)) in params.iter().zip(defaults).enumerate() {
+        if i > 0 {
+            ret.push_str(", ");
+        }
+        ret.push_str(&format!("{}: {}", ident(name), type_name(iface, ty)));
+        if let Some(default) = default {
+            ret.push_str(&format!(" = {}", default));
+        }
+    }
+    ret.push(')');
+    match &func.results {
+        Results::Named(results) if results.is_empty() => {}
+        Results::Named(results) => {
+            let results = results
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", ident(name), type_name(iface, ty)))
+                .collect::<Vec<_>>();
+            ret.push_str(&format!(" -> ({})", results.join(", ")));
+        }
+        Results::Anon(ty) => ret.push_str(&format!(" -> {}", type_name(iface, ty))),
+    }
+    ret
+}
+
+/// Returns `name` as an identifier, escaped with `%` if it's a keyword.
+fn ident(name: &str) -> String {
+    if ast::keywords().any(|k| k == name) {
+        format!("%{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns how `ty` is referred to, by name if it has one.
+fn type_name(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::Float32 => "float32".to_string(),
+        Type::Float64 => "float64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::Id(id) => {
+            let ty = &iface.types[*id];
+            match &ty.name {
+                Some(name) => ident(name),
+                None => kind_name(iface, &ty.kind),
+            }
+        }
+    }
+}
+
+/// Returns how a type of `kind` is written without a name.
+fn kind_name(iface: &Interface, kind: &TypeDefKind) -> String {
+    let name = |ty: &Type| type_name(iface, ty);
+    let optional = |ty: &Option<Type>| ty.as_ref().map_or("_".to_string(), name);
+    match kind {
+        TypeDefKind::Type(ty) => name(ty),
+        TypeDefKind::Tuple(t) => {
+            let types = t.types.iter().map(name).collect::<Vec<_>>();
+            format!("tuple<{}>", types.join(", "))
+        }
+        TypeDefKind::Option(ty) => format!("option<{}>", name(ty)),
+        TypeDefKind::Result(r) => match (&r.ok, &r.err) {
+            (None, None) => "result".to_string(),
+            (ok, None) => format!("result<{}>", optional(ok)),
+            (ok, Some(err)) => format!("result<{}, {}>", optional(ok), name(err)),
+        },
+        TypeDefKind::List(ty) => format!("list<{}>", name(ty)),
+        TypeDefKind::Future(None) => "future".to_string(),
+        TypeDefKind::Future(Some(ty)) => format!("future<{}>", name(ty)),
+        TypeDefKind::Stream(s) => match (&s.element, &s.end) {
+            (None, None) => "stream".to_string(),
+            (element, None) => format!("stream<{}>", optional(element)),
+            (element, Some(end)) => format!("stream<{}, {}>", optional(element), name(end)),
+        },
+        TypeDefKind::Handle(Handle::Own(r)) => format!("own<{}>", name(&Type::Id(*r))),
+        TypeDefKind::Handle(Handle::Borrow(r)) => format!("borrow<{}>", name(&Type::Id(*r))),
+        TypeDefKind::Record(_)
+        | TypeDefKind::Flags(_)
+        | TypeDefKind::Variant(_)
+        | TypeDefKind::Enum(_)
+        | TypeDefKind::Union(_)
+        | TypeDefKind::Resource => unreachable!("anonymous {:?}", kind),
+    }
+}
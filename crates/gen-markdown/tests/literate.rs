@@ -0,0 +1,32 @@
+use std::fs;
+use std::path::Path;
+use wit_bindgen_core::wit_parser::Interface;
+use wit_bindgen_core::{Files, Generator};
+use wit_bindgen_gen_markdown::Markdown;
+
+fn markdown(iface: &Interface) -> String {
+    let mut files = Files::default();
+    Markdown::new()
+        .generate_all(std::slice::from_ref(iface), &[], &mut files)
+        .unwrap();
+    let (_, md) = files
+        .iter()
+        .find(|(name, _)| *name == "bindings.md")
+        .unwrap();
+    String::from_utf8(md.to_vec()).unwrap()
+}
+
+/// The generated markdown is itself a literate interface, which parses back
+/// to the interface it documents.
+#[test]
+fn round_trip() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/codegen");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let iface = Interface::parse_file(&path).unwrap();
+        let md = markdown(&iface);
+        let parsed = Interface::parse("bindings.wit.md", &md)
+            .unwrap_or_else(|e| panic!("{}: {:?}\n{}", path.display(), e, md));
+        assert_eq!(markdown(&parsed), md, "{}", path.display());
+    }
+}
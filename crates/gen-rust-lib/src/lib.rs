@@ -2,6 +2,7 @@ use heck::*;
 use std::collections::HashMap;
 use std::fmt::{self, Write};
 use std::iter::zip;
+use wit_bindgen_core::lint::{Item, Naming};
 use wit_bindgen_core::wit_parser::abi::{Bitcast, LiftLower, WasmType};
 use wit_bindgen_core::{wit_parser::*, TypeInfo, Types};
 
@@ -37,14 +38,45 @@ pub trait RustGenerator {
     fn default_param_mode(&self) -> TypeMode;
 
     fn rustdoc(&mut self, docs: &Docs) {
-        let docs = match &docs.contents {
-            Some(docs) => docs,
+        self.rustdoc_contents(docs);
+        self.rustdoc_deprecated(docs);
+    }
+
+    /// Prints the doc comment lines of `docs`, including the version it's
+    /// available since.
+    fn rustdoc_contents(&mut self, docs: &Docs) {
+        if let Some(contents) = &docs.contents {
+            for line in contents.trim().lines() {
+                self.push_str("/// ");
+                self.push_str(line);
+                self.push_str("\n");
+            }
+        }
+        if let Some(since) = &docs.since {
+            if docs.contents.is_some() {
+                self.push_str("///\n");
+            }
+            self.push_str(&format!("/// Available since `{}`.\n", since));
+        }
+    }
+
+    /// Prints a `#[deprecated]` attribute if the item is deprecated.
+    fn rustdoc_deprecated(&mut self, docs: &Docs) {
+        let deprecated = match &docs.deprecated {
+            Some(deprecated) => deprecated,
             None => return,
         };
-        for line in docs.trim().lines() {
-            self.push_str("/// ");
-            self.push_str(line);
-            self.push_str("\n");
+        let mut args = Vec::new();
+        if let Some(since) = &deprecated.since {
+            args.push(format!("since = {:?}", since));
+        }
+        if let Some(note) = &deprecated.note {
+            args.push(format!("note = {:?}", note));
+        }
+        if args.is_empty() {
+            self.push_str("#[deprecated]\n");
+        } else {
+            self.push_str(&format!("#[deprecated({})]\n", args.join(", ")));
         }
     }
 
@@ -84,6 +116,65 @@ pub trait RustGenerator {
         // }
     }
 
+    /// Documents the default values of `func`'s parameters, as Rust has no
+    /// way of declaring them.
+    fn rustdoc_defaults(&mut self, func: &Function) {
+        let defaults = func
+            .params
+            .iter()
+            .zip(&func.defaults)
+            .filter_map(|((name, _), default)| Some((name, default.as_ref()?)))
+            .collect::<Vec<_>>();
+        if defaults.is_empty() {
+            return;
+        }
+        if func.docs.contents.is_some() || func.docs.since.is_some() {
+            self.push_str("///\n");
+        }
+        for (name, default) in defaults {
+            self.push_str(&format!(
+                "/// `{}` defaults to `{}`.\n",
+                to_rust_ident(name),
+                rust_literal(default)
+            ));
+        }
+    }
+
+    /// Prints the extra derives asked for with `@rust(derive = "...")`.
+    fn print_rust_derives(&mut self, attrs: &Attributes) {
+        for attr in attrs.get_all("rust") {
+            if let Some(Literal::String(derives)) = attr.arg("derive") {
+                self.push_str(&format!("#[derive({})]\n", derives));
+            }
+        }
+    }
+
+    fn print_constant(&mut self, iface: &Interface, constant: &Constant) {
+        self.rustdoc(&constant.docs);
+        let ty = match iface.unalias(&constant.ty) {
+            Type::Bool => "bool",
+            Type::U8 => "u8",
+            Type::U16 => "u16",
+            Type::U32 => "u32",
+            Type::U64 => "u64",
+            Type::S8 => "i8",
+            Type::S16 => "i16",
+            Type::S32 => "i32",
+            Type::S64 => "i64",
+            Type::Float32 => "f32",
+            Type::Float64 => "f64",
+            Type::Char => "char",
+            Type::String => "&str",
+            Type::Id(_) => unreachable!("constants have primitive types"),
+        };
+        self.push_str(&format!(
+            "pub const {}: {} = {};\n",
+            constant.name.to_shouty_snake_case(),
+            ty,
+            rust_literal(&constant.value)
+        ));
+    }
+
     fn print_signature(
         &mut self,
         iface: &Interface,
@@ -104,8 +195,10 @@ pub trait RustGenerator {
         param_mode: TypeMode,
         sig: &FnSig,
     ) -> Vec<String> {
-        self.rustdoc(&func.docs);
+        self.rustdoc_contents(&func.docs);
         self.rustdoc_params(&func.params, "Parameters");
+        self.rustdoc_defaults(func);
+        self.rustdoc_deprecated(&func.docs);
         // TODO: re-add this when docs are back
         // self.rustdoc_params(&func.results, "Return");
 
@@ -119,10 +212,10 @@ pub trait RustGenerator {
             self.push_str("async ");
         }
         self.push_str("fn ");
-        let func_name = if sig.use_item_name {
-            func.item_name()
-        } else {
-            &func.name
+        let func_name = match &sig.name {
+            Some(name) => name,
+            None if sig.use_item_name => resource_fn_name(func),
+            None => &func.name,
         };
         self.push_str(&to_rust_ident(&func_name));
         if let Some(generics) = &sig.generics {
@@ -238,7 +331,7 @@ pub trait RustGenerator {
                     | TypeDefKind::Union(_) => true,
                     TypeDefKind::Type(Type::Id(t)) => needs_generics(iface, &iface.types[*t].kind),
                     TypeDefKind::Type(Type::String) => true,
-                    TypeDefKind::Type(_) => false,
+                    TypeDefKind::Type(_) | TypeDefKind::Resource | TypeDefKind::Handle(_) => false,
                 }
             }
         }
@@ -248,7 +341,7 @@ pub trait RustGenerator {
 
             TypeDefKind::Option(t) => {
                 self.push_str("Option<");
-                self.print_ty(iface, t, mode);
+                self.print_option_payload(iface, id, t, mode);
                 self.push_str(">");
             }
 
@@ -297,11 +390,40 @@ pub trait RustGenerator {
                 self.print_optional_ty(iface, stream.end.as_ref(), mode);
                 self.push_str(">");
             }
+            TypeDefKind::Handle(h) => {
+                if let Handle::Borrow(_) = h {
+                    self.push_str("&");
+                    if let TypeMode::AllBorrowed(lt) | TypeMode::LeafBorrowed(lt) = mode {
+                        self.push_str(lt);
+                        self.push_str(" ");
+                    }
+                }
+                self.print_resource(iface, h.resource());
+            }
+            TypeDefKind::Resource => unreachable!("resources are always named"),
 
             TypeDefKind::Type(t) => self.print_ty(iface, t, mode),
         }
     }
 
+    /// Prints the type which represents the resource `id` within bindings.
+    fn print_resource(&mut self, iface: &Interface, id: TypeId) {
+        let name = iface.types[id].name.as_ref().unwrap();
+        self.push_str(&name.to_upper_camel_case());
+    }
+
+    /// Prints the payload of the option `id`, which is boxed if the option is
+    /// part of a recursive type.
+    fn print_option_payload(&mut self, iface: &Interface, id: TypeId, ty: &Type, mode: TypeMode) {
+        if iface.is_recursive(id) {
+            self.push_str("Box<");
+            self.print_ty(iface, ty, mode);
+            self.push_str(">");
+        } else {
+            self.print_ty(iface, ty, mode);
+        }
+    }
+
     fn print_list(&mut self, iface: &Interface, ty: &Type, mode: TypeMode) {
         match mode {
             TypeMode::AllBorrowed(lt) => {
@@ -377,6 +499,9 @@ pub trait RustGenerator {
     }
 
     /// Writes the camel-cased 'name' of the passed type to `out`, as used to name union variants.
+    ///
+    /// Union variants are public, so anonymous types keep these names rather
+    /// than using `Types::anonymous_name`, which would rename them.
     fn write_name(&self, iface: &Interface, ty: &Type, out: &mut String) {
         match ty {
             Type::Bool => out.push_str("Bool"),
@@ -423,6 +548,15 @@ pub trait RustGenerator {
                         TypeDefKind::Variant(_) => out.push_str("Variant"),
                         TypeDefKind::Enum(_) => out.push_str("Enum"),
                         TypeDefKind::Union(_) => out.push_str("Union"),
+                        TypeDefKind::Handle(Handle::Own(id)) => {
+                            out.push_str("Own");
+                            self.write_name(iface, &Type::Id(*id), out);
+                        }
+                        TypeDefKind::Handle(Handle::Borrow(id)) => {
+                            out.push_str("Borrow");
+                            self.write_name(iface, &Type::Id(*id), out);
+                        }
+                        TypeDefKind::Resource => unreachable!("resources are always named"),
                     },
                 }
             }
@@ -514,11 +648,19 @@ pub trait RustGenerator {
             } else {
                 self.push_str("#[derive(Clone)]\n");
             }
+            self.print_rust_derives(&iface.types[id].attrs);
             self.push_str(&format!("pub struct {}", name));
             self.print_generics(lt);
             self.push_str(" {\n");
             for field in record.fields.iter() {
-                self.rustdoc(&field.docs);
+                self.rustdoc_contents(&field.docs);
+                if let Some(default) = &field.default {
+                    if field.docs.contents.is_some() || field.docs.since.is_some() {
+                        self.push_str("///\n");
+                    }
+                    self.push_str(&format!("/// Defaults to `{}`.\n", rust_literal(default)));
+                }
+                self.rustdoc_deprecated(&field.docs);
                 if derive_component {
                     self.push_str(&format!("#[component(name = \"{}\")]\n", field.name));
                 }
@@ -652,6 +794,7 @@ pub trait RustGenerator {
             } else {
                 self.push_str("#[derive(Clone)]\n");
             }
+            self.print_rust_derives(&iface.types[id].attrs);
             self.push_str(&format!("pub enum {name}"));
             self.print_generics(lt);
             self.push_str("{\n");
@@ -732,7 +875,7 @@ pub trait RustGenerator {
             self.push_str(&format!("pub type {}", name));
             self.print_generics(lt);
             self.push_str("= Option<");
-            self.print_ty(iface, payload, mode);
+            self.print_option_payload(iface, id, payload, mode);
             self.push_str(">;\n");
         }
     }
@@ -761,8 +904,8 @@ pub trait RustGenerator {
 
     fn print_typedef_enum(
         &mut self,
+        iface: &Interface,
         id: TypeId,
-        name: &str,
         enum_: &Enum,
         docs: &Docs,
         attrs: &[String],
@@ -770,8 +913,15 @@ pub trait RustGenerator {
     ) where
         Self: Sized,
     {
-        // TODO: should this perhaps be an attribute in the wit file?
-        let is_error = name.contains("errno");
+        // Enums are errors if they're marked `@rust(error)`, or otherwise if
+        // their name looks like one.
+        let ty = &iface.types[id];
+        let name = ty.name.as_deref().unwrap();
+        let wit_attrs = &ty.attrs;
+        let is_error = match wit_attrs.lookup("rust", "error") {
+            Some(Literal::Bool(b)) => *b,
+            _ => name.contains("errno"),
+        };
 
         let name = name.to_upper_camel_case();
         self.rustdoc(docs);
@@ -781,6 +931,7 @@ pub trait RustGenerator {
         self.push_str("#[repr(");
         self.int_repr(enum_.tag());
         self.push_str(")]\n#[derive(Clone, Copy, PartialEq, Eq)]\n");
+        self.print_rust_derives(wit_attrs);
         self.push_str(&format!("pub enum {} {{\n", name.to_upper_camel_case()));
         for case in enum_.cases.iter() {
             self.rustdoc(&case.docs);
@@ -933,12 +1084,45 @@ pub trait RustGenerator {
     }
 }
 
+/// Returns the name of `func` when it's printed as an associated function of
+/// its resource, where constructors follow Rust's convention of being named
+/// `new`.
+pub fn resource_fn_name(func: &Function) -> &str {
+    match func.kind {
+        FunctionKind::Constructor { .. } => "new",
+        _ => func.item_name(),
+    }
+}
+
+/// Returns what the Rust generators don't support about handles in the type
+/// `id`, for use in `Generator::unsupported_type`.
+///
+/// Handles are only supported as the parameters and results of functions,
+/// where the bindings can take ownership of them one at a time.
+pub fn unsupported_handle(iface: &Interface, id: TypeId) -> Option<String> {
+    let ty = &iface.types[id];
+    if let TypeDefKind::Handle(_) = ty.kind {
+        if ty.name.is_some() {
+            return Some("named handle types".to_string());
+        }
+    }
+    let is_handle = |ty: &Type| match ty {
+        Type::Id(id) => matches!(iface.types[*id].kind, TypeDefKind::Handle(_)),
+        _ => false,
+    };
+    if iface.contained_types(id).iter().any(is_handle) {
+        return Some("handles within other types".to_string());
+    }
+    None
+}
+
 #[derive(Default)]
 pub struct FnSig {
     pub async_: bool,
     pub unsafe_: bool,
     pub private: bool,
     pub use_item_name: bool,
+    pub name: Option<String>,
     pub generics: Option<String>,
     pub self_arg: Option<String>,
     pub self_is_first_param: bool,
@@ -1059,61 +1243,81 @@ pub trait RustFunctionGenerator {
     }
 }
 
+/// Rust's keywords, which [`to_rust_ident`] escapes.
+///
+/// Source: https://doc.rust-lang.org/reference/keywords.html
+pub const RUST_KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+];
+
 pub fn to_rust_ident(name: &str) -> String {
-    match name {
-        // Escape Rust keywords.
-        // Source: https://doc.rust-lang.org/reference/keywords.html
-        "as" => "as_".into(),
-        "break" => "break_".into(),
-        "const" => "const_".into(),
-        "continue" => "continue_".into(),
-        "crate" => "crate_".into(),
-        "else" => "else_".into(),
-        "enum" => "enum_".into(),
-        "extern" => "extern_".into(),
-        "false" => "false_".into(),
-        "fn" => "fn_".into(),
-        "for" => "for_".into(),
-        "if" => "if_".into(),
-        "impl" => "impl_".into(),
-        "in" => "in_".into(),
-        "let" => "let_".into(),
-        "loop" => "loop_".into(),
-        "match" => "match_".into(),
-        "mod" => "mod_".into(),
-        "move" => "move_".into(),
-        "mut" => "mut_".into(),
-        "pub" => "pub_".into(),
-        "ref" => "ref_".into(),
-        "return" => "return_".into(),
-        "self" => "self_".into(),
-        "static" => "static_".into(),
-        "struct" => "struct_".into(),
-        "super" => "super_".into(),
-        "trait" => "trait_".into(),
-        "true" => "true_".into(),
-        "type" => "type_".into(),
-        "unsafe" => "unsafe_".into(),
-        "use" => "use_".into(),
-        "where" => "where_".into(),
-        "while" => "while_".into(),
-        "async" => "async_".into(),
-        "await" => "await_".into(),
-        "dyn" => "dyn_".into(),
-        "abstract" => "abstract_".into(),
-        "become" => "become_".into(),
-        "box" => "box_".into(),
-        "do" => "do_".into(),
-        "final" => "final_".into(),
-        "macro" => "macro_".into(),
-        "override" => "override_".into(),
-        "priv" => "priv_".into(),
-        "typeof" => "typeof_".into(),
-        "unsized" => "unsized_".into(),
-        "virtual" => "virtual_".into(),
-        "yield" => "yield_".into(),
-        "try" => "try_".into(),
-        s => s.to_snake_case(),
+    if RUST_KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_snake_case()
+    }
+}
+
+/// The [`Naming`] of generated Rust bindings.
+pub struct RustNaming;
+
+impl Naming for RustNaming {
+    fn language(&self) -> &'static str {
+        "rust"
+    }
+
+    fn convert(&self, item: &Item<'_>) -> Option<String> {
+        let name = item.name();
+        Some(match item {
+            Item::Type(_) | Item::Case { .. } | Item::EnumCase { .. } => name.to_upper_camel_case(),
+            Item::Flag { .. } | Item::Constant(_) => name.to_shouty_snake_case(),
+            Item::Field { .. } | Item::Function { .. } | Item::Param { .. } => name.to_snake_case(),
+        })
+    }
+
+    fn escape(&self, item: &Item<'_>, ident: &str) -> Option<String> {
+        match item {
+            Item::Field { .. } | Item::Function { .. } | Item::Param { .. }
+                if RUST_KEYWORDS.contains(&ident) =>
+            {
+                Some(to_rust_ident(ident))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_reserved(&self, item: &Item<'_>, ident: &str) -> bool {
+        // Types are declared next to imports of these, which generated code
+        // refers to unqualified.
+        let shadowed = match item {
+            Item::Type(_) => ["Box", "Option", "Result", "String", "Vec"].contains(&ident),
+            _ => false,
+        };
+        shadowed || ident == "Self" || RUST_KEYWORDS.contains(&ident)
+    }
+}
+
+/// Returns a Rust expression which is `true` if `value`, a place of the
+/// refined type, satisfies `refinement`.
+pub fn rust_refinement_check(refinement: &Refinement, value: &str) -> String {
+    // Wit ranges are written just like Rust's, and using `contains` avoids
+    // warnings about comparisons which are always true.
+    match refinement {
+        Refinement::Range(range) => format!("({}).contains(&{})", range, value),
+        Refinement::Length(range) => format!("({}).contains(&{}.len())", range, value),
+    }
+}
+
+/// Returns `literal` written as a Rust expression.
+pub fn rust_literal(literal: &Literal) -> String {
+    match literal {
+        Literal::Char(c) => format!("{:?}", c),
+        Literal::String(s) => format!("{:?}", s),
+        other => other.to_string(),
     }
 }
 
@@ -1141,7 +1345,7 @@ trait TypeInfoExt {
 
 impl TypeInfoExt for TypeInfo {
     fn owns_data(&self) -> bool {
-        self.has_list
+        self.has_list || self.has_box
     }
 }
 
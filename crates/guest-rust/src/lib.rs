@@ -9,12 +9,41 @@ pub use wit_bindgen_guest_rust_macro::{export, import};
 #[doc(hidden)]
 pub use bitflags;
 
+// Re-export `async_trait` so that implementations of traits with `async`
+// exports can use the same version as the generated code.
+#[cfg(feature = "async")]
+pub use async_trait::async_trait;
+
+/// A value which didn't satisfy the `where` refinement of its WIT type.
+///
+/// Guests trap by design when they lift such a value, as neither imports nor
+/// exports have a way to return this error: the generated code panics with
+/// it as the message. Hosts, on the other hand, report it as an error from
+/// the call which passed the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefinementError {
+    /// The name of the refined type.
+    pub ty: &'static str,
+    /// The refinement itself, as written in WIT.
+    pub refinement: &'static str,
+}
+
+impl core::fmt::Display for RefinementError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "value of `{}` does not satisfy `where {}`",
+            self.ty, self.refinement
+        )
+    }
+}
+
 #[doc(hidden)]
 pub mod rt {
     use super::alloc::alloc::Layout;
 
     // Re-export things from liballoc for convenient use.
-    pub use super::alloc::{alloc, string, vec};
+    pub use super::alloc::{alloc, boxed, string, vec};
 
     #[no_mangle]
     unsafe extern "C" fn cabi_realloc(
@@ -48,6 +77,43 @@ pub mod rt {
         alloc::dealloc(ptr as *mut u8, layout);
     }
 
+    /// Called by generated lifting code when a value fails the refinement of
+    /// its type, trapping the guest.
+    #[cold]
+    pub fn refinement_failed(ty: &'static str, refinement: &'static str) -> ! {
+        panic!("{}", super::RefinementError { ty, refinement })
+    }
+
+    /// Drives `future` to completion on the current thread.
+    ///
+    /// Guests have no event loop to wait on, and async imports are
+    /// synchronous from the guest's point of view, with the host suspending
+    /// the guest while they run. Exports must therefore complete after a
+    /// single poll: nothing could ever wake a pending future, so this panics
+    /// (trapping the guest) rather than spinning forever.
+    pub fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+        fn noop_raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                noop_raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+
+        let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = future;
+        // SAFETY: `future` is shadowed and never moved again.
+        let mut future = unsafe { core::pin::Pin::new_unchecked(&mut future) };
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => panic!("async export awaited a future which never completes"),
+        }
+    }
+
     macro_rules! as_traits {
         ($(($trait_:ident $func:ident $ty:ident <=> $($tys:ident)*))*) => ($(
             pub fn $func<T: $trait_>(t: T) -> $ty {
@@ -1,10 +1,11 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 
 use proc_macro::TokenStream;
 use syn::parse::{Error, Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{token, Token};
-use wit_bindgen_core::{wit_parser::Interface, Direction, Files, Generator};
+use wit_bindgen_core::wit_parser::{Interface, WitCache};
+use wit_bindgen_core::{Direction, Files, Generator};
 
 /// Generate code to support consuming the given interfaces, importaing them
 /// from wasm modules.
@@ -28,24 +29,25 @@ fn run(input: TokenStream, dir: Direction) -> TokenStream {
         Direction::Import => (input.interfaces, vec![]),
         Direction::Export => (vec![], input.interfaces),
     };
-    gen.generate_all(&imports, &exports, &mut files);
+    if let Err(e) = gen.generate_all(&imports, &exports, &mut files) {
+        return Error::new(proc_macro2::Span::call_site(), e)
+            .to_compile_error()
+            .into();
+    }
 
     let (_, contents) = files.iter().next().unwrap();
 
     let contents = std::str::from_utf8(contents).unwrap();
     let mut contents = contents.parse::<TokenStream>().unwrap();
 
-    // Include a dummy `include_str!` for any files we read so rustc knows that
-    // we depend on the contents of those files.
-    let cwd = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    // Include a dummy `include_str!` for any files we read, including those
+    // loaded for `use` statements, so rustc knows that we depend on the
+    // contents of those files.
     for file in input.files.iter() {
         contents.extend(
-            format!(
-                "const _: &str = include_str!(r#\"{}\"#);\n",
-                Path::new(&cwd).join(file).display()
-            )
-            .parse::<TokenStream>()
-            .unwrap(),
+            format!("const _: &str = include_str!(r#\"{}\"#);\n", file.display())
+                .parse::<TokenStream>()
+                .unwrap(),
         );
     }
 
@@ -55,7 +57,7 @@ fn run(input: TokenStream, dir: Direction) -> TokenStream {
 struct Opts {
     opts: wit_bindgen_gen_host_wasmtime_rust::Opts,
     interfaces: Vec<Interface>,
-    files: Vec<String>,
+    files: Vec<PathBuf>,
 }
 
 mod kw {
@@ -79,8 +81,12 @@ impl Parse for Opts {
             let fields = Punctuated::<ConfigField, Token![,]>::parse_terminated(&content)?;
             for field in fields.into_pairs() {
                 match field.into_value() {
-                    ConfigField::Interfaces(v) => interfaces = v,
+                    ConfigField::Interfaces(v, f) => {
+                        interfaces = v;
+                        files = f;
+                    }
                     ConfigField::Tracing(v) => opts.tracing = v,
+                    ConfigField::Async(v) => opts.async_ = v,
                 }
             }
             if interfaces.is_empty() {
@@ -91,17 +97,12 @@ impl Parse for Opts {
             }
             interfaces
         } else {
+            let mut paths = Vec::new();
             while !input.is_empty() {
-                let s = input.parse::<syn::LitStr>()?;
-                files.push(s.value());
-            }
-            let mut interfaces = Vec::new();
-            let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for path in files.iter() {
-                let path = manifest_dir.join(path);
-                let iface = Interface::parse_file(path).map_err(|e| Error::new(call_site, e))?;
-                interfaces.push(iface);
+                paths.push(input.parse::<syn::LitStr>()?.value());
             }
+            let (interfaces, f) = parse_files(&paths, call_site)?;
+            files = f;
             interfaces
         };
         Ok(Opts {
@@ -113,8 +114,10 @@ impl Parse for Opts {
 }
 
 enum ConfigField {
-    Interfaces(Vec<Interface>),
+    /// The interfaces along with every file read to parse them.
+    Interfaces(Vec<Interface>, Vec<PathBuf>),
     Tracing(bool),
+    Async(bool),
 }
 
 impl Parse for ConfigField {
@@ -129,7 +132,7 @@ impl Parse for ConfigField {
             let s = input.parse::<syn::LitStr>()?;
             let interface =
                 Interface::parse(&name.value(), &s.value()).map_err(|e| Error::new(s.span(), e))?;
-            Ok(ConfigField::Interfaces(vec![interface]))
+            Ok(ConfigField::Interfaces(vec![interface], Vec::new()))
         } else if l.peek(kw::paths) {
             input.parse::<kw::paths>()?;
             input.parse::<Token![:]>()?;
@@ -137,21 +140,37 @@ impl Parse for ConfigField {
             let bracket = syn::bracketed!(paths in input);
             let paths = Punctuated::<syn::LitStr, Token![,]>::parse_terminated(&paths)?;
             let values = paths.iter().map(|s| s.value()).collect::<Vec<_>>();
-            let mut interfaces = Vec::new();
-            let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
-            for value in &values {
-                let value = manifest_dir.join(value);
-                let interface =
-                    Interface::parse_file(value).map_err(|e| Error::new(bracket.span, e))?;
-                interfaces.push(interface);
-            }
-            Ok(ConfigField::Interfaces(interfaces))
+            let (interfaces, files) = parse_files(&values, bracket.span)?;
+            Ok(ConfigField::Interfaces(interfaces, files))
         } else if l.peek(kw::tracing) {
             input.parse::<kw::tracing>()?;
             input.parse::<Token![:]>()?;
             Ok(ConfigField::Tracing(input.parse::<syn::LitBool>()?.value))
+        } else if l.peek(Token![async]) {
+            input.parse::<Token![async]>()?;
+            input.parse::<Token![:]>()?;
+            Ok(ConfigField::Async(input.parse::<syn::LitBool>()?.value))
         } else {
             Err(l.error())
         }
     }
 }
+
+/// Parses the interfaces in `paths`, relative to the crate being compiled,
+/// returning them along with every file read to parse them. Errors are
+/// reported at `span`.
+fn parse_files(
+    paths: &[String],
+    span: proc_macro2::Span,
+) -> Result<(Vec<Interface>, Vec<PathBuf>)> {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut cache = WitCache::default();
+    let mut interfaces = Vec::new();
+    for path in paths {
+        let (iface, _) = cache
+            .parse_file(manifest_dir.join(path))
+            .map_err(|e| Error::new(span, e))?;
+        interfaces.push(iface);
+    }
+    Ok((interfaces, cache.files().map(|p| p.to_path_buf()).collect()))
+}
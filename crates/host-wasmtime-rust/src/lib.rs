@@ -1,6 +1,101 @@
 pub use wit_bindgen_host_wasmtime_rust_macro::{export, import};
 
+#[cfg(feature = "async")]
+pub use async_trait::async_trait;
 #[cfg(feature = "tracing-lib")]
 pub use tracing_lib as tracing;
 #[doc(hidden)]
 pub use {anyhow, wasmtime};
+
+/// A value which didn't satisfy the `where` refinement of its WIT type.
+///
+/// Generated bindings return this, wrapped in an `anyhow::Error`, when a
+/// value crossing the boundary is out of range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RefinementError {
+    /// The name of the refined type.
+    pub ty: &'static str,
+    /// The refinement itself, as written in WIT.
+    pub refinement: &'static str,
+}
+
+impl std::fmt::Display for RefinementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "value of `{}` does not satisfy `where {}`",
+            self.ty, self.refinement
+        )
+    }
+}
+
+impl std::error::Error for RefinementError {}
+
+/// The resources which one side of a component boundary has handed out handles
+/// to, indexed by the `u32` which represents each handle in wasm.
+///
+/// Generated bindings keep a table for each resource implemented by the host.
+pub struct Table<T> {
+    entries: Vec<Option<T>>,
+    free: Vec<u32>,
+}
+
+impl<T> Table<T> {
+    /// Adds `value` to the table, returning the index of its handle.
+    pub fn insert(&mut self, value: T) -> u32 {
+        match self.free.pop() {
+            Some(index) => {
+                self.entries[index as usize] = Some(value);
+                index
+            }
+            None => {
+                self.entries.push(Some(value));
+                (self.entries.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Returns the resource which the handle `index` refers to.
+    pub fn get(&self, index: u32) -> anyhow::Result<&T> {
+        match self.entries.get(index as usize) {
+            Some(Some(value)) => Ok(value),
+            _ => Err(InvalidHandle { index }.into()),
+        }
+    }
+
+    /// Removes the resource which the handle `index` refers to, after which
+    /// the index may be reused for another resource.
+    pub fn remove(&mut self, index: u32) -> anyhow::Result<T> {
+        match self.entries.get_mut(index as usize).and_then(Option::take) {
+            Some(value) => {
+                self.free.push(index);
+                Ok(value)
+            }
+            None => Err(InvalidHandle { index }.into()),
+        }
+    }
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Table<T> {
+        Table {
+            entries: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+/// A handle passed by wasm which doesn't refer to a live resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidHandle {
+    /// The index of the handle.
+    pub index: u32,
+}
+
+impl std::fmt::Display for InvalidHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid handle index {}", self.index)
+    }
+}
+
+impl std::error::Error for InvalidHandle {}
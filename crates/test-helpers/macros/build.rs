@@ -104,10 +104,12 @@ fn main() {
             // TODO: should combine this into one
             wit_bindgen_gen_guest_c::Opts::default()
                 .build()
-                .generate_all(&[import], &[], &mut files);
+                .generate_all(&[import], &[], &mut files)
+                .unwrap();
             wit_bindgen_gen_guest_c::Opts::default()
                 .build()
-                .generate_all(&[], &[export], &mut files);
+                .generate_all(&[], &[export], &mut files)
+                .unwrap();
 
             let out_dir = out_dir.join(format!(
                 "c-{}",
@@ -227,7 +229,8 @@ fn main() {
                             &[]
                         },
                         &mut files,
-                    );
+                    )
+                    .unwrap();
 
                 for (file, contents) in files.iter() {
                     let dst = package_dir.join(file);
@@ -4,7 +4,7 @@ pub use test_helpers_macros::*;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use wit_bindgen_core::{Files, Generator};
+use wit_bindgen_core::{Files, Generator, UnsupportedFeature};
 use wit_parser::abi::{AbiVariant, WasmType};
 use wit_parser::{Function, Interface};
 
@@ -33,7 +33,7 @@ pub fn run_codegen_test(
         Direction::Import => (vec![iface], vec![]),
         Direction::Export => (vec![], vec![iface]),
     };
-    gen.generate_all(&imports, &exports, &mut files);
+    gen.generate_all(&imports, &exports, &mut files).unwrap();
 
     let gen_name = format!(
         "{gen_name}-{}",
@@ -49,6 +49,17 @@ pub fn run_codegen_test(
     verify(&dir, wit_name);
 }
 
+/// Asserts that `gen` rejects the `*.wit` file specified by `wit_contents`
+/// with an [`UnsupportedFeature`] error for `construct`, rather than
+/// panicking while generating bindings for it.
+pub fn assert_unsupported(mut gen: impl Generator, wit_contents: &str, construct: &str) {
+    let mut files = Default::default();
+    let iface = Interface::parse("unsupported", wit_contents).unwrap();
+    let err = gen.generate_all(&[iface], &[], &mut files).unwrap_err();
+    let err = err.downcast::<UnsupportedFeature>().unwrap();
+    assert_eq!(err.construct, construct);
+}
+
 /// Returns a suitable directory to place output for tests within.
 ///
 /// This tries to pick a location in the `target` directory that can be
@@ -0,0 +1,3 @@
+include!("../../../../tests/runtime/async_functions/wasm.rs");
+
+fn main() {}
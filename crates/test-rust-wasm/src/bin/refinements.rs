@@ -0,0 +1,3 @@
+include!("../../../../tests/runtime/refinements/wasm.rs");
+
+fn main() {}
@@ -0,0 +1,3 @@
+include!("../../../../tests/runtime/resources/wasm.rs");
+
+fn main() {}
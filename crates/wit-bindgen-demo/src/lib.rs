@@ -1,7 +1,8 @@
 use anyhow::Result;
 use std::sync::Once;
-use wit_bindgen_core::wit_parser::Interface;
-use wit_bindgen_core::{Files, Generator};
+use wit_bindgen_core::registry::{Options, Registry};
+use wit_bindgen_core::wit_parser::{Interface, ParseOptions, WitSources};
+use wit_bindgen_core::Files;
 
 wit_bindgen_guest_rust::export!("demo.wit");
 wit_bindgen_guest_rust::import!("console.wit");
@@ -15,24 +16,48 @@ impl demo::Demo for Demo {
         options: demo::Options,
     ) -> Result<Vec<(String, String)>, String> {
         init();
+        let iface = Interface::parse("input", &wit).map_err(|e| format!("{:?}", e))?;
+        render_interface(lang, iface, &options)
+    }
 
-        let mut files = Files::default();
-        render(lang, &wit, &mut files, &options).map_err(|e| format!("{:?}", e))?;
-
-        Ok(files
-            .iter()
-            .map(|(name, contents)| {
-                let contents = if contents.starts_with(b"\0asm") {
-                    wasmprinter::print_bytes(contents).unwrap()
-                } else {
-                    String::from_utf8_lossy(&contents).into()
-                };
-                (name.to_string(), contents)
-            })
-            .collect())
+    fn render_files(
+        lang: demo::Lang,
+        files: Vec<(String, String)>,
+        root: String,
+        options: demo::Options,
+    ) -> Result<Vec<(String, String)>, String> {
+        init();
+        let parse_options = ParseOptions {
+            sources: files.into_iter().collect::<WitSources>(),
+            ..ParseOptions::default()
+        };
+        let (iface, _) =
+            Interface::parse_file_with(&root, &parse_options).map_err(|e| format!("{:?}", e))?;
+        render_interface(lang, iface, &options)
     }
 }
 
+fn render_interface(
+    lang: demo::Lang,
+    iface: Interface,
+    options: &demo::Options,
+) -> Result<Vec<(String, String)>, String> {
+    let mut files = Files::default();
+    render(lang, iface, &mut files, options).map_err(|e| format!("{:?}", e))?;
+
+    Ok(files
+        .iter()
+        .map(|(name, contents)| {
+            let contents = if contents.starts_with(b"\0asm") {
+                wasmprinter::print_bytes(contents).unwrap()
+            } else {
+                String::from_utf8_lossy(&contents).into()
+            };
+            (name.to_string(), contents)
+        })
+        .collect())
+}
+
 fn init() {
     static INIT: Once = Once::new();
     INIT.call_once(|| {
@@ -45,39 +70,30 @@ fn init() {
     });
 }
 
-fn render(lang: demo::Lang, wit: &str, files: &mut Files, options: &demo::Options) -> Result<()> {
-    let iface = Interface::parse("input", &wit)?;
-
-    let mut gen_world = |mut gen: Box<dyn Generator>| {
-        let (imports, exports) = if options.import {
-            (vec![iface.clone()], vec![])
-        } else {
-            (vec![], vec![iface.clone()])
-        };
-        gen.generate_all(&imports, &exports, files);
-    };
-
-    match lang {
+fn render(
+    lang: demo::Lang,
+    iface: Interface,
+    files: &mut Files,
+    options: &demo::Options,
+) -> Result<()> {
+    let mut gen_options = Options::default();
+    let name = match lang {
         demo::Lang::Rust => {
-            let mut opts = wit_bindgen_gen_guest_rust::Opts::default();
-            opts.unchecked = options.rust_unchecked;
-            gen_world(Box::new(opts.build()))
+            if options.rust_unchecked {
+                gen_options.set_flag("unchecked");
+            }
+            "guest-rust"
         }
-        demo::Lang::Java => gen_world(Box::new(
-            wit_bindgen_gen_guest_teavm_java::Opts::default().build(),
-        )),
+        demo::Lang::Java => "guest-teavm-java",
         demo::Lang::Wasmtime => {
-            let mut opts = wit_bindgen_gen_host_wasmtime_rust::Opts::default();
-            opts.tracing = options.wasmtime_tracing;
-            gen_world(Box::new(opts.build()))
-        }
-        demo::Lang::WasmtimePy => gen_world(Box::new(
-            wit_bindgen_gen_host_wasmtime_py::Opts::default().build(),
-        )),
-        demo::Lang::C => gen_world(Box::new(wit_bindgen_gen_guest_c::Opts::default().build())),
-        demo::Lang::Markdown => {
-            gen_world(Box::new(wit_bindgen_gen_markdown::Opts::default().build()))
+            if options.wasmtime_tracing {
+                gen_options.set_flag("tracing");
+            }
+            "host-wasmtime-rust"
         }
+        demo::Lang::WasmtimePy => "host-wasmtime-py",
+        demo::Lang::C => "guest-c",
+        demo::Lang::Markdown => "markdown",
 
         // JS is different from other languages at this time where it takes a
         // component as input as opposed to an `Interface`. To work with this
@@ -102,8 +118,26 @@ fn render(lang: demo::Lang, wit: &str, files: &mut Files, options: &demo::Option
             }
             let wasm = encoder.encode()?;
             wit_bindgen_gen_host_js::Opts::default().generate("input", &wasm, files)?;
+            return Ok(());
         }
-    }
+    };
+
+    let mut gen = registry().get(name).unwrap().build(&gen_options)?;
+    let (imports, exports) = if options.import {
+        (vec![iface], vec![])
+    } else {
+        (vec![], vec![iface])
+    };
+    gen.generate_all(&imports, &exports, files)
+}
 
-    Ok(())
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    registry.register(wit_bindgen_gen_guest_rust::registration());
+    registry.register(wit_bindgen_gen_guest_c::registration());
+    registry.register(wit_bindgen_gen_guest_teavm_java::registration());
+    registry.register(wit_bindgen_gen_host_wasmtime_rust::registration());
+    registry.register(wit_bindgen_gen_host_wasmtime_py::registration());
+    registry.register(wit_bindgen_gen_markdown::registration());
+    registry
 }
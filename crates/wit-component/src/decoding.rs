@@ -1,3 +1,4 @@
+use crate::metadata::{self, Metadata, Position};
 use anyhow::{anyhow, Context, Result};
 use indexmap::IndexMap;
 use std::hash::{Hash, Hasher};
@@ -16,6 +17,8 @@ struct ComponentInfo<'a> {
     imports: IndexMap<&'a str, ComponentTypeRef>,
     /// Map of exports and what they're exporting.
     exports: IndexMap<&'a str, ComponentExport<'a>>,
+    /// Metadata about the interfaces which their types can't express.
+    metadata: Metadata,
 }
 
 impl<'a> ComponentInfo<'a> {
@@ -29,6 +32,7 @@ impl<'a> ComponentInfo<'a> {
         let mut imports = IndexMap::new();
         let mut depth = 1;
         let mut types = None;
+        let mut metadata = Metadata::default();
 
         for payload in Parser::new(0).parse_all(bytes) {
             let payload = payload?;
@@ -59,6 +63,10 @@ impl<'a> ComponentInfo<'a> {
                         assert!(prev.is_none());
                     }
                 }
+                Payload::CustomSection(s) if depth == 1 && s.name() == metadata::SECTION_NAME => {
+                    metadata = Metadata::decode(s.data())
+                        .context("failed to decode interface metadata")?;
+                }
                 _ => {}
             }
         }
@@ -66,6 +74,7 @@ impl<'a> ComponentInfo<'a> {
             types: types.unwrap(),
             imports,
             exports,
+            metadata,
         })
     }
 }
@@ -130,6 +139,7 @@ pub fn decode_interface_component(bytes: &[u8]) -> Result<ComponentInterfaces<'_
         };
         let mut iface = InterfaceDecoder::new(&info).decode(ty.exports(info.types.as_ref()))?;
         iface.name = name.to_string();
+        info.metadata.apply(Position::Import, &mut iface)?;
         imports.insert(*name, iface);
     }
 
@@ -165,6 +175,7 @@ pub fn decode_interface_component(bytes: &[u8]) -> Result<ComponentInterfaces<'_
                 let mut iface =
                     InterfaceDecoder::new(&info).decode(ty.exports(info.types.as_ref()))?;
                 iface.name = name.to_string();
+                info.metadata.apply(Position::Export, &mut iface)?;
                 exports.insert(*name, iface);
             }
 
@@ -178,7 +189,9 @@ pub fn decode_interface_component(bytes: &[u8]) -> Result<ComponentInterfaces<'_
     let default = if default.is_empty() {
         None
     } else {
-        Some(InterfaceDecoder::new(&info).decode(&default)?)
+        let mut iface = InterfaceDecoder::new(&info).decode(&default)?;
+        info.metadata.apply(Position::Default, &mut iface)?;
+        Some(iface)
     };
 
     Ok(ComponentInterfaces {
@@ -188,6 +201,36 @@ pub fn decode_interface_component(bytes: &[u8]) -> Result<ComponentInterfaces<'_
     })
 }
 
+/// Decode the world described by a component.
+///
+/// This is the same as [`decode_interface_component`] except that the result
+/// is returned as a [`World`] named `name`. Named exports have their `module`
+/// set to their export name, matching what `wit-parser` produces for a
+/// `world` definition.
+pub fn decode_world(name: &str, bytes: &[u8]) -> Result<World> {
+    let ComponentInterfaces {
+        default,
+        imports,
+        exports,
+    } = decode_interface_component(bytes)?;
+    Ok(World {
+        name: name.to_string(),
+        docs: Docs::default(),
+        imports: imports
+            .into_iter()
+            .map(|(name, iface)| (name.to_string(), iface))
+            .collect(),
+        exports: exports
+            .into_iter()
+            .map(|(name, mut iface)| {
+                iface.module = Some(name.to_string());
+                (name.to_string(), iface)
+            })
+            .collect(),
+        default,
+    })
+}
+
 impl<'a> InterfaceDecoder<'a> {
     /// Creates a new interface decoder for the given component information.
     fn new(info: &'a ComponentInfo<'a>) -> InterfaceDecoder<'a> {
@@ -250,9 +293,12 @@ impl<'a> InterfaceDecoder<'a> {
             let ty = self.type_map[&key];
             self.interface.types.alloc(TypeDef {
                 docs: Default::default(),
+                attrs: Default::default(),
                 kind: TypeDefKind::Type(ty),
                 name: Some(name.to_string()),
                 foreign_module: None,
+                refinement: None,
+                span: None,
             });
         }
 
@@ -335,7 +381,10 @@ impl<'a> InterfaceDecoder<'a> {
     }
 
     fn add_function(&mut self, func_name: &str, ty: &types::ComponentFuncType) -> Result<()> {
-        validate_id(func_name)
+        // Functions of resources are named `{resource}::{function}`.
+        func_name
+            .split("::")
+            .try_for_each(validate_id)
             .with_context(|| format!("function name `{}` is not a valid identifier", func_name))?;
 
         let params = self.decode_params(func_name, &ty.params)?;
@@ -343,10 +392,14 @@ impl<'a> InterfaceDecoder<'a> {
 
         self.interface.functions.push(Function {
             docs: Docs::default(),
+            attrs: Attributes::default(),
             name: func_name.to_string(),
             kind: FunctionKind::Freestanding,
+            is_async: false,
+            defaults: vec![None; params.len()],
             params,
             results,
+            span: None,
         });
 
         Ok(())
@@ -476,8 +529,10 @@ impl<'a> InterfaceDecoder<'a> {
 
                     Ok(Field {
                         docs: Docs::default(),
+                        attrs: Attributes::default(),
                         name: name.to_string(),
                         ty: self.decode_type(ty)?,
+                        default: None,
                     })
                 })
                 .collect::<Result<_>>()?,
@@ -509,6 +564,7 @@ impl<'a> InterfaceDecoder<'a> {
 
                     Ok(Case {
                         docs: Docs::default(),
+                        attrs: Attributes::default(),
                         name: name.to_string(),
                         ty: self.decode_optional_type(case.ty.as_ref())?,
                     })
@@ -557,6 +613,7 @@ impl<'a> InterfaceDecoder<'a> {
 
                     Ok(Flag {
                         docs: Docs::default(),
+                        attrs: Attributes::default(),
                         name: name.clone(),
                     })
                 })
@@ -586,6 +643,7 @@ impl<'a> InterfaceDecoder<'a> {
 
                     Ok(EnumCase {
                         docs: Docs::default(),
+                        attrs: Attributes::default(),
                         name: name.to_string(),
                     })
                 })
@@ -608,6 +666,7 @@ impl<'a> InterfaceDecoder<'a> {
                 .map(|ty| {
                     Ok(UnionCase {
                         docs: Docs::default(),
+                        attrs: Attributes::default(),
                         ty: self.decode_type(ty)?,
                     })
                 })
@@ -645,9 +704,12 @@ impl<'a> InterfaceDecoder<'a> {
     fn alloc_type(&mut self, name: Option<String>, kind: TypeDefKind) -> TypeId {
         self.interface.types.alloc(TypeDef {
             docs: Docs::default(),
+            attrs: Attributes::default(),
             kind,
             name,
             foreign_module: None,
+            refinement: None,
+            span: None,
         })
     }
 }
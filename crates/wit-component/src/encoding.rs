@@ -54,6 +54,7 @@
 
 use crate::extract::{extract_module_interfaces, ModuleInterfaces};
 use crate::{
+    metadata::{self, Position},
     validation::{
         expected_export_name, validate_adapter_module, validate_module, ValidatedAdapter,
         ValidatedModule,
@@ -69,8 +70,8 @@ use wasm_encoder::*;
 use wasmparser::{Validator, WasmFeatures};
 use wit_parser::{
     abi::{AbiVariant, WasmSignature, WasmType},
-    Enum, Flags, Function, FunctionKind, Interface, Params, Record, Result_, Results, Tuple, Type,
-    TypeDef, TypeDefKind, Union, Variant,
+    Enum, Flags, Function, Handle, Interface, Params, ParseOptions, Record, Result_, Results,
+    Tuple, Type, TypeDef, TypeDefKind, Union, Variant, World,
 };
 
 const INDIRECT_TABLE_NAME: &str = "$imports";
@@ -259,6 +260,33 @@ impl PartialEq for TypeDefKey<'_> {
                     };
                     ok_eq && err_eq
                 }
+                (TypeDefKind::Future(t1), TypeDefKind::Future(t2)) => {
+                    t1.map(|ty| TypeKey {
+                        interface: self.interface,
+                        ty,
+                    }) == t2.map(|ty| TypeKey {
+                        interface: other.interface,
+                        ty,
+                    })
+                }
+                (TypeDefKind::Stream(s1), TypeDefKind::Stream(s2)) => {
+                    let key = |interface, ty: Option<Type>| ty.map(|ty| TypeKey { interface, ty });
+                    key(self.interface, s1.element) == key(other.interface, s2.element)
+                        && key(self.interface, s1.end) == key(other.interface, s2.end)
+                }
+                (TypeDefKind::Resource, TypeDefKind::Resource) => true,
+                (TypeDefKind::Handle(h1), TypeDefKind::Handle(h2)) => {
+                    matches!(
+                        (h1, h2),
+                        (Handle::Own(_), Handle::Own(_)) | (Handle::Borrow(_), Handle::Borrow(_))
+                    ) && TypeKey {
+                        interface: self.interface,
+                        ty: Type::Id(h1.resource()),
+                    } == TypeKey {
+                        interface: other.interface,
+                        ty: Type::Id(h2.resource()),
+                    }
+                }
                 _ => false,
             }
     }
@@ -369,8 +397,39 @@ impl Hash for TypeDefKey<'_> {
                     .hash(state);
                 }
             }
-            TypeDefKind::Future(_) => todo!("hash for future"),
-            TypeDefKind::Stream(_) => todo!("hash for stream"),
+            TypeDefKind::Future(ty) => {
+                state.write_u8(12);
+                ty.map(|ty| TypeKey {
+                    interface: self.interface,
+                    ty,
+                })
+                .hash(state);
+            }
+            TypeDefKind::Stream(s) => {
+                state.write_u8(13);
+                s.element
+                    .map(|ty| TypeKey {
+                        interface: self.interface,
+                        ty,
+                    })
+                    .hash(state);
+                s.end
+                    .map(|ty| TypeKey {
+                        interface: self.interface,
+                        ty,
+                    })
+                    .hash(state);
+            }
+            TypeDefKind::Resource => state.write_u8(10),
+            TypeDefKind::Handle(h) => {
+                state.write_u8(11);
+                matches!(h, Handle::Own(_)).hash(state);
+                TypeKey {
+                    interface: self.interface,
+                    ty: Type::Id(h.resource()),
+                }
+                .hash(state);
+            }
         }
     }
 }
@@ -629,6 +688,11 @@ impl<'a> TypeEncoder<'a> {
                 Some(name) => name,
                 None => continue,
             };
+            // Resources have no representation of their own, only their
+            // handles and functions are encoded.
+            if def.kind == TypeDefKind::Resource {
+                continue;
+            }
             let idx = match self.encode_valtype(interface, &Type::Id(id))? {
                 ComponentValType::Type(idx) => idx,
                 // With a name this type should be converted to an indexed type
@@ -643,8 +707,6 @@ impl<'a> TypeEncoder<'a> {
 
     fn encode_func_types(&mut self, interfaces: impl Iterator<Item = &'a Interface>) -> Result<()> {
         for export in interfaces {
-            // TODO: stick interface documentation in a custom section?
-
             for func in &export.functions {
                 Self::validate_function(func)?;
 
@@ -720,6 +782,12 @@ impl<'a> TypeEncoder<'a> {
             Type::String => ComponentValType::Primitive(PrimitiveValType::String),
             Type::Id(id) => {
                 let ty = &interface.types[*id];
+                if interface.is_recursive(*id) {
+                    bail!(
+                        "unsupported type `{}`: recursive types can't be encoded in a component type",
+                        ty.name.as_deref().unwrap_or("<anonymous>")
+                    )
+                }
                 let key = TypeDefKey::new(interface, &interface.types[*id]);
                 let encoded = if let Some(index) = self.type_map.get(&key) {
                     ComponentValType::Type(*index)
@@ -741,8 +809,24 @@ impl<'a> TypeEncoder<'a> {
                             ComponentValType::Type(index)
                         }
                         TypeDefKind::Type(ty) => self.encode_valtype(interface, ty)?,
-                        TypeDefKind::Future(_) => todo!("encoding for future type"),
-                        TypeDefKind::Stream(_) => todo!("encoding for stream type"),
+                        TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {
+                            bail!(
+                                "unsupported type `{}`: futures and streams are not currently supported",
+                                ty.name.as_deref().unwrap_or("<anonymous>")
+                            )
+                        }
+                        // Handles are passed as indices into the table of
+                        // resources of the side which implements them.
+                        TypeDefKind::Handle(_) => {
+                            ComponentValType::Primitive(PrimitiveValType::U32)
+                        }
+                        TypeDefKind::Resource => {
+                            bail!(
+                                "unsupported type `{}`: resources can only be referred to \
+                                 through handles",
+                                ty.name.as_deref().unwrap_or("<anonymous>")
+                            )
+                        }
                     };
 
                     if ty.name.is_some() {
@@ -895,13 +979,6 @@ impl<'a> TypeEncoder<'a> {
             bail!("interface has an unnamed function");
         }
 
-        if !matches!(function.kind, FunctionKind::Freestanding) {
-            bail!(
-                "unsupported function `{}`: only free-standing functions are currently supported",
-                function.name
-            );
-        }
-
         Ok(())
     }
 }
@@ -1091,6 +1168,9 @@ impl TypeContents {
 
     fn for_type(interface: &Interface, ty: &Type) -> Self {
         match ty {
+            // Values of a recursive type may contain arbitrarily many lists
+            // and strings, so conservatively assume both.
+            Type::Id(id) if interface.is_recursive(*id) => Self::LIST | Self::STRING,
             Type::Id(id) => match &interface.types[*id].kind {
                 TypeDefKind::Record(r) => {
                     Self::for_types(interface, r.fields.iter().map(|f| &f.ty))
@@ -1109,8 +1189,12 @@ impl TypeContents {
                 TypeDefKind::Enum(_) => Self::empty(),
                 TypeDefKind::List(t) => Self::for_type(interface, t) | Self::LIST,
                 TypeDefKind::Type(t) => Self::for_type(interface, t),
-                TypeDefKind::Future(_) => todo!("encoding for future"),
-                TypeDefKind::Stream(_) => todo!("encoding for stream"),
+                // Futures, streams and handles are all passed as `i32`
+                // indices.
+                TypeDefKind::Future(_)
+                | TypeDefKind::Stream(_)
+                | TypeDefKind::Resource
+                | TypeDefKind::Handle(_) => Self::empty(),
             },
             Type::String => Self::STRING,
             _ => Self::empty(),
@@ -2019,6 +2103,20 @@ fn inc(idx: &mut u32) -> u32 {
     ret
 }
 
+/// Adds the implicit destructor of each resource in `interface` to its
+/// functions, so it's imported or exported along with the resource's other
+/// functions.
+fn with_resource_drops(mut interface: Interface) -> Interface {
+    let drops = interface
+        .types
+        .iter()
+        .filter(|(_, ty)| ty.kind == TypeDefKind::Resource)
+        .map(|(id, _)| interface.resource_drop(id))
+        .collect::<Vec<_>>();
+    interface.functions.extend(drops);
+    interface
+}
+
 /// The import encoder handles indirect lowering of any imports
 /// that require canonical options to be specified.
 ///
@@ -2143,7 +2241,7 @@ impl ComponentEncoder {
         if self.interface.is_some() {
             bail!("default interface cannot be specified twice");
         }
-        self.interface = Some(interface.clone());
+        self.interface = Some(with_resource_drops(interface));
         Ok(self)
     }
 
@@ -2153,7 +2251,7 @@ impl ComponentEncoder {
             if self.imports.contains_key(&i.name) {
                 bail!("cannot specifiy import interface for `{}` twice", i.name);
             }
-            self.imports.insert(i.name.clone(), i);
+            self.imports.insert(i.name.clone(), with_resource_drops(i));
         }
         Ok(self)
     }
@@ -2164,11 +2262,37 @@ impl ComponentEncoder {
             if self.exports.contains_key(&i.name) {
                 bail!("cannot specifiy export interface for `{}` twice", i.name);
             }
-            self.exports.insert(i.name.clone(), i);
+            self.exports.insert(i.name.clone(), with_resource_drops(i));
         }
         Ok(self)
     }
 
+    /// Set the imports, exports, and default interface of the component from
+    /// the `world` provided.
+    pub fn world(self, world: World) -> Result<Self> {
+        let World {
+            imports,
+            exports,
+            default,
+            ..
+        } = world;
+        let mut me = self.imports(imports.into_values())?;
+        me = me.exports(exports.into_values())?;
+        if let Some(default) = default {
+            me = me.interface(default)?;
+        }
+        Ok(me)
+    }
+
+    /// This is a convenience method for [`ComponentEncoder::world`] which
+    /// parses the world at `path`, reading it and any files it uses from
+    /// `options.sources`.
+    pub fn world_file(self, path: &Path, options: &ParseOptions) -> Result<Self> {
+        let (world, _) = World::parse_file_with(path, options)
+            .with_context(|| format!("failed to parse world file `{}`", path.display()))?;
+        self.world(world)
+    }
+
     /// Specifies a new adapter which is used to translate from a historical
     /// wasm ABI to the canonical ABI and the `interface` provided.
     ///
@@ -2339,6 +2463,20 @@ impl ComponentEncoder {
             state.encode_exports(self.encoding, exports, &types)?;
         }
 
+        let metadata = metadata::encode(
+            self.interface
+                .iter()
+                .map(|i| (Position::Default, i))
+                .chain(self.imports.values().map(|i| (Position::Import, i)))
+                .chain(self.exports.values().map(|i| (Position::Export, i))),
+        );
+        if let Some(data) = &metadata {
+            state.component.section(&CustomSection {
+                name: metadata::SECTION_NAME,
+                data,
+            });
+        }
+
         let bytes = state.component.finish();
 
         if self.validate {
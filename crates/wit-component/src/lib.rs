@@ -12,10 +12,11 @@ mod decoding;
 mod encoding;
 mod extract;
 mod gc;
+mod metadata;
 mod printing;
 mod validation;
 
-pub use decoding::{decode_interface_component, ComponentInterfaces};
+pub use decoding::{decode_interface_component, decode_world, ComponentInterfaces};
 pub use encoding::*;
 pub use extract::*;
 pub use printing::*;
@@ -0,0 +1,405 @@
+//! Metadata about interfaces which component types can't express, such as
+//! whether a function is `async`, the documentation of functions and types,
+//! and the refinements of types.
+//!
+//! The encoder stores this metadata in a custom section of the component and
+//! the decoder applies it to the interfaces it infers from the component's
+//! types, so it survives the trip through a component binary.
+//!
+//! The section is a sequence of interfaces, each holding the items of the
+//! interface which have metadata, each of which is a list of tagged fields:
+//!
+//! ```text
+//! section   ::= interface*
+//! interface ::= position:u8 name:string count:u32 item*
+//! item      ::= kind:u8 name:string count:u32 field*
+//! field     ::= tag:u8 payload:bytes
+//! ```
+//!
+//! Items are functions, named types, and the members of named types: their
+//! fields, cases or flags. A member's name is the name of its type and its
+//! index within the type, separated by a `.`, such as `point.0`.
+//!
+//! Integers are unsigned LEB128 and strings and byte payloads are prefixed
+//! with their length. Fields with an unknown tag are skipped when decoding.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use wasm_encoder::Encode;
+use wasmparser::BinaryReader;
+use wit_parser::{
+    Deprecated, Docs, Function, Interface, Literal, Range, Refinement, TypeDef, TypeDefKind,
+};
+
+/// The name of the custom section holding the metadata.
+pub const SECTION_NAME: &str = "wit-interface-metadata";
+
+/// Where an interface is found in a component.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Position {
+    /// The default interface, whose name isn't recorded.
+    Default,
+    /// An imported instance, named by its import.
+    Import,
+    /// An exported instance, named by its export.
+    Export,
+}
+
+impl Position {
+    fn from_u8(byte: u8) -> Result<Self> {
+        Ok(match byte {
+            0 => Position::Default,
+            1 => Position::Import,
+            2 => Position::Export,
+            _ => bail!("invalid interface position {}", byte),
+        })
+    }
+
+    fn as_u8(&self) -> u8 {
+        match self {
+            Position::Default => 0,
+            Position::Import => 1,
+            Position::Export => 2,
+        }
+    }
+}
+
+const ITEM_FUNCTION: u8 = 0;
+const ITEM_TYPE: u8 = 1;
+const ITEM_MEMBER: u8 = 2;
+
+/// The function is `async`, with an empty payload.
+const FIELD_ASYNC: u8 = 0;
+/// The item's documentation, with the string as its payload.
+const FIELD_DOCS: u8 = 1;
+/// The item's `@since` version, with the string as its payload.
+const FIELD_SINCE: u8 = 2;
+/// The item is `@deprecated`, with the optional strings of the version it
+/// was deprecated in and the note as its payload.
+const FIELD_DEPRECATED: u8 = 3;
+/// The type's refinement, with its kind, whether its range is inclusive and
+/// the optional literals of the start and end of its range as its payload.
+const FIELD_REFINEMENT: u8 = 4;
+
+type Fields = Vec<(u8, Vec<u8>)>;
+
+/// Encodes the metadata of `interfaces`, returning `None` if none of them
+/// have any.
+pub fn encode<'a>(
+    interfaces: impl IntoIterator<Item = (Position, &'a Interface)>,
+) -> Option<Vec<u8>> {
+    let mut section = Vec::new();
+    for (position, iface) in interfaces {
+        let mut items = Vec::new();
+        for func in iface.functions.iter() {
+            items.push((ITEM_FUNCTION, func.name.clone(), function_fields(func)));
+        }
+        for (_, ty) in iface.types.iter() {
+            let name = match &ty.name {
+                Some(name) => name,
+                None => continue,
+            };
+            items.push((ITEM_TYPE, name.clone(), type_fields(ty)));
+            for (i, docs) in member_docs(&ty.kind).into_iter().enumerate() {
+                items.push((ITEM_MEMBER, format!("{}.{}", name, i), docs_fields(docs)));
+            }
+        }
+        items.retain(|(_, _, fields)| !fields.is_empty());
+        if items.is_empty() {
+            continue;
+        }
+
+        section.push(position.as_u8());
+        interface_name(position, iface).encode(&mut section);
+        items.len().encode(&mut section);
+        for (kind, name, fields) in items {
+            section.push(kind);
+            name.encode(&mut section);
+            fields.len().encode(&mut section);
+            for (tag, payload) in fields {
+                section.push(tag);
+                payload.encode(&mut section);
+            }
+        }
+    }
+    if section.is_empty() {
+        None
+    } else {
+        Some(section)
+    }
+}
+
+fn function_fields(func: &Function) -> Fields {
+    let mut fields = docs_fields(&func.docs);
+    if func.is_async {
+        fields.push((FIELD_ASYNC, Vec::new()));
+    }
+    fields
+}
+
+fn docs_fields(docs: &Docs) -> Fields {
+    let mut fields = Vec::new();
+    if let Some(contents) = &docs.contents {
+        let mut payload = Vec::new();
+        contents.encode(&mut payload);
+        fields.push((FIELD_DOCS, payload));
+    }
+    if let Some(since) = &docs.since {
+        let mut payload = Vec::new();
+        since.encode(&mut payload);
+        fields.push((FIELD_SINCE, payload));
+    }
+    if let Some(deprecated) = &docs.deprecated {
+        let mut payload = Vec::new();
+        write_optional_string(&mut payload, deprecated.since.as_deref());
+        write_optional_string(&mut payload, deprecated.note.as_deref());
+        fields.push((FIELD_DEPRECATED, payload));
+    }
+    fields
+}
+
+fn type_fields(ty: &TypeDef) -> Fields {
+    let mut fields = docs_fields(&ty.docs);
+    if let Some(refinement) = &ty.refinement {
+        let (kind, range) = match refinement {
+            Refinement::Range(range) => (0, range),
+            Refinement::Length(range) => (1, range),
+        };
+        let mut payload = vec![kind, range.inclusive as u8];
+        write_optional_literal(&mut payload, range.start.as_ref());
+        write_optional_literal(&mut payload, range.end.as_ref());
+        fields.push((FIELD_REFINEMENT, payload));
+    }
+    fields
+}
+
+/// Returns the docs of the fields, cases or flags of a type.
+fn member_docs(kind: &TypeDefKind) -> Vec<&Docs> {
+    match kind {
+        TypeDefKind::Record(r) => r.fields.iter().map(|f| &f.docs).collect(),
+        TypeDefKind::Flags(f) => f.flags.iter().map(|f| &f.docs).collect(),
+        TypeDefKind::Variant(v) => v.cases.iter().map(|c| &c.docs).collect(),
+        TypeDefKind::Enum(e) => e.cases.iter().map(|c| &c.docs).collect(),
+        TypeDefKind::Union(u) => u.cases.iter().map(|c| &c.docs).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn member_docs_mut(kind: &mut TypeDefKind) -> Vec<&mut Docs> {
+    match kind {
+        TypeDefKind::Record(r) => r.fields.iter_mut().map(|f| &mut f.docs).collect(),
+        TypeDefKind::Flags(f) => f.flags.iter_mut().map(|f| &mut f.docs).collect(),
+        TypeDefKind::Variant(v) => v.cases.iter_mut().map(|c| &mut c.docs).collect(),
+        TypeDefKind::Enum(e) => e.cases.iter_mut().map(|c| &mut c.docs).collect(),
+        TypeDefKind::Union(u) => u.cases.iter_mut().map(|c| &mut c.docs).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn interface_name(position: Position, iface: &Interface) -> &str {
+    match position {
+        Position::Default => "",
+        Position::Import | Position::Export => &iface.name,
+    }
+}
+
+/// The metadata decoded from a component's custom section.
+#[derive(Default)]
+pub struct Metadata {
+    items: HashMap<(Position, String), HashMap<(u8, String), Fields>>,
+}
+
+impl Metadata {
+    /// Decodes the contents of the custom section named [`SECTION_NAME`].
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let mut metadata = Metadata::default();
+        while !reader.eof() {
+            let position = Position::from_u8(reader.u8()?)?;
+            let name = reader.string()?;
+            let items = metadata.items.entry((position, name)).or_default();
+            for _ in 0..reader.u32()? {
+                let kind = reader.u8()?;
+                let name = reader.string()?;
+                let fields = items.entry((kind, name)).or_default();
+                for _ in 0..reader.u32()? {
+                    let tag = reader.u8()?;
+                    fields.push((tag, reader.bytes()?.to_vec()));
+                }
+            }
+        }
+        Ok(metadata)
+    }
+
+    /// Applies the metadata recorded for the interface at `position` to
+    /// `iface`, which has been decoded from the component's types.
+    pub fn apply(&self, position: Position, iface: &mut Interface) -> Result<()> {
+        let key = (position, interface_name(position, iface).to_string());
+        let items = match self.items.get(&key) {
+            Some(items) => items,
+            None => return Ok(()),
+        };
+        for func in iface.functions.iter_mut() {
+            let fields = match items.get(&(ITEM_FUNCTION, func.name.clone())) {
+                Some(fields) => fields,
+                None => continue,
+            };
+            apply_docs(&mut func.docs, fields)?;
+            for (tag, _payload) in fields {
+                if *tag == FIELD_ASYNC {
+                    func.is_async = true;
+                }
+            }
+        }
+        for (_, ty) in iface.types.iter_mut() {
+            let name = match &ty.name {
+                Some(name) => name.clone(),
+                None => continue,
+            };
+            if let Some(fields) = items.get(&(ITEM_TYPE, name.clone())) {
+                apply_docs(&mut ty.docs, fields)?;
+                for (tag, payload) in fields {
+                    if *tag == FIELD_REFINEMENT {
+                        ty.refinement = Some(Reader::new(payload).refinement()?);
+                    }
+                }
+            }
+            for (i, docs) in member_docs_mut(&mut ty.kind).into_iter().enumerate() {
+                if let Some(fields) = items.get(&(ITEM_MEMBER, format!("{}.{}", name, i))) {
+                    apply_docs(docs, fields)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply_docs(docs: &mut Docs, fields: &Fields) -> Result<()> {
+    for (tag, payload) in fields {
+        let mut payload = Reader::new(payload);
+        match *tag {
+            FIELD_DOCS => docs.contents = Some(payload.string()?),
+            FIELD_SINCE => docs.since = Some(payload.string()?),
+            FIELD_DEPRECATED => {
+                docs.deprecated = Some(Deprecated {
+                    since: payload.optional_string()?,
+                    note: payload.optional_string()?,
+                })
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn write_optional_literal(dst: &mut Vec<u8>, literal: Option<&Literal>) {
+    match literal {
+        None => dst.push(0),
+        Some(Literal::Integer(i)) => {
+            dst.push(1);
+            dst.extend_from_slice(&i.to_le_bytes());
+        }
+        Some(Literal::Float(f)) => {
+            dst.push(2);
+            dst.extend_from_slice(&f.to_le_bytes());
+        }
+        Some(Literal::Bool(b)) => {
+            dst.push(3);
+            dst.push(*b as u8);
+        }
+        Some(Literal::Char(c)) => {
+            dst.push(4);
+            (*c as u32).encode(dst);
+        }
+        Some(Literal::String(s)) => {
+            dst.push(5);
+            s.encode(dst);
+        }
+    }
+}
+
+fn write_optional_string(dst: &mut Vec<u8>, s: Option<&str>) {
+    match s {
+        Some(s) => {
+            dst.push(1);
+            s.encode(dst);
+        }
+        None => dst.push(0),
+    }
+}
+
+/// Reads the metadata encoding on top of `wasmparser`'s reader.
+struct Reader<'a> {
+    reader: BinaryReader<'a>,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader {
+            reader: BinaryReader::new(data),
+        }
+    }
+
+    fn eof(&self) -> bool {
+        self.reader.eof()
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.reader.read_bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(self.reader.read_var_u32()?)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.reader.read_var_u32()?;
+        Ok(self.reader.read_bytes(len as usize)?)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        Ok(std::str::from_utf8(self.bytes()?)?.to_string())
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.reader.read_bytes(N)?.try_into().unwrap())
+    }
+
+    fn refinement(&mut self) -> Result<Refinement> {
+        let kind = self.u8()?;
+        let inclusive = self.u8()? != 0;
+        let range = Range {
+            start: self.optional_literal()?,
+            end: self.optional_literal()?,
+            inclusive,
+        };
+        Ok(match kind {
+            0 => Refinement::Range(range),
+            1 => Refinement::Length(range),
+            _ => bail!("invalid refinement kind {}", kind),
+        })
+    }
+
+    fn optional_literal(&mut self) -> Result<Option<Literal>> {
+        Ok(Some(match self.u8()? {
+            0 => return Ok(None),
+            1 => Literal::Integer(i128::from_le_bytes(self.array()?)),
+            2 => Literal::Float(f64::from_le_bytes(self.array()?)),
+            3 => Literal::Bool(self.u8()? != 0),
+            4 => match char::from_u32(self.u32()?) {
+                Some(c) => Literal::Char(c),
+                None => bail!("invalid char in interface metadata"),
+            },
+            5 => Literal::String(self.string()?),
+            tag => bail!("invalid literal tag {}", tag),
+        }))
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.string()?)),
+            byte => bail!("invalid optional string tag {}", byte),
+        }
+    }
+}
@@ -2,8 +2,8 @@ use anyhow::{bail, Result};
 use indexmap::IndexSet;
 use std::fmt::Write;
 use wit_parser::{
-    Enum, Flags, Interface, Record, Result_, Results, Tuple, Type, TypeDefKind, TypeId, Union,
-    Variant,
+    Enum, Flags, Handle, Interface, Record, Result_, Results, Tuple, Type, TypeDefKind, TypeId,
+    Union, Variant,
 };
 
 /// A utility for printing WebAssembly interface definitions to a string.
@@ -20,14 +20,23 @@ impl InterfacePrinter {
             self.declare_type(interface, &Type::Id(id))?;
         }
 
+        for constant in &interface.constants {
+            write!(&mut self.output, "const {}: ", constant.name)?;
+            self.print_type_name(interface, &constant.ty)?;
+            write!(&mut self.output, " = {}\n\n", constant.value)?;
+        }
+
         for func in &interface.functions {
             write!(&mut self.output, "{}: func(", func.name)?;
-            for (i, (name, ty)) in func.params.iter().enumerate() {
+            for (i, ((name, ty), default)) in func.params.iter().zip(&func.defaults).enumerate() {
                 if i > 0 {
                     self.output.push_str(", ");
                 }
                 write!(&mut self.output, "{}: ", name)?;
                 self.print_type_name(interface, ty)?;
+                if let Some(default) = default {
+                    write!(&mut self.output, " = {}", default)?;
+                }
             }
             self.output.push(')');
 
@@ -113,6 +122,10 @@ impl InterfacePrinter {
                     TypeDefKind::Stream(_) => {
                         todo!("interface has an unnamed stream type")
                     }
+                    TypeDefKind::Resource => {
+                        bail!("interface has unnamed resource type")
+                    }
+                    TypeDefKind::Handle(h) => self.print_handle(interface, h)?,
                 }
             }
         }
@@ -234,12 +247,37 @@ impl InterfacePrinter {
                     },
                     TypeDefKind::Future(_) => todo!("declare future"),
                     TypeDefKind::Stream(_) => todo!("declare stream"),
+                    TypeDefKind::Resource => match ty.name.as_deref() {
+                        Some(name) => {
+                            writeln!(&mut self.output, "resource {}\n", name)?;
+                        }
+                        None => bail!("unnamed resource in interface"),
+                    },
+                    TypeDefKind::Handle(h) => {
+                        self.declare_type(interface, &Type::Id(h.resource()))?;
+                        if let Some(name) = ty.name.as_deref() {
+                            write!(&mut self.output, "type {} = ", name)?;
+                            self.print_handle(interface, h)?;
+                            self.output.push_str("\n\n");
+                        }
+                    }
                 }
             }
         }
         Ok(())
     }
 
+    fn print_handle(&mut self, interface: &Interface, handle: &Handle) -> Result<()> {
+        let (kind, resource) = match handle {
+            Handle::Own(r) => ("own", r),
+            Handle::Borrow(r) => ("borrow", r),
+        };
+        write!(&mut self.output, "{}<", kind)?;
+        self.print_type_name(interface, &Type::Id(*resource))?;
+        self.output.push('>');
+        Ok(())
+    }
+
     fn declare_record(
         &mut self,
         interface: &Interface,
@@ -257,6 +295,9 @@ impl InterfacePrinter {
                     write!(&mut self.output, "  {}: ", field.name)?;
                     self.declare_type(interface, &field.ty)?;
                     self.print_type_name(interface, &field.ty)?;
+                    if let Some(default) = &field.default {
+                        write!(&mut self.output, " = {}", default)?;
+                    }
                     self.output.push_str(",\n");
                 }
                 self.output.push_str("}\n\n");
@@ -3,7 +3,7 @@ use pretty_assertions::assert_eq;
 use std::fs;
 use std::path::{Path, PathBuf};
 use wit_component::ComponentEncoder;
-use wit_parser::Interface;
+use wit_parser::{Interface, Type, TypeDefKind};
 
 /// Tests the encoding of the "types only" mode of `wit-component`.
 ///
@@ -151,6 +151,140 @@ fn assert_wit(wit_path: &Path, interface: &Interface) -> Result<()> {
     Ok(())
 }
 
+/// Types which can't be encoded in a component yet are rejected with an error
+/// rather than a panic.
+#[test]
+fn unsupported_types() -> Result<()> {
+    let tests = [
+        (
+            "wait: func(f: future<u32>)",
+            "futures and streams are not currently supported",
+        ),
+        (
+            "type bytes = stream<u8, string>\nread: func() -> bytes",
+            "futures and streams are not currently supported",
+        ),
+    ];
+    for (wit, expected) in tests {
+        let interface = Interface::parse("unsupported", wit)?;
+        let err = ComponentEncoder::default()
+            .types_only(true)
+            .interface(interface)?
+            .encode()
+            .err()
+            .with_context(|| format!("encoding `{}` should fail", wit))?;
+        assert!(format!("{:?}", err).contains(expected), "{:?}", err);
+    }
+    Ok(())
+}
+
+/// Handles are encoded as `u32` indices and each resource gets a destructor,
+/// named along with the resource's other functions after the resource.
+#[test]
+fn resources() -> Result<()> {
+    let interface = Interface::parse(
+        "resources",
+        "
+            resource blob {
+              constructor(size: u32)
+              size: func() -> u32
+            }
+            open: func(name: string) -> own<blob>
+        ",
+    )?;
+    let bytes = ComponentEncoder::default()
+        .types_only(true)
+        .validate(true)
+        .imports([interface])?
+        .encode()?;
+    let interfaces = wit_component::decode_interface_component(&bytes)?;
+    let decoded = &interfaces.imports["resources"];
+    let functions = decoded
+        .functions
+        .iter()
+        .map(|f| {
+            let params = f.params.iter().map(|(_, ty)| *ty).collect::<Vec<_>>();
+            let results = f.results.iter_types().copied().collect::<Vec<_>>();
+            (f.name.as_str(), params, results)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        functions,
+        [
+            ("blob::constructor", vec![Type::U32], vec![Type::U32]),
+            ("blob::size", vec![Type::U32], vec![Type::U32]),
+            ("open", vec![Type::String], vec![Type::U32]),
+            ("blob::drop", vec![Type::U32], vec![]),
+        ]
+    );
+    Ok(())
+}
+
+/// Metadata which component types can't express survives a round trip
+/// through a component.
+#[test]
+fn metadata() -> Result<()> {
+    let default = Interface::parse(
+        "default",
+        "
+            /// A point.
+            @since(\"1.2.0\")
+            record point {
+              @deprecated(\"use `y`\")
+              x: u32,
+              y: u32,
+            }
+
+            type port = u16 where 1..=65535
+
+            @deprecated(since = \"2.0.0\", note = \"use `g`\")
+            f: async func(p: point)
+            g: func() -> port
+        ",
+    )?;
+    let import = Interface::parse("imported", "h: async func(a: u32)")?;
+    let bytes = ComponentEncoder::default()
+        .types_only(true)
+        .validate(true)
+        .interface(default)?
+        .imports([import])?
+        .encode()?;
+    let decoded = wit_component::decode_interface_component(&bytes)?;
+
+    let default = decoded.default.unwrap();
+    let func = |name| default.functions.iter().find(|f| f.name == name).unwrap();
+    assert!(func("f").is_async);
+    assert!(!func("g").is_async);
+    let deprecated = func("f").docs.deprecated.as_ref().unwrap();
+    assert_eq!(deprecated.since.as_deref(), Some("2.0.0"));
+    assert_eq!(deprecated.note.as_deref(), Some("use `g`"));
+    assert!(func("g").docs.is_empty());
+
+    let (_, point) = default
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some("point"))
+        .unwrap();
+    assert_eq!(point.docs.contents.as_deref(), Some("A point.\n"));
+    assert_eq!(point.docs.since.as_deref(), Some("1.2.0"));
+    let fields = match &point.kind {
+        TypeDefKind::Record(r) => &r.fields,
+        _ => unreachable!(),
+    };
+    let deprecated = fields[0].docs.deprecated.as_ref().unwrap();
+    assert_eq!(deprecated.note.as_deref(), Some("use `y`"));
+    assert!(fields[1].docs.is_empty());
+
+    let (_, port) = default
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some("port"))
+        .unwrap();
+    assert_eq!(port.refinement.as_ref().unwrap().to_string(), "1..=65535");
+    assert!(decoded.imports["imported"].functions[0].is_async);
+    Ok(())
+}
+
 fn read_interface(path: &Path) -> Result<Interface> {
     Interface::parse_file(&path)
         .with_context(|| format!("failed to parse interface file `{}`", path.display()))
@@ -0,0 +1,771 @@
+//! Answers questions about the contents of a wit file from its syntax tree.
+
+use crate::position::LineIndex;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, DocumentSymbol, Documentation, Range, SymbolKind,
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use wit_parser::ast::{
+    self, Ast, ExternKind, Id, Item, ResourceFuncKind, ResultList, Type, Use, ValueKind, WorldItem,
+};
+use wit_parser::{Diagnostic, Diagnostics, Interface, PackageName, ParseOptions, Span, World};
+
+/// How many files deep `use` statements are followed when looking for the
+/// definition of a name, in case they form a cycle.
+const MAX_DEPTH: usize = 16;
+
+/// A wit file along with what's needed to load the files it uses.
+pub struct File {
+    pub path: PathBuf,
+    /// The wit source of the file, which for markdown files is the contents
+    /// of its code blocks.
+    pub text: String,
+    /// The directory of the file that `use` statements are resolved
+    /// relative to.
+    root: PathBuf,
+    /// The package the file belongs to, if it was loaded from one.
+    package: Option<PackageName>,
+}
+
+impl File {
+    pub fn new(path: &Path, contents: &str) -> File {
+        let text = if path.extension().and_then(|s| s.to_str()) == Some("md") {
+            wit_parser::unwrap_md(contents)
+        } else {
+            contents.to_string()
+        };
+        File {
+            path: path.to_path_buf(),
+            text,
+            root: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            package: None,
+        }
+    }
+
+    /// Loads the file which `u`, a `use` within this file, imports from.
+    fn load(&self, u: &Use<'_>, options: &ParseOptions) -> Option<File> {
+        let package = u.target_package(self.package.as_ref());
+        let (path, contents) = options
+            .load(&self.root, package.as_ref(), &u.from[0].name)
+            .ok()?;
+        let mut file = File::new(&path, &contents);
+        file.root = self.root.clone();
+        file.package = package;
+        Some(file)
+    }
+}
+
+/// What sort of thing a name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Record,
+    Flags,
+    Variant,
+    Enum,
+    Union,
+    Alias,
+    Resource,
+    Function,
+    Global,
+    Constant,
+    Interface,
+    World,
+    Field,
+    Flag,
+    Case,
+    Constructor,
+    Method,
+    StaticMethod,
+    Import,
+    Export,
+}
+
+impl Kind {
+    fn of_type(ty: &Type<'_>) -> Kind {
+        match ty {
+            Type::Record(_) => Kind::Record,
+            Type::Flags(_) => Kind::Flags,
+            Type::Variant(_) => Kind::Variant,
+            Type::Enum(_) => Kind::Enum,
+            Type::Union(_) => Kind::Union,
+            _ => Kind::Alias,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Kind::Record => "record",
+            Kind::Flags => "flags",
+            Kind::Variant => "variant",
+            Kind::Enum => "enum",
+            Kind::Union => "union",
+            Kind::Alias => "type",
+            Kind::Resource => "resource",
+            Kind::Function => "func",
+            Kind::Global => "global",
+            Kind::Constant => "const",
+            Kind::Interface => "interface",
+            Kind::World => "world",
+            Kind::Field => "field",
+            Kind::Flag => "flag",
+            Kind::Case => "case",
+            Kind::Constructor => "constructor",
+            Kind::Method => "method",
+            Kind::StaticMethod => "static method",
+            Kind::Import => "import",
+            Kind::Export => "export",
+        }
+    }
+
+    /// Returns whether this is an item which may be referred to by name
+    /// from elsewhere, as opposed to a member of an item.
+    fn is_item(&self) -> bool {
+        !matches!(
+            self,
+            Kind::Field
+                | Kind::Flag
+                | Kind::Case
+                | Kind::Constructor
+                | Kind::Method
+                | Kind::StaticMethod
+                | Kind::Import
+                | Kind::Export
+        )
+    }
+
+    /// Returns whether this may be named where a type is expected.
+    fn is_type(&self) -> bool {
+        matches!(
+            self,
+            Kind::Record
+                | Kind::Flags
+                | Kind::Variant
+                | Kind::Enum
+                | Kind::Union
+                | Kind::Alias
+                | Kind::Resource
+        )
+    }
+
+    fn symbol_kind(&self) -> SymbolKind {
+        match self {
+            Kind::Record => SymbolKind::STRUCT,
+            Kind::Flags | Kind::Variant | Kind::Enum | Kind::Union => SymbolKind::ENUM,
+            Kind::Alias => SymbolKind::TYPE_PARAMETER,
+            Kind::Resource => SymbolKind::CLASS,
+            Kind::Function | Kind::StaticMethod => SymbolKind::FUNCTION,
+            Kind::Global => SymbolKind::VARIABLE,
+            Kind::Constant => SymbolKind::CONSTANT,
+            Kind::Interface | Kind::Import | Kind::Export => SymbolKind::INTERFACE,
+            Kind::World => SymbolKind::MODULE,
+            Kind::Field => SymbolKind::FIELD,
+            Kind::Flag => SymbolKind::CONSTANT,
+            Kind::Case => SymbolKind::ENUM_MEMBER,
+            Kind::Constructor => SymbolKind::CONSTRUCTOR,
+            Kind::Method => SymbolKind::METHOD,
+        }
+    }
+
+    fn completion_kind(&self) -> CompletionItemKind {
+        match self {
+            Kind::Record => CompletionItemKind::STRUCT,
+            Kind::Flags | Kind::Variant | Kind::Enum | Kind::Union => CompletionItemKind::ENUM,
+            Kind::Alias => CompletionItemKind::TYPE_PARAMETER,
+            Kind::Resource => CompletionItemKind::CLASS,
+            Kind::Function => CompletionItemKind::FUNCTION,
+            Kind::Global => CompletionItemKind::VARIABLE,
+            Kind::Constant => CompletionItemKind::CONSTANT,
+            Kind::Interface => CompletionItemKind::INTERFACE,
+            Kind::World | Kind::Import | Kind::Export => CompletionItemKind::MODULE,
+            Kind::Field => CompletionItemKind::FIELD,
+            Kind::Flag => CompletionItemKind::CONSTANT,
+            Kind::Case => CompletionItemKind::ENUM_MEMBER,
+            Kind::Constructor => CompletionItemKind::CONSTRUCTOR,
+            Kind::Method | Kind::StaticMethod => CompletionItemKind::METHOD,
+        }
+    }
+}
+
+/// Where a name is defined.
+pub struct Definition {
+    pub path: PathBuf,
+    pub range: Range,
+    pub kind: Kind,
+    pub name: String,
+    pub docs: Option<String>,
+}
+
+/// An occurrence of a name within a file.
+struct Occurrence<'a> {
+    span: Span,
+    name: &'a str,
+    role: Role<'a>,
+}
+
+enum Role<'a> {
+    /// The name is being defined here.
+    Definition(Kind, Option<&'a ast::Docs<'a>>),
+    /// The name refers to an item defined elsewhere.
+    Reference,
+    /// The name is imported by a `use` under the name `name` in the
+    /// interface it's imported from. `local` is whether this is the name the
+    /// import is known by within this file.
+    Import {
+        use_: &'a Use<'a>,
+        name: &'a str,
+        local: bool,
+    },
+    /// The name is the interface a `use` imports from.
+    UseTarget(&'a Use<'a>),
+}
+
+/// Returns every occurrence of a name within `items`.
+fn occurrences<'a>(items: &'a [Item<'a>]) -> Vec<Occurrence<'a>> {
+    let mut ret = Vec::new();
+    visit_items(items, &mut ret);
+    return ret;
+
+    fn define<'a>(
+        dst: &mut Vec<Occurrence<'a>>,
+        id: &'a Id<'a>,
+        kind: Kind,
+        docs: Option<&'a ast::Docs<'a>>,
+    ) {
+        dst.push(Occurrence {
+            span: id.span,
+            name: &id.name,
+            role: Role::Definition(kind, docs),
+        });
+    }
+
+    fn refer<'a>(dst: &mut Vec<Occurrence<'a>>, id: &'a Id<'a>) {
+        dst.push(Occurrence {
+            span: id.span,
+            name: &id.name,
+            role: Role::Reference,
+        });
+    }
+
+    fn visit_items<'a>(items: &'a [Item<'a>], dst: &mut Vec<Occurrence<'a>>) {
+        for item in items {
+            match item {
+                Item::Use(u) => {
+                    dst.push(Occurrence {
+                        span: u.target_span(),
+                        name: &u.from[0].name,
+                        role: Role::UseTarget(u),
+                    });
+                    for n in u.names.iter().flatten() {
+                        dst.push(Occurrence {
+                            span: n.name.span,
+                            name: &n.name.name,
+                            role: Role::Import {
+                                use_: u,
+                                name: &n.name.name,
+                                local: n.as_.is_none(),
+                            },
+                        });
+                        if let Some(as_) = &n.as_ {
+                            dst.push(Occurrence {
+                                span: as_.span,
+                                name: &as_.name,
+                                role: Role::Import {
+                                    use_: u,
+                                    name: &n.name.name,
+                                    local: true,
+                                },
+                            });
+                        }
+                    }
+                }
+                Item::Include(i) => dst.push(Occurrence {
+                    span: i.target.target_span(),
+                    name: &i.target.from[0].name,
+                    role: Role::UseTarget(&i.target),
+                }),
+                Item::TypeDef(t) => {
+                    define(dst, &t.name, Kind::of_type(&t.ty), Some(&t.docs));
+                    visit_type(&t.ty, dst);
+                }
+                Item::Resource(r) => {
+                    define(dst, &r.name, Kind::Resource, Some(&r.docs));
+                    for f in r.funcs.iter() {
+                        let kind = match f.kind {
+                            ResourceFuncKind::Constructor => Kind::Constructor,
+                            ResourceFuncKind::Method => Kind::Method,
+                            ResourceFuncKind::Static => Kind::StaticMethod,
+                        };
+                        define(dst, &f.name, kind, Some(&f.docs));
+                        visit_func(&f.params, &f.results, dst);
+                    }
+                }
+                Item::Value(v) => match &v.kind {
+                    ValueKind::Function {
+                        params, results, ..
+                    } => {
+                        define(dst, &v.name, Kind::Function, Some(&v.docs));
+                        visit_func(params, results, dst);
+                    }
+                    ValueKind::Global(ty) => {
+                        define(dst, &v.name, Kind::Global, Some(&v.docs));
+                        visit_type(ty, dst);
+                    }
+                    ValueKind::Const { ty, .. } => {
+                        define(dst, &v.name, Kind::Constant, Some(&v.docs));
+                        visit_type(ty, dst);
+                    }
+                },
+                Item::Interface(i) => {
+                    define(dst, &i.name, Kind::Interface, Some(&i.docs));
+                    visit_items(&i.items, dst);
+                }
+                Item::World(w) => {
+                    define(dst, &w.name, Kind::World, Some(&w.docs));
+                    for item in w.items.iter() {
+                        match item {
+                            WorldItem::Import(i) => {
+                                define(dst, &i.name, Kind::Import, None);
+                                visit_extern(&i.kind, dst);
+                            }
+                            WorldItem::Export(e) => {
+                                define(dst, &e.name, Kind::Export, None);
+                                visit_extern(&e.kind, dst);
+                            }
+                            WorldItem::ExportDefault(kind) => visit_extern(kind, dst),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_extern<'a>(kind: &'a ExternKind<'a>, dst: &mut Vec<Occurrence<'a>>) {
+        match kind {
+            ExternKind::Interface(_, items) => visit_items(items, dst),
+            ExternKind::Id(id) => refer(dst, id),
+        }
+    }
+
+    fn visit_func<'a>(
+        params: &'a ast::ParamList<'a>,
+        results: &'a ResultList<'a>,
+        dst: &mut Vec<Occurrence<'a>>,
+    ) {
+        let results = match results {
+            ResultList::Named(results) => &results[..],
+            ResultList::Anon(ty) => {
+                visit_type(ty, dst);
+                &[]
+            }
+        };
+        for param in params.iter().chain(results) {
+            visit_type(&param.ty, dst);
+        }
+    }
+
+    fn visit_type<'a>(ty: &'a Type<'a>, dst: &mut Vec<Occurrence<'a>>) {
+        match ty {
+            Type::Name(id) | Type::Own(id) | Type::Borrow(id) => refer(dst, id),
+            Type::List(ty) | Type::Option(ty) => visit_type(ty, dst),
+            Type::Tuple(types) => {
+                for ty in types {
+                    visit_type(ty, dst);
+                }
+            }
+            Type::Result(r) => {
+                for ty in r.ok.iter().chain(r.err.iter()) {
+                    visit_type(ty, dst);
+                }
+            }
+            Type::Future(ty) => {
+                for ty in ty.iter() {
+                    visit_type(ty, dst);
+                }
+            }
+            Type::Stream(s) => {
+                for ty in s.element.iter().chain(s.end.iter()) {
+                    visit_type(ty, dst);
+                }
+            }
+            Type::Record(r) => {
+                for field in r.fields.iter() {
+                    define(dst, &field.name, Kind::Field, Some(&field.docs));
+                    visit_type(&field.ty, dst);
+                }
+            }
+            Type::Flags(f) => {
+                for flag in f.flags.iter() {
+                    define(dst, &flag.name, Kind::Flag, Some(&flag.docs));
+                }
+            }
+            Type::Variant(v) => {
+                for case in v.cases.iter() {
+                    define(dst, &case.name, Kind::Case, Some(&case.docs));
+                    if let Some(ty) = &case.ty {
+                        visit_type(ty, dst);
+                    }
+                }
+            }
+            Type::Enum(e) => {
+                for case in e.cases.iter() {
+                    define(dst, &case.name, Kind::Case, Some(&case.docs));
+                }
+            }
+            Type::Union(u) => {
+                for case in u.cases.iter() {
+                    visit_type(&case.ty, dst);
+                }
+            }
+            Type::Bool
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::S8
+            | Type::S16
+            | Type::S32
+            | Type::S64
+            | Type::Float32
+            | Type::Float64
+            | Type::Char
+            | Type::String => {}
+        }
+    }
+}
+
+/// Parses and resolves `contents`, the contents of the file at `path`,
+/// returning any problems found.
+pub fn diagnostics(path: &Path, contents: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+    let file = File::new(path, contents);
+    let (ast, _) = Ast::parse_lenient(&file.text);
+    let is_world = ast.items.iter().any(|i| matches!(i, Item::World(_)));
+    let result = if is_world {
+        World::parse_contents_with(path, contents, options).map(drop)
+    } else {
+        Interface::parse_contents_with(path, contents, options).map(drop)
+    };
+    match result {
+        Ok(()) => Vec::new(),
+        Err(e) => match e.downcast::<Diagnostics>() {
+            Ok(diagnostics) => diagnostics.diagnostics,
+            Err(e) => {
+                let span = Span { start: 0, end: 0 };
+                vec![Diagnostic::error(
+                    path,
+                    &file.text,
+                    span,
+                    format!("{:#}", e),
+                )]
+            }
+        },
+    }
+}
+
+/// Returns where the name at byte `offset` within `file` is defined.
+pub fn definition(file: &File, offset: usize, options: &ParseOptions) -> Option<Definition> {
+    let (ast, _) = Ast::parse_lenient(&file.text);
+    let occurrences = occurrences(&ast.items);
+    let occurrence = occurrences
+        .iter()
+        .find(|o| o.span.start as usize <= offset && offset <= o.span.end as usize)?;
+    match &occurrence.role {
+        Role::Definition(kind, docs) => Some(to_definition(file, occurrence, *kind, *docs)),
+        Role::Reference => lookup(file, &occurrences, occurrence.name, options, 0),
+        Role::Import { use_, name, .. } => lookup_use(file, use_, name, options, 0),
+        Role::UseTarget(u) => {
+            let dep = file.load(u, options)?;
+            Some(Definition {
+                path: dep.path,
+                range: Range::default(),
+                kind: Kind::Interface,
+                name: u.from[0].name.to_string(),
+                docs: None,
+            })
+        }
+    }
+}
+
+fn to_definition(
+    file: &File,
+    occurrence: &Occurrence<'_>,
+    kind: Kind,
+    docs: Option<&ast::Docs<'_>>,
+) -> Definition {
+    Definition {
+        path: file.path.clone(),
+        range: LineIndex::new(&file.text).range(occurrence.span),
+        kind,
+        name: occurrence.name.to_string(),
+        docs: docs.and_then(|d| d.contents()),
+    }
+}
+
+/// Looks up the item `name` within `file`, whose names are `occurrences`,
+/// following `use` statements to other files as necessary.
+fn lookup(
+    file: &File,
+    occurrences: &[Occurrence<'_>],
+    name: &str,
+    options: &ParseOptions,
+    depth: usize,
+) -> Option<Definition> {
+    for o in occurrences.iter().filter(|o| o.name == name) {
+        match &o.role {
+            Role::Definition(kind, docs) if kind.is_item() => {
+                return Some(to_definition(file, o, *kind, *docs));
+            }
+            Role::Import {
+                use_,
+                name,
+                local: true,
+            } => return lookup_use(file, use_, name, options, depth),
+            _ => {}
+        }
+    }
+
+    // Names not found otherwise may come from a `use *`.
+    for o in occurrences {
+        if let Role::UseTarget(u) = &o.role {
+            if u.names.is_none() {
+                if let Some(definition) = lookup_use(file, u, name, options, depth) {
+                    return Some(definition);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Looks up the item `name` within the interface that `u`, a `use` within
+/// `file`, imports from.
+fn lookup_use(
+    file: &File,
+    u: &Use<'_>,
+    name: &str,
+    options: &ParseOptions,
+    depth: usize,
+) -> Option<Definition> {
+    if depth >= MAX_DEPTH {
+        return None;
+    }
+    let dep = file.load(u, options)?;
+    let (ast, _) = Ast::parse_lenient(&dep.text);
+    lookup(&dep, &occurrences(&ast.items), name, options, depth + 1)
+}
+
+/// Returns markdown describing the name at byte `offset` within `file`.
+pub fn hover(file: &File, offset: usize, options: &ParseOptions) -> Option<String> {
+    let definition = definition(file, offset, options)?;
+    let mut ret = format!(
+        "```wit\n{} {}\n```",
+        definition.kind.describe(),
+        definition.name
+    );
+    if let Some(docs) = &definition.docs {
+        ret.push_str("\n\n");
+        ret.push_str(docs.trim_end());
+    }
+    Some(ret)
+}
+
+/// Returns the keywords of the language along with every type, resource and
+/// interface in scope within `file`.
+pub fn completions(file: &File, options: &ParseOptions) -> Vec<CompletionItem> {
+    let (ast, _) = Ast::parse_lenient(&file.text);
+    let mut seen = HashSet::new();
+    let mut ret = Vec::new();
+    let mut push =
+        |label: &str, kind: CompletionItemKind, detail: Option<&str>, docs: Option<String>| {
+            if seen.insert(label.to_string()) {
+                ret.push(CompletionItem {
+                    label: label.to_string(),
+                    kind: Some(kind),
+                    detail: detail.map(|s| s.to_string()),
+                    documentation: docs.map(Documentation::String),
+                    ..CompletionItem::default()
+                });
+            }
+        };
+    for o in occurrences(&ast.items) {
+        match &o.role {
+            Role::Definition(kind, docs) if kind.is_type() || *kind == Kind::Interface => push(
+                o.name,
+                kind.completion_kind(),
+                Some(kind.describe()),
+                docs.and_then(|d| d.contents()),
+            ),
+            Role::Import {
+                use_,
+                name,
+                local: true,
+            } => match lookup_use(file, use_, name, options, 0) {
+                Some(d) => push(
+                    o.name,
+                    d.kind.completion_kind(),
+                    Some(d.kind.describe()),
+                    d.docs,
+                ),
+                None => push(o.name, CompletionItemKind::TYPE_PARAMETER, None, None),
+            },
+            _ => {}
+        }
+    }
+    for keyword in ast::keywords() {
+        push(keyword, CompletionItemKind::KEYWORD, None, None);
+    }
+    ret
+}
+
+/// Returns the outline of the items within `file`.
+pub fn symbols(file: &File) -> Vec<DocumentSymbol> {
+    let (ast, _) = Ast::parse_lenient(&file.text);
+    let index = LineIndex::new(&file.text);
+    return item_symbols(&index, &ast.items);
+
+    #[allow(deprecated)] // the `deprecated` field is required to be specified
+    fn symbol(
+        index: &LineIndex<'_>,
+        name: &str,
+        span: Span,
+        kind: Kind,
+        children: Vec<DocumentSymbol>,
+    ) -> DocumentSymbol {
+        let range = index.range(span);
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: Some(kind.describe().to_string()),
+            kind: kind.symbol_kind(),
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: range,
+            children: if children.is_empty() {
+                None
+            } else {
+                Some(children)
+            },
+        }
+    }
+
+    fn members<'a>(
+        index: &LineIndex<'_>,
+        ids: impl Iterator<Item = &'a Id<'a>>,
+        kind: Kind,
+    ) -> Vec<DocumentSymbol> {
+        ids.map(|id| symbol(index, &id.name, id.span, kind, Vec::new()))
+            .collect()
+    }
+
+    fn extern_items(index: &LineIndex<'_>, kind: &ExternKind<'_>) -> Vec<DocumentSymbol> {
+        match kind {
+            ExternKind::Interface(_, items) => item_symbols(index, items),
+            ExternKind::Id(_) => Vec::new(),
+        }
+    }
+
+    fn item_symbols(index: &LineIndex<'_>, items: &[Item<'_>]) -> Vec<DocumentSymbol> {
+        let mut ret = Vec::new();
+        for item in items {
+            match item {
+                Item::Use(_) | Item::Include(_) => {}
+                Item::TypeDef(t) => {
+                    let children = match &t.ty {
+                        Type::Record(r) => {
+                            members(index, r.fields.iter().map(|f| &f.name), Kind::Field)
+                        }
+                        Type::Flags(f) => {
+                            members(index, f.flags.iter().map(|f| &f.name), Kind::Flag)
+                        }
+                        Type::Variant(v) => {
+                            members(index, v.cases.iter().map(|c| &c.name), Kind::Case)
+                        }
+                        Type::Enum(e) => {
+                            members(index, e.cases.iter().map(|c| &c.name), Kind::Case)
+                        }
+                        _ => Vec::new(),
+                    };
+                    let kind = Kind::of_type(&t.ty);
+                    ret.push(symbol(index, &t.name.name, t.name.span, kind, children));
+                }
+                Item::Resource(r) => {
+                    let children = r
+                        .funcs
+                        .iter()
+                        .map(|f| {
+                            let kind = match f.kind {
+                                ResourceFuncKind::Constructor => Kind::Constructor,
+                                ResourceFuncKind::Method => Kind::Method,
+                                ResourceFuncKind::Static => Kind::StaticMethod,
+                            };
+                            symbol(index, &f.name.name, f.name.span, kind, Vec::new())
+                        })
+                        .collect();
+                    ret.push(symbol(
+                        index,
+                        &r.name.name,
+                        r.name.span,
+                        Kind::Resource,
+                        children,
+                    ));
+                }
+                Item::Value(v) => {
+                    let kind = match v.kind {
+                        ValueKind::Function { .. } => Kind::Function,
+                        ValueKind::Global(_) => Kind::Global,
+                        ValueKind::Const { .. } => Kind::Constant,
+                    };
+                    ret.push(symbol(index, &v.name.name, v.name.span, kind, Vec::new()));
+                }
+                Item::Interface(i) => {
+                    let children = item_symbols(index, &i.items);
+                    ret.push(symbol(
+                        index,
+                        &i.name.name,
+                        i.name.span,
+                        Kind::Interface,
+                        children,
+                    ));
+                }
+                Item::World(w) => {
+                    let children = w
+                        .items
+                        .iter()
+                        .map(|item| match item {
+                            WorldItem::Import(i) => symbol(
+                                index,
+                                &i.name.name,
+                                i.name.span,
+                                Kind::Import,
+                                extern_items(index, &i.kind),
+                            ),
+                            WorldItem::Export(e) => symbol(
+                                index,
+                                &e.name.name,
+                                e.name.span,
+                                Kind::Export,
+                                extern_items(index, &e.kind),
+                            ),
+                            WorldItem::ExportDefault(kind) => {
+                                let span = match kind {
+                                    ExternKind::Interface(span, _) => *span,
+                                    ExternKind::Id(id) => id.span,
+                                };
+                                let children = extern_items(index, kind);
+                                symbol(index, "default", span, Kind::Export, children)
+                            }
+                        })
+                        .collect();
+                    ret.push(symbol(
+                        index,
+                        &w.name.name,
+                        w.name.span,
+                        Kind::World,
+                        children,
+                    ));
+                }
+            }
+        }
+        ret
+    }
+}
@@ -0,0 +1,341 @@
+//! A language server for wit files.
+//!
+//! The server communicates over stdin and stdout and supports diagnostics as
+//! files are edited, go-to-definition (including across `use` statements),
+//! hover documentation, completion of types and keywords, and document
+//! symbols.
+//!
+//! Packages used by files are searched for as with `wit-bindgen`'s
+//! `--search-path` option, with the directories given by the `searchPaths`
+//! array of the client's initialization options.
+
+use analysis::File;
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+};
+use lsp_types::{
+    CompletionOptions, CompletionParams, CompletionResponse, DiagnosticRelatedInformation,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    InitializeParams, Location, MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextDocumentSyncOptions, TextDocumentSyncSaveOptions, Url,
+};
+use position::LineIndex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use wit_parser::{ParseOptions, Severity};
+
+mod analysis;
+mod position;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(capabilities())?;
+    let params = connection.initialize(capabilities)?;
+    let params: InitializeParams = serde_json::from_value(params)?;
+    let server = Server {
+        connection,
+        options: parse_options(&params),
+        documents: HashMap::new(),
+        published: HashMap::new(),
+    };
+    server.run()?;
+    io_threads.join()?;
+    Ok(())
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..TextDocumentSyncOptions::default()
+            },
+        )),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    }
+}
+
+fn parse_options(params: &InitializeParams) -> ParseOptions {
+    let search_paths = params
+        .initialization_options
+        .as_ref()
+        .and_then(|options| options.get("searchPaths"))
+        .and_then(|paths| paths.as_array())
+        .map(|paths| {
+            paths
+                .iter()
+                .filter_map(|p| p.as_str())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    ParseOptions {
+        search_paths,
+        ..ParseOptions::default()
+    }
+}
+
+struct Server {
+    connection: Connection,
+    options: ParseOptions,
+    /// The contents of every open document.
+    documents: HashMap<Url, String>,
+    /// The other files, such as dependencies, that diagnostics were last
+    /// published to on behalf of each open document.
+    published: HashMap<Url, Vec<Url>>,
+}
+
+impl Server {
+    fn run(mut self) -> Result<()> {
+        let receiver = self.connection.receiver.clone();
+        for message in receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => {
+                    self.handle_notification(notification)?;
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Server::definition),
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Server::hover),
+            Completion::METHOD => self.respond::<Completion>(request, Server::completion),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, Server::document_symbols)
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", request.method),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        f: fn(&Server, R::Params) -> R::Result,
+    ) -> Response {
+        let id = request.id.clone();
+        match request.extract::<R::Params>(R::METHOD) {
+            Ok((id, params)) => Response::new_ok(id, f(self, params)),
+            Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, format!("{:?}", e)),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.clone(), params.text_document.text);
+                self.publish(&uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Documents are synchronized in full, so the last change
+                // holds the entire new contents.
+                if let Some(change) = params.content_changes.pop() {
+                    let uri = params.text_document.uri;
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish(&uri)?;
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                // Dependencies are read from disk, so any open document may
+                // be affected by a save.
+                let uris = self.documents.keys().cloned().collect::<Vec<_>>();
+                for uri in uris {
+                    self.publish(&uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                let related = self.published.remove(&uri).unwrap_or_default();
+                for uri in std::iter::once(uri).chain(related) {
+                    self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+                        uri,
+                        Vec::new(),
+                        None,
+                    ))?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) -> Result<()> {
+        let notification = Notification::new(N::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    /// Publishes the diagnostics of the open document `uri`.
+    ///
+    /// Problems within the files it depends on are published to those files.
+    fn publish(&mut self, uri: &Url) -> Result<()> {
+        let contents = match self.documents.get(uri) {
+            Some(contents) => contents,
+            None => return Ok(()),
+        };
+        let path = to_path(uri);
+        let mut by_file = BTreeMap::new();
+        by_file.insert(uri.clone(), Vec::new());
+        for d in analysis::diagnostics(&path, contents, &self.options) {
+            let file_uri = if d.file == path {
+                uri.clone()
+            } else {
+                match Url::from_file_path(&d.file) {
+                    Ok(file_uri) => file_uri,
+                    Err(()) => uri.clone(),
+                }
+            };
+            let diagnostic = to_lsp_diagnostic(&d, &file_uri);
+            by_file
+                .entry(file_uri)
+                .or_insert_with(Vec::new)
+                .push(diagnostic);
+        }
+
+        // Clear out diagnostics previously published to other files which
+        // no longer have any.
+        let related = by_file.keys().filter(|u| *u != uri).cloned().collect();
+        for stale in self
+            .published
+            .insert(uri.clone(), related)
+            .unwrap_or_default()
+        {
+            by_file.entry(stale).or_insert_with(Vec::new);
+        }
+
+        for (uri, diagnostics) in by_file {
+            self.notify::<PublishDiagnostics>(PublishDiagnosticsParams::new(
+                uri,
+                diagnostics,
+                None,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the open document `uri` along with the byte offset of
+    /// `position` within it.
+    fn file_at(&self, position: &TextDocumentPositionParams) -> Option<(File, usize)> {
+        let file = self.file(&position.text_document.uri)?;
+        let offset = LineIndex::new(&file.text).offset(position.position);
+        Some((file, offset))
+    }
+
+    fn file(&self, uri: &Url) -> Option<File> {
+        let contents = self.documents.get(uri)?;
+        Some(File::new(&to_path(uri), contents))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = &params.text_document_position_params;
+        let (file, offset) = self.file_at(position)?;
+        let definition = analysis::definition(&file, offset, &self.options)?;
+        let uri = if definition.path == file.path {
+            position.text_document.uri.clone()
+        } else {
+            Url::from_file_path(&definition.path).ok()?
+        };
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri,
+            definition.range,
+        )))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (file, offset) = self.file_at(&params.text_document_position_params)?;
+        let value = analysis::hover(&file, offset, &self.options)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let file = self.file(&params.text_document_position.text_document.uri)?;
+        let items = analysis::completions(&file, &self.options);
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let file = self.file(&params.text_document.uri)?;
+        Some(DocumentSymbolResponse::Nested(analysis::symbols(&file)))
+    }
+}
+
+fn to_path(uri: &Url) -> PathBuf {
+    uri.to_file_path()
+        .unwrap_or_else(|()| PathBuf::from(uri.path()))
+}
+
+fn to_lsp_diagnostic(d: &wit_parser::Diagnostic, uri: &Url) -> lsp_types::Diagnostic {
+    let index = LineIndex::new(d.source());
+    let mut message = d.message.clone();
+    for note in d.notes.iter() {
+        message.push_str("\nnote: ");
+        message.push_str(note);
+    }
+    let related_information = d
+        .labels
+        .iter()
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location::new(uri.clone(), index.range(label.span)),
+            message: label.message.clone(),
+        })
+        .collect::<Vec<_>>();
+    lsp_types::Diagnostic {
+        range: index.range(d.span),
+        severity: Some(match d.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        source: Some("wit".to_string()),
+        message,
+        related_information: if related_information.is_empty() {
+            None
+        } else {
+            Some(related_information)
+        },
+        ..lsp_types::Diagnostic::default()
+    }
+}
@@ -0,0 +1,283 @@
+//! Drives the `wit-lsp` binary over stdio as an editor would.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// A minimal language server client speaking to a `wit-lsp` process.
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_wit-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut client = Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        client.request("initialize", json!({ "capabilities": {} }));
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn recv(&mut self) -> Value {
+        let mut len = None;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(n) = line.strip_prefix("Content-Length: ") {
+                len = Some(n.parse::<usize>().unwrap());
+            }
+        }
+        let mut body = vec![0; len.expect("missing Content-Length")];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    /// Sends a request and returns its result, skipping past any
+    /// notifications sent in the meantime.
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        loop {
+            let message = self.recv();
+            if message["id"] == json!(id) {
+                assert!(message.get("error").is_none(), "{}", message);
+                return message["result"].clone();
+            }
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    /// Waits for the diagnostics published for `uri`.
+    fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        loop {
+            let message = self.recv();
+            if message["method"] == "textDocument/publishDiagnostics"
+                && message["params"]["uri"] == uri
+            {
+                return message["params"]["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+
+    /// Opens the file `name` within the fixtures directory with the contents
+    /// `text`, returning its uri.
+    fn open(&mut self, name: &str, text: &str) -> String {
+        let uri = fixture(name);
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "wit", "version": 1, "text": text },
+            }),
+        );
+        uri
+    }
+
+    fn change(&mut self, uri: &str, version: u32, text: &str) {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        );
+    }
+
+    fn at(&mut self, method: &str, uri: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+            }),
+        )
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+        let status = self.child.wait().unwrap();
+        if !std::thread::panicking() {
+            assert!(status.success());
+        }
+    }
+}
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    format!("file://{}", path.display())
+}
+
+const SHAPES: &str = "\
+use { point, style as look } from types
+
+/// A circle, centered on a point.
+record circle {
+  center: point,
+  radius: u32,
+  look: look,
+}
+
+area: func(c: circle) -> float64
+";
+
+#[test]
+fn diagnostics_as_typed() {
+    let mut client = Client::start();
+    let uri = client.open("shapes.wit", SHAPES);
+    assert_eq!(client.diagnostics(&uri), Vec::<Value>::new());
+
+    client.change(&uri, 2, &SHAPES.replace("radius: u32", "radius: length"));
+    let diagnostics = client.diagnostics(&uri);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["message"], "no type named `length`");
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({
+            "start": { "line": 5, "character": 10 },
+            "end": { "line": 5, "character": 16 },
+        })
+    );
+
+    client.change(&uri, 3, SHAPES);
+    assert_eq!(client.diagnostics(&uri), Vec::<Value>::new());
+}
+
+#[test]
+fn definition_across_use() {
+    let mut client = Client::start();
+    let uri = client.open("shapes.wit", SHAPES);
+
+    // `point` in the record resolves through the `use` to `types.wit`.
+    let location = client.at("textDocument/definition", &uri, 4, 11);
+    assert_eq!(location["uri"], fixture("types.wit"));
+    assert_eq!(
+        location["range"],
+        json!({
+            "start": { "line": 1, "character": 7 },
+            "end": { "line": 1, "character": 12 },
+        })
+    );
+
+    // as does a renamed import
+    let location = client.at("textDocument/definition", &uri, 6, 9);
+    assert_eq!(location["uri"], fixture("types.wit"));
+    assert_eq!(
+        location["range"]["start"],
+        json!({ "line": 7, "character": 6 })
+    );
+
+    // while `circle` is defined locally.
+    let location = client.at("textDocument/definition", &uri, 9, 15);
+    assert_eq!(location["uri"], uri);
+    assert_eq!(
+        location["range"]["start"],
+        json!({ "line": 3, "character": 7 })
+    );
+}
+
+#[test]
+fn hover_shows_docs() {
+    let mut client = Client::start();
+    let uri = client.open("shapes.wit", SHAPES);
+
+    let hover = client.at("textDocument/hover", &uri, 4, 11);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```wit\nrecord point\n```\n\nA point in two dimensions."
+    );
+
+    let hover = client.at("textDocument/hover", &uri, 9, 15);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```wit\nrecord circle\n```\n\nA circle, centered on a point."
+    );
+
+    let hover = client.at("textDocument/hover", &uri, 5, 11);
+    assert_eq!(hover, Value::Null);
+}
+
+#[test]
+fn completion() {
+    let mut client = Client::start();
+    let uri = client.open("shapes.wit", SHAPES);
+
+    let items = client.at("textDocument/completion", &uri, 5, 10);
+    let labels = items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    for expected in ["circle", "point", "look", "record", "u32", "resource"] {
+        assert!(labels.contains(&expected), "missing `{}`", expected);
+    }
+    assert!(!labels.contains(&"style"));
+    assert!(!labels.contains(&"area"));
+}
+
+#[test]
+fn document_symbols() {
+    let mut client = Client::start();
+    let uri = client.open("shapes.wit", SHAPES);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": uri } }),
+    );
+    let outline = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            let children = symbol["children"]
+                .as_array()
+                .map(|c| c.iter().map(|c| c["name"].clone()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            (symbol["name"].clone(), symbol["detail"].clone(), children)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        outline,
+        [
+            (
+                json!("circle"),
+                json!("record"),
+                vec![json!("center"), json!("radius"), json!("look")]
+            ),
+            (json!("area"), json!("func"), vec![]),
+        ]
+    );
+}
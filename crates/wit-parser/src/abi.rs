@@ -1,9 +1,40 @@
+//! Lifting and lowering values of interface types to and from core wasm
+//! values, following the canonical ABI.
+//!
+//! # Recursive types
+//!
+//! The canonical ABI has no representation for recursive types, which refer
+//! to themselves through a `list` or an `option` (see
+//! `Interface::is_recursive`), so they use an extension of it which is
+//! specific to `wit-bindgen`:
+//!
+//! * Lists are represented as usual, with their elements in memory.
+//! * An option which is part of a recursive type is *boxed*. It's a single
+//!   `i32` pointer to its payload, or zero for `none`, both when flattened
+//!   and in memory, where it has a size and alignment of 4. The payload is
+//!   allocated with `realloc`, using the payload's own size and alignment,
+//!   and is owned by the option.
+//! * Values are read and written through helpers, see
+//!   `Interface::recursive_helper`, so the instructions generated for them
+//!   stay finite.
+//!
+//! Component types can't describe recursive types either, so `wit-component`
+//! refuses to encode them. Only generators for both sides of a call which
+//! agree on this representation, as the Rust guest and the Python host do,
+//! support them, see `Generator::supports_recursive_types`.
+
 use crate::sizealign::align_to;
 use crate::{
-    Enum, Flags, FlagsRepr, Function, Int, Interface, Record, Result_, Results, Tuple, Type,
-    TypeDefKind, TypeId, Union, Variant,
+    Enum, Flags, FlagsRepr, Function, Handle, Int, Interface, Record, Refinement, Result_, Results,
+    Stream, Tuple, Type, TypeDefKind, TypeId, Union, Variant,
 };
 
+mod interpreter;
+mod recorder;
+
+pub use interpreter::{Interpreter, Memory, Value, WasmValue};
+pub use recorder::{record, Recorder};
+
 /// A raw WebAssembly signature with params and results.
 #[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
 pub struct WasmSignature {
@@ -116,6 +147,33 @@ macro_rules! def_instruction {
                     )*
                 }
             }
+
+            /// The name of this instruction, e.g. `I32Load`.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(
+                        Self::$variant { .. } => stringify!($variant),
+                    )*
+                }
+            }
+
+            /// Calls `f` with the name and value of each of this
+            /// instruction's fields, in order.
+            fn fields(&self, f: &mut dyn FnMut(&'static str, &dyn recorder::Field)) {
+                match self {
+                    $(
+                        Self::$variant $( {
+                            $(
+                                $field,
+                            )*
+                        } )? => {
+                            $($(
+                                f(stringify!($field), $field);
+                            )*)?
+                        }
+                    )*
+                }
+            }
         }
     };
 }
@@ -364,6 +422,31 @@ def_instruction! {
         /// This is used for both lifting and lowering lists.
         IterBasePointer : [0] => [1],
 
+        /// Lowers a boxed option, which is an option that's part of a
+        /// recursive type, see `Interface::is_recursive`.
+        ///
+        /// Pops an option off the stack and pushes a pointer to its payload,
+        /// or zero if it's `none`. This also pops a block from the block
+        /// stack which writes the payload, available through `IterElem`, to
+        /// the pointer, available through `IterBasePointer`.
+        ///
+        /// The `realloc` field here behaves the same way as `ListLower`.
+        BoxedOptionLower {
+            payload: &'a Type,
+            ty: TypeId,
+            realloc: Option<&'a str>,
+        } : [1] => [1],
+
+        /// Lifts a boxed option from a pointer to its payload, or zero if it's
+        /// `none`.
+        ///
+        /// This also pops a block from the block stack which reads the payload
+        /// from the pointer, available through `IterBasePointer`.
+        BoxedOptionLift {
+            payload: &'a Type,
+            ty: TypeId,
+        } : [1] => [1],
+
         // records and tuples
 
         /// Pops a record value off the stack, decomposes the record to all of
@@ -502,10 +585,112 @@ def_instruction! {
             ty: TypeId,
         } : [1] => [1],
 
+        /// Pops a value which was just lifted into `ty`, a `type` alias named
+        /// `name` with a `where` clause, and pushes it back once it's been
+        /// checked to satisfy `refinement`.
+        ///
+        /// Generators which don't check refinements may pass the value
+        /// through unchanged.
+        RefinementCheck {
+            refinement: &'a Refinement,
+            name: &'a str,
+            ty: TypeId,
+        } : [1] => [1],
+
+        // handles
+
+        /// Pops a handle to the resource `name` off the stack and pushes the
+        /// `i32` index representing it.
+        ///
+        /// Lowering an owned handle transfers ownership of the resource to
+        /// the receiver, while a borrowed handle is only valid for the
+        /// duration of the call.
+        HandleLower {
+            handle: &'a Handle,
+            name: &'a str,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Pops an `i32` index off the stack and lifts it into a handle to
+        /// the resource `name`.
+        HandleLift {
+            handle: &'a Handle,
+            name: &'a str,
+            ty: TypeId,
+        } : [1] => [1],
+
+        // futures and streams
+
+        /// Pops a language-specific future off the stack and pushes the
+        /// `i32` handle to the readable end of the future, transferring it to
+        /// the receiver.
+        FutureLower {
+            payload: &'a Option<Type>,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Pops an `i32` handle to the readable end of a future off the stack
+        /// and lifts it into a language-specific future.
+        FutureLift {
+            payload: &'a Option<Type>,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Pops a language-specific stream off the stack and pushes the
+        /// `i32` handle to the readable end of the stream, transferring it to
+        /// the receiver.
+        StreamLower {
+            stream: &'a Stream,
+            ty: TypeId,
+        } : [1] => [1],
+
+        /// Pops an `i32` handle to the readable end of a stream off the stack
+        /// and lifts it into a language-specific stream.
+        StreamLift {
+            stream: &'a Stream,
+            ty: TypeId,
+        } : [1] => [1],
+
+        // recursive types
+
+        /// Pops a value of the recursive type `ty` and an address off the
+        /// stack and writes the value at `offset` from the address.
+        ///
+        /// Values of recursive types are read and written through helpers so
+        /// the instructions generated for them stay finite. This calls the
+        /// helper generated by `Interface::recursive_helper` with
+        /// `RecursiveHelper::Store`, and `realloc` is what the helper
+        /// allocates lists and boxes with, as with `ListLower`.
+        RecursiveStore {
+            ty: TypeId,
+            name: &'a str,
+            realloc: Option<&'a str>,
+            offset: i32,
+        } : [2] => [0],
+
+        /// Pops an address off the stack and pushes the value of the
+        /// recursive type `ty` read at `offset` from it, by calling the helper
+        /// generated with `RecursiveHelper::Load`.
+        RecursiveLoad {
+            ty: TypeId,
+            name: &'a str,
+            offset: i32,
+        } : [1] => [1],
+
+        /// Returns `amt` values on the stack from a helper generated by
+        /// `Interface::recursive_helper`. This is always the last instruction
+        /// of a helper.
+        RecursiveReturn { amt: usize } : [*amt] => [0],
+
         // calling/control flow
 
         /// Represents a call to a raw WebAssembly API. The module/name are
         /// provided inline as well as the types if necessary.
+        ///
+        /// Async functions use the same core wasm signature as synchronous
+        /// ones, so this is also used to call them. Suspending the caller
+        /// while an async callee runs is left to the host, e.g. through
+        /// Wasmtime's async support.
         CallWasm {
             iface: &'a Interface,
             name: &'a str,
@@ -515,7 +700,9 @@ def_instruction! {
         /// Same as `CallWasm`, except the dual where an interface is being
         /// called rather than a raw wasm function.
         ///
-        /// Note that this will be used for async functions.
+        /// If `func.is_async` is set then the language-level callee is
+        /// asynchronous, and generators must wait for it to complete before
+        /// pushing its results, e.g. with `.await` or by blocking on it.
         CallInterface {
             module: &'a str,
             func: &'a Function,
@@ -568,10 +755,31 @@ def_instruction! {
         GuestDeallocateVariant {
             blocks: usize,
         } : [1] => [0],
+
+        /// Used exclusively for guest-code generation this indicates that
+        /// a boxed option is being deallocated. The pointer to the payload,
+        /// or zero, is popped off the stack.
+        ///
+        /// This variant also pops a block off the block stack which
+        /// deallocates the payload, available through `IterBasePointer`,
+        /// before the box itself is deallocated.
+        GuestDeallocateBoxedOption {
+            payload: &'a Type,
+        } : [1] => [0],
+
+        /// Used exclusively for guest-code generation this indicates that
+        /// a value of the recursive type `ty` at `offset` from the address
+        /// popped off the stack is being deallocated. This calls the helper
+        /// generated with `RecursiveHelper::Deallocate`.
+        GuestDeallocateRecursive {
+            ty: TypeId,
+            name: &'a str,
+            offset: i32,
+        } : [1] => [0],
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Bitcast {
     // Upcasts
     F32ToI32,
@@ -606,6 +814,18 @@ pub enum LiftLower {
     LowerArgsLiftResults,
 }
 
+/// The helpers generated for each recursive type, see
+/// `Interface::recursive_helper`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RecursiveHelper {
+    /// Writes a value to memory, called by `RecursiveStore`.
+    Store,
+    /// Reads a value from memory, called by `RecursiveLoad`.
+    Load,
+    /// Deallocates a value in memory, called by `GuestDeallocateRecursive`.
+    Deallocate,
+}
+
 /// We use a different ABI for wasm importing functions exported by the host
 /// than for wasm exporting functions imported by the host.
 ///
@@ -731,7 +951,13 @@ impl Interface {
         // would have multiple results then instead truncate it. Imports take a
         // return pointer to write into and exports return a pointer they wrote
         // into.
-        if results.len() > MAX_FLAT_RESULTS {
+        //
+        // Results which need a post-return function are also always returned
+        // through memory. Lists and strings already have two flat values,
+        // but a boxed option in a recursive type may only have one.
+        if results.len() > MAX_FLAT_RESULTS
+            || func.results.iter_types().any(|t| self.needs_post_return(t))
+        {
             retptr = true;
             results.truncate(0);
             match variant {
@@ -804,6 +1030,8 @@ impl Interface {
 
                 TypeDefKind::Enum(e) => result.push(e.tag().into()),
 
+                TypeDefKind::Option(_) if self.is_recursive(*id) => result.push(WasmType::I32),
+
                 TypeDefKind::Option(t) => {
                     result.push(WasmType::I32);
                     self.push_wasm_variants(variant, [None, Some(t)], result);
@@ -819,13 +1047,11 @@ impl Interface {
                     self.push_wasm_variants(variant, u.cases.iter().map(|c| Some(&c.ty)), result);
                 }
 
-                TypeDefKind::Future(_) => {
-                    result.push(WasmType::I32);
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) | TypeDefKind::Handle(_) => {
+                    result.push(WasmType::I32)
                 }
 
-                TypeDefKind::Stream(_) => {
-                    result.push(WasmType::I32);
-                }
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
         }
     }
@@ -884,6 +1110,30 @@ impl Interface {
         Generator::new(self, variant, lift_lower, bindgen).call(func);
     }
 
+    /// Generates the body of a helper for the recursive type `ty` in the
+    /// context of `variant` and `lift_lower`.
+    ///
+    /// Values of recursive types are read, written and deallocated by calling
+    /// these helpers from `RecursiveLoad`, `RecursiveStore` and
+    /// `GuestDeallocateRecursive` instructions. The helper's arguments are
+    /// available through `GetArg`: a `Store` helper takes the value and then
+    /// the address to write it to, while `Load` and `Deallocate` helpers take
+    /// just the address. The body ends with a `RecursiveReturn`.
+    ///
+    /// Helpers may call other helpers, including themselves, so generators
+    /// typically keep generating helpers until every helper which is called
+    /// has been generated.
+    pub fn recursive_helper(
+        &self,
+        variant: AbiVariant,
+        lift_lower: LiftLower,
+        ty: TypeId,
+        helper: RecursiveHelper,
+        bindgen: &mut impl Bindgen,
+    ) {
+        Generator::new(self, variant, lift_lower, bindgen).recursive_helper(ty, helper);
+    }
+
     /// Returns whether the `Function` specified needs a post-return function to
     /// be generated in guest code.
     ///
@@ -898,6 +1148,9 @@ impl Interface {
             Type::String => true,
             Type::Id(id) => match &self.types[*id].kind {
                 TypeDefKind::List(_) => true,
+                // Boxed options own the memory of their payload, and every
+                // recursive type contains either one of these or a list.
+                TypeDefKind::Option(_) if self.is_recursive(*id) => true,
                 TypeDefKind::Type(t) => self.needs_post_return(t),
                 TypeDefKind::Record(r) => r.fields.iter().any(|f| self.needs_post_return(&f.ty)),
                 TypeDefKind::Tuple(t) => t.types.iter().any(|t| self.needs_post_return(t)),
@@ -912,8 +1165,12 @@ impl Interface {
                     .iter()
                     .filter_map(|t| t.as_ref())
                     .any(|t| self.needs_post_return(t)),
-                TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => false,
-                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => unimplemented!(),
+                TypeDefKind::Flags(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Handle(_)
+                | TypeDefKind::Future(_)
+                | TypeDefKind::Stream(_) => false,
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
 
             Type::Bool
@@ -957,6 +1214,9 @@ struct Generator<'a, B: Bindgen> {
     results: Vec<B::Operand>,
     stack: Vec<B::Operand>,
     return_pointer: Option<B::Operand>,
+    /// The recursive type whose helper is being generated, which is expanded
+    /// once rather than calling its own helper.
+    expand_recursive: Option<TypeId>,
 }
 
 impl<'a, B: Bindgen> Generator<'a, B> {
@@ -975,6 +1235,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
             results: Vec::new(),
             stack: Vec::new(),
             return_pointer: None,
+            expand_recursive: None,
         }
     }
 
@@ -1186,10 +1447,8 @@ impl<'a, B: Bindgen> Generator<'a, B> {
     fn post_return(&mut self, func: &Function) {
         let sig = self.iface.wasm_signature(self.variant, func);
 
-        // Currently post-return is only used for lists and lists are always
-        // returned indirectly through memory due to their flat representation
-        // having more than one type. Assert that a return pointer is used,
-        // though, in case this ever changes.
+        // Results which need a post-return are always returned indirectly
+        // through memory, see `wasm_signature`.
         assert!(sig.retptr);
 
         self.emit(&Instruction::GetArg { nth: 0 });
@@ -1211,6 +1470,58 @@ impl<'a, B: Bindgen> Generator<'a, B> {
         );
     }
 
+    fn recursive_helper(&mut self, id: TypeId, helper: RecursiveHelper) {
+        let ty = Type::Id(id);
+        self.expand_recursive = Some(id);
+        match helper {
+            RecursiveHelper::Store => {
+                self.emit(&Instruction::GetArg { nth: 0 });
+                self.emit(&Instruction::GetArg { nth: 1 });
+                let addr = self.stack.pop().unwrap();
+                self.write_to_memory(&ty, addr, 0);
+                self.emit(&Instruction::RecursiveReturn { amt: 0 });
+            }
+            RecursiveHelper::Load => {
+                self.emit(&Instruction::GetArg { nth: 0 });
+                let addr = self.stack.pop().unwrap();
+                self.read_from_memory(&ty, addr, 0);
+                self.emit(&Instruction::RecursiveReturn { amt: 1 });
+            }
+            RecursiveHelper::Deallocate => {
+                self.emit(&Instruction::GetArg { nth: 0 });
+                let addr = self.stack.pop().unwrap();
+                self.deallocate(&ty, addr, 0);
+                self.emit(&Instruction::RecursiveReturn { amt: 0 });
+            }
+        }
+
+        assert!(
+            self.stack.is_empty(),
+            "stack has {} items remaining",
+            self.stack.len()
+        );
+    }
+
+    /// Returns whether values of `id` in memory are handled by calling its
+    /// recursive helper rather than expanding it here.
+    ///
+    /// Every cycle of types goes through a record, variant or union so only
+    /// those call helpers.
+    fn calls_recursive_helper(&mut self, id: TypeId) -> bool {
+        match &self.iface.types[id].kind {
+            TypeDefKind::Record(_) | TypeDefKind::Variant(_) | TypeDefKind::Union(_) => {}
+            _ => return false,
+        }
+        if !self.iface.is_recursive(id) {
+            return false;
+        }
+        if self.expand_recursive == Some(id) {
+            self.expand_recursive = None;
+            return false;
+        }
+        true
+    }
+
     fn emit(&mut self, inst: &Instruction<'_>) {
         self.operands.clear();
         self.results.clear();
@@ -1342,6 +1653,20 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                         name: self.iface.types[id].name.as_deref().unwrap(),
                     });
                 }
+                TypeDefKind::Option(payload) if self.iface.is_recursive(id) => {
+                    let realloc = self.list_realloc();
+                    self.push_block();
+                    self.emit(&IterElem { element: payload });
+                    self.emit(&IterBasePointer);
+                    let addr = self.stack.pop().unwrap();
+                    self.write_to_memory(payload, addr, 0);
+                    self.finish_block(0);
+                    self.emit(&BoxedOptionLower {
+                        payload,
+                        ty: id,
+                        realloc,
+                    });
+                }
                 TypeDefKind::Option(t) => {
                     let results = self.lower_variant_arms(ty, [None, Some(t)]);
                     self.emit(&OptionLower {
@@ -1368,8 +1693,20 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                         name: self.iface.types[id].name.as_deref().unwrap(),
                     });
                 }
-                TypeDefKind::Future(_) => todo!("lower future"),
-                TypeDefKind::Stream(_) => todo!("lower stream"),
+                TypeDefKind::Future(payload) => {
+                    self.emit(&FutureLower { payload, ty: id });
+                }
+                TypeDefKind::Stream(stream) => {
+                    self.emit(&StreamLower { stream, ty: id });
+                }
+                TypeDefKind::Handle(handle) => {
+                    self.emit(&HandleLower {
+                        handle,
+                        ty: id,
+                        name: self.resource_name(handle),
+                    });
+                }
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
         }
     }
@@ -1459,7 +1796,10 @@ impl<'a, B: Bindgen> Generator<'a, B> {
             Type::Float64 => self.emit(&Float64FromF64),
             Type::String => self.emit(&StringLift),
             Type::Id(id) => match &self.iface.types[id].kind {
-                TypeDefKind::Type(t) => self.lift(t),
+                TypeDefKind::Type(t) => {
+                    self.lift(t);
+                    self.check_refinement(id);
+                }
                 TypeDefKind::List(element) => {
                     if self.is_char(element) || self.bindgen.is_list_canonical(self.iface, element)
                     {
@@ -1472,6 +1812,7 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                         self.finish_block(1);
                         self.emit(&ListLift { element, ty: id });
                     }
+                    self.check_refinement(id);
                 }
                 TypeDefKind::Record(record) => {
                     let mut temp = Vec::new();
@@ -1532,6 +1873,15 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
+                TypeDefKind::Option(payload) if self.iface.is_recursive(id) => {
+                    self.push_block();
+                    self.emit(&IterBasePointer);
+                    let addr = self.stack.pop().unwrap();
+                    self.read_from_memory(payload, addr, 0);
+                    self.finish_block(1);
+                    self.emit(&BoxedOptionLift { payload, ty: id });
+                }
+
                 TypeDefKind::Option(t) => {
                     self.lift_variant_arms(ty, [None, Some(t)]);
                     self.emit(&OptionLift { payload: t, ty: id });
@@ -1551,8 +1901,22 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
-                TypeDefKind::Future(_) => todo!("lift future"),
-                TypeDefKind::Stream(_) => todo!("lift stream"),
+                TypeDefKind::Future(payload) => {
+                    self.emit(&FutureLift { payload, ty: id });
+                }
+                TypeDefKind::Stream(stream) => {
+                    self.emit(&StreamLift { stream, ty: id });
+                }
+
+                TypeDefKind::Handle(handle) => {
+                    self.emit(&HandleLift {
+                        handle,
+                        ty: id,
+                        name: self.resource_name(handle),
+                    });
+                }
+
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
         }
     }
@@ -1615,6 +1979,16 @@ impl<'a, B: Bindgen> Generator<'a, B> {
             Type::Float64 => self.lower_and_emit(ty, addr, &F64Store { offset }),
             Type::String => self.write_list_to_memory(ty, addr, offset),
 
+            Type::Id(id) if self.calls_recursive_helper(id) => {
+                self.stack.push(addr);
+                self.emit(&RecursiveStore {
+                    ty: id,
+                    name: self.iface.types[id].name.as_deref().unwrap(),
+                    realloc: self.list_realloc(),
+                    offset,
+                });
+            }
+
             Type::Id(id) => match &self.iface.types[id].kind {
                 TypeDefKind::Type(t) => self.write_to_memory(t, addr, offset),
                 TypeDefKind::List(_) => self.write_list_to_memory(ty, addr, offset),
@@ -1675,6 +2049,12 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
+                TypeDefKind::Option(_) if self.iface.is_recursive(id) => {
+                    self.lower(ty);
+                    self.stack.push(addr);
+                    self.emit(&I32Store { offset });
+                }
+
                 TypeDefKind::Option(t) => {
                     self.write_variant_arms_to_memory(offset, addr, Int::U8, [None, Some(t)]);
                     self.emit(&OptionLower {
@@ -1719,8 +2099,13 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
-                TypeDefKind::Future(_) => todo!("write future to memory"),
-                TypeDefKind::Stream(_) => todo!("write stream to memory"),
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) | TypeDefKind::Handle(_) => {
+                    self.lower(ty);
+                    self.stack.push(addr);
+                    self.emit(&I32Store { offset });
+                }
+
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
         }
     }
@@ -1812,8 +2197,20 @@ impl<'a, B: Bindgen> Generator<'a, B> {
             Type::Float64 => self.emit_and_lift(ty, addr, &F64Load { offset }),
             Type::String => self.read_list_from_memory(ty, addr, offset),
 
+            Type::Id(id) if self.calls_recursive_helper(id) => {
+                self.stack.push(addr);
+                self.emit(&RecursiveLoad {
+                    ty: id,
+                    name: self.iface.types[id].name.as_deref().unwrap(),
+                    offset,
+                });
+            }
+
             Type::Id(id) => match &self.iface.types[id].kind {
-                TypeDefKind::Type(t) => self.read_from_memory(t, addr, offset),
+                TypeDefKind::Type(t) => {
+                    self.read_from_memory(t, addr, offset);
+                    self.check_refinement(id);
+                }
 
                 TypeDefKind::List(_) => self.read_list_from_memory(ty, addr, offset),
 
@@ -1873,6 +2270,10 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
+                TypeDefKind::Option(_) if self.iface.is_recursive(id) => {
+                    self.emit_and_lift(ty, addr, &I32Load { offset });
+                }
+
                 TypeDefKind::Option(t) => {
                     self.read_variant_arms_from_memory(offset, addr, Int::U8, [None, Some(t)]);
                     self.emit(&OptionLift { payload: t, ty: id });
@@ -1908,8 +2309,13 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
-                TypeDefKind::Future(_) => todo!("read future from memory"),
-                TypeDefKind::Stream(_) => todo!("read stream from memory"),
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) | TypeDefKind::Handle(_) => {
+                    self.stack.push(addr);
+                    self.emit(&I32Load { offset });
+                    self.lift(ty);
+                }
+
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
         }
     }
@@ -1959,6 +2365,19 @@ impl<'a, B: Bindgen> Generator<'a, B> {
         }
     }
 
+    /// Checks the value which was just lifted into `id` if it has a
+    /// refinement.
+    fn check_refinement(&mut self, id: TypeId) {
+        let ty = &self.iface.types[id];
+        if let Some(refinement) = &ty.refinement {
+            self.emit(&Instruction::RefinementCheck {
+                refinement,
+                name: ty.name.as_deref().unwrap(),
+                ty: id,
+            });
+        }
+    }
+
     fn emit_and_lift(&mut self, ty: &Type, addr: B::Operand, instr: &Instruction) {
         self.stack.push(addr);
         self.emit(instr);
@@ -2025,6 +2444,15 @@ impl<'a, B: Bindgen> Generator<'a, B> {
             | Type::Float32
             | Type::Float64 => {}
 
+            Type::Id(id) if self.calls_recursive_helper(id) => {
+                self.stack.push(addr);
+                self.emit(&GuestDeallocateRecursive {
+                    ty: id,
+                    name: self.iface.types[id].name.as_deref().unwrap(),
+                    offset,
+                });
+            }
+
             Type::Id(id) => match &self.iface.types[id].kind {
                 TypeDefKind::Type(t) => self.deallocate(t, addr, offset),
 
@@ -2067,6 +2495,18 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
+                TypeDefKind::Option(payload) if self.iface.is_recursive(id) => {
+                    self.push_block();
+                    self.emit(&IterBasePointer);
+                    let base = self.stack.pop().unwrap();
+                    self.deallocate(payload, base, 0);
+                    self.finish_block(0);
+
+                    self.stack.push(addr);
+                    self.emit(&Instruction::I32Load { offset });
+                    self.emit(&GuestDeallocateBoxedOption { payload });
+                }
+
                 TypeDefKind::Option(t) => {
                     self.deallocate_variant(offset, addr, Int::U8, [None, Some(t)]);
                     self.emit(&GuestDeallocateVariant { blocks: 2 });
@@ -2091,8 +2531,11 @@ impl<'a, B: Bindgen> Generator<'a, B> {
                     });
                 }
 
-                TypeDefKind::Future(_) => todo!("read future from memory"),
-                TypeDefKind::Stream(_) => todo!("read stream from memory"),
+                // Handles, futures and streams are all represented as `i32`
+                // indices and don't own any memory in the guest.
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) | TypeDefKind::Handle(_) => {}
+
+                TypeDefKind::Resource => unreachable!("resources are used through handles"),
             },
         }
     }
@@ -2117,6 +2560,10 @@ impl<'a, B: Bindgen> Generator<'a, B> {
         }
     }
 
+    fn resource_name(&self, handle: &Handle) -> &'a str {
+        self.iface.types[handle.resource()].name.as_deref().unwrap()
+    }
+
     fn deallocate_fields(&mut self, tys: &[Type], addr: B::Operand, offset: i32) {
         for (field_offset, ty) in self.bindgen.sizes().field_offsets(tys) {
             self.deallocate(ty, addr.clone(), offset + (field_offset as i32));
@@ -0,0 +1,1309 @@
+use super::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, RecursiveHelper, WasmType};
+use crate::compat::describe;
+use crate::sizealign::{align_to, SizeAlign};
+use crate::{Function, Interface, Literal, Range, Refinement, Type, TypeDefKind, TypeId};
+use anyhow::{anyhow, bail, Result};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+/// A value of an interface type, as it's passed to or returned from
+/// functions called through an [`Interpreter`].
+///
+/// Aggregates hold their fields, cases and flags in the order they're
+/// declared in, and cases are referred to by their index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    S8(i8),
+    S16(i16),
+    S32(i32),
+    S64(i64),
+    Float32(f32),
+    Float64(f64),
+    Char(char),
+    String(String),
+    List(Vec<Value>),
+    Record(Vec<Value>),
+    Tuple(Vec<Value>),
+    Flags(Vec<bool>),
+    Variant(u32, Option<Box<Value>>),
+    Enum(u32),
+    Union(u32, Box<Value>),
+    Option(Option<Box<Value>>),
+    Result(Result<Option<Box<Value>>, Option<Box<Value>>>),
+    /// The index representing a handle to a resource.
+    Handle(u32),
+}
+
+/// A core WebAssembly value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WasmValue {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl WasmValue {
+    pub fn ty(&self) -> WasmType {
+        match self {
+            WasmValue::I32(_) => WasmType::I32,
+            WasmValue::I64(_) => WasmType::I64,
+            WasmValue::F32(_) => WasmType::F32,
+            WasmValue::F64(_) => WasmType::F64,
+        }
+    }
+}
+
+/// A linear memory for an [`Interpreter`] to read and write values in.
+///
+/// Memory is allocated by bumping the end of the memory and never reused, so
+/// a use after free is never hidden by another allocation. Allocations made
+/// through `cabi_realloc` are tracked until they're freed, which is checked,
+/// and whatever is still allocated after a call is leaked.
+#[derive(Debug, Clone)]
+pub struct Memory {
+    bytes: Vec<u8>,
+    live: BTreeMap<u32, usize>,
+}
+
+impl Default for Memory {
+    fn default() -> Memory {
+        Memory::new()
+    }
+}
+
+impl Memory {
+    /// Creates an empty memory.
+    ///
+    /// Address zero is never allocated, so it can stand for `none` in boxed
+    /// options.
+    pub fn new() -> Memory {
+        Memory {
+            bytes: vec![0; 8],
+            live: BTreeMap::new(),
+        }
+    }
+
+    /// Allocates `size` bytes aligned to `align`, as a call to `cabi_realloc`
+    /// for a new allocation would.
+    pub fn alloc(&mut self, size: usize, align: usize) -> Result<u32> {
+        let addr = self.bump(size.max(1), align)?;
+        self.live.insert(addr, size);
+        Ok(addr)
+    }
+
+    /// Frees the allocation at `addr`, failing if it isn't live.
+    pub fn free(&mut self, addr: u32) -> Result<()> {
+        match self.live.remove(&addr) {
+            Some(_) => Ok(()),
+            None => bail!("free of {:#x}, which isn't a live allocation", addr),
+        }
+    }
+
+    /// Returns the address and size of each allocation which hasn't been
+    /// freed.
+    pub fn live_allocations(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.live.iter().map(|(addr, size)| (*addr, *size))
+    }
+
+    pub fn load(&self, addr: u32, len: usize) -> Result<&[u8]> {
+        let range = self.range(addr, len)?;
+        Ok(&self.bytes[range])
+    }
+
+    pub fn store(&mut self, addr: u32, bytes: &[u8]) -> Result<()> {
+        let range = self.range(addr, bytes.len())?;
+        self.bytes[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Allocates space which is never freed, like the static return areas
+    /// of generated bindings.
+    fn scratch(&mut self, size: usize, align: usize) -> Result<u32> {
+        self.bump(size, align)
+    }
+
+    fn bump(&mut self, size: usize, align: usize) -> Result<u32> {
+        let addr = align_to(self.bytes.len(), align.max(1));
+        let end = addr + size;
+        if end > u32::MAX as usize {
+            bail!("out of memory allocating {} bytes", size);
+        }
+        self.bytes.resize(end, 0);
+        Ok(addr as u32)
+    }
+
+    fn range(&self, addr: u32, len: usize) -> Result<std::ops::Range<usize>> {
+        let start = addr as usize;
+        match start.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => Ok(start..end),
+            _ => bail!("out of bounds access of {} bytes at {:#x}", len, addr),
+        }
+    }
+
+    fn read<const N: usize>(&self, addr: u32) -> Result<[u8; N]> {
+        Ok(self.load(addr, N)?.try_into().unwrap())
+    }
+}
+
+/// A reference implementation of the canonical ABI, which runs the
+/// instructions of [`Interface::call`] over a [`Memory`] instead of
+/// generating code for them.
+///
+/// Lowering a [`Value`] and lifting it back on the other side of a call is
+/// meant to round-trip, so bindings generated for either side can be tested
+/// against this. Anything the other side of a call can get wrong, like an
+/// invalid discriminant or `char`, a refinement which isn't satisfied or an
+/// access out of bounds, is an error rather than a panic.
+///
+/// Lists, strings and boxes lowered without `cabi_realloc` are temporary
+/// and freed after the call, and those lifted on the guest side of a call
+/// are owned and freed once they've been read, as guest bindings do.
+/// Futures and streams aren't supported.
+pub struct Interpreter<'a> {
+    iface: &'a Interface,
+    sizes: SizeAlign,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(iface: &'a Interface) -> Interpreter<'a> {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+        Interpreter { iface, sizes }
+    }
+
+    /// Calls the core wasm function of `func`, lowering `args` for `callee`
+    /// and lifting the values it returns.
+    ///
+    /// This is a guest calling an import for `AbiVariant::GuestImport`, and
+    /// a host calling an export for `AbiVariant::GuestExport`. The caller of
+    /// an export is expected to call [`Interpreter::post_return`] with the
+    /// core wasm results once it's done with them.
+    pub fn call_wasm(
+        &self,
+        variant: AbiVariant,
+        func: &Function,
+        args: &[Value],
+        memory: &mut Memory,
+        mut callee: impl FnMut(&[WasmValue], &mut Memory) -> Result<Vec<WasmValue>>,
+    ) -> Result<Vec<Value>> {
+        let mut callee = |args: Vec<Val>, memory: &mut Memory| -> Result<Vec<Val>> {
+            let args = args.iter().map(Val::wasm).collect::<Result<Vec<_>>>()?;
+            let results = callee(&args, memory)?;
+            Ok(results.into_iter().map(Val::Wasm).collect())
+        };
+        let args = args.iter().cloned().map(Val::Value).collect();
+        let results = self.run(
+            variant,
+            LiftLower::LowerArgsLiftResults,
+            memory,
+            Some(&mut callee),
+            args,
+            |iface, variant, lift_lower, compiler| iface.call(variant, lift_lower, func, compiler),
+        )?;
+        results.into_iter().map(Val::into_value).collect()
+    }
+
+    /// Implements the core wasm function of `func`, lifting `args` for
+    /// `callee` and lowering the values it returns.
+    ///
+    /// This is a host implementing an import for `AbiVariant::GuestImport`,
+    /// and a guest implementing an export for `AbiVariant::GuestExport`.
+    pub fn call_interface(
+        &self,
+        variant: AbiVariant,
+        func: &Function,
+        args: &[WasmValue],
+        memory: &mut Memory,
+        mut callee: impl FnMut(&[Value]) -> Result<Vec<Value>>,
+    ) -> Result<Vec<WasmValue>> {
+        let mut callee = |args: Vec<Val>, _: &mut Memory| -> Result<Vec<Val>> {
+            let args = args
+                .into_iter()
+                .map(Val::into_value)
+                .collect::<Result<Vec<_>>>()?;
+            Ok(callee(&args)?.into_iter().map(Val::Value).collect())
+        };
+        let args = args.iter().copied().map(Val::Wasm).collect();
+        let results = self.run(
+            variant,
+            LiftLower::LiftArgsLowerResults,
+            memory,
+            Some(&mut callee),
+            args,
+            |iface, variant, lift_lower, compiler| iface.call(variant, lift_lower, func, compiler),
+        )?;
+        results.iter().map(Val::wasm).collect()
+    }
+
+    /// Frees what an export returned in `results`, if anything, after its
+    /// caller is done with it.
+    pub fn post_return(
+        &self,
+        func: &Function,
+        results: &[WasmValue],
+        memory: &mut Memory,
+    ) -> Result<()> {
+        if !self.iface.guest_export_needs_post_return(func) {
+            return Ok(());
+        }
+        let args = results.iter().copied().map(Val::Wasm).collect();
+        self.run(
+            AbiVariant::GuestExport,
+            LiftLower::LiftArgsLowerResults,
+            memory,
+            None,
+            args,
+            |iface, _, _, compiler| iface.post_return(func, compiler),
+        )?;
+        Ok(())
+    }
+
+    fn run(
+        &self,
+        variant: AbiVariant,
+        lift_lower: LiftLower,
+        memory: &mut Memory,
+        callee: Option<&mut Callee<'_>>,
+        args: Vec<Val>,
+        generate: impl FnOnce(&Interface, AbiVariant, LiftLower, &mut Compiler<'_>),
+    ) -> Result<Vec<Val>> {
+        let call = Call {
+            iface: self.iface,
+            sizes: &self.sizes,
+            variant,
+            lift_lower,
+            helpers: Default::default(),
+            temporaries: Default::default(),
+        };
+        let program = call.compile(|compiler| generate(self.iface, variant, lift_lower, compiler));
+        let results = Machine::new(&call, memory, callee, args).execute(&program)?;
+        for addr in call.temporaries.take() {
+            memory.free(addr)?;
+        }
+        Ok(results)
+    }
+}
+
+type Callee<'a> = dyn FnMut(Vec<Val>, &mut Memory) -> Result<Vec<Val>> + 'a;
+
+/// An operand of an instruction, which is either a core wasm value or a
+/// value of an interface type.
+#[derive(Debug, Clone)]
+enum Val {
+    Wasm(WasmValue),
+    Value(Value),
+}
+
+impl Val {
+    fn wasm(&self) -> Result<WasmValue> {
+        match self {
+            Val::Wasm(val) => Ok(*val),
+            Val::Value(val) => bail!("expected a core wasm value, found {:?}", val),
+        }
+    }
+
+    fn into_value(self) -> Result<Value> {
+        match self {
+            Val::Value(val) => Ok(val),
+            Val::Wasm(val) => bail!("expected an interface value, found {:?}", val),
+        }
+    }
+
+    fn i32(&self) -> Result<i32> {
+        match self.wasm()? {
+            WasmValue::I32(x) => Ok(x),
+            val => bail!("expected an `i32`, found {:?}", val),
+        }
+    }
+
+    fn i64(&self) -> Result<i64> {
+        match self.wasm()? {
+            WasmValue::I64(x) => Ok(x),
+            val => bail!("expected an `i64`, found {:?}", val),
+        }
+    }
+
+    fn f32(&self) -> Result<f32> {
+        match self.wasm()? {
+            WasmValue::F32(x) => Ok(x),
+            val => bail!("expected an `f32`, found {:?}", val),
+        }
+    }
+
+    fn f64(&self) -> Result<f64> {
+        match self.wasm()? {
+            WasmValue::F64(x) => Ok(x),
+            val => bail!("expected an `f64`, found {:?}", val),
+        }
+    }
+
+    /// Returns this `i32` address plus `offset`.
+    fn addr(&self, offset: i32) -> Result<u32> {
+        (self.i32()? as u32)
+            .checked_add(offset as u32)
+            .ok_or_else(|| anyhow!("address overflow"))
+    }
+
+    /// Returns the discriminant of a variant, which is an `i64` if it has
+    /// a `u64` tag.
+    fn discriminant(&self) -> Result<usize> {
+        match self.wasm()? {
+            WasmValue::I32(x) => Ok(x as u32 as usize),
+            WasmValue::I64(x) => Ok(x as u64 as usize),
+            val => bail!("expected a discriminant, found {:?}", val),
+        }
+    }
+}
+
+fn i32(x: i32) -> Val {
+    Val::Wasm(WasmValue::I32(x))
+}
+
+fn mismatch<T>(expected: impl fmt::Display, found: &Value) -> Result<T> {
+    bail!("expected a `{}` value, found {:?}", expected, found)
+}
+
+/// The state shared by a function's instructions and the recursive helpers
+/// they call.
+struct Call<'a> {
+    iface: &'a Interface,
+    sizes: &'a SizeAlign,
+    variant: AbiVariant,
+    lift_lower: LiftLower,
+    helpers: RefCell<HashMap<(TypeId, RecursiveHelper), Rc<Program>>>,
+    /// Allocations made without `cabi_realloc`, freed after the call.
+    temporaries: RefCell<Vec<u32>>,
+}
+
+impl Call<'_> {
+    fn compile(&self, generate: impl FnOnce(&mut Compiler<'_>)) -> Program {
+        let mut compiler = Compiler {
+            iface: self.iface,
+            sizes: self.sizes,
+            blocks: vec![Vec::new()],
+            finished: Vec::new(),
+            slots: 0,
+        };
+        generate(&mut compiler);
+        assert!(compiler.finished.is_empty());
+        Program {
+            stmts: compiler.blocks.pop().unwrap(),
+            slots: compiler.slots,
+        }
+    }
+
+    fn helper(&self, ty: TypeId, helper: RecursiveHelper) -> Rc<Program> {
+        if let Some(program) = self.helpers.borrow().get(&(ty, helper)) {
+            return program.clone();
+        }
+        let program = Rc::new(self.compile(|compiler| {
+            self.iface
+                .recursive_helper(self.variant, self.lift_lower, ty, helper, compiler)
+        }));
+        self.helpers
+            .borrow_mut()
+            .insert((ty, helper), program.clone());
+        program
+    }
+
+    /// Returns whether lists, strings and boxes lifted in this call are owned
+    /// by the side lifting them, which is the guest.
+    fn owns_lifted(&self) -> bool {
+        matches!(
+            (self.variant, self.lift_lower),
+            (AbiVariant::GuestImport, LiftLower::LowerArgsLiftResults)
+                | (AbiVariant::GuestExport, LiftLower::LiftArgsLowerResults)
+        )
+    }
+}
+
+/// A function or helper compiled from its instructions, whose operands are
+/// numbered slots.
+struct Program {
+    stmts: Vec<Stmt>,
+    slots: usize,
+}
+
+struct Stmt {
+    op: Op,
+    operands: Vec<usize>,
+    results: Vec<usize>,
+    blocks: Vec<Block>,
+}
+
+struct Block {
+    stmts: Vec<Stmt>,
+    results: Vec<usize>,
+}
+
+type Op = Box<dyn Fn(&mut Machine<'_, '_>, Vec<Val>, &[Block]) -> Result<Vec<Val>>>;
+
+fn op(f: impl Fn(&mut Machine<'_, '_>, Vec<Val>, &[Block]) -> Result<Vec<Val>> + 'static) -> Op {
+    Box::new(f)
+}
+
+/// An op which maps its only operand to its only result.
+fn unary(f: impl Fn(Val) -> Result<Val> + 'static) -> Op {
+    op(move |_, mut operands, _| Ok(vec![f(operands.pop().unwrap())?]))
+}
+
+struct Compiler<'a> {
+    iface: &'a Interface,
+    sizes: &'a SizeAlign,
+    /// The statements of the blocks being compiled, innermost last.
+    blocks: Vec<Vec<Stmt>>,
+    /// Blocks which have been finished but not yet used by an instruction.
+    finished: Vec<Block>,
+    slots: usize,
+}
+
+impl Compiler<'_> {
+    fn slot(&mut self) -> usize {
+        self.slots += 1;
+        self.slots - 1
+    }
+
+    fn push(&mut self, op: Op, operands: Vec<usize>, results: Vec<usize>, blocks: usize) {
+        let blocks = self.finished.split_off(self.finished.len() - blocks);
+        self.blocks.last_mut().unwrap().push(Stmt {
+            op,
+            operands,
+            results,
+            blocks,
+        });
+    }
+}
+
+impl Bindgen for Compiler<'_> {
+    type Operand = usize;
+
+    fn emit(
+        &mut self,
+        _iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<usize>,
+        results: &mut Vec<usize>,
+    ) {
+        for _ in 0..inst.results_len() {
+            results.push(self.slot());
+        }
+        let op = compile(inst, self.sizes);
+        self.push(op, operands.clone(), results.clone(), blocks(inst));
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, size: usize, align: usize) -> usize {
+        let result = self.slot();
+        let op = op(move |m, _, _| Ok(vec![i32(m.memory.scratch(size, align)? as i32)]));
+        self.push(op, Vec::new(), vec![result], 0);
+        result
+    }
+
+    fn push_block(&mut self) {
+        self.blocks.push(Vec::new());
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<usize>) {
+        let stmts = self.blocks.pop().unwrap();
+        self.finished.push(Block {
+            stmts,
+            results: std::mem::take(operands),
+        });
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.sizes
+    }
+
+    fn is_list_canonical(&self, _iface: &Interface, element: &Type) -> bool {
+        self.iface.all_bits_valid(element)
+    }
+}
+
+/// Returns how many blocks `inst` pops off the block stack.
+fn blocks(inst: &Instruction<'_>) -> usize {
+    use Instruction::*;
+
+    match inst {
+        VariantLower { variant, .. } | VariantLift { variant, .. } => variant.cases.len(),
+        UnionLower { union, .. } | UnionLift { union, .. } => union.cases.len(),
+        OptionLower { .. } | OptionLift { .. } | ResultLower { .. } | ResultLift { .. } => 2,
+        ListLower { .. }
+        | ListLift { .. }
+        | BoxedOptionLower { .. }
+        | BoxedOptionLift { .. }
+        | GuestDeallocateList { .. }
+        | GuestDeallocateBoxedOption { .. } => 1,
+        GuestDeallocateVariant { blocks } => *blocks,
+        _ => 0,
+    }
+}
+
+/// Lowers a scalar value of the `$ty` case of `Value` into a core wasm value.
+macro_rules! lower {
+    ($ty:ident, $name:tt, |$x:ident| $result:expr) => {
+        unary(|val| match val.into_value()? {
+            Value::$ty($x) => Ok(Val::Wasm($result)),
+            val => mismatch($name, &val),
+        })
+    };
+}
+
+fn compile(inst: &Instruction<'_>, sizes: &SizeAlign) -> Op {
+    use Instruction::*;
+
+    match *inst {
+        GetArg { nth } => op(move |m, _, _| match m.args.get(nth) {
+            Some(arg) => Ok(vec![arg.clone()]),
+            None => bail!("missing argument {}", nth),
+        }),
+        I32Const { val } => op(move |_, _, _| Ok(vec![i32(val)])),
+        Bitcasts { casts } => {
+            let casts = casts.to_vec();
+            op(move |_, operands, _| {
+                casts
+                    .iter()
+                    .zip(operands)
+                    .map(|(cast, val)| bitcast(*cast, val))
+                    .collect()
+            })
+        }
+        ConstZero { tys } => {
+            let zeros = tys
+                .iter()
+                .map(|ty| {
+                    Val::Wasm(match ty {
+                        WasmType::I32 => WasmValue::I32(0),
+                        WasmType::I64 => WasmValue::I64(0),
+                        WasmType::F32 => WasmValue::F32(0.0),
+                        WasmType::F64 => WasmValue::F64(0.0),
+                    })
+                })
+                .collect::<Vec<_>>();
+            op(move |_, _, _| Ok(zeros.clone()))
+        }
+
+        I32Load { offset } => load(offset, |b: [u8; 4]| WasmValue::I32(i32::from_le_bytes(b))),
+        I32Load8U { offset } => load(offset, |b: [u8; 1]| WasmValue::I32(b[0] as i32)),
+        I32Load8S { offset } => load(offset, |b: [u8; 1]| WasmValue::I32(b[0] as i8 as i32)),
+        I32Load16U { offset } => load(offset, |b: [u8; 2]| {
+            WasmValue::I32(u16::from_le_bytes(b) as i32)
+        }),
+        I32Load16S { offset } => load(offset, |b: [u8; 2]| {
+            WasmValue::I32(i16::from_le_bytes(b) as i32)
+        }),
+        I64Load { offset } => load(offset, |b: [u8; 8]| WasmValue::I64(i64::from_le_bytes(b))),
+        F32Load { offset } => load(offset, |b: [u8; 4]| WasmValue::F32(f32::from_le_bytes(b))),
+        F64Load { offset } => load(offset, |b: [u8; 8]| WasmValue::F64(f64::from_le_bytes(b))),
+
+        I32Store { offset } => store(offset, |val| Ok(val.i32()?.to_le_bytes().to_vec())),
+        I32Store8 { offset } => store(offset, |val| Ok(vec![val.i32()? as u8])),
+        I32Store16 { offset } => {
+            store(offset, |val| Ok((val.i32()? as u16).to_le_bytes().to_vec()))
+        }
+        I64Store { offset } => store(offset, |val| Ok(val.i64()?.to_le_bytes().to_vec())),
+        F32Store { offset } => store(offset, |val| Ok(val.f32()?.to_le_bytes().to_vec())),
+        F64Store { offset } => store(offset, |val| Ok(val.f64()?.to_le_bytes().to_vec())),
+
+        I32FromChar => lower!(Char, "char", |x| WasmValue::I32(x as i32)),
+        I64FromU64 => lower!(U64, "u64", |x| WasmValue::I64(x as i64)),
+        I64FromS64 => lower!(S64, "s64", |x| WasmValue::I64(x)),
+        I32FromU32 => lower!(U32, "u32", |x| WasmValue::I32(x as i32)),
+        I32FromS32 => lower!(S32, "s32", |x| WasmValue::I32(x)),
+        I32FromU16 => lower!(U16, "u16", |x| WasmValue::I32(x as i32)),
+        I32FromS16 => lower!(S16, "s16", |x| WasmValue::I32(x as i32)),
+        I32FromU8 => lower!(U8, "u8", |x| WasmValue::I32(x as i32)),
+        I32FromS8 => lower!(S8, "s8", |x| WasmValue::I32(x as i32)),
+        F32FromFloat32 => lower!(Float32, "float32", |x| WasmValue::F32(x)),
+        F64FromFloat64 => lower!(Float64, "float64", |x| WasmValue::F64(x)),
+        I32FromBool => lower!(Bool, "bool", |x| WasmValue::I32(x as i32)),
+
+        S8FromI32 => unary(|val| Ok(Val::Value(Value::S8(val.i32()? as i8)))),
+        U8FromI32 => unary(|val| Ok(Val::Value(Value::U8(val.i32()? as u8)))),
+        S16FromI32 => unary(|val| Ok(Val::Value(Value::S16(val.i32()? as i16)))),
+        U16FromI32 => unary(|val| Ok(Val::Value(Value::U16(val.i32()? as u16)))),
+        S32FromI32 => unary(|val| Ok(Val::Value(Value::S32(val.i32()?)))),
+        U32FromI32 => unary(|val| Ok(Val::Value(Value::U32(val.i32()? as u32)))),
+        S64FromI64 => unary(|val| Ok(Val::Value(Value::S64(val.i64()?)))),
+        U64FromI64 => unary(|val| Ok(Val::Value(Value::U64(val.i64()? as u64)))),
+        Float32FromF32 => unary(|val| Ok(Val::Value(Value::Float32(val.f32()?)))),
+        Float64FromF64 => unary(|val| Ok(Val::Value(Value::Float64(val.f64()?)))),
+        CharFromI32 => unary(|val| Ok(Val::Value(Value::Char(char_from(val.i32()?)?)))),
+        BoolFromI32 => unary(|val| match val.i32()? {
+            0 => Ok(Val::Value(Value::Bool(false))),
+            1 => Ok(Val::Value(Value::Bool(true))),
+            x => bail!("invalid bool {}", x),
+        }),
+
+        StringLower { realloc } => {
+            let realloc = realloc.is_some();
+            op(move |m, mut operands, _| {
+                let s = match operands.pop().unwrap().into_value()? {
+                    Value::String(s) => s,
+                    val => return mismatch("string", &val),
+                };
+                let ptr = m.alloc(s.len(), 1, realloc)?;
+                m.memory.store(ptr, s.as_bytes())?;
+                Ok(vec![i32(ptr as i32), i32(s.len() as i32)])
+            })
+        }
+        ListCanonLower { element, realloc } => {
+            let element = *element;
+            let (size, align) = (sizes.size(&element), sizes.align(&element));
+            let realloc = realloc.is_some();
+            op(move |m, mut operands, _| {
+                let list = m.list(operands.pop().unwrap(), &element)?;
+                let ptr = m.alloc(list.len() * size, align, realloc)?;
+                for (i, val) in list.iter().enumerate() {
+                    m.store_plain(&element, ptr + (i * size) as u32, val)?;
+                }
+                Ok(vec![i32(ptr as i32), i32(list.len() as i32)])
+            })
+        }
+        ListLower { element, realloc } => {
+            let element = *element;
+            let (size, align) = (sizes.size(&element), sizes.align(&element));
+            let realloc = realloc.is_some();
+            op(move |m, mut operands, blocks| {
+                let list = m.list(operands.pop().unwrap(), &element)?;
+                let ptr = m.alloc(list.len() * size, align, realloc)?;
+                let len = list.len();
+                for (i, val) in list.into_iter().enumerate() {
+                    m.iterate(Some(val), ptr + (i * size) as u32, &blocks[0])?;
+                }
+                Ok(vec![i32(ptr as i32), i32(len as i32)])
+            })
+        }
+        StringLift => op(|m, operands, _| {
+            let (ptr, len) = (operands[0].addr(0)?, operands[1].i32()? as u32 as usize);
+            let bytes = m.memory.load(ptr, len)?.to_vec();
+            let s = String::from_utf8(bytes).map_err(|_| anyhow!("invalid utf-8 string"))?;
+            m.free_lifted(ptr, len)?;
+            Ok(vec![Val::Value(Value::String(s))])
+        }),
+        ListCanonLift { element, .. } => {
+            let element = *element;
+            let size = sizes.size(&element);
+            op(move |m, operands, _| {
+                let (ptr, len) = (operands[0].addr(0)?, operands[1].i32()? as u32 as usize);
+                let list = (0..len)
+                    .map(|i| m.load_plain(&element, elem_addr(ptr, i, size)?))
+                    .collect::<Result<Vec<_>>>()?;
+                m.free_lifted(ptr, len)?;
+                Ok(vec![Val::Value(Value::List(list))])
+            })
+        }
+        ListLift { element, .. } => {
+            let size = sizes.size(element);
+            op(move |m, operands, blocks| {
+                let (ptr, len) = (operands[0].addr(0)?, operands[1].i32()? as u32 as usize);
+                let mut list = Vec::new();
+                for i in 0..len {
+                    let base = elem_addr(ptr, i, size)?;
+                    let mut results = m.iterate(None, base, &blocks[0])?;
+                    list.push(results.pop().unwrap().into_value()?);
+                }
+                m.free_lifted(ptr, len)?;
+                Ok(vec![Val::Value(Value::List(list))])
+            })
+        }
+        IterElem { .. } => op(|m, _, _| match m.iters.last() {
+            Some((Some(elem), _)) => Ok(vec![Val::Value(elem.clone())]),
+            _ => bail!("`IterElem` outside of lowering a list"),
+        }),
+        IterBasePointer => op(|m, _, _| match m.iters.last() {
+            Some((_, base)) => Ok(vec![i32(*base as i32)]),
+            None => bail!("`IterBasePointer` outside of a list"),
+        }),
+        BoxedOptionLower {
+            payload, realloc, ..
+        } => {
+            let (size, align) = (sizes.size(payload), sizes.align(payload));
+            let realloc = realloc.is_some();
+            op(
+                move |m, mut operands, blocks| match operands.pop().unwrap().into_value()? {
+                    Value::Option(None) => Ok(vec![i32(0)]),
+                    Value::Option(Some(payload)) => {
+                        let ptr = m.alloc(size, align, realloc)?;
+                        m.iterate(Some(*payload), ptr, &blocks[0])?;
+                        Ok(vec![i32(ptr as i32)])
+                    }
+                    val => mismatch("option", &val),
+                },
+            )
+        }
+        BoxedOptionLift { .. } => op(|m, operands, blocks| {
+            let ptr = operands[0].addr(0)?;
+            if ptr == 0 {
+                return Ok(vec![Val::Value(Value::Option(None))]);
+            }
+            let payload = m.iterate(None, ptr, &blocks[0])?.pop().unwrap();
+            if m.call.owns_lifted() {
+                m.memory.free(ptr)?;
+            }
+            let payload = Box::new(payload.into_value()?);
+            Ok(vec![Val::Value(Value::Option(Some(payload)))])
+        }),
+
+        RecordLower { record, name, .. } => {
+            let (name, len) = (name.to_string(), record.fields.len());
+            op(
+                move |_, mut operands, _| match operands.pop().unwrap().into_value()? {
+                    Value::Record(fields) if fields.len() == len => {
+                        Ok(fields.into_iter().map(Val::Value).collect())
+                    }
+                    val => mismatch(&name, &val),
+                },
+            )
+        }
+        RecordLift { .. } => op(|_, operands, _| {
+            let fields = operands.into_iter().map(Val::into_value);
+            Ok(vec![Val::Value(Value::Record(
+                fields.collect::<Result<_>>()?,
+            ))])
+        }),
+        TupleLower { tuple, .. } => {
+            let len = tuple.types.len();
+            op(
+                move |_, mut operands, _| match operands.pop().unwrap().into_value()? {
+                    Value::Tuple(types) if types.len() == len => {
+                        Ok(types.into_iter().map(Val::Value).collect())
+                    }
+                    val => mismatch(format_args!("tuple of {}", len), &val),
+                },
+            )
+        }
+        TupleLift { .. } => op(|_, operands, _| {
+            let types = operands.into_iter().map(Val::into_value);
+            Ok(vec![Val::Value(Value::Tuple(
+                types.collect::<Result<_>>()?,
+            ))])
+        }),
+        FlagsLower { flags, name, .. } => {
+            let (name, len, count) = (name.to_string(), flags.flags.len(), flags.repr().count());
+            op(
+                move |_, mut operands, _| match operands.pop().unwrap().into_value()? {
+                    Value::Flags(bits) if bits.len() == len => {
+                        let mut words = vec![0u32; count];
+                        for (i, bit) in bits.into_iter().enumerate() {
+                            words[i / 32] |= (bit as u32) << (i % 32);
+                        }
+                        Ok(words.into_iter().map(|w| i32(w as i32)).collect())
+                    }
+                    val => mismatch(&name, &val),
+                },
+            )
+        }
+        FlagsLift { flags, .. } => {
+            // Bits beyond the last flag are ignored, which only matters for
+            // `FlagsRepr::U32` as smaller reprs are loaded zero-extended.
+            let len = flags.flags.len();
+            op(move |_, operands, _| {
+                let words = operands
+                    .iter()
+                    .map(|w| Ok(w.i32()? as u32))
+                    .collect::<Result<Vec<_>>>()?;
+                let bits = (0..len).map(|i| words[i / 32] & (1 << (i % 32)) != 0);
+                Ok(vec![Val::Value(Value::Flags(bits.collect()))])
+            })
+        }
+
+        VariantPayloadName => op(|m, _, _| match m.payloads.last() {
+            Some(payload) => Ok(vec![Val::Value(payload.clone())]),
+            None => bail!("`VariantPayloadName` outside of lowering a variant"),
+        }),
+        VariantLower { variant, name, .. } => {
+            let name = name.to_string();
+            let payloads = variant.cases.iter().map(|c| c.ty.is_some()).collect();
+            lower_cases(payloads, move |val| match val {
+                Value::Variant(i, payload) => Ok((i as usize, payload.map(|p| *p))),
+                val => mismatch(&name, &val),
+            })
+        }
+        VariantLift { .. } => lift_cases(|i, payload| Value::Variant(i as u32, payload)),
+        UnionLower { union, name, .. } => {
+            let name = name.to_string();
+            let payloads = vec![true; union.cases.len()];
+            lower_cases(payloads, move |val| match val {
+                Value::Union(i, payload) => Ok((i as usize, Some(*payload))),
+                val => mismatch(&name, &val),
+            })
+        }
+        UnionLift { .. } => lift_cases(|i, payload| Value::Union(i as u32, payload.unwrap())),
+        OptionLower { .. } => lower_cases(vec![false, true], |val| match val {
+            Value::Option(None) => Ok((0, None)),
+            Value::Option(Some(payload)) => Ok((1, Some(*payload))),
+            val => mismatch("option", &val),
+        }),
+        OptionLift { .. } => lift_cases(|_, payload| Value::Option(payload)),
+        ResultLower { result, .. } => {
+            let payloads = vec![result.ok.is_some(), result.err.is_some()];
+            lower_cases(payloads, |val| match val {
+                Value::Result(Ok(payload)) => Ok((0, payload.map(|p| *p))),
+                Value::Result(Err(payload)) => Ok((1, payload.map(|p| *p))),
+                val => mismatch("result", &val),
+            })
+        }
+        ResultLift { .. } => {
+            lift_cases(|i, payload| Value::Result(if i == 0 { Ok(payload) } else { Err(payload) }))
+        }
+        EnumLower { enum_, name, .. } => {
+            let (name, len) = (name.to_string(), enum_.cases.len());
+            unary(move |val| match val.into_value()? {
+                Value::Enum(i) if (i as usize) < len => Ok(i32(i as i32)),
+                val => mismatch(&name, &val),
+            })
+        }
+        EnumLift { enum_, .. } => {
+            let len = enum_.cases.len();
+            unary(move |val| match val.discriminant()? {
+                i if i < len => Ok(Val::Value(Value::Enum(i as u32))),
+                i => bail!("invalid discriminant {}", i),
+            })
+        }
+
+        RefinementCheck { refinement, .. } => {
+            let refinement = refinement.clone();
+            unary(move |val| {
+                check_refinement(&refinement, &val.clone().into_value()?)?;
+                Ok(val)
+            })
+        }
+
+        HandleLower { name, .. } => {
+            let name = name.to_string();
+            unary(move |val| match val.into_value()? {
+                Value::Handle(i) => Ok(i32(i as i32)),
+                val => mismatch(&name, &val),
+            })
+        }
+        HandleLift { .. } => unary(|val| Ok(Val::Value(Value::Handle(val.i32()? as u32)))),
+
+        FutureLower { .. } | FutureLift { .. } | StreamLower { .. } | StreamLift { .. } => {
+            op(|_, _, _| bail!("futures and streams aren't supported"))
+        }
+
+        RecursiveStore { ty, offset, .. } => op(move |m, operands, _| {
+            let addr = operands[1].addr(offset)?;
+            let args = vec![operands[0].clone(), i32(addr as i32)];
+            m.helper(ty, RecursiveHelper::Store, args)
+        }),
+        RecursiveLoad { ty, offset, .. } => op(move |m, operands, _| {
+            let addr = operands[0].addr(offset)?;
+            m.helper(ty, RecursiveHelper::Load, vec![i32(addr as i32)])
+        }),
+        GuestDeallocateRecursive { ty, offset, .. } => op(move |m, operands, _| {
+            let addr = operands[0].addr(offset)?;
+            m.helper(ty, RecursiveHelper::Deallocate, vec![i32(addr as i32)])
+        }),
+        RecursiveReturn { .. } | Return { .. } => op(|m, operands, _| {
+            m.returned = Some(operands);
+            Ok(Vec::new())
+        }),
+
+        CallWasm { sig, .. } => {
+            let results = sig.results.clone();
+            op(move |m, operands, _| {
+                let callee = m
+                    .callee
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("nothing to call"))?;
+                let vals = callee(operands, m.memory)?;
+                let tys = vals.iter().map(|v| Ok(v.wasm()?.ty()));
+                if tys.collect::<Result<Vec<_>>>()? != results {
+                    bail!("expected results of types {:?}, found {:?}", results, vals);
+                }
+                Ok(vals)
+            })
+        }
+        CallInterface { func, .. } => {
+            let results = func.results.len();
+            op(move |m, operands, _| {
+                let callee = m
+                    .callee
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("nothing to call"))?;
+                let vals = callee(operands, m.memory)?;
+                if vals.len() != results {
+                    bail!("expected {} results, found {:?}", results, vals);
+                }
+                Ok(vals)
+            })
+        }
+
+        Malloc { size, align, .. } => {
+            op(move |m, _, _| Ok(vec![i32(m.memory.alloc(size, align)? as i32)]))
+        }
+        GuestDeallocate { .. } => op(|m, operands, _| {
+            m.memory.free(operands[0].addr(0)?)?;
+            Ok(Vec::new())
+        }),
+        GuestDeallocateString => op(|m, operands, _| {
+            if operands[1].i32()? != 0 {
+                m.memory.free(operands[0].addr(0)?)?;
+            }
+            Ok(Vec::new())
+        }),
+        GuestDeallocateList { element } => {
+            let size = sizes.size(element);
+            op(move |m, operands, blocks| {
+                let (ptr, len) = (operands[0].addr(0)?, operands[1].i32()? as u32 as usize);
+                for i in 0..len {
+                    m.iterate(None, elem_addr(ptr, i, size)?, &blocks[0])?;
+                }
+                if len != 0 {
+                    m.memory.free(ptr)?;
+                }
+                Ok(Vec::new())
+            })
+        }
+        GuestDeallocateVariant { .. } => op(|m, operands, blocks| {
+            let i = operands[0].discriminant()?;
+            match blocks.get(i) {
+                Some(block) => m.run(block),
+                None => bail!("invalid discriminant {}", i),
+            }
+        }),
+        GuestDeallocateBoxedOption { .. } => op(|m, operands, blocks| {
+            let ptr = operands[0].addr(0)?;
+            if ptr != 0 {
+                m.iterate(None, ptr, &blocks[0])?;
+                m.memory.free(ptr)?;
+            }
+            Ok(Vec::new())
+        }),
+    }
+}
+
+fn load<const N: usize>(offset: i32, f: impl Fn([u8; N]) -> WasmValue + 'static) -> Op {
+    op(move |m, operands, _| {
+        let bytes = m.memory.read::<N>(operands[0].addr(offset)?)?;
+        Ok(vec![Val::Wasm(f(bytes))])
+    })
+}
+
+fn store(offset: i32, f: impl Fn(&Val) -> Result<Vec<u8>> + 'static) -> Op {
+    op(move |m, operands, _| {
+        let bytes = f(&operands[0])?;
+        m.memory.store(operands[1].addr(offset)?, &bytes)?;
+        Ok(Vec::new())
+    })
+}
+
+/// Lowers a variant-like value, which `case` splits into the index of its
+/// case and its payload, by running the block of its case.
+fn lower_cases(
+    payloads: Vec<bool>,
+    case: impl Fn(Value) -> Result<(usize, Option<Value>)> + 'static,
+) -> Op {
+    op(move |m, mut operands, blocks| {
+        let (i, payload) = case(operands.pop().unwrap().into_value()?)?;
+        match payloads.get(i) {
+            Some(expected) if *expected == payload.is_some() => {}
+            Some(_) => bail!("payload of case {} is {:?}", i, payload),
+            None => bail!("no case {}", i),
+        }
+        // Blocks of cases without a payload still name it, but never use it.
+        let payload = payload.unwrap_or(Value::Tuple(Vec::new()));
+        m.payloads.push(payload);
+        let results = m.run(&blocks[i]);
+        m.payloads.pop();
+        results
+    })
+}
+
+/// Lifts a variant-like value with `value` from the index of its case and
+/// the payload lifted by the block of its case.
+fn lift_cases(value: impl Fn(usize, Option<Box<Value>>) -> Value + 'static) -> Op {
+    op(move |m, operands, blocks| {
+        let i = operands[0].discriminant()?;
+        let block = blocks
+            .get(i)
+            .ok_or_else(|| anyhow!("invalid discriminant {}", i))?;
+        let payload = match m.run(block)?.pop() {
+            Some(payload) => Some(Box::new(payload.into_value()?)),
+            None => None,
+        };
+        Ok(vec![Val::Value(value(i, payload))])
+    })
+}
+
+fn bitcast(cast: Bitcast, val: Val) -> Result<Val> {
+    Ok(Val::Wasm(match cast {
+        Bitcast::None => return Ok(val),
+        Bitcast::F32ToI32 => WasmValue::I32(val.f32()?.to_bits() as i32),
+        Bitcast::F64ToI64 => WasmValue::I64(val.f64()?.to_bits() as i64),
+        Bitcast::I32ToI64 => WasmValue::I64(val.i32()? as u32 as i64),
+        Bitcast::F32ToI64 => WasmValue::I64(val.f32()?.to_bits() as i64),
+        Bitcast::I32ToF32 => WasmValue::F32(f32::from_bits(val.i32()? as u32)),
+        Bitcast::I64ToF64 => WasmValue::F64(f64::from_bits(val.i64()? as u64)),
+        Bitcast::I64ToI32 => WasmValue::I32(val.i64()? as i32),
+        Bitcast::I64ToF32 => WasmValue::F32(f32::from_bits(val.i64()? as u32)),
+    }))
+}
+
+fn char_from(x: i32) -> Result<char> {
+    char::from_u32(x as u32).ok_or_else(|| anyhow!("invalid char {:#x}", x))
+}
+
+fn elem_addr(ptr: u32, i: usize, size: usize) -> Result<u32> {
+    u32::try_from(ptr as usize + i * size).map_err(|_| anyhow!("list out of bounds"))
+}
+
+fn check_refinement(refinement: &Refinement, val: &Value) -> Result<()> {
+    let ok = match refinement {
+        Refinement::Range(range) => match number(val) {
+            Some(x) => contains(range, &x),
+            None => false,
+        },
+        Refinement::Length(range) => match val {
+            Value::String(s) => contains(range, &Literal::Integer(s.len() as i128)),
+            Value::List(list) => contains(range, &Literal::Integer(list.len() as i128)),
+            _ => false,
+        },
+    };
+    if !ok {
+        bail!("{:?} doesn't satisfy `where {}`", val, refinement);
+    }
+    Ok(())
+}
+
+fn number(val: &Value) -> Option<Literal> {
+    Some(match *val {
+        Value::U8(x) => Literal::Integer(x.into()),
+        Value::U16(x) => Literal::Integer(x.into()),
+        Value::U32(x) => Literal::Integer(x.into()),
+        Value::U64(x) => Literal::Integer(x.into()),
+        Value::S8(x) => Literal::Integer(x.into()),
+        Value::S16(x) => Literal::Integer(x.into()),
+        Value::S32(x) => Literal::Integer(x.into()),
+        Value::S64(x) => Literal::Integer(x.into()),
+        Value::Float32(x) => Literal::Float(x.into()),
+        Value::Float64(x) => Literal::Float(x),
+        _ => return None,
+    })
+}
+
+fn contains(range: &Range, x: &Literal) -> bool {
+    let compare = |a: &Literal, b: &Literal| match (a, b) {
+        (Literal::Integer(a), Literal::Integer(b)) => Some(a.cmp(b)),
+        _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    };
+    let above = match &range.start {
+        Some(start) => matches!(compare(x, start), Some(Ordering::Greater | Ordering::Equal)),
+        None => true,
+    };
+    let below = match &range.end {
+        Some(end) => match compare(x, end) {
+            Some(Ordering::Less) => true,
+            Some(Ordering::Equal) => range.inclusive,
+            _ => false,
+        },
+        None => true,
+    };
+    above && below
+}
+
+/// Runs a compiled [`Program`] over a memory.
+struct Machine<'a, 'c> {
+    call: &'a Call<'a>,
+    memory: &'a mut Memory,
+    callee: Option<&'a mut Callee<'c>>,
+    args: Vec<Val>,
+    env: Vec<Option<Val>>,
+    /// The element and base pointer of the list or box being iterated over,
+    /// innermost last.
+    iters: Vec<(Option<Value>, u32)>,
+    /// The payloads of the variants being lowered, innermost last.
+    payloads: Vec<Value>,
+    returned: Option<Vec<Val>>,
+}
+
+impl<'a, 'c> Machine<'a, 'c> {
+    fn new(
+        call: &'a Call<'a>,
+        memory: &'a mut Memory,
+        callee: Option<&'a mut Callee<'c>>,
+        args: Vec<Val>,
+    ) -> Machine<'a, 'c> {
+        Machine {
+            call,
+            memory,
+            callee,
+            args,
+            env: Vec::new(),
+            iters: Vec::new(),
+            payloads: Vec::new(),
+            returned: None,
+        }
+    }
+
+    fn execute(mut self, program: &Program) -> Result<Vec<Val>> {
+        self.env = vec![None; program.slots];
+        self.stmts(&program.stmts)?;
+        self.returned
+            .ok_or_else(|| anyhow!("program ended without returning"))
+    }
+
+    fn run(&mut self, block: &Block) -> Result<Vec<Val>> {
+        self.stmts(&block.stmts)?;
+        block.results.iter().map(|slot| self.get(*slot)).collect()
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) -> Result<()> {
+        for stmt in stmts {
+            let operands = stmt
+                .operands
+                .iter()
+                .map(|slot| self.get(*slot))
+                .collect::<Result<Vec<_>>>()?;
+            let results = (stmt.op)(self, operands, &stmt.blocks)?;
+            assert_eq!(results.len(), stmt.results.len());
+            for (slot, val) in stmt.results.iter().zip(results) {
+                self.env[*slot] = Some(val);
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, slot: usize) -> Result<Val> {
+        self.env[slot]
+            .clone()
+            .ok_or_else(|| anyhow!("operand {} used before it's defined", slot))
+    }
+
+    /// Runs the body of a list or boxed option for the element `elem` at
+    /// `base`.
+    fn iterate(&mut self, elem: Option<Value>, base: u32, block: &Block) -> Result<Vec<Val>> {
+        self.iters.push((elem, base));
+        let results = self.run(block);
+        self.iters.pop();
+        results
+    }
+
+    fn helper(&mut self, ty: TypeId, helper: RecursiveHelper, args: Vec<Val>) -> Result<Vec<Val>> {
+        let program = self.call.helper(ty, helper);
+        Machine::new(self.call, self.memory, None, args).execute(&program)
+    }
+
+    /// Allocates the contents of a list, which is temporary unless it's
+    /// allocated with `cabi_realloc`. Empty lists aren't allocated.
+    fn alloc(&mut self, size: usize, align: usize, realloc: bool) -> Result<u32> {
+        if size == 0 {
+            return Ok(align as u32);
+        }
+        let addr = self.memory.alloc(size, align)?;
+        if !realloc {
+            self.call.temporaries.borrow_mut().push(addr);
+        }
+        Ok(addr)
+    }
+
+    /// Frees the contents of a list which was just lifted, if they're owned.
+    fn free_lifted(&mut self, ptr: u32, len: usize) -> Result<()> {
+        if len != 0 && self.call.owns_lifted() {
+            self.memory.free(ptr)?;
+        }
+        Ok(())
+    }
+
+    fn list(&self, val: Val, element: &Type) -> Result<Vec<Value>> {
+        match val.into_value()? {
+            Value::List(list) => Ok(list),
+            val => mismatch(
+                format_args!("list<{}>", describe(self.call.iface, element)),
+                &val,
+            ),
+        }
+    }
+
+    /// Writes a value of a type whose bit patterns are all valid, as the
+    /// elements of canonical lists are, to `addr`.
+    fn store_plain(&mut self, ty: &Type, addr: u32, val: &Value) -> Result<()> {
+        let bytes = match (ty, val) {
+            (Type::U8, Value::U8(x)) => x.to_le_bytes().to_vec(),
+            (Type::S8, Value::S8(x)) => x.to_le_bytes().to_vec(),
+            (Type::U16, Value::U16(x)) => x.to_le_bytes().to_vec(),
+            (Type::S16, Value::S16(x)) => x.to_le_bytes().to_vec(),
+            (Type::U32, Value::U32(x)) => x.to_le_bytes().to_vec(),
+            (Type::S32, Value::S32(x)) => x.to_le_bytes().to_vec(),
+            (Type::U64, Value::U64(x)) => x.to_le_bytes().to_vec(),
+            (Type::S64, Value::S64(x)) => x.to_le_bytes().to_vec(),
+            (Type::Float32, Value::Float32(x)) => x.to_le_bytes().to_vec(),
+            (Type::Float64, Value::Float64(x)) => x.to_le_bytes().to_vec(),
+            (Type::Char, Value::Char(x)) => (*x as u32).to_le_bytes().to_vec(),
+            (Type::Id(id), _) => {
+                let iface = self.call.iface;
+                let (tys, vals) = match (&iface.types[*id].kind, val) {
+                    (TypeDefKind::Type(t), _) => return self.store_plain(t, addr, val),
+                    (TypeDefKind::Record(r), Value::Record(vals))
+                        if vals.len() == r.fields.len() =>
+                    {
+                        (r.fields.iter().map(|f| f.ty).collect::<Vec<_>>(), vals)
+                    }
+                    (TypeDefKind::Tuple(t), Value::Tuple(vals)) if vals.len() == t.types.len() => {
+                        (t.types.clone(), vals)
+                    }
+                    _ => return mismatch(describe(iface, ty), val),
+                };
+                let offsets = self.call.sizes.field_offsets(&tys);
+                for ((offset, ty), val) in offsets.into_iter().zip(vals) {
+                    self.store_plain(ty, addr + offset as u32, val)?;
+                }
+                return Ok(());
+            }
+            _ => return mismatch(describe(self.call.iface, ty), val),
+        };
+        self.memory.store(addr, &bytes)
+    }
+
+    /// Reads a value written by `store_plain`, also allowing `char`s as
+    /// their lists are lifted as canonical lists.
+    fn load_plain(&self, ty: &Type, addr: u32) -> Result<Value> {
+        let memory = &*self.memory;
+        Ok(match ty {
+            Type::U8 => Value::U8(u8::from_le_bytes(memory.read(addr)?)),
+            Type::S8 => Value::S8(i8::from_le_bytes(memory.read(addr)?)),
+            Type::U16 => Value::U16(u16::from_le_bytes(memory.read(addr)?)),
+            Type::S16 => Value::S16(i16::from_le_bytes(memory.read(addr)?)),
+            Type::U32 => Value::U32(u32::from_le_bytes(memory.read(addr)?)),
+            Type::S32 => Value::S32(i32::from_le_bytes(memory.read(addr)?)),
+            Type::U64 => Value::U64(u64::from_le_bytes(memory.read(addr)?)),
+            Type::S64 => Value::S64(i64::from_le_bytes(memory.read(addr)?)),
+            Type::Float32 => Value::Float32(f32::from_le_bytes(memory.read(addr)?)),
+            Type::Float64 => Value::Float64(f64::from_le_bytes(memory.read(addr)?)),
+            Type::Char => Value::Char(char_from(i32::from_le_bytes(memory.read(addr)?))?),
+            Type::Id(id) => match &self.call.iface.types[*id].kind {
+                TypeDefKind::Type(t) => self.load_plain(t, addr)?,
+                TypeDefKind::Record(r) => {
+                    Value::Record(self.load_fields(r.fields.iter().map(|f| &f.ty), addr)?)
+                }
+                TypeDefKind::Tuple(t) => Value::Tuple(self.load_fields(&t.types, addr)?),
+                _ => unreachable!("`{}` isn't canonical", describe(self.call.iface, ty)),
+            },
+            Type::Bool | Type::String => {
+                unreachable!("`{}` isn't canonical", describe(self.call.iface, ty))
+            }
+        })
+    }
+
+    fn load_fields<'b>(
+        &self,
+        tys: impl IntoIterator<Item = &'b Type>,
+        addr: u32,
+    ) -> Result<Vec<Value>> {
+        let offsets = self.call.sizes.field_offsets(tys);
+        offsets
+            .into_iter()
+            .map(|(offset, ty)| self.load_plain(ty, addr + offset as u32))
+            .collect()
+    }
+}
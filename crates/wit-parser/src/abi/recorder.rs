@@ -0,0 +1,274 @@
+use super::{AbiVariant, Bindgen, Bitcast, Instruction, LiftLower, WasmSignature, WasmType};
+use crate::compat::describe;
+use crate::sizealign::SizeAlign;
+use crate::{
+    Enum, Flags, Function, Handle, Interface, Record, Refinement, Result_, Stream, Tuple, Type,
+    TypeId, Union, Variant,
+};
+
+/// A [`Bindgen`] which records the instructions it's fed as text instead of
+/// generating code, to see what bindings are generated from.
+///
+/// Each instruction is written on its own line, with the results it pushes
+/// and the operands it pops. Operands are numbered `%0`, `%1`, ... in the
+/// order they're created. Blocks are written as `block { ... }` followed by
+/// the operands they result in, before the instruction which uses them.
+///
+/// Types are written as in a wit file and lists are canonical whenever all
+/// bit patterns of their elements are valid, so the text only changes when
+/// the ABI itself does.
+pub struct Recorder {
+    sizes: SizeAlign,
+    src: String,
+    indent: usize,
+    next_operand: usize,
+}
+
+impl Recorder {
+    pub fn new(iface: &Interface) -> Recorder {
+        let mut sizes = SizeAlign::default();
+        sizes.fill(iface);
+        Recorder {
+            sizes,
+            src: String::new(),
+            indent: 0,
+            next_operand: 0,
+        }
+    }
+
+    /// Returns the text recorded so far.
+    pub fn finish(self) -> String {
+        self.src
+    }
+
+    fn line(&mut self, line: &str) {
+        for _ in 0..self.indent {
+            self.src.push_str("  ");
+        }
+        self.src.push_str(line);
+        self.src.push('\n');
+    }
+
+    fn operand(&mut self) -> String {
+        let operand = format!("%{}", self.next_operand);
+        self.next_operand += 1;
+        operand
+    }
+}
+
+impl Bindgen for Recorder {
+    type Operand = String;
+
+    fn emit(
+        &mut self,
+        iface: &Interface,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        let mut line = String::new();
+        for _ in 0..inst.results_len() {
+            results.push(self.operand());
+        }
+        if !results.is_empty() {
+            line.push_str(&results.join(", "));
+            line.push_str(" = ");
+        }
+        line.push_str(inst.name());
+        if !operands.is_empty() {
+            line.push(' ');
+            line.push_str(&operands.join(", "));
+        }
+        let mut fields = Vec::new();
+        inst.fields(&mut |name, field| {
+            if let Some(value) = field.describe(iface) {
+                fields.push(format!("{name}: {value}"));
+            }
+        });
+        if !fields.is_empty() {
+            line.push_str(&format!(" {{ {} }}", fields.join(", ")));
+        }
+        self.line(&line);
+    }
+
+    fn return_pointer(&mut self, _iface: &Interface, size: usize, align: usize) -> String {
+        let operand = self.operand();
+        self.line(&format!(
+            "{operand} = ReturnPointer {{ size: {size}, align: {align} }}"
+        ));
+        operand
+    }
+
+    fn push_block(&mut self) {
+        self.line("block {");
+        self.indent += 1;
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        self.indent -= 1;
+        if operands.is_empty() {
+            self.line("}");
+        } else {
+            self.line(&format!("}} -> {}", operands.join(", ")));
+        }
+        operands.clear();
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        &self.sizes
+    }
+
+    fn is_list_canonical(&self, iface: &Interface, element: &Type) -> bool {
+        iface.all_bits_valid(element)
+    }
+}
+
+/// Records the instructions of calling `func` with [`Interface::call`],
+/// followed by those of its [`Interface::post_return`] if it needs one.
+pub fn record(
+    iface: &Interface,
+    variant: AbiVariant,
+    lift_lower: LiftLower,
+    func: &Function,
+) -> String {
+    let mut recorder = Recorder::new(iface);
+    recorder.line("call {");
+    recorder.indent += 1;
+    iface.call(variant, lift_lower, func, &mut recorder);
+    recorder.indent -= 1;
+    recorder.line("}");
+
+    if variant == AbiVariant::GuestExport
+        && lift_lower == LiftLower::LiftArgsLowerResults
+        && iface.guest_export_needs_post_return(func)
+    {
+        recorder.next_operand = 0;
+        recorder.line("post-return {");
+        recorder.indent += 1;
+        iface.post_return(func, &mut recorder);
+        recorder.indent -= 1;
+        recorder.line("}");
+    }
+    recorder.finish()
+}
+
+/// A field of an [`Instruction`], as written by a [`Recorder`].
+pub(super) trait Field {
+    /// Returns how this field is written, or `None` to leave it out because
+    /// it's described by another field, like a record by its `ty`.
+    fn describe(&self, iface: &Interface) -> Option<String>;
+}
+
+impl<T: Field + ?Sized> Field for &T {
+    fn describe(&self, iface: &Interface) -> Option<String> {
+        (**self).describe(iface)
+    }
+}
+
+impl Field for usize {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl Field for i32 {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl Field for str {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl Field for Option<&str> {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.unwrap_or("none").to_string())
+    }
+}
+
+impl Field for Type {
+    fn describe(&self, iface: &Interface) -> Option<String> {
+        Some(describe(iface, self))
+    }
+}
+
+impl Field for Option<Type> {
+    fn describe(&self, iface: &Interface) -> Option<String> {
+        Some(match self {
+            Some(ty) => describe(iface, ty),
+            None => "_".to_string(),
+        })
+    }
+}
+
+impl Field for TypeId {
+    fn describe(&self, iface: &Interface) -> Option<String> {
+        Some(describe(iface, &Type::Id(*self)))
+    }
+}
+
+impl Field for [Bitcast] {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        let casts = self.iter().map(|cast| format!("{cast:?}"));
+        Some(format!("[{}]", casts.collect::<Vec<_>>().join(", ")))
+    }
+}
+
+impl Field for [WasmType] {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(format!("[{}]", wasm_types(self)))
+    }
+}
+
+impl Field for WasmSignature {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(format!(
+            "({}) -> ({})",
+            wasm_types(&self.params),
+            wasm_types(&self.results)
+        ))
+    }
+}
+
+impl Field for Interface {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.name.clone())
+    }
+}
+
+impl Field for Function {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.name.clone())
+    }
+}
+
+impl Field for Refinement {
+    fn describe(&self, _iface: &Interface) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+macro_rules! described_by_ty {
+    ($($ty:ty)*) => ($(
+        impl Field for $ty {
+            fn describe(&self, _iface: &Interface) -> Option<String> {
+                None
+            }
+        }
+    )*)
+}
+
+described_by_ty!(Record Tuple Flags Variant Union Enum Result_ Handle Stream);
+
+fn wasm_types(tys: &[WasmType]) -> String {
+    let tys = tys.iter().map(|ty| match ty {
+        WasmType::I32 => "i32",
+        WasmType::I64 => "i64",
+        WasmType::F32 => "f32",
+        WasmType::F64 => "f64",
+    });
+    tys.collect::<Vec<_>>().join(", ")
+}
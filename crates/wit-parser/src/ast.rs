@@ -1,24 +1,48 @@
+//! The syntax tree of wit files.
+//!
+//! Unlike the resolved [`Interface`](crate::Interface) and
+//! [`World`](crate::World), the syntax tree retains the location of every
+//! name, which tools such as editors need to map positions in a file back
+//! to what's written there.
+
 use anyhow::Result;
-use lex::{Span, Token, Tokenizer};
+use lex::{Token, Tokenizer};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::path::Path;
+
+pub use lex::Span;
 
+mod format;
 mod lex;
 mod resolve;
 
+pub use format::format;
+
 pub use lex::validate_id;
 
+/// Returns every keyword of the language.
+pub fn keywords() -> impl Iterator<Item = &'static str> {
+    lex::KEYWORDS
+        .iter()
+        .map(|(keyword, _)| *keyword)
+        .filter(|keyword| *keyword != "_")
+}
+
 pub struct Ast<'a> {
     pub items: Vec<Item<'a>>,
 }
 
 pub enum Item<'a> {
     Use(Use<'a>),
+    Include(Include<'a>),
     TypeDef(TypeDef<'a>),
     Value(Value<'a>),
+    Resource(Resource<'a>),
     Interface(Interface<'a>),
+    World(World<'a>),
 }
 
 pub struct Id<'a> {
@@ -45,27 +69,104 @@ impl<'a> From<String> for Id<'a> {
 }
 
 pub struct Use<'a> {
+    /// The package named by a `use` of the form `ns:pkg/iface@1.2.0`, if any.
+    pub package: Option<PackageRef<'a>>,
     pub from: Vec<Id<'a>>,
-    names: Option<Vec<UseName<'a>>>,
+    pub names: Option<Vec<UseName<'a>>>,
 }
 
-struct UseName<'a> {
-    name: Id<'a>,
-    as_: Option<Id<'a>>,
+/// `include other-iface`, which merges the types and functions of another
+/// interface into the one containing it.
+pub struct Include<'a> {
+    /// The interface to include, which is referred to as it would be by a
+    /// `use` of all of its names.
+    pub target: Use<'a>,
+}
+
+pub struct PackageRef<'a> {
+    pub namespace: Option<Id<'a>>,
+    pub name: Id<'a>,
+    pub version: Option<(Span, &'a str)>,
+}
+
+pub struct UseName<'a> {
+    pub name: Id<'a>,
+    pub as_: Option<Id<'a>>,
 }
 
 #[derive(Default)]
-struct Docs<'a> {
-    docs: Vec<Cow<'a, str>>,
+pub struct Docs<'a> {
+    pub docs: Vec<Cow<'a, str>>,
+    /// The attributes written among the comments before an item.
+    pub attrs: Vec<Attribute<'a>>,
+}
+
+/// `@name` or `@name(arg, key = value, ...)`
+pub struct Attribute<'a> {
+    pub span: Span,
+    pub name: Id<'a>,
+    pub args: Vec<AttributeArg<'a>>,
+}
+
+pub struct AttributeArg<'a> {
+    /// The key of a `key = value` argument, or `None` if there's no key.
+    pub key: Option<Id<'a>>,
+    /// The value of the argument, which is `true` for a key written on its
+    /// own.
+    pub value: Literal<'a>,
+}
+
+impl Docs<'_> {
+    /// Returns an error if any attributes were written before an item which
+    /// can't have them.
+    fn reject_attrs(&self) -> Result<()> {
+        match self.attrs.first() {
+            Some(attr) => Err(Error {
+                span: attr.span,
+                msg: "attributes are not allowed here".to_string(),
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the text of the doc comments among these comments, if any.
+    ///
+    /// Comments which are not doc comments are ignored.
+    pub fn contents(&self) -> Option<String> {
+        let mut docs = None;
+        for doc in self.docs.iter() {
+            if let Some(doc) = doc.strip_prefix("///") {
+                let docs = docs.get_or_insert_with(String::new);
+                docs.push_str(doc.trim_start_matches('/').trim());
+                docs.push('\n');
+            } else if let Some(doc) = doc.strip_prefix("/*") {
+                // We have to strip this before checking if this is a doc
+                // comment to avoid breaking on empty block comments, `/**/`.
+                let doc = doc.strip_suffix("*/").unwrap();
+
+                if let Some(doc) = doc.strip_prefix("*") {
+                    let docs = docs.get_or_insert_with(String::new);
+                    for line in doc.lines() {
+                        docs.push_str(line);
+                        docs.push('\n');
+                    }
+                }
+            }
+        }
+        docs
+    }
 }
 
 pub struct TypeDef<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
-    ty: Type<'a>,
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub ty: Type<'a>,
+    /// The `where` clause of a `type` alias.
+    pub refinement: Option<Refinement<'a>>,
 }
 
-enum Type<'a> {
+pub enum Type<'a> {
     Bool,
     U8,
     U16,
@@ -91,122 +192,321 @@ enum Type<'a> {
     Future(Option<Box<Type<'a>>>),
     Stream(Stream<'a>),
     Union(Union<'a>),
+    Own(Id<'a>),
+    Borrow(Id<'a>),
 }
 
-struct Record<'a> {
-    fields: Vec<Field<'a>>,
+pub struct Record<'a> {
+    pub fields: Vec<Field<'a>>,
 }
 
-struct Field<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
-    ty: Type<'a>,
+pub struct Field<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub ty: Type<'a>,
+    pub default: Option<Literal<'a>>,
 }
 
-struct Flags<'a> {
-    flags: Vec<Flag<'a>>,
+pub struct Flags<'a> {
+    pub flags: Vec<Flag<'a>>,
 }
 
-struct Flag<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
+pub struct Flag<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
 }
 
-struct Variant<'a> {
-    span: Span,
-    cases: Vec<Case<'a>>,
+pub struct Variant<'a> {
+    pub span: Span,
+    pub cases: Vec<Case<'a>>,
 }
 
-struct Case<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
-    ty: Option<Type<'a>>,
+pub struct Case<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub ty: Option<Type<'a>>,
 }
 
-struct Enum<'a> {
-    span: Span,
-    cases: Vec<EnumCase<'a>>,
+pub struct Enum<'a> {
+    pub span: Span,
+    pub cases: Vec<EnumCase<'a>>,
 }
 
-struct EnumCase<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
+pub struct EnumCase<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
 }
 
-struct Result_<'a> {
-    ok: Option<Box<Type<'a>>>,
-    err: Option<Box<Type<'a>>>,
+pub struct Result_<'a> {
+    pub ok: Option<Box<Type<'a>>>,
+    pub err: Option<Box<Type<'a>>>,
 }
 
-struct Stream<'a> {
-    element: Option<Box<Type<'a>>>,
-    end: Option<Box<Type<'a>>>,
+pub struct Stream<'a> {
+    pub element: Option<Box<Type<'a>>>,
+    pub end: Option<Box<Type<'a>>>,
 }
 
 pub struct Value<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
-    kind: ValueKind<'a>,
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub kind: ValueKind<'a>,
 }
 
-struct Union<'a> {
-    span: Span,
-    cases: Vec<UnionCase<'a>>,
+pub struct Union<'a> {
+    pub span: Span,
+    pub cases: Vec<UnionCase<'a>>,
 }
 
-struct UnionCase<'a> {
-    docs: Docs<'a>,
-    ty: Type<'a>,
+pub struct UnionCase<'a> {
+    pub docs: Docs<'a>,
+    pub ty: Type<'a>,
 }
 
-type ParamList<'a> = Vec<(Id<'a>, Type<'a>)>;
+pub struct Resource<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub funcs: Vec<ResourceFunc<'a>>,
+}
+
+pub struct ResourceFunc<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub kind: ResourceFuncKind,
+    pub is_async: bool,
+    pub params: ParamList<'a>,
+    pub results: ResultList<'a>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ResourceFuncKind {
+    Method,
+    Static,
+    Constructor,
+}
 
-enum ResultList<'a> {
+pub type ParamList<'a> = Vec<Param<'a>>;
+
+pub struct Param<'a> {
+    pub name: Id<'a>,
+    pub ty: Type<'a>,
+    /// The value used for this parameter when a caller omits it, which is
+    /// only allowed on function parameters and not on named results.
+    pub default: Option<Literal<'a>>,
+}
+
+pub enum ResultList<'a> {
     Named(ParamList<'a>),
     Anon(Type<'a>),
 }
 
-enum ValueKind<'a> {
+pub enum ValueKind<'a> {
     Function {
+        is_async: bool,
         params: ParamList<'a>,
         results: ResultList<'a>,
     },
     Global(Type<'a>),
+    /// `const name: ty = value`
+    Const {
+        ty: Type<'a>,
+        value: Literal<'a>,
+    },
+}
+
+/// A literal value, such as the value of a constant or a default.
+pub struct Literal<'a> {
+    pub span: Span,
+    pub kind: LiteralKind<'a>,
+}
+
+pub enum LiteralKind<'a> {
+    /// `true` or `false`.
+    Bool(bool),
+    /// The text of a number, which is only parsed once its type is known.
+    Number(&'a str),
+    /// A string, with its escapes already processed.
+    String(String),
+}
+
+/// The constraint after `where` in `type port = u16 where 1..=65535`.
+pub struct Refinement<'a> {
+    pub span: Span,
+    pub kind: RefinementKind<'a>,
+}
+
+pub enum RefinementKind<'a> {
+    /// `start..end` or `start..=end`, either of which may be left out.
+    Range(Range<'a>),
+    /// `len <= max`, `len >= min` or `len in start..end`.
+    Length(Range<'a>),
+}
+
+pub struct Range<'a> {
+    pub span: Span,
+    pub start: Option<Literal<'a>>,
+    pub end: Option<Literal<'a>>,
+    pub inclusive: bool,
 }
 
-#[allow(dead_code)] // TODO
 pub struct Interface<'a> {
-    docs: Docs<'a>,
-    name: Id<'a>,
-    items: Vec<Item<'a>>,
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub items: Vec<Item<'a>>,
+}
+
+pub struct World<'a> {
+    pub docs: Docs<'a>,
+    pub name: Id<'a>,
+    pub items: Vec<WorldItem<'a>>,
+}
+
+pub enum WorldItem<'a> {
+    Import(Import<'a>),
+    Export(Export<'a>),
+    ExportDefault(ExternKind<'a>),
+}
+
+pub struct Import<'a> {
+    pub name: Id<'a>,
+    pub kind: ExternKind<'a>,
+}
+
+pub struct Export<'a> {
+    pub name: Id<'a>,
+    pub kind: ExternKind<'a>,
+}
+
+pub enum ExternKind<'a> {
+    Interface(Span, Vec<Item<'a>>),
+    Id(Id<'a>),
 }
 
 impl<'a> Ast<'a> {
     pub fn parse(input: &'a str) -> Result<Ast<'a>> {
-        let mut lexer = Tokenizer::new(input)?;
+        let (ast, result) = Ast::parse_lenient(input);
+        result?;
+        Ok(ast)
+    }
+
+    /// Parses `input`, skipping over any items which fail to parse.
+    ///
+    /// Returns the items which did parse along with the errors found in the
+    /// rest, which is useful for working with files as they're being edited.
+    pub fn parse_lenient(input: &'a str) -> (Ast<'a>, Result<()>) {
         let mut items = Vec::new();
-        while lexer.clone().next()?.is_some() {
-            let docs = parse_docs(&mut lexer)?;
-            items.push(Item::parse(&mut lexer, docs)?);
+        let result = Ast::parse_items(input, &mut items);
+        (Ast { items }, result)
+    }
+
+    fn parse_items(input: &'a str, items: &mut Vec<Item<'a>>) -> Result<()> {
+        let mut lexer = Tokenizer::new(input)?;
+        let mut errors = Vec::new();
+        loop {
+            let start = match lexer.clone().next() {
+                Ok(Some((span, _))) => span.start,
+                Ok(None) => break,
+                Err(e) => {
+                    collect_error::<()>(&mut errors, Err(e.into()))?;
+                    break;
+                }
+            };
+            let before = lexer.clone();
+            let item = parse_docs(&mut lexer).and_then(|docs| Item::parse(&mut lexer, docs));
+            if let Some(item) = collect_error(&mut errors, item)? {
+                items.push(item);
+                continue;
+            }
+            lexer = before;
+            if !recover(&mut lexer, start) {
+                break;
+            }
         }
-        Ok(Ast { items })
+        check_errors(errors)
     }
 
     pub fn resolve(
         &self,
         name: &str,
+        package: Option<&crate::PackageName>,
         map: &HashMap<String, crate::Interface>,
     ) -> Result<crate::Interface> {
         let mut resolver = resolve::Resolver::default();
-        let instance = resolver.resolve(name, &self.items, map)?;
+        let instance = resolver.resolve(name, &self.items, package, map, &HashMap::new())?;
         Ok(instance)
     }
+
+    pub fn resolve_world(
+        &self,
+        package: Option<&crate::PackageName>,
+        map: &HashMap<String, crate::Interface>,
+    ) -> Result<crate::World> {
+        resolve::resolve_world(&self.items, package, map)
+    }
+
+    /// Invokes `f` for every `use` item in this AST, including those nested
+    /// within interfaces and worlds, and for the target of every `include`.
+    ///
+    /// Uses of interfaces defined within this same file, rather than loaded
+    /// from another, are skipped.
+    pub fn for_each_use(&self, mut f: impl FnMut(&Use<'a>) -> Result<()>) -> Result<()> {
+        return visit(&self.items, &mut Vec::new(), &mut f);
+
+        fn visit<'a, 'b>(
+            items: &'b [Item<'a>],
+            scope: &mut Vec<&'b str>,
+            f: &mut dyn FnMut(&Use<'a>) -> Result<()>,
+        ) -> Result<()> {
+            let len = scope.len();
+            scope.extend(items.iter().filter_map(|item| match item {
+                Item::Interface(i) => Some(&*i.name.name),
+                _ => None,
+            }));
+            for item in items {
+                match item {
+                    Item::Use(u) => visit_use(u, scope, f)?,
+                    Item::Include(i) => visit_use(&i.target, scope, f)?,
+                    Item::Interface(i) => visit(&i.items, scope, f)?,
+                    Item::World(w) => {
+                        for item in w.items.iter() {
+                            let kind = match item {
+                                WorldItem::Import(Import { kind, .. })
+                                | WorldItem::Export(Export { kind, .. })
+                                | WorldItem::ExportDefault(kind) => kind,
+                            };
+                            if let ExternKind::Interface(_, items) = kind {
+                                visit(items, scope, f)?;
+                            }
+                        }
+                    }
+                    Item::TypeDef(_) | Item::Value(_) | Item::Resource(_) => {}
+                }
+            }
+            scope.truncate(len);
+            Ok(())
+        }
+
+        fn visit_use<'a>(
+            u: &Use<'a>,
+            scope: &[&str],
+            f: &mut dyn FnMut(&Use<'a>) -> Result<()>,
+        ) -> Result<()> {
+            if u.package.is_none() && scope.contains(&&*u.from[0].name) {
+                return Ok(());
+            }
+            f(u)
+        }
+    }
 }
 
 impl<'a> Item<'a> {
     fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Item<'a>> {
+        if at_contextual_name(tokens)? {
+            return Value::parse(tokens, docs).map(Item::Value);
+        }
         match tokens.clone().next()? {
             Some((_span, Token::Use)) => Use::parse(tokens, docs).map(Item::Use),
+            Some((_span, Token::Include)) => Include::parse(tokens, docs).map(Item::Include),
             Some((_span, Token::Type)) => TypeDef::parse(tokens, docs).map(Item::TypeDef),
             Some((_span, Token::Flags)) => TypeDef::parse_flags(tokens, docs).map(Item::TypeDef),
             Some((_span, Token::Enum)) => TypeDef::parse_enum(tokens, docs).map(Item::TypeDef),
@@ -215,17 +515,21 @@ impl<'a> Item<'a> {
             }
             Some((_span, Token::Record)) => TypeDef::parse_record(tokens, docs).map(Item::TypeDef),
             Some((_span, Token::Union)) => TypeDef::parse_union(tokens, docs).map(Item::TypeDef),
+            Some((_span, Token::Resource)) => Resource::parse(tokens, docs).map(Item::Resource),
             Some((_span, Token::Interface)) => Interface::parse(tokens, docs).map(Item::Interface),
+            Some((_span, Token::World)) => World::parse(tokens, docs).map(Item::World),
             Some((_span, Token::Id)) | Some((_span, Token::ExplicitId)) => {
                 Value::parse(tokens, docs).map(Item::Value)
             }
+            Some((_span, Token::Const)) => Value::parse_const(tokens, docs).map(Item::Value),
             other => Err(err_expected(tokens, "`type` or `func`", other).into()),
         }
     }
 }
 
 impl<'a> Use<'a> {
-    fn parse(tokens: &mut Tokenizer<'a>, _docs: Docs<'a>) -> Result<Self> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        docs.reject_attrs()?;
         tokens.expect(Token::Use)?;
         let mut names = None;
         loop {
@@ -253,13 +557,150 @@ impl<'a> Use<'a> {
             tokens.expect(Token::RightBrace)?;
         }
         tokens.expect(Token::From_)?;
-        let mut from = vec![parse_id(tokens)?];
+        let (package, from) = Use::parse_path(tokens)?;
+        Ok(Use {
+            package,
+            from,
+            names,
+        })
+    }
+
+    /// Parses the path of the interface being used, such as `foo`,
+    /// `foo::bar` or `ns:pkg/foo@1.0.0`.
+    fn parse_path(tokens: &mut Tokenizer<'a>) -> Result<(Option<PackageRef<'a>>, Vec<Id<'a>>)> {
+        let mut first = parse_id(tokens)?;
+
+        // A single `:` (as opposed to the `::` of a nested interface path)
+        // separates a package's namespace from its name, and a `/` separates
+        // the package from the interface within it.
+        let mut package = None;
+        let mut lookahead = tokens.clone();
+        if lookahead.eat(Token::Colon)? && lookahead.expect_raw(Token::Colon).is_err() {
+            tokens.expect(Token::Colon)?;
+            let name = parse_id(tokens)?;
+            tokens.expect(Token::Slash)?;
+            package = Some(PackageRef {
+                namespace: Some(first),
+                name,
+                version: None,
+            });
+            first = parse_id(tokens)?;
+        } else if tokens.eat(Token::Slash)? {
+            package = Some(PackageRef {
+                namespace: None,
+                name: first,
+                version: None,
+            });
+            first = parse_id(tokens)?;
+        }
+        if let Some(package) = &mut package {
+            if tokens.eat(Token::At)? {
+                let span = tokens.expect_version()?;
+                let version = tokens.get_span(span);
+                validate_version(span, version)?;
+                package.version = Some((span, version));
+            }
+        }
+
+        let mut from = vec![first];
         while tokens.eat(Token::Colon)? {
             tokens.expect_raw(Token::Colon)?;
             from.push(parse_id(tokens)?);
         }
-        Ok(Use { from, names })
+        Ok((package, from))
+    }
+
+    /// Returns the package this `use` loads an interface from.
+    ///
+    /// `package` is the package of the file containing this `use`, if any.
+    /// Unqualified names within a package refer to other interfaces of that
+    /// same package.
+    pub fn target_package(
+        &self,
+        package: Option<&crate::PackageName>,
+    ) -> Option<crate::PackageName> {
+        match &self.package {
+            Some(p) => Some(crate::PackageName {
+                namespace: p.namespace.as_ref().map(|id| id.name.to_string()),
+                name: p.name.name.to_string(),
+                version: p.version.map(|(_, v)| v.to_string()),
+            }),
+            None => package.cloned(),
+        }
+    }
+
+    /// Returns the identifier of the interface this `use` loads, which is
+    /// unique among all files involved in a parse.
+    pub fn dependency_id(&self, package: Option<&crate::PackageName>) -> String {
+        match self.target_package(package) {
+            Some(p) => p.interface_id(&self.from[0].name),
+            None => self.from[0].name.to_string(),
+        }
+    }
+
+    /// Returns the span of the interface, or package, that this `use`
+    /// refers to.
+    pub fn target_span(&self) -> Span {
+        match &self.package {
+            Some(p) => {
+                let start = p.namespace.as_ref().unwrap_or(&p.name).span.start;
+                let end = match p.version {
+                    Some((span, _)) => span.end,
+                    None => self.from[0].span.end,
+                };
+                Span { start, end }
+            }
+            None => self.from[0].span,
+        }
+    }
+}
+
+impl<'a> Include<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        docs.reject_attrs()?;
+        tokens.expect(Token::Include)?;
+        let (package, from) = Use::parse_path(tokens)?;
+        Ok(Include {
+            target: Use {
+                package,
+                from,
+                names: None,
+            },
+        })
+    }
+}
+
+/// Validates that `version` looks like `major.minor.patch`, optionally
+/// followed by `-prerelease` and/or `+build` suffixes.
+fn validate_version(span: Span, full: &str) -> Result<()> {
+    let (version, build) = match full.split_once('+') {
+        Some((v, b)) => (v, Some(b)),
+        None => (full, None),
+    };
+    let (version, pre) = match version.split_once('-') {
+        Some((v, p)) => (v, Some(p)),
+        None => (version, None),
+    };
+    let numbers = version.split('.').collect::<Vec<_>>();
+    let valid_ident = |s: &str| {
+        !s.is_empty()
+            && s.split('.').all(|part| {
+                !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+    };
+    let valid = numbers.len() == 3
+        && numbers
+            .iter()
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        && pre.into_iter().chain(build).all(valid_ident);
+    if !valid {
+        return Err(Error {
+            span,
+            msg: format!("invalid version `{}`, expected `major.minor.patch`", full),
+        }
+        .into());
     }
+    Ok(())
 }
 
 impl<'a> TypeDef<'a> {
@@ -268,7 +709,17 @@ impl<'a> TypeDef<'a> {
         let name = parse_id(tokens)?;
         tokens.expect(Token::Equals)?;
         let ty = Type::parse(tokens)?;
-        Ok(TypeDef { docs, name, ty })
+        let refinement = if !at_contextual_name(tokens)? && tokens.eat(Token::Where)? {
+            Some(Refinement::parse(tokens)?)
+        } else {
+            None
+        };
+        Ok(TypeDef {
+            docs,
+            name,
+            ty,
+            refinement,
+        })
     }
 
     fn parse_flags(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
@@ -285,7 +736,12 @@ impl<'a> TypeDef<'a> {
                 },
             )?,
         });
-        Ok(TypeDef { docs, name, ty })
+        Ok(TypeDef {
+            docs,
+            name,
+            ty,
+            refinement: None,
+        })
     }
 
     fn parse_record(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
@@ -300,11 +756,22 @@ impl<'a> TypeDef<'a> {
                     let name = parse_id(tokens)?;
                     tokens.expect(Token::Colon)?;
                     let ty = Type::parse(tokens)?;
-                    Ok(Field { docs, name, ty })
+                    let default = parse_default(tokens)?;
+                    Ok(Field {
+                        docs,
+                        name,
+                        ty,
+                        default,
+                    })
                 },
             )?,
         });
-        Ok(TypeDef { docs, name, ty })
+        Ok(TypeDef {
+            docs,
+            name,
+            ty,
+            refinement: None,
+        })
     }
 
     fn parse_variant(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
@@ -329,7 +796,12 @@ impl<'a> TypeDef<'a> {
                 },
             )?,
         });
-        Ok(TypeDef { docs, name, ty })
+        Ok(TypeDef {
+            docs,
+            name,
+            ty,
+            refinement: None,
+        })
     }
 
     fn parse_union(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
@@ -347,7 +819,12 @@ impl<'a> TypeDef<'a> {
                 },
             )?,
         });
-        Ok(TypeDef { docs, name, ty })
+        Ok(TypeDef {
+            docs,
+            name,
+            ty,
+            refinement: None,
+        })
     }
 
     fn parse_enum(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
@@ -365,7 +842,12 @@ impl<'a> TypeDef<'a> {
                 },
             )?,
         });
-        Ok(TypeDef { docs, name, ty })
+        Ok(TypeDef {
+            docs,
+            name,
+            ty,
+            refinement: None,
+        })
     }
 }
 
@@ -374,41 +856,248 @@ impl<'a> Value<'a> {
         let name = parse_id(tokens)?;
         tokens.expect(Token::Colon)?;
 
-        let kind = if tokens.eat(Token::Func)? {
-            parse_func(tokens)?
+        // `async` is only a keyword before `func`, otherwise it's the name of
+        // a type.
+        let mut lookahead = tokens.clone();
+        let is_async = matches!(lookahead.next()?, Some((_, Token::Async)))
+            && matches!(lookahead.next()?, Some((_, Token::Func)));
+        let kind = if is_async {
+            tokens.expect(Token::Async)?;
+            tokens.expect(Token::Func)?;
+            let (params, results) = parse_func(tokens)?;
+            ValueKind::Function {
+                is_async: true,
+                params,
+                results,
+            }
+        } else if tokens.eat(Token::Func)? {
+            let (params, results) = parse_func(tokens)?;
+            ValueKind::Function {
+                is_async: false,
+                params,
+                results,
+            }
         } else {
+            docs.reject_attrs()?;
             ValueKind::Global(Type::parse(tokens)?)
         };
-        return Ok(Value { docs, name, kind });
+        Ok(Value { docs, name, kind })
+    }
 
-        fn parse_params<'a>(tokens: &mut Tokenizer<'a>, left_paren: bool) -> Result<ParamList<'a>> {
-            if left_paren {
-                tokens.expect(Token::LeftParen)?;
-            };
-            parse_list_trailer(tokens, Token::RightParen, |_docs, tokens| {
-                let name = parse_id(tokens)?;
-                tokens.expect(Token::Colon)?;
-                let ty = Type::parse(tokens)?;
-                Ok((name, ty))
-            })
+    fn parse_const(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        docs.reject_attrs()?;
+        tokens.expect(Token::Const)?;
+        let name = parse_id(tokens)?;
+        tokens.expect(Token::Colon)?;
+        let ty = Type::parse(tokens)?;
+        tokens.expect(Token::Equals)?;
+        let value = Literal::parse(tokens)?;
+        Ok(Value {
+            docs,
+            name,
+            kind: ValueKind::Const { ty, value },
+        })
+    }
+}
+
+impl<'a> Literal<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<Self> {
+        let (span, kind) = match tokens.next()? {
+            Some((span, Token::Number)) => (span, LiteralKind::Number(tokens.get_span(span))),
+            Some((span, Token::StrLit)) => (span, LiteralKind::String(tokens.parse_str(span)?)),
+            Some((span, Token::Id)) if tokens.get_span(span) == "true" => {
+                (span, LiteralKind::Bool(true))
+            }
+            Some((span, Token::Id)) if tokens.get_span(span) == "false" => {
+                (span, LiteralKind::Bool(false))
+            }
+            other => return Err(err_expected(tokens, "a literal value", other).into()),
+        };
+        Ok(Literal { span, kind })
+    }
+}
+
+impl<'a> Refinement<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<Self> {
+        let (kind, span) = match tokens.clone().next()? {
+            Some((len, Token::Id)) if tokens.get_span(len) == "len" => {
+                tokens.next()?;
+                let range = match tokens.next()? {
+                    Some((_, Token::LessThanEq)) => {
+                        let end = Literal::parse(tokens)?;
+                        Range {
+                            span: end.span,
+                            start: None,
+                            end: Some(end),
+                            inclusive: true,
+                        }
+                    }
+                    Some((_, Token::GreaterThanEq)) => {
+                        let start = Literal::parse(tokens)?;
+                        Range {
+                            span: start.span,
+                            start: Some(start),
+                            end: None,
+                            inclusive: false,
+                        }
+                    }
+                    Some((span, Token::Id)) if tokens.get_span(span) == "in" => {
+                        Range::parse(tokens)?
+                    }
+                    other => return Err(err_expected(tokens, "`<=`, `>=` or `in`", other).into()),
+                };
+                let span = Span {
+                    start: len.start,
+                    end: range.span.end,
+                };
+                (RefinementKind::Length(range), span)
+            }
+            _ => {
+                let range = Range::parse(tokens)?;
+                let span = range.span;
+                (RefinementKind::Range(range), span)
+            }
+        };
+        Ok(Refinement { span, kind })
+    }
+}
+
+impl<'a> Range<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<Self> {
+        let start = match tokens.clone().next()? {
+            Some((_, Token::Number)) => Some(Literal::parse(tokens)?),
+            _ => None,
+        };
+        let (dots, inclusive) = match tokens.next()? {
+            Some((span, Token::DotDot)) => (span, false),
+            Some((span, Token::DotDotEq)) => (span, true),
+            other => return Err(err_expected(tokens, "`..` or `..=`", other).into()),
+        };
+        // Only `start..` may leave out its end.
+        let end = match tokens.clone().next()? {
+            Some((_, Token::Number)) => Some(Literal::parse(tokens)?),
+            _ if inclusive || start.is_none() => Some(Literal::parse(tokens)?),
+            _ => None,
+        };
+        let span = Span {
+            start: start.as_ref().map_or(dots.start, |l| l.span.start),
+            end: end.as_ref().map_or(dots.end, |l| l.span.end),
+        };
+        Ok(Range {
+            span,
+            start,
+            end,
+            inclusive,
+        })
+    }
+}
+
+/// Parses an optional `= value` following the type of a field or parameter.
+fn parse_default<'a>(tokens: &mut Tokenizer<'a>) -> Result<Option<Literal<'a>>> {
+    if tokens.eat(Token::Equals)? {
+        Ok(Some(Literal::parse(tokens)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn parse_params<'a>(tokens: &mut Tokenizer<'a>, left_paren: bool) -> Result<ParamList<'a>> {
+    if left_paren {
+        tokens.expect(Token::LeftParen)?;
+    };
+    parse_list_trailer(tokens, Token::RightParen, |docs, tokens| {
+        docs.reject_attrs()?;
+        let name = parse_id(tokens)?;
+        tokens.expect(Token::Colon)?;
+        let ty = Type::parse(tokens)?;
+        // Named results, which don't start with a `(` of their own, can't
+        // have defaults.
+        let default = if left_paren {
+            parse_default(tokens)?
+        } else {
+            None
+        };
+        Ok(Param { name, ty, default })
+    })
+}
+
+fn parse_func<'a>(tokens: &mut Tokenizer<'a>) -> Result<(ParamList<'a>, ResultList<'a>)> {
+    let params = parse_params(tokens, true)?;
+    let results = if tokens.eat(Token::RArrow)? {
+        // If we eat a '(', parse the remainder of the named
+        // result types. Otherwise parse a single anonymous type.
+        if tokens.eat(Token::LeftParen)? {
+            let results = parse_params(tokens, false)?;
+            ResultList::Named(results)
+        } else {
+            let ty = Type::parse(tokens)?;
+            ResultList::Anon(ty)
         }
+    } else {
+        ResultList::Named(Vec::new())
+    };
+    Ok((params, results))
+}
 
-        fn parse_func<'a>(tokens: &mut Tokenizer<'a>) -> Result<ValueKind<'a>> {
-            let params = parse_params(tokens, true)?;
-            let results = if tokens.eat(Token::RArrow)? {
-                // If we eat a '(', parse the remainder of the named
-                // result types. Otherwise parse a single anonymous type.
-                if tokens.eat(Token::LeftParen)? {
-                    let results = parse_params(tokens, false)?;
-                    ResultList::Named(results)
-                } else {
-                    let ty = Type::parse(tokens)?;
-                    ResultList::Anon(ty)
+impl<'a> Resource<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        tokens.expect(Token::Resource)?;
+        let name = parse_id(tokens)?;
+        let mut funcs = Vec::new();
+        if tokens.eat(Token::LeftBrace)? {
+            loop {
+                let docs = parse_docs(tokens)?;
+                if tokens.eat(Token::RightBrace)? {
+                    docs.reject_attrs()?;
+                    break;
                 }
-            } else {
-                ResultList::Named(Vec::new())
-            };
-            Ok(ValueKind::Function { params, results })
+                funcs.push(ResourceFunc::parse(tokens, docs)?);
+            }
+        }
+        Ok(Resource { docs, name, funcs })
+    }
+}
+
+impl<'a> ResourceFunc<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        let named = at_contextual_name(tokens)?;
+        match tokens.clone().next()? {
+            Some((span, Token::Constructor)) if !named => {
+                tokens.expect(Token::Constructor)?;
+                let params = parse_params(tokens, true)?;
+                Ok(ResourceFunc {
+                    docs,
+                    name: Id {
+                        name: "constructor".into(),
+                        span,
+                    },
+                    kind: ResourceFuncKind::Constructor,
+                    is_async: false,
+                    params,
+                    results: ResultList::Named(Vec::new()),
+                })
+            }
+            Some((_span, token)) if named || matches!(token, Token::Id | Token::ExplicitId) => {
+                let name = parse_id(tokens)?;
+                tokens.expect(Token::Colon)?;
+                let kind = if tokens.eat(Token::Static)? {
+                    ResourceFuncKind::Static
+                } else {
+                    ResourceFuncKind::Method
+                };
+                let is_async = tokens.eat(Token::Async)?;
+                tokens.expect(Token::Func)?;
+                let (params, results) = parse_func(tokens)?;
+                Ok(ResourceFunc {
+                    docs,
+                    name,
+                    kind,
+                    is_async,
+                    params,
+                    results,
+                })
+            }
+            other => Err(err_expected(tokens, "`constructor` or a function", other).into()),
         }
     }
 }
@@ -423,10 +1112,32 @@ fn parse_id<'a>(tokens: &mut Tokenizer<'a>) -> Result<Id<'a>> {
             name: tokens.parse_explicit_id(span)?.into(),
             span,
         }),
+        Some((span, token)) if token.is_contextual() => Ok(Id {
+            name: tokens.parse_id(span)?.into(),
+            span,
+        }),
         other => Err(err_expected(tokens, "an identifier or string", other).into()),
     }
 }
 
+/// Returns whether the next token is a contextual keyword which is used as a
+/// name, because it's followed by a `:`.
+fn at_contextual_name(tokens: &Tokenizer<'_>) -> Result<bool> {
+    let mut lookahead = tokens.clone();
+    Ok(match lookahead.next()? {
+        Some((_, token)) if token.is_contextual() => {
+            matches!(lookahead.next()?, Some((_, Token::Colon)))
+        }
+        _ => false,
+    })
+}
+
+/// Returns whether the next token is a `<`.
+fn next_is_less_than(tokens: &Tokenizer<'_>) -> Result<bool> {
+    Ok(matches!(tokens.clone().next()?, Some((_, Token::LessThan))))
+}
+
+/// Parses the comments and attributes before an item.
 fn parse_docs<'a>(tokens: &mut Tokenizer<'a>) -> Result<Docs<'a>> {
     let mut docs = Docs::default();
     let mut clone = tokens.clone();
@@ -434,6 +1145,7 @@ fn parse_docs<'a>(tokens: &mut Tokenizer<'a>) -> Result<Docs<'a>> {
         match token {
             Token::Whitespace => {}
             Token::Comment => docs.docs.push(tokens.get_span(span).into()),
+            Token::At => docs.attrs.push(Attribute::parse(&mut clone, span)?),
             _ => break,
         };
         *tokens = clone.clone();
@@ -441,6 +1153,66 @@ fn parse_docs<'a>(tokens: &mut Tokenizer<'a>) -> Result<Docs<'a>> {
     Ok(docs)
 }
 
+impl<'a> Attribute<'a> {
+    /// Parses the rest of an attribute, after the `@` at `at`.
+    fn parse(tokens: &mut Tokenizer<'a>, at: Span) -> Result<Self> {
+        let name = parse_id(tokens)?;
+        let span = Span {
+            start: at.start,
+            end: name.span.end,
+        };
+        let mut args = Vec::new();
+        if let Some((_, Token::LeftParen)) = tokens.clone().next()? {
+            args = parse_list(
+                tokens,
+                Token::LeftParen,
+                Token::RightParen,
+                |docs, tokens| {
+                    docs.reject_attrs()?;
+                    AttributeArg::parse(tokens)
+                },
+            )?;
+        }
+        Ok(Attribute { span, name, args })
+    }
+}
+
+impl<'a> AttributeArg<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<Self> {
+        // An identifier is a key unless it's a literal on its own, like
+        // `true`.
+        let mut lookahead = tokens.clone();
+        let is_key = match lookahead.next()? {
+            Some((span, Token::Id)) => {
+                let next = lookahead.next()?;
+                matches!(next, Some((_, Token::Equals)))
+                    || (!matches!(tokens.get_span(span), "true" | "false")
+                        && matches!(next, Some((_, Token::Comma | Token::RightParen))))
+            }
+            Some((_, Token::ExplicitId)) => true,
+            _ => false,
+        };
+        if !is_key {
+            return Ok(AttributeArg {
+                key: None,
+                value: Literal::parse(tokens)?,
+            });
+        }
+        let key = parse_id(tokens)?;
+        let value = match parse_default(tokens)? {
+            Some(value) => value,
+            None => Literal {
+                span: key.span,
+                kind: LiteralKind::Bool(true),
+            },
+        };
+        Ok(AttributeArg {
+            key: Some(key),
+            value,
+        })
+    }
+}
+
 impl<'a> Type<'a> {
     fn parse(tokens: &mut Tokenizer<'a>) -> Result<Self> {
         match tokens.next()? {
@@ -462,7 +1234,10 @@ impl<'a> Type<'a> {
                     tokens,
                     Token::LessThan,
                     Token::GreaterThan,
-                    |_docs, tokens| Type::parse(tokens),
+                    |docs, tokens| {
+                        docs.reject_attrs()?;
+                        Type::parse(tokens)
+                    },
                 )?;
                 Ok(Type::Tuple(types))
             }
@@ -544,6 +1319,22 @@ impl<'a> Type<'a> {
                 Ok(Type::Stream(Stream { element, end }))
             }
 
+            // own<T>
+            Some((_span, Token::Own)) if next_is_less_than(tokens)? => {
+                tokens.expect(Token::LessThan)?;
+                let id = parse_id(tokens)?;
+                tokens.expect(Token::GreaterThan)?;
+                Ok(Type::Own(id))
+            }
+
+            // borrow<T>
+            Some((_span, Token::Borrow)) if next_is_less_than(tokens)? => {
+                tokens.expect(Token::LessThan)?;
+                let id = parse_id(tokens)?;
+                tokens.expect(Token::GreaterThan)?;
+                Ok(Type::Borrow(id))
+            }
+
             // `foo`
             Some((span, Token::Id)) => Ok(Type::Name(Id {
                 name: tokens.parse_id(span)?.into(),
@@ -554,6 +1345,11 @@ impl<'a> Type<'a> {
                 name: tokens.parse_explicit_id(span)?.into(),
                 span,
             })),
+            // `own`, `world`, ...
+            Some((span, token)) if token.is_contextual() => Ok(Type::Name(Id {
+                name: tokens.parse_id(span)?.into(),
+                span,
+            })),
 
             other => Err(err_expected(tokens, "a type", other).into()),
         }
@@ -562,18 +1358,99 @@ impl<'a> Type<'a> {
 
 impl<'a> Interface<'a> {
     fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        docs.reject_attrs()?;
         tokens.expect(Token::Interface)?;
         let name = parse_id(tokens)?;
+        let items = Self::parse_items(tokens)?;
+        Ok(Interface { docs, name, items })
+    }
+
+    fn parse_items(tokens: &mut Tokenizer<'a>) -> Result<Vec<Item<'a>>> {
         tokens.expect(Token::LeftBrace)?;
         let mut items = Vec::new();
         loop {
             let docs = parse_docs(tokens)?;
             if tokens.eat(Token::RightBrace)? {
+                docs.reject_attrs()?;
                 break;
             }
             items.push(Item::parse(tokens, docs)?);
         }
-        Ok(Interface { docs, name, items })
+        Ok(items)
+    }
+}
+
+impl<'a> World<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>, docs: Docs<'a>) -> Result<Self> {
+        docs.reject_attrs()?;
+        tokens.expect(Token::World)?;
+        let name = parse_id(tokens)?;
+        tokens.expect(Token::LeftBrace)?;
+        let mut items = Vec::new();
+        loop {
+            // Documentation on imports/exports is not yet preserved anywhere,
+            // but it's skipped here to allow it to be written.
+            parse_docs(tokens)?.reject_attrs()?;
+            if tokens.eat(Token::RightBrace)? {
+                break;
+            }
+            items.push(WorldItem::parse(tokens)?);
+        }
+        Ok(World { docs, name, items })
+    }
+}
+
+impl<'a> WorldItem<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<WorldItem<'a>> {
+        match tokens.clone().next()? {
+            Some((_span, Token::Import)) => Import::parse(tokens).map(WorldItem::Import),
+            Some((_span, Token::Export)) => Export::parse(tokens).map(WorldItem::Export),
+            Some((_span, Token::Default)) => {
+                tokens.expect(Token::Default)?;
+                tokens.expect(Token::Export)?;
+                ExternKind::parse(tokens).map(WorldItem::ExportDefault)
+            }
+            other => {
+                Err(err_expected(tokens, "`import`, `export` or `default export`", other).into())
+            }
+        }
+    }
+}
+
+impl<'a> Import<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<Import<'a>> {
+        tokens.expect(Token::Import)?;
+        let name = parse_id(tokens)?;
+        tokens.expect(Token::Colon)?;
+        let kind = ExternKind::parse(tokens)?;
+        Ok(Import { name, kind })
+    }
+}
+
+impl<'a> Export<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<Export<'a>> {
+        tokens.expect(Token::Export)?;
+        let name = parse_id(tokens)?;
+        tokens.expect(Token::Colon)?;
+        let kind = ExternKind::parse(tokens)?;
+        Ok(Export { name, kind })
+    }
+}
+
+impl<'a> ExternKind<'a> {
+    fn parse(tokens: &mut Tokenizer<'a>) -> Result<ExternKind<'a>> {
+        match tokens.clone().next()? {
+            Some((span, Token::Interface)) => {
+                tokens.expect(Token::Interface)?;
+                let items = Interface::parse_items(tokens)?;
+                Ok(ExternKind::Interface(span, items))
+            }
+            Some((_span, Token::Id)) | Some((_span, Token::ExplicitId)) => {
+                parse_id(tokens).map(ExternKind::Id)
+            }
+            Some((_span, token)) if token.is_contextual() => parse_id(tokens).map(ExternKind::Id),
+            other => Err(err_expected(tokens, "an interface name or `interface`", other).into()),
+        }
     }
 }
 
@@ -599,6 +1476,7 @@ fn parse_list_trailer<'a, T>(
 
         // if we found an end token then we're done
         if tokens.eat(end)? {
+            docs.reject_attrs()?;
             break;
         }
 
@@ -649,65 +1527,122 @@ impl fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
-pub fn rewrite_error(err: &mut anyhow::Error, file: &str, contents: &str) {
-    let parse = match err.downcast_mut::<Error>() {
-        Some(err) => err,
-        None => return lex::rewrite_error(err, file, contents),
+/// Several errors found within a single file.
+#[derive(Debug)]
+struct Errors(Vec<Error>);
+
+impl fmt::Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            error.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Errors {}
+
+/// Returns the errors within `err` if it refers to locations within the file
+/// being parsed, or otherwise returns `err` itself.
+fn into_errors(err: anyhow::Error) -> Result<Vec<Error>, anyhow::Error> {
+    let err = match err.downcast::<Error>() {
+        Ok(e) => return Ok(vec![e]),
+        Err(e) => e,
     };
-    let msg = highlight_err(
-        parse.span.start as usize,
-        Some(parse.span.end as usize),
-        file,
-        contents,
-        &parse.msg,
-    );
-    *err = anyhow::anyhow!("{}", msg);
-}
-
-fn highlight_err(
-    start: usize,
-    end: Option<usize>,
-    file: &str,
-    input: &str,
-    err: impl fmt::Display,
-) -> String {
-    let (line, col) = linecol_in(start, input);
-    let snippet = input.lines().nth(line).unwrap_or("");
-    let mut msg = format!(
-        "\
-{err}
-     --> {file}:{line}:{col}
-      |
- {line:4} | {snippet}
-      | {marker:>0$}",
-        col + 1,
-        file = file,
-        line = line + 1,
-        col = col + 1,
-        err = err,
-        snippet = snippet,
-        marker = "^",
-    );
-    if let Some(end) = end {
-        if let Some(s) = input.get(start..end) {
-            for _ in s.chars().skip(1) {
-                msg.push('-');
-            }
-        }
-    }
-    return msg;
-
-    fn linecol_in(pos: usize, text: &str) -> (usize, usize) {
-        let mut cur = 0;
-        // Use split_terminator instead of lines so that if there is a `\r`,
-        // it is included in the offset calculation. The `+1` values below
-        // account for the `\n`.
-        for (i, line) in text.split_terminator('\n').enumerate() {
-            if cur + line.len() + 1 > pos {
-                return (i, pos - cur);
-            }
-            cur += line.len() + 1;
-        }
-        (text.lines().count(), 0)
+    let err = match err.downcast::<Errors>() {
+        Ok(e) => return Ok(e.0),
+        Err(e) => e,
+    };
+    match err.downcast::<lex::Error>() {
+        Ok(e) => {
+            let at = u32::try_from(e.position()).unwrap();
+            Ok(vec![Error {
+                span: Span { start: at, end: at },
+                msg: e.to_string(),
+            }])
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Records the error of `result`, if any, in `errors` so parsing may continue
+/// and find further errors.
+///
+/// Errors which don't refer to a location in the file are propagated.
+fn collect_error<T>(errors: &mut Vec<Error>, result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(t) => Ok(Some(t)),
+        Err(e) => {
+            errors.extend(into_errors(e)?);
+            Ok(None)
+        }
+    }
+}
+
+/// Returns all of `errors`, in the order they appear in the file, unless there
+/// are none.
+fn check_errors(mut errors: Vec<Error>) -> Result<()> {
+    if errors.is_empty() {
+        return Ok(());
+    }
+    errors.sort_by_key(|e| e.span.start);
+    Err(Errors(errors).into())
+}
+
+/// Skips past the item starting at `start` after an error was found within
+/// it, so that parsing may resume with the next item.
+///
+/// The next item is taken to be the next token which can start an item and
+/// is at the very beginning of a line. Returns `false` if there's no such
+/// token.
+fn recover(tokens: &mut Tokenizer<'_>, start: u32) -> bool {
+    loop {
+        let mut clone = tokens.clone();
+        let (span, token) = match clone.next() {
+            Ok(Some(pair)) => pair,
+            Ok(None) | Err(_) => return false,
+        };
+        let at_line_start =
+            span.start == 0 || tokens.input()[..span.start as usize].ends_with('\n');
+        let starts_item = matches!(
+            token,
+            Token::Use
+                | Token::Include
+                | Token::Type
+                | Token::Flags
+                | Token::Enum
+                | Token::Variant
+                | Token::Record
+                | Token::Union
+                | Token::Resource
+                | Token::Interface
+                | Token::World
+                | Token::Const
+                | Token::At
+                | Token::Id
+                | Token::ExplicitId
+        );
+        if span.start > start && at_line_start && starts_item {
+            return true;
+        }
+        *tokens = clone;
+    }
+}
+
+/// Converts `err` into [`Diagnostics`](crate::Diagnostics) if it refers to
+/// locations within `contents`, the contents of `file`.
+pub fn to_diagnostics(err: anyhow::Error, file: &Path, contents: &str) -> anyhow::Error {
+    match into_errors(err) {
+        Ok(errors) => crate::Diagnostics {
+            diagnostics: errors
+                .into_iter()
+                .map(|e| crate::Diagnostic::error(file, contents, e.span, e.msg))
+                .collect(),
+        }
+        .into(),
+        Err(e) => e,
     }
 }
@@ -0,0 +1,552 @@
+//! Formatting of wit files in a canonical style.
+//!
+//! Formatting works directly on the token stream, rather than the resolved
+//! interface, so that comments are kept where they're written. The syntax
+//! tree is only used to reject files which don't parse.
+
+use super::lex::{Token, Tokenizer};
+use super::Ast;
+use anyhow::{bail, Result};
+
+const INDENT: &str = "  ";
+
+/// Formats `input` in the canonical style, returning an error if it doesn't
+/// parse.
+pub fn format(input: &str) -> Result<String> {
+    Ast::parse(input)?;
+    let mut formatter = Formatter {
+        tokens: Tokenizer::new(input)?,
+        out: String::new(),
+        stack: vec![Group::new(Kind::Block, true)],
+        prev: None,
+        newlines: 0,
+        break_line: false,
+    };
+    formatter.run()?;
+    let mut out = formatter.out;
+    if !out.is_empty() {
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+    }
+
+    // Only whitespace and trailing commas may differ, so double-check that
+    // to be sure nothing was lost.
+    if significant_tokens(input)? != significant_tokens(&out)? {
+        bail!("formatting changed the meaning of the file, this is a bug");
+    }
+    Ok(out)
+}
+
+/// A bracketed group of tokens.
+struct Group {
+    kind: Kind,
+    /// Whether each item within this group is placed on its own line.
+    multiline: bool,
+    /// Whether nothing, including comments, has been written inside this
+    /// group yet.
+    empty: bool,
+    /// Whether the last item written in this group has no comma after it,
+    /// and the position in the output to insert one.
+    needs_comma: bool,
+    comma_at: usize,
+    /// Whether a comma was found after the last item of a single line group,
+    /// which is only written if another item follows.
+    pending_comma: bool,
+    /// The token which starts the item currently being written in a block.
+    item: Option<Token>,
+    /// Whether the current item of a block is a `use` whose path is being
+    /// written, within which tokens aren't separated by spaces.
+    use_path: bool,
+    /// Whether the current item of a block is a type alias whose `where`
+    /// refinement is being written, which only a number or `..` may end.
+    refinement: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    /// The top level of a file or the body of an interface, world or
+    /// resource, containing items which are not separated by commas.
+    Block,
+    /// The body of a record, flags, variant, enum or union, containing items
+    /// which are always placed on their own line with trailing commas.
+    List,
+    /// The parameters of a function, which are placed on their own lines
+    /// with trailing commas only if they already started on a new line.
+    Params,
+    /// The names of a `use`, which are always on one line.
+    UseNames,
+    /// Type parameters or the payload of a variant case, which are always
+    /// on one line.
+    Inline,
+}
+
+impl Group {
+    fn new(kind: Kind, multiline: bool) -> Group {
+        Group {
+            kind,
+            multiline,
+            empty: true,
+            needs_comma: false,
+            comma_at: 0,
+            pending_comma: false,
+            item: None,
+            use_path: false,
+            refinement: false,
+        }
+    }
+}
+
+struct Formatter<'a> {
+    tokens: Tokenizer<'a>,
+    out: String,
+    stack: Vec<Group>,
+    /// The last token written, other than a comment.
+    prev: Option<Token>,
+    /// How many newlines were found since the last token.
+    newlines: usize,
+    /// Whether the next token must start a new line, as it follows a line
+    /// comment.
+    break_line: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn run(&mut self) -> Result<()> {
+        while let Some((span, token)) = self.tokens.next_raw()? {
+            let text = self.tokens.get_span(span);
+            match token {
+                Token::Whitespace => self.newlines += text.matches('\n').count(),
+                Token::Comment => self.comment(text),
+                Token::RightBrace | Token::RightParen | Token::GreaterThan => {
+                    self.close(token, text)
+                }
+                Token::At if version_follows(&self.tokens) => {
+                    self.token(token, text);
+                    let version = self.tokens.expect_version()?;
+                    self.out.push_str(self.tokens.get_span(version));
+                    self.prev = Some(Token::Id);
+                }
+                Token::At => self.attribute(text)?,
+                _ => self.token(self.classify(token), text),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `token`, or `Token::Id` if it's a contextual keyword which is
+    /// used as a name here.
+    fn classify(&self, token: Token) -> Token {
+        if !token.is_contextual() {
+            return token;
+        }
+        let next = self.peek();
+        let is_keyword = match token {
+            Token::Own | Token::Borrow => next == Some(Token::LessThan),
+            Token::Async => next == Some(Token::Func),
+            Token::Constructor => next == Some(Token::LeftParen),
+            _ => {
+                !matches!(
+                    next,
+                    Some(Token::Colon)
+                        | Some(Token::Equals)
+                        | Some(Token::Comma)
+                        | Some(Token::GreaterThan)
+                        | Some(Token::RightParen)
+                        | Some(Token::RightBrace)
+                ) && !matches!(self.prev, Some(prev) if precedes_name(prev))
+            }
+        };
+        if is_keyword {
+            token
+        } else {
+            Token::Id
+        }
+    }
+
+    /// Writes an attribute, after its `@`, on a line of its own before the
+    /// item it's attached to.
+    fn attribute(&mut self, at: &str) -> Result<()> {
+        // The item after the attribute starts a new line just as if the
+        // attribute weren't there.
+        let prev = self.prev;
+        self.token(Token::At, at);
+        if let Some((span, _)) = self.tokens.next()? {
+            self.out.push_str(self.tokens.get_span(span));
+        }
+        if let Some((_, Token::LeftParen)) = self.tokens.clone().next()? {
+            let mut args = String::new();
+            while let Some((span, token)) = self.tokens.next()? {
+                match token {
+                    Token::Comma => {
+                        if let Some((_, Token::RightParen)) = self.tokens.clone().next()? {
+                            continue;
+                        }
+                        args.push_str(", ");
+                    }
+                    Token::Equals => args.push_str(" = "),
+                    _ => args.push_str(self.tokens.get_span(span)),
+                }
+                if token == Token::RightParen {
+                    break;
+                }
+            }
+            self.out.push_str(&args);
+        }
+        self.prev = prev;
+        self.newlines = 0;
+        Ok(())
+    }
+
+    fn top(&mut self) -> &mut Group {
+        self.stack.last_mut().unwrap()
+    }
+
+    /// Returns the indentation of lines directly within the innermost group.
+    fn indent(&self) -> usize {
+        self.stack[1..].iter().filter(|g| g.multiline).count()
+    }
+
+    fn newline(&mut self, indent: usize, blank: bool) {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        if !self.out.is_empty() {
+            self.out.push('\n');
+            if blank {
+                self.out.push('\n');
+            }
+        }
+        for _ in 0..indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Returns whether `next` starts a new item within the innermost group,
+    /// or is the end of the group.
+    fn at_boundary(&self, next: Option<Token>) -> bool {
+        let group = self.stack.last().unwrap();
+        let closes = matches!(
+            next,
+            None | Some(Token::RightBrace) | Some(Token::RightParen) | Some(Token::GreaterThan)
+        );
+        match group.kind {
+            Kind::Block => {
+                matches!(self.prev, None | Some(Token::LeftBrace))
+                    || closes
+                    || (matches!(self.prev, Some(t) if ends_item(t))
+                        && (!group.refinement
+                            || matches!(self.prev, Some(Token::Number | Token::DotDot)))
+                        && matches!(next, Some(t) if starts_item(t)))
+            }
+            Kind::List | Kind::Params if group.multiline => {
+                matches!(
+                    self.prev,
+                    Some(Token::LeftBrace) | Some(Token::LeftParen) | Some(Token::Comma)
+                ) || closes
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes a comma found after the last item of a single line group, now
+    /// that another item follows it.
+    fn flush_comma(&mut self) {
+        if self.top().pending_comma {
+            self.top().pending_comma = false;
+            self.out.push(',');
+        }
+    }
+
+    fn comment(&mut self, text: &str) {
+        self.flush_comma();
+        let text = text.trim_end();
+        if self.newlines > 0 || self.out.is_empty() {
+            let next = self.peek();
+            if self.at_boundary(next) {
+                let blank = self.newlines > 1 && !self.stack.last().unwrap().empty;
+                self.newline(self.indent(), blank);
+            } else {
+                // Comments within an item are indented like any other line
+                // continuing the item.
+                self.newline(self.indent() + 1, false);
+            }
+        } else {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+        self.top().empty = false;
+
+        // Line comments include the newline which ends them.
+        self.break_line = text.starts_with("//");
+        self.newlines = usize::from(self.break_line);
+    }
+
+    /// Returns the next token which isn't whitespace or a comment.
+    fn peek(&self) -> Option<Token> {
+        let mut tokens = self.tokens.clone();
+        loop {
+            match tokens.next_raw() {
+                Ok(Some((_, Token::Whitespace))) | Ok(Some((_, Token::Comment))) => {}
+                Ok(Some((_, token))) => break Some(token),
+                Ok(None) | Err(_) => break None,
+            }
+        }
+    }
+
+    fn token(&mut self, token: Token, text: &str) {
+        self.flush_comma();
+        let group = self.stack.last().unwrap();
+        let (kind, multiline, empty) = (group.kind, group.multiline, group.empty);
+        if token == Token::Comma && !multiline {
+            self.top().pending_comma = true;
+            self.finish(token);
+            return;
+        }
+
+        if self.at_boundary(Some(token)) {
+            self.newline(self.indent(), self.newlines > 1 && !empty);
+            if kind == Kind::Block {
+                self.top().item = Some(token);
+                self.top().use_path = false;
+                self.top().refinement = false;
+            }
+        } else if self.break_line {
+            self.newline(self.indent() + 1, false);
+        } else if self.space_before(token) {
+            self.out.push(' ');
+        }
+        self.out.push_str(text);
+
+        let group = self.top();
+        if (token == Token::From_ && group.item == Some(Token::Use)) || token == Token::Include {
+            group.use_path = true;
+        }
+        if token == Token::Where {
+            group.refinement = true;
+        }
+        self.finish(token);
+
+        let kind = match token {
+            Token::LeftBrace => match self.stack.last().unwrap().item {
+                Some(Token::Use) => Kind::UseNames,
+                Some(Token::Record) | Some(Token::Flags) | Some(Token::Variant)
+                | Some(Token::Enum) | Some(Token::Union) => Kind::List,
+                _ => Kind::Block,
+            },
+            Token::LeftParen if self.stack.last().unwrap().kind == Kind::List => Kind::Inline,
+            Token::LeftParen => Kind::Params,
+            Token::LessThan => Kind::Inline,
+            _ => return,
+        };
+        let multiline = match kind {
+            Kind::Block | Kind::List => true,
+            Kind::Params => self.starts_new_line(),
+            Kind::UseNames | Kind::Inline => false,
+        };
+        self.stack.push(Group::new(kind, multiline));
+    }
+
+    /// Returns whether the next token is on a later line than the current
+    /// one, or is preceded by a comment.
+    fn starts_new_line(&self) -> bool {
+        let mut tokens = self.tokens.clone();
+        loop {
+            match tokens.next_raw() {
+                Ok(Some((span, Token::Whitespace))) => {
+                    if tokens.get_span(span).contains('\n') {
+                        break true;
+                    }
+                }
+                Ok(Some((_, Token::Comment))) => break true,
+                _ => break false,
+            }
+        }
+    }
+
+    fn close(&mut self, token: Token, text: &str) {
+        let group = self.stack.pop().unwrap();
+        if !group.multiline {
+            if self.break_line {
+                self.newline(self.indent() + 1, false);
+            } else if group.kind == Kind::UseNames {
+                self.out.push(' ');
+            }
+        } else if !group.empty {
+            if group.needs_comma && group.kind != Kind::Block {
+                self.out.insert(group.comma_at, ',');
+            }
+            self.newline(self.indent(), false);
+        }
+        self.out.push_str(text);
+        self.finish(token);
+    }
+
+    /// Records that `token` was just written.
+    fn finish(&mut self, token: Token) {
+        let at = self.out.len();
+        let group = self.top();
+        group.empty = false;
+        if token == Token::Comma {
+            group.needs_comma = false;
+        } else {
+            group.needs_comma = true;
+            group.comma_at = at;
+        }
+        self.prev = Some(token);
+        self.newlines = 0;
+        self.break_line = false;
+    }
+
+    fn space_before(&self, token: Token) -> bool {
+        let group = self.stack.last().unwrap();
+        let prev = match self.prev {
+            Some(prev) => prev,
+            None => return false,
+        };
+        if group.use_path && !matches!(prev, Token::From_ | Token::Include) {
+            return false;
+        }
+        if self.out.ends_with(' ') || self.out.ends_with('\n') {
+            return false;
+        }
+        !matches!(
+            token,
+            Token::Comma
+                | Token::Colon
+                | Token::Semicolon
+                | Token::LeftParen
+                | Token::RightParen
+                | Token::LessThan
+                | Token::GreaterThan
+                | Token::DotDot
+                | Token::DotDotEq
+        ) && !matches!(
+            prev,
+            Token::LeftParen | Token::LessThan | Token::DotDot | Token::DotDotEq
+        )
+    }
+}
+
+/// Returns whether an item may end with `token`.
+fn ends_item(token: Token) -> bool {
+    matches!(
+        token,
+        Token::Id
+            | Token::ExplicitId
+            | Token::StrLit
+            | Token::Number
+            | Token::DotDot
+            | Token::RightBrace
+            | Token::RightParen
+            | Token::GreaterThan
+            | Token::U8
+            | Token::U16
+            | Token::U32
+            | Token::U64
+            | Token::S8
+            | Token::S16
+            | Token::S32
+            | Token::S64
+            | Token::Float32
+            | Token::Float64
+            | Token::Char
+            | Token::Bool
+            | Token::String_
+            | Token::Result_
+            | Token::Future
+            | Token::Stream
+    )
+}
+
+/// Returns whether an item within a block may start with `token`.
+fn starts_item(token: Token) -> bool {
+    matches!(
+        token,
+        Token::Use
+            | Token::Include
+            | Token::Type
+            | Token::Flags
+            | Token::Enum
+            | Token::Variant
+            | Token::Record
+            | Token::Union
+            | Token::Resource
+            | Token::Interface
+            | Token::World
+            | Token::Import
+            | Token::Export
+            | Token::Default
+            | Token::Constructor
+            | Token::Const
+            | Token::At
+            | Token::Id
+            | Token::ExplicitId
+    )
+}
+
+/// Returns whether `token` may be followed by a name.
+fn precedes_name(token: Token) -> bool {
+    matches!(
+        token,
+        Token::Use
+            | Token::Include
+            | Token::Type
+            | Token::Flags
+            | Token::Enum
+            | Token::Variant
+            | Token::Record
+            | Token::Union
+            | Token::Resource
+            | Token::Interface
+            | Token::World
+            | Token::Import
+            | Token::Export
+            | Token::Const
+            | Token::From_
+            | Token::As
+            | Token::Colon
+            | Token::Equals
+            | Token::LessThan
+            | Token::Comma
+            | Token::LeftParen
+            | Token::RArrow
+    )
+}
+
+/// Returns whether the `@` just read is followed by a package version, as in
+/// `use x from pkg@1.0.0`, rather than the name of an attribute.
+fn version_follows(tokens: &Tokenizer<'_>) -> bool {
+    !matches!(
+        tokens.clone().next_raw(),
+        Ok(Some((_, Token::Id | Token::ExplicitId)))
+    )
+}
+
+/// Returns the text of every token of `input` other than whitespace and
+/// trailing commas.
+fn significant_tokens(input: &str) -> Result<Vec<&str>> {
+    let mut tokens = Tokenizer::new(input)?;
+    let mut ret = Vec::new();
+    while let Some((span, token)) = tokens.next_raw()? {
+        match token {
+            Token::Whitespace => {}
+            Token::Comment => ret.push(tokens.get_span(span).trim_end()),
+            Token::At if version_follows(&tokens) => {
+                ret.push(tokens.get_span(span));
+                let version = tokens.expect_version()?;
+                ret.push(tokens.get_span(version));
+            }
+            _ => ret.push(tokens.get_span(span)),
+        }
+    }
+    let is_comment = |s: &str| s.starts_with("//") || s.starts_with("/*");
+    let trailing_comma = |i: usize| {
+        ret[i] == ","
+            && ret[i + 1..]
+                .iter()
+                .find(|s| !is_comment(s))
+                .map(|s| matches!(*s, "}" | ")" | ">"))
+                .unwrap_or(false)
+    };
+    Ok((0..ret.len())
+        .filter(|i| !trailing_comma(*i))
+        .map(|i| ret[i])
+        .collect())
+}
@@ -28,6 +28,55 @@ pub struct Span {
     pub end: u32,
 }
 
+/// Every keyword of the language along with the token it is lexed as.
+pub const KEYWORDS: &[(&str, Token)] = &[
+    ("use", Token::Use),
+    ("include", Token::Include),
+    ("type", Token::Type),
+    ("func", Token::Func),
+    ("u8", Token::U8),
+    ("u16", Token::U16),
+    ("u32", Token::U32),
+    ("u64", Token::U64),
+    ("s8", Token::S8),
+    ("s16", Token::S16),
+    ("s32", Token::S32),
+    ("s64", Token::S64),
+    ("float32", Token::Float32),
+    ("float64", Token::Float64),
+    ("char", Token::Char),
+    ("record", Token::Record),
+    ("flags", Token::Flags),
+    ("variant", Token::Variant),
+    ("enum", Token::Enum),
+    ("union", Token::Union),
+    ("bool", Token::Bool),
+    ("string", Token::String_),
+    ("option", Token::Option_),
+    ("result", Token::Result_),
+    ("future", Token::Future),
+    ("stream", Token::Stream),
+    ("list", Token::List),
+    ("_", Token::Underscore),
+    ("as", Token::As),
+    ("from", Token::From_),
+    ("static", Token::Static),
+    ("interface", Token::Interface),
+    ("tuple", Token::Tuple),
+    ("implements", Token::Implements),
+    ("world", Token::World),
+    ("import", Token::Import),
+    ("export", Token::Export),
+    ("default", Token::Default),
+    ("resource", Token::Resource),
+    ("own", Token::Own),
+    ("borrow", Token::Borrow),
+    ("constructor", Token::Constructor),
+    ("async", Token::Async),
+    ("const", Token::Const),
+    ("where", Token::Where),
+];
+
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub enum Token {
     Whitespace,
@@ -42,11 +91,18 @@ pub enum Token {
     LeftBrace,
     RightBrace,
     LessThan,
+    LessThanEq,
     GreaterThan,
+    GreaterThanEq,
+    DotDot,
+    DotDotEq,
     RArrow,
     Star,
+    Slash,
+    At,
 
     Use,
+    Include,
     Type,
     Func,
     U8,
@@ -79,10 +135,22 @@ pub enum Token {
     Interface,
     Tuple,
     Implements,
+    World,
+    Import,
+    Export,
+    Default,
+    Resource,
+    Own,
+    Borrow,
+    Constructor,
+    Async,
+    Const,
+    Where,
 
     Id,
     ExplicitId,
     StrLit,
+    Number,
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -135,8 +203,6 @@ impl<'a> Tokenizer<'a> {
         Ok(ret)
     }
 
-    // This is going to be used shortly for a new feature.
-    #[allow(dead_code)]
     pub fn parse_str(&self, span: Span) -> Result<String> {
         let mut ret = String::new();
         let s = self.get_span(span);
@@ -198,7 +264,7 @@ impl<'a> Tokenizer<'a> {
                         }
                     }
                 } else {
-                    return Err(Error::Unexpected(start, ch));
+                    return Ok(Some(self.finish(start, Slash)));
                 }
 
                 Comment
@@ -211,16 +277,46 @@ impl<'a> Tokenizer<'a> {
             ')' => RightParen,
             '{' => LeftBrace,
             '}' => RightBrace,
-            '<' => LessThan,
-            '>' => GreaterThan,
+            '<' => {
+                if self.eatc('=') {
+                    LessThanEq
+                } else {
+                    LessThan
+                }
+            }
+            '>' => {
+                if self.eatc('=') {
+                    GreaterThanEq
+                } else {
+                    GreaterThan
+                }
+            }
+            '.' => {
+                if !self.eatc('.') {
+                    return Err(Error::Unexpected(start, '.'));
+                }
+                if self.eatc('=') {
+                    DotDotEq
+                } else {
+                    DotDot
+                }
+            }
             '*' => Star,
+            '@' => At,
             '-' => {
                 if self.eatc('>') {
                     RArrow
+                } else if matches!(self.chars.clone().next(), Some((_, '0'..='9'))) {
+                    self.eat_number();
+                    Number
                 } else {
                     return Err(Error::Unexpected(start, '-'));
                 }
             }
+            '0'..='9' => {
+                self.eat_number();
+                Number
+            }
             '"' => {
                 while let Some(_ch) = self.eat_str_char(start)? {}
                 StrLit
@@ -250,45 +346,18 @@ impl<'a> Tokenizer<'a> {
                     self.chars = iter.clone();
                 }
                 let end = start + ch.len_utf8() + (remaining - self.chars.chars.as_str().len());
-                match &self.input[start..end] {
-                    "use" => Use,
-                    "type" => Type,
-                    "func" => Func,
-                    "u8" => U8,
-                    "u16" => U16,
-                    "u32" => U32,
-                    "u64" => U64,
-                    "s8" => S8,
-                    "s16" => S16,
-                    "s32" => S32,
-                    "s64" => S64,
-                    "float32" => Float32,
-                    "float64" => Float64,
-                    "char" => Char,
-                    "record" => Record,
-                    "flags" => Flags,
-                    "variant" => Variant,
-                    "enum" => Enum,
-                    "union" => Union,
-                    "bool" => Bool,
-                    "string" => String_,
-                    "option" => Option_,
-                    "result" => Result_,
-                    "future" => Future,
-                    "stream" => Stream,
-                    "list" => List,
-                    "_" => Underscore,
-                    "as" => As,
-                    "from" => From_,
-                    "static" => Static,
-                    "interface" => Interface,
-                    "tuple" => Tuple,
-                    "implements" => Implements,
-                    _ => Id,
+                let word = &self.input[start..end];
+                match KEYWORDS.iter().find(|(keyword, _)| *keyword == word) {
+                    Some((_, token)) => *token,
+                    None => Id,
                 }
             }
             ch => return Err(Error::Unexpected(start, ch)),
         };
+        Ok(Some(self.finish(start, token)))
+    }
+
+    fn finish(&self, start: usize, token: Token) -> (Span, Token) {
         let end = match self.chars.clone().next() {
             Some((i, _)) => i,
             None => self.input.len(),
@@ -296,7 +365,44 @@ impl<'a> Tokenizer<'a> {
 
         let start = u32::try_from(start).unwrap();
         let end = u32::try_from(end).unwrap();
-        Ok(Some((Span { start, end }, token)))
+        (Span { start, end }, token)
+    }
+
+    /// Consumes a version such as `1.2.0` or `0.2.0-rc.1+build` which must
+    /// immediately follow the current position.
+    ///
+    /// Versions aren't tokens of their own since they're only valid directly
+    /// after the `@` of a package reference.
+    pub fn expect_version(&mut self) -> Result<Span, Error> {
+        let start = match self.chars.clone().next() {
+            Some((i, _)) => i,
+            None => {
+                return Err(Error::Wanted {
+                    at: self.input.len(),
+                    expected: "a version",
+                    found: "eof",
+                })
+            }
+        };
+        while let Some((_, ch)) = self.chars.clone().next() {
+            if !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '-' || ch == '+') {
+                break;
+            }
+            self.chars.next();
+        }
+        let (span, _) = self.finish(start, Id);
+        if span.start == span.end {
+            let found = match self.clone().next_raw()? {
+                Some((_, token)) => token.describe(),
+                None => "eof",
+            };
+            return Err(Error::Wanted {
+                at: start,
+                expected: "a version",
+                found,
+            });
+        }
+        Ok(span)
     }
 
     pub fn eat(&mut self, expected: Token) -> Result<bool, Error> {
@@ -353,6 +459,31 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Consumes the rest of a number literal such as `4096`, `0xff`, `-1.5` or
+    /// `1e-3`, whose validity is checked once its type is known.
+    fn eat_number(&mut self) {
+        let mut prev = '0';
+        loop {
+            let mut iter = self.chars.clone();
+            let ch = match iter.next() {
+                Some((_, ch)) => ch,
+                None => break,
+            };
+            let ok = match ch {
+                // A `.` is only part of the number if a digit follows, so that
+                // `1..2` lexes as two numbers.
+                '.' => matches!(iter.next(), Some((_, '0'..='9'))),
+                '+' | '-' => matches!(prev, 'e' | 'E'),
+                ch => ch.is_ascii_alphanumeric() || ch == '_',
+            };
+            if !ok {
+                break;
+            }
+            self.chars.next();
+            prev = ch;
+        }
+    }
+
     fn eatc(&mut self, ch: char) -> bool {
         let mut iter = self.chars.clone();
         match iter.next() {
@@ -524,6 +655,30 @@ pub fn validate_id(start: usize, id: &str) -> Result<(), Error> {
 }
 
 impl Token {
+    /// Returns whether this is a keyword which was added after identifiers of
+    /// the same name could already be in use.
+    ///
+    /// These keywords are contextual: they're still accepted as identifiers
+    /// wherever the keyword itself isn't expected, so `.wit` files which
+    /// used them as names keep parsing.
+    pub fn is_contextual(&self) -> bool {
+        matches!(
+            self,
+            Include
+                | World
+                | Import
+                | Export
+                | Default
+                | Resource
+                | Own
+                | Borrow
+                | Constructor
+                | Async
+                | Const
+                | Where
+        )
+    }
+
     pub fn describe(&self) -> &'static str {
         match self {
             Whitespace => "whitespace",
@@ -537,8 +692,13 @@ impl Token {
             LeftBrace => "'{'",
             RightBrace => "'}'",
             LessThan => "'<'",
+            LessThanEq => "`<=`",
             GreaterThan => "'>'",
+            GreaterThanEq => "`>=`",
+            DotDot => "`..`",
+            DotDotEq => "`..=`",
             Use => "keyword `use`",
+            Include => "keyword `include`",
             Type => "keyword `type`",
             Func => "keyword `func`",
             U8 => "keyword `u8`",
@@ -568,14 +728,28 @@ impl Token {
             Id => "an identifier",
             ExplicitId => "an '%' identifier",
             StrLit => "a string",
+            Number => "a number",
             RArrow => "`->`",
             Star => "`*`",
+            Slash => "`/`",
+            At => "`@`",
             As => "keyword `as`",
             From_ => "keyword `from`",
             Static => "keyword `static`",
             Interface => "keyword `interface`",
             Tuple => "keyword `tuple`",
             Implements => "keyword `implements`",
+            World => "keyword `world`",
+            Import => "keyword `import`",
+            Export => "keyword `export`",
+            Default => "keyword `default`",
+            Resource => "keyword `resource`",
+            Own => "keyword `own`",
+            Borrow => "keyword `borrow`",
+            Constructor => "keyword `constructor`",
+            Async => "keyword `async`",
+            Const => "keyword `const`",
+            Where => "keyword `where`",
         }
     }
 }
@@ -601,25 +775,22 @@ impl fmt::Display for Error {
     }
 }
 
-pub fn rewrite_error(err: &mut anyhow::Error, file: &str, contents: &str) {
-    let lex = match err.downcast_mut::<Error>() {
-        Some(err) => err,
-        None => return,
-    };
-    let pos = match lex {
-        Error::Unexpected(at, _)
-        | Error::UnterminatedComment(at)
-        | Error::Wanted { at, .. }
-        | Error::UnterminatedString(at)
-        | Error::NewlineInString(at)
-        | Error::InvalidCharInString(at, _)
-        | Error::InvalidCharInId(at, _)
-        | Error::IdNotSSNFC(at)
-        | Error::IdPartEmpty(at)
-        | Error::InvalidEscape(at, _) => *at,
-    };
-    let msg = super::highlight_err(pos, None, file, contents, lex);
-    *err = anyhow::anyhow!("{}", msg);
+impl Error {
+    /// Returns the byte offset of this error within the input.
+    pub fn position(&self) -> usize {
+        match self {
+            Error::Unexpected(at, _)
+            | Error::UnterminatedComment(at)
+            | Error::Wanted { at, .. }
+            | Error::UnterminatedString(at)
+            | Error::NewlineInString(at)
+            | Error::InvalidCharInString(at, _)
+            | Error::InvalidCharInId(at, _)
+            | Error::IdNotSSNFC(at)
+            | Error::IdPartEmpty(at)
+            | Error::InvalidEscape(at, _) => *at,
+        }
+    }
 }
 
 #[test]
@@ -728,11 +899,45 @@ fn test_tokenizer() {
             Token::RightParen
         ]
     );
+    assert_eq!(
+        collect("a:b/c@ // comment").unwrap(),
+        vec![
+            Token::Id,
+            Token::Colon,
+            Token::Id,
+            Token::Slash,
+            Token::Id,
+            Token::At
+        ]
+    );
 
     assert_eq!(collect("\"a\"").unwrap(), vec![Token::StrLit]);
     assert_eq!(collect("\"a-a\"").unwrap(), vec![Token::StrLit]);
     assert_eq!(collect("\"bool\"").unwrap(), vec![Token::StrLit]);
 
+    assert_eq!(collect("0").unwrap(), vec![Token::Number]);
+    assert_eq!(collect("-12").unwrap(), vec![Token::Number]);
+    assert_eq!(collect("0xff_ff").unwrap(), vec![Token::Number]);
+    assert_eq!(collect("1.5e-3").unwrap(), vec![Token::Number]);
+    assert_eq!(
+        collect("= 1, 2").unwrap(),
+        vec![Token::Equals, Token::Number, Token::Comma, Token::Number]
+    );
+    assert!(collect("-").is_err());
+    assert_eq!(
+        collect("1..=2 ..3 <= >=").unwrap(),
+        vec![
+            Token::Number,
+            Token::DotDotEq,
+            Token::Number,
+            Token::DotDot,
+            Token::Number,
+            Token::LessThanEq,
+            Token::GreaterThanEq
+        ]
+    );
+    assert!(collect(".").is_err());
+
     assert!(collect("\u{149}").is_err(), "strongly discouraged");
     assert!(collect("\u{673}").is_err(), "strongly discouraged");
     assert!(collect("\u{17a3}").is_err(), "strongly discouraged");
@@ -1,6 +1,10 @@
-use super::{Error, Item, ParamList, ResultList, Span, Value, ValueKind};
+use super::{
+    check_errors, collect_error, Error, ExternKind, Include, Item, LiteralKind, ParamList,
+    Resource, ResourceFuncKind, ResultList, Span, Use, Value, ValueKind, WorldItem,
+};
 use crate::*;
 use anyhow::Result;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::mem;
 
@@ -9,9 +13,16 @@ pub struct Resolver {
     type_lookup: HashMap<String, TypeId>,
     types: Arena<TypeDef>,
     types_copied: HashMap<(String, TypeId), TypeId>,
+    /// The names of the interfaces which are included, whose types become
+    /// part of this interface rather than remaining foreign to it.
+    included: HashSet<String>,
+    /// The interface each included type was defined in, for those which
+    /// aren't foreign.
+    origins: HashMap<TypeId, String>,
     anon_types: HashMap<Key, TypeId>,
     functions: Vec<Function>,
     globals: Vec<Global>,
+    constants: Vec<Constant>,
 }
 
 #[derive(PartialEq, Eq, Hash)]
@@ -27,19 +38,74 @@ enum Key {
     Union(Vec<Type>),
     Future(Option<Type>),
     Stream(Option<Type>, Option<Type>),
+    Handle(Handle),
 }
 
 impl Resolver {
+    /// Resolves the interface `name` made up of `fields`.
+    ///
+    /// `scope` holds the interfaces defined earlier in the same file, which
+    /// a `use` or `include` refers to in preference to `deps`.
     pub(super) fn resolve(
         &mut self,
         name: &str,
         fields: &[Item<'_>],
+        package: Option<&PackageName>,
         deps: &HashMap<String, Interface>,
+        scope: &HashMap<String, Interface>,
     ) -> Result<Interface> {
-        // First pull in any names from our dependencies
-        self.process_use(fields, deps)?;
+        let mut errors = Vec::new();
+
+        // Interfaces nested within this one are resolved first, each of them
+        // able to refer to those defined before it.
+        let mut scope = Cow::Borrowed(scope);
+        let mut interfaces = Arena::new();
+        let mut interface_lookup = HashMap::new();
+        for field in fields {
+            let i = match field {
+                Item::Interface(i) => i,
+                _ => continue,
+            };
+            let iface = Resolver::default().resolve(&i.name.name, &i.items, package, deps, &scope);
+            let iface = match collect_error(&mut errors, iface)? {
+                Some(iface) => iface,
+                None => continue,
+            };
+            if interface_lookup.contains_key(&iface.name) {
+                errors.push(Error {
+                    span: i.name.span,
+                    msg: format!("interface `{}` defined twice", i.name.name),
+                });
+                continue;
+            }
+            scope.to_mut().insert(iface.name.clone(), iface.clone());
+            interface_lookup.insert(iface.name.clone(), interfaces.alloc(iface));
+        }
+        check_errors(mem::take(&mut errors))?;
+
+        // Then pull in any names from our dependencies and included
+        // interfaces ...
+        for field in fields {
+            if let Item::Include(i) = field {
+                self.included.insert(i.target.from[0].name.to_string());
+            }
+        }
+        let mut values = HashSet::new();
+        for field in fields {
+            let result = match field {
+                Item::Use(u) => self.process_use(u, package, deps, &scope),
+                Item::Include(i) => self.process_include(i, package, deps, &scope, &mut values),
+                _ => continue,
+            };
+            collect_error(&mut errors, result)?;
+        }
         // ... then register our own names
-        self.register_names(fields)?;
+        for field in fields {
+            collect_error(&mut errors, self.register_name(field, &mut values))?;
+        }
+        // If any names failed to be defined then stop here, as continuing
+        // would report a cascade of errors about undefined names.
+        check_errors(mem::take(&mut errors))?;
 
         // With all names registered we can now fully expand and translate all
         // types.
@@ -49,8 +115,9 @@ impl Resolver {
                 _ => continue,
             };
             let id = self.type_lookup[&*t.name.name];
-            let kind = self.resolve_type_def(&t.ty)?;
-            self.types.get_mut(id).unwrap().kind = kind;
+            if let Some(kind) = collect_error(&mut errors, self.resolve_type_def(&t.ty))? {
+                self.types.get_mut(id).unwrap().kind = kind;
+            }
         }
 
         // And finally we can resolve all type references in functions/globals
@@ -58,111 +125,258 @@ impl Resolver {
         let mut valid_types = HashSet::new();
         let mut visiting = HashSet::new();
         for field in fields {
-            match field {
-                Item::Value(v) => self.resolve_value(v)?,
-                Item::TypeDef(t) => {
-                    self.validate_type_not_recursive(
+            let result = match field {
+                Item::Value(v) => self.resolve_value(v),
+                Item::Resource(r) => self.resolve_resource(r),
+                Item::TypeDef(t) => self
+                    .validate_type_not_recursive(
                         t.name.span,
                         self.type_lookup[&*t.name.name],
                         &mut visiting,
                         &mut valid_types,
-                    )?;
-                }
+                    )
+                    .and_then(|()| {
+                        self.validate_recursion_is_nominal(
+                            t.name.span,
+                            self.type_lookup[&*t.name.name],
+                        )
+                    })
+                    .and_then(|()| self.resolve_field_defaults(t))
+                    .and_then(|()| self.resolve_refinement(t)),
                 _ => continue,
-            }
+            };
+            collect_error(&mut errors, result)?;
         }
+        check_errors(errors)?;
 
         Ok(Interface {
             name: name.to_string(),
             module: None,
             types: mem::take(&mut self.types),
             type_lookup: mem::take(&mut self.type_lookup),
-            interface_lookup: Default::default(),
-            interfaces: Default::default(),
+            interface_lookup,
+            interfaces,
             functions: mem::take(&mut self.functions),
             globals: mem::take(&mut self.globals),
+            constants: mem::take(&mut self.constants),
         })
     }
 
-    fn process_use<'a>(
+    fn process_use(
         &mut self,
-        fields: &[Item<'a>],
-        deps: &'a HashMap<String, Interface>,
+        u: &Use<'_>,
+        package: Option<&PackageName>,
+        deps: &HashMap<String, Interface>,
+        scope: &HashMap<String, Interface>,
     ) -> Result<()> {
-        for field in fields {
-            let u = match field {
-                Item::Use(u) => u,
-                _ => continue,
-            };
-            let mut dep = &deps[&*u.from[0].name];
-            let mut prev = &*u.from[0].name;
-            for name in u.from[1..].iter() {
-                dep = match dep.interface_lookup.get(&*name.name) {
-                    Some(i) => &dep.interfaces[*i],
-                    None => {
-                        return Err(Error {
-                            span: name.span,
-                            msg: format!("`{}` not defined in `{}`", name.name, prev),
-                        }
-                        .into())
-                    }
-                };
-                prev = &*name.name;
-            }
+        let dep = lookup_use(u, package, deps, scope)?;
+        let mod_name = &u.from[0];
 
-            let mod_name = &u.from[0];
+        match &u.names {
+            Some(names) => {
+                for name in names {
+                    let (my_name, span) = match &name.as_ {
+                        Some(id) => (&id.name, id.span),
+                        None => (&name.name.name, name.name.span),
+                    };
+                    let mut found = false;
 
-            match &u.names {
-                Some(names) => {
-                    for name in names {
-                        let (my_name, span) = match &name.as_ {
-                            Some(id) => (&id.name, id.span),
-                            None => (&name.name.name, name.name.span),
-                        };
-                        let mut found = false;
-
-                        if let Some(id) = dep.type_lookup.get(&*name.name.name) {
-                            let ty = self.copy_type_def(&mod_name.name, dep, *id);
-                            self.define_type(my_name, span, ty)?;
-                            found = true;
-                        }
+                    if let Some(id) = dep.type_lookup.get(&*name.name.name) {
+                        let ty = self.copy_type_def(&mod_name.name, dep, *id);
+                        self.define_type(my_name, span, ty)?;
+                        found = true;
+                    }
 
-                        if !found {
-                            return Err(Error {
-                                span: name.name.span,
-                                msg: "name not defined in submodule".to_string(),
-                            }
-                            .into());
+                    if !found {
+                        return Err(Error {
+                            span: name.name.span,
+                            msg: "name not defined in submodule".to_string(),
                         }
+                        .into());
                     }
                 }
+            }
+            None => {
+                let mut names = dep.type_lookup.iter().collect::<Vec<_>>();
+                names.sort(); // produce a stable order by which to add names
+                for (name, id) in names {
+                    let ty = self.copy_type_def(&mod_name.name, dep, *id);
+                    self.define_type(name, mod_name.span, ty)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the types and values of the interface included by `i` into
+    /// this one.
+    ///
+    /// `values` holds the names of the values defined so far, which the
+    /// included values must not conflict with.
+    fn process_include(
+        &mut self,
+        i: &Include<'_>,
+        package: Option<&PackageName>,
+        deps: &HashMap<String, Interface>,
+        scope: &HashMap<String, Interface>,
+        values: &mut HashSet<String>,
+    ) -> Result<()> {
+        let u = &i.target;
+        let dep = lookup_use(u, package, deps, scope)?;
+        let dep_name = &*u.from[0].name;
+        let conflict = |kind: &str, name: &str| -> anyhow::Error {
+            Error {
+                span: u.target_span(),
+                msg: format!(
+                    "{} `{}` included from `{}` is already defined",
+                    kind, name, dep_name
+                ),
+            }
+            .into()
+        };
+
+        // Types are included in the order they were defined in.
+        let mut names = dep.type_lookup.iter().collect::<Vec<_>>();
+        names.sort_by_key(|(name, id)| (id.index(), *name));
+        for (name, id) in names {
+            let ty = &dep.types[*id];
+            let origin = ty.foreign_module.as_deref().unwrap_or(dep_name);
+            let prev = match self.type_lookup.get(name) {
+                Some(prev) => *prev,
                 None => {
-                    let mut names = dep.type_lookup.iter().collect::<Vec<_>>();
-                    names.sort(); // produce a stable order by which to add names
-                    for (name, id) in names {
-                        let ty = self.copy_type_def(&mod_name.name, dep, *id);
-                        self.define_type(name, mod_name.span, ty)?;
+                    let id = self.copy_type_def(dep_name, dep, *id);
+                    if self.types[id].foreign_module.is_none() {
+                        self.origins.insert(id, origin.to_string());
                     }
+                    self.type_lookup.insert(name.clone(), id);
+                    continue;
                 }
+            };
+            // The same type may be included by way of more than one
+            // interface, or already have been pulled in by a `use`, in which
+            // case it's shared rather than copied again.
+            let prev_ty = &self.types[prev];
+            let prev_origin = prev_ty
+                .foreign_module
+                .as_ref()
+                .or_else(|| self.origins.get(&prev));
+            if prev_origin.map(|m| &m[..]) != Some(origin) || prev_ty.name != ty.name {
+                return Err(conflict("type", name));
+            }
+            self.types_copied.insert((dep_name.to_string(), *id), prev);
+        }
+
+        for func in dep.functions.iter() {
+            let func = self.copy_function(dep_name, dep, func);
+            let name = func.name.clone();
+            if !include_item(&mut self.functions, values, &name, func) {
+                return Err(conflict("function", &name));
+            }
+        }
+        for global in dep.globals.iter() {
+            let global = Global {
+                docs: global.docs.clone(),
+                name: global.name.clone(),
+                ty: self.copy_type(dep_name, dep, global.ty),
+            };
+            let name = global.name.clone();
+            if !include_item(&mut self.globals, values, &name, global) {
+                return Err(conflict("global", &name));
+            }
+        }
+        for constant in dep.constants.iter() {
+            let constant = Constant {
+                docs: constant.docs.clone(),
+                name: constant.name.clone(),
+                ty: self.copy_type(dep_name, dep, constant.ty),
+                value: constant.value.clone(),
+            };
+            let name = constant.name.clone();
+            if !include_item(&mut self.constants, values, &name, constant) {
+                return Err(conflict("constant", &name));
             }
         }
         Ok(())
     }
 
+    fn copy_function(&mut self, dep_name: &str, dep: &Interface, func: &Function) -> Function {
+        let kind = match &func.kind {
+            FunctionKind::Freestanding => FunctionKind::Freestanding,
+            FunctionKind::Method { resource, name } => FunctionKind::Method {
+                resource: self.copy_type_def(dep_name, dep, *resource),
+                name: name.clone(),
+            },
+            FunctionKind::Static { resource, name } => FunctionKind::Static {
+                resource: self.copy_type_def(dep_name, dep, *resource),
+                name: name.clone(),
+            },
+            FunctionKind::Constructor { resource } => FunctionKind::Constructor {
+                resource: self.copy_type_def(dep_name, dep, *resource),
+            },
+        };
+        let mut copy_params = |params: &Params| {
+            params
+                .iter()
+                .map(|(name, ty)| (name.clone(), self.copy_type(dep_name, dep, *ty)))
+                .collect()
+        };
+        let params = copy_params(&func.params);
+        let results = match &func.results {
+            Results::Named(results) => Results::Named(copy_params(results)),
+            Results::Anon(ty) => Results::Anon(self.copy_type(dep_name, dep, *ty)),
+        };
+        Function {
+            docs: func.docs.clone(),
+            attrs: func.attrs.clone(),
+            name: func.name.clone(),
+            kind,
+            is_async: func.is_async,
+            params,
+            defaults: func.defaults.clone(),
+            results,
+            span: None,
+        }
+    }
+
     fn copy_type_def(&mut self, dep_name: &str, dep: &Interface, dep_id: TypeId) -> TypeId {
         if let Some(id) = self.types_copied.get(&(dep_name.to_string(), dep_id)) {
             return *id;
         }
         let ty = &dep.types[dep_id];
 
+        // Recursive types refer back to themselves, so they're allocated
+        // before what they contain is copied and filled in afterwards.
+        let placeholder = if dep.is_recursive(dep_id) {
+            let id = self.types.alloc(TypeDef {
+                docs: Default::default(),
+                attrs: Default::default(),
+                kind: TypeDefKind::List(Type::U8),
+                name: None,
+                foreign_module: None,
+                refinement: None,
+                span: None,
+            });
+            self.types_copied.insert((dep_name.to_string(), dep_id), id);
+            Some(id)
+        } else {
+            None
+        };
+
         let ty = TypeDef {
             docs: ty.docs.clone(),
+            attrs: ty.attrs.clone(),
             name: ty.name.clone(),
-            foreign_module: Some(
-                ty.foreign_module
-                    .clone()
-                    .unwrap_or_else(|| dep_name.to_string()),
-            ),
+            foreign_module: if self.included.contains(dep_name) {
+                ty.foreign_module.clone()
+            } else {
+                Some(
+                    ty.foreign_module
+                        .clone()
+                        .unwrap_or_else(|| dep_name.to_string()),
+                )
+            },
+            refinement: ty.refinement.clone(),
+            span: None,
             kind: match &ty.kind {
                 TypeDefKind::Type(t) => TypeDefKind::Type(self.copy_type(dep_name, dep, *t)),
                 TypeDefKind::Record(r) => TypeDefKind::Record(Record {
@@ -171,8 +385,10 @@ impl Resolver {
                         .iter()
                         .map(|field| Field {
                             docs: field.docs.clone(),
+                            attrs: field.attrs.clone(),
                             name: field.name.clone(),
                             ty: self.copy_type(dep_name, dep, field.ty),
+                            default: field.default.clone(),
                         })
                         .collect(),
                 }),
@@ -190,6 +406,7 @@ impl Resolver {
                         .iter()
                         .map(|case| Case {
                             docs: case.docs.clone(),
+                            attrs: case.attrs.clone(),
                             name: case.name.clone(),
                             ty: self.copy_optional_type(dep_name, dep, case.ty),
                         })
@@ -210,6 +427,7 @@ impl Resolver {
                         .iter()
                         .map(|c| UnionCase {
                             docs: c.docs.clone(),
+                            attrs: c.attrs.clone(),
                             ty: self.copy_type(dep_name, dep, c.ty),
                         })
                         .collect(),
@@ -221,8 +439,19 @@ impl Resolver {
                     element: self.copy_optional_type(dep_name, dep, e.element),
                     end: self.copy_optional_type(dep_name, dep, e.end),
                 }),
+                TypeDefKind::Resource => TypeDefKind::Resource,
+                TypeDefKind::Handle(Handle::Own(id)) => {
+                    TypeDefKind::Handle(Handle::Own(self.copy_type_def(dep_name, dep, *id)))
+                }
+                TypeDefKind::Handle(Handle::Borrow(id)) => {
+                    TypeDefKind::Handle(Handle::Borrow(self.copy_type_def(dep_name, dep, *id)))
+                }
             },
         };
+        if let Some(id) = placeholder {
+            *self.types.get_mut(id).unwrap() = ty;
+            return id;
+        }
         let id = self.types.alloc(ty);
         self.types_copied.insert((dep_name.to_string(), dep_id), id);
         id
@@ -247,34 +476,54 @@ impl Resolver {
         }
     }
 
-    fn register_names(&mut self, fields: &[Item<'_>]) -> Result<()> {
-        let mut values = HashSet::new();
-        for field in fields {
-            match field {
-                Item::TypeDef(t) => {
-                    let docs = self.docs(&t.docs);
-                    let id = self.types.alloc(TypeDef {
-                        docs,
-                        // a dummy kind is used for now which will get filled in
-                        // later with the actual desired contents.
-                        kind: TypeDefKind::List(Type::U8),
-                        name: Some(t.name.name.to_string()),
-                        foreign_module: None,
-                    });
-                    self.define_type(&t.name.name, t.name.span, id)?;
-                }
-                Item::Value(f) => {
-                    if !values.insert(&f.name.name) {
-                        return Err(Error {
-                            span: f.name.span,
-                            msg: format!("{:?} defined twice", f.name.name),
-                        }
-                        .into());
+    fn register_name(&mut self, field: &Item<'_>, values: &mut HashSet<String>) -> Result<()> {
+        match field {
+            Item::TypeDef(t) => {
+                let docs = self.docs(&t.docs)?;
+                let attrs = self.attrs(&t.docs)?;
+                let id = self.types.alloc(TypeDef {
+                    docs,
+                    attrs,
+                    // a dummy kind is used for now which will get filled in
+                    // later with the actual desired contents.
+                    kind: TypeDefKind::List(Type::U8),
+                    name: Some(t.name.name.to_string()),
+                    foreign_module: None,
+                    refinement: None,
+                    span: Some(t.name.span),
+                });
+                self.define_type(&t.name.name, t.name.span, id)?;
+            }
+            Item::Value(f) => {
+                if !values.insert(f.name.name.to_string()) {
+                    return Err(Error {
+                        span: f.name.span,
+                        msg: format!("{:?} defined twice", f.name.name),
                     }
+                    .into());
                 }
-                Item::Use(_) => {}
-
-                Item::Interface(_) => unimplemented!(),
+            }
+            Item::Resource(r) => {
+                let docs = self.docs(&r.docs)?;
+                let attrs = self.attrs(&r.docs)?;
+                let id = self.types.alloc(TypeDef {
+                    docs,
+                    attrs,
+                    kind: TypeDefKind::Resource,
+                    name: Some(r.name.name.to_string()),
+                    foreign_module: None,
+                    refinement: None,
+                    span: Some(r.name.span),
+                });
+                self.define_type(&r.name.name, r.name.span, id)?;
+            }
+            Item::Use(_) | Item::Include(_) | Item::Interface(_) => {}
+            Item::World(w) => {
+                return Err(Error {
+                    span: w.name.span,
+                    msg: "worlds can only be defined at the top level of a file".to_string(),
+                }
+                .into())
             }
         }
 
@@ -319,7 +568,18 @@ impl Resolver {
                         .into())
                     }
                 };
-                TypeDefKind::Type(Type::Id(id))
+                // Referring to a resource by name is shorthand for an owned
+                // handle to it.
+                match self.types[id].kind {
+                    TypeDefKind::Resource => TypeDefKind::Handle(Handle::Own(id)),
+                    _ => TypeDefKind::Type(Type::Id(id)),
+                }
+            }
+            super::Type::Own(name) => {
+                TypeDefKind::Handle(Handle::Own(self.resolve_resource_name(name)?))
+            }
+            super::Type::Borrow(name) => {
+                TypeDefKind::Handle(Handle::Borrow(self.resolve_resource_name(name)?))
             }
             super::Type::List(list) => {
                 let ty = self.resolve_type(list)?;
@@ -331,9 +591,13 @@ impl Resolver {
                     .iter()
                     .map(|field| {
                         Ok(Field {
-                            docs: self.docs(&field.docs),
+                            docs: self.docs(&field.docs)?,
+                            attrs: self.attrs(&field.docs)?,
                             name: field.name.name.to_string(),
                             ty: self.resolve_type(&field.ty)?,
+                            // Filled in by `resolve_field_defaults` once all
+                            // types are known.
+                            default: None,
                         })
                     })
                     .collect::<Result<Vec<_>>>()?;
@@ -343,11 +607,14 @@ impl Resolver {
                 let flags = flags
                     .flags
                     .iter()
-                    .map(|flag| Flag {
-                        docs: self.docs(&flag.docs),
-                        name: flag.name.name.to_string(),
+                    .map(|flag| {
+                        Ok(Flag {
+                            docs: self.docs(&flag.docs)?,
+                            attrs: self.attrs(&flag.docs)?,
+                            name: flag.name.name.to_string(),
+                        })
                     })
-                    .collect::<Vec<_>>();
+                    .collect::<Result<Vec<_>>>()?;
                 TypeDefKind::Flags(Flags { flags })
             }
             super::Type::Tuple(types) => {
@@ -370,7 +637,8 @@ impl Resolver {
                     .iter()
                     .map(|case| {
                         Ok(Case {
-                            docs: self.docs(&case.docs),
+                            docs: self.docs(&case.docs)?,
+                            attrs: self.attrs(&case.docs)?,
                             name: case.name.name.to_string(),
                             ty: self.resolve_optional_type(case.ty.as_ref())?,
                         })
@@ -391,7 +659,8 @@ impl Resolver {
                     .iter()
                     .map(|case| {
                         Ok(EnumCase {
-                            docs: self.docs(&case.docs),
+                            docs: self.docs(&case.docs)?,
+                            attrs: self.attrs(&case.docs)?,
                             name: case.name.name.to_string(),
                         })
                     })
@@ -416,7 +685,8 @@ impl Resolver {
                     .iter()
                     .map(|case| {
                         Ok(UnionCase {
-                            docs: self.docs(&case.docs),
+                            docs: self.docs(&case.docs)?,
+                            attrs: self.attrs(&case.docs)?,
                             ty: self.resolve_type(&case.ty)?,
                         })
                     })
@@ -433,13 +703,32 @@ impl Resolver {
         })
     }
 
+    fn resolve_resource_name(&self, name: &super::Id<'_>) -> Result<TypeId> {
+        match self.type_lookup.get(&*name.name) {
+            Some(id) if self.types[*id].kind == TypeDefKind::Resource => Ok(*id),
+            Some(_) => Err(Error {
+                span: name.span,
+                msg: format!("type `{}` is not a resource", name.name),
+            }
+            .into()),
+            None => Err(Error {
+                span: name.span,
+                msg: format!("no type named `{}`", name.name),
+            }
+            .into()),
+        }
+    }
+
     fn resolve_type(&mut self, ty: &super::Type<'_>) -> Result<Type> {
         let kind = self.resolve_type_def(ty)?;
         Ok(self.anon_type_def(TypeDef {
             kind,
             name: None,
             docs: Docs::default(),
+            attrs: Attributes::default(),
             foreign_module: None,
+            refinement: None,
+            span: None,
         }))
     }
 
@@ -451,7 +740,10 @@ impl Resolver {
                     kind,
                     name: None,
                     docs: Docs::default(),
+                    attrs: Attributes::default(),
                     foreign_module: None,
+                    refinement: None,
+                    span: None,
                 })))
             }
             None => Ok(None),
@@ -486,6 +778,8 @@ impl Resolver {
             TypeDefKind::Union(u) => Key::Union(u.cases.iter().map(|c| c.ty).collect()),
             TypeDefKind::Future(ty) => Key::Future(*ty),
             TypeDefKind::Stream(s) => Key::Stream(s.element, s.end),
+            TypeDefKind::Handle(h) => Key::Handle(*h),
+            TypeDefKind::Resource => unreachable!("resources are always named"),
         };
         let types = &mut self.types;
         let id = self
@@ -495,43 +789,145 @@ impl Resolver {
         Type::Id(*id)
     }
 
-    fn docs(&mut self, doc: &super::Docs<'_>) -> Docs {
-        let mut docs = None;
-        for doc in doc.docs.iter() {
-            // Comments which are not doc-comments are silently ignored
-            if let Some(doc) = doc.strip_prefix("///") {
-                let docs = docs.get_or_insert_with(String::new);
-                docs.push_str(doc.trim_start_matches('/').trim());
-                docs.push('\n');
-            } else if let Some(doc) = doc.strip_prefix("/*") {
-                // We have to strip this before checking if this is a doc
-                // comment to avoid breaking on empty block comments, `/**/`.
-                let doc = doc.strip_suffix("*/").unwrap();
-
-                if let Some(doc) = doc.strip_prefix("*") {
-                    let docs = docs.get_or_insert_with(String::new);
-                    for line in doc.lines() {
-                        docs.push_str(line);
-                        docs.push('\n');
+    fn docs(&self, doc: &super::Docs<'_>) -> Result<Docs> {
+        let mut docs = Docs {
+            contents: doc.contents(),
+            ..Docs::default()
+        };
+        for attr in doc.attrs.iter() {
+            match &*attr.name.name {
+                "deprecated" => {
+                    let mut deprecated = Deprecated::default();
+                    for arg in attr.args.iter() {
+                        let key = arg.key.as_ref().map(|k| &*k.name);
+                        let value = self.attr_string(attr, arg)?;
+                        match key {
+                            None | Some("note") => deprecated.note = Some(value),
+                            Some("since") => {
+                                super::validate_version(arg.value.span, &value)?;
+                                deprecated.since = Some(value);
+                            }
+                            Some(key) => {
+                                return Err(Error {
+                                    span: arg.key.as_ref().unwrap().span,
+                                    msg: format!("unknown argument `{}` to `@deprecated`", key),
+                                }
+                                .into())
+                            }
+                        }
                     }
+                    docs.deprecated = Some(deprecated);
                 }
+                "since" => {
+                    let mut since = None;
+                    for arg in attr.args.iter() {
+                        match arg.key.as_ref().map(|k| &*k.name) {
+                            None | Some("version") => {
+                                let value = self.attr_string(attr, arg)?;
+                                super::validate_version(arg.value.span, &value)?;
+                                since = Some(value);
+                            }
+                            Some(key) => {
+                                return Err(Error {
+                                    span: arg.key.as_ref().unwrap().span,
+                                    msg: format!("unknown argument `{}` to `@since`", key),
+                                }
+                                .into())
+                            }
+                        }
+                    }
+                    match since {
+                        Some(since) => docs.since = Some(since),
+                        None => {
+                            return Err(Error {
+                                span: attr.span,
+                                msg: "`@since` requires a version".to_string(),
+                            }
+                            .into())
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(docs)
+    }
+
+    /// Returns the string value of `arg`, an argument of the well-known
+    /// attribute `attr`.
+    fn attr_string(
+        &self,
+        attr: &super::Attribute<'_>,
+        arg: &super::AttributeArg<'_>,
+    ) -> Result<String> {
+        match &arg.value.kind {
+            LiteralKind::String(s) => Ok(s.clone()),
+            _ => Err(Error {
+                span: arg.value.span,
+                msg: format!("arguments to `@{}` must be strings", attr.name.name),
+            }
+            .into()),
+        }
+    }
+
+    /// Resolves the attributes among `doc`, giving each number the type it
+    /// appears to have.
+    fn attrs(&self, doc: &super::Docs<'_>) -> Result<Attributes> {
+        let mut attrs = Vec::new();
+        for attr in doc.attrs.iter() {
+            let mut args = Vec::new();
+            for arg in attr.args.iter() {
+                let value = match &arg.value.kind {
+                    LiteralKind::Bool(b) => Literal::Bool(*b),
+                    LiteralKind::String(s) => Literal::String(s.clone()),
+                    LiteralKind::Number(text) => match parse_integer(text) {
+                        Some(i) => Literal::Integer(i),
+                        None => match text.replace('_', "").parse::<f64>() {
+                            Ok(f) if f.is_finite() => Literal::Float(f),
+                            _ => {
+                                return Err(Error {
+                                    span: arg.value.span,
+                                    msg: format!("invalid number `{}`", text),
+                                }
+                                .into())
+                            }
+                        },
+                    },
+                };
+                args.push(AttributeArg {
+                    key: arg.key.as_ref().map(|k| k.name.to_string()),
+                    value,
+                });
             }
+            attrs.push(Attribute {
+                name: attr.name.name.to_string(),
+                args,
+            });
         }
-        Docs { contents: docs }
+        Ok(Attributes { attrs })
     }
 
     fn resolve_value(&mut self, value: &Value<'_>) -> Result<()> {
-        let docs = self.docs(&value.docs);
+        let docs = self.docs(&value.docs)?;
         match &value.kind {
-            ValueKind::Function { params, results } => {
-                let params = self.resolve_params(params)?;
+            ValueKind::Function {
+                is_async,
+                params,
+                results,
+            } => {
+                let (params, defaults) = self.resolve_params(params)?;
                 let results = self.resolve_results(results)?;
+                self.validate_results_not_borrowed(value.name.span, &results)?;
                 self.functions.push(Function {
                     docs,
+                    attrs: self.attrs(&value.docs)?,
                     name: value.name.name.to_string(),
                     kind: FunctionKind::Freestanding,
+                    is_async: *is_async,
                     params,
+                    defaults,
                     results,
+                    span: Some(value.name.span),
                 });
             }
             ValueKind::Global(ty) => {
@@ -542,24 +938,353 @@ impl Resolver {
                     ty,
                 });
             }
+            ValueKind::Const { ty, value: literal } => {
+                let ty = self.resolve_type(ty)?;
+                let literal = self.resolve_literal(ty, literal)?;
+                self.constants.push(Constant {
+                    docs,
+                    name: value.name.name.to_string(),
+                    ty,
+                    value: literal,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_refinement(&mut self, t: &super::TypeDef<'_>) -> Result<()> {
+        let refinement = match &t.refinement {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+        let id = self.type_lookup[&*t.name.name];
+        let mut ty = Type::Id(id);
+        while let Type::Id(id) = ty {
+            match &self.types[id].kind {
+                TypeDefKind::Type(t) => ty = *t,
+                _ => break,
+            }
+        }
+        let err = |msg: &str| -> Result<()> {
+            Err(Error {
+                span: refinement.span,
+                msg: msg.to_string(),
+            }
+            .into())
+        };
+        let resolved = match &refinement.kind {
+            super::RefinementKind::Range(range) => {
+                if !matches!(
+                    ty,
+                    Type::U8
+                        | Type::U16
+                        | Type::U32
+                        | Type::U64
+                        | Type::S8
+                        | Type::S16
+                        | Type::S32
+                        | Type::S64
+                        | Type::Float32
+                        | Type::Float64
+                ) {
+                    return err("ranges can only refine integer and floating point types");
+                }
+                Refinement::Range(self.resolve_range(ty, range)?)
+            }
+            super::RefinementKind::Length(range) => {
+                let is_list = match ty {
+                    Type::String => true,
+                    Type::Id(id) => matches!(self.types[id].kind, TypeDefKind::List(_)),
+                    _ => false,
+                };
+                if !is_list {
+                    return err("`len` can only refine strings and lists");
+                }
+                Refinement::Length(self.resolve_range(Type::U32, range)?)
+            }
+        };
+        self.types.get_mut(id).unwrap().refinement = Some(resolved);
+        Ok(())
+    }
+
+    /// Resolves the bounds of `range` as values of `ty`, checking that the
+    /// range isn't empty.
+    fn resolve_range(&self, ty: Type, range: &super::Range<'_>) -> Result<Range> {
+        let start = match &range.start {
+            Some(l) => Some(self.resolve_literal(ty, l)?),
+            None => None,
+        };
+        let end = match &range.end {
+            Some(l) => Some(self.resolve_literal(ty, l)?),
+            None => None,
+        };
+        let order = match (&start, &end) {
+            (Some(Literal::Integer(a)), Some(Literal::Integer(b))) => a.partial_cmp(b),
+            (Some(a), Some(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+            _ => None,
+        };
+        if let Some(order) = order {
+            let empty = if range.inclusive {
+                order.is_gt()
+            } else {
+                order.is_ge()
+            };
+            if empty {
+                return Err(Error {
+                    span: range.span,
+                    msg: "this range is empty".to_string(),
+                }
+                .into());
+            }
+        }
+        Ok(Range {
+            start,
+            end,
+            inclusive: range.inclusive,
+        })
+    }
+
+    fn resolve_field_defaults(&mut self, t: &super::TypeDef<'_>) -> Result<()> {
+        let record = match &t.ty {
+            super::Type::Record(r) => r,
+            _ => return Ok(()),
+        };
+        let id = self.type_lookup[&*t.name.name];
+        for (i, field) in record.fields.iter().enumerate() {
+            let literal = match &field.default {
+                Some(literal) => literal,
+                None => continue,
+            };
+            let ty = match &self.types[id].kind {
+                TypeDefKind::Record(r) => r.fields[i].ty,
+                _ => unreachable!(),
+            };
+            let default = self.resolve_literal(ty, literal)?;
+            match &mut self.types[id].kind {
+                TypeDefKind::Record(r) => r.fields[i].default = Some(default),
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `literal` is a valid value of type `ty`, which must be a
+    /// primitive type or an alias of one.
+    fn resolve_literal(&self, ty: Type, literal: &super::Literal<'_>) -> Result<Literal> {
+        let err = |msg: String| -> Result<Literal> {
+            Err(Error {
+                span: literal.span,
+                msg,
+            }
+            .into())
+        };
+        let mut ty = ty;
+        let mut seen = HashSet::new();
+        while let Type::Id(id) = ty {
+            match self.types[id].kind {
+                TypeDefKind::Type(t) if seen.insert(id) => ty = t,
+                _ => return err("constants and defaults must have a primitive type".to_string()),
+            }
+        }
+        let name = primitive_name(ty);
+        match (ty, &literal.kind) {
+            (Type::Bool, LiteralKind::Bool(b)) => Ok(Literal::Bool(*b)),
+            (
+                Type::U8
+                | Type::U16
+                | Type::U32
+                | Type::U64
+                | Type::S8
+                | Type::S16
+                | Type::S32
+                | Type::S64,
+                LiteralKind::Number(text),
+            ) => {
+                let value = match parse_integer(text) {
+                    Some(value) => value,
+                    None => return err(format!("invalid integer `{}`", text)),
+                };
+                let (min, max) = match ty {
+                    Type::U8 => (0, u8::MAX.into()),
+                    Type::U16 => (0, u16::MAX.into()),
+                    Type::U32 => (0, u32::MAX.into()),
+                    Type::U64 => (0, u64::MAX.into()),
+                    Type::S8 => (i8::MIN.into(), i8::MAX.into()),
+                    Type::S16 => (i16::MIN.into(), i16::MAX.into()),
+                    Type::S32 => (i32::MIN.into(), i32::MAX.into()),
+                    _ => (i64::MIN.into(), i64::MAX.into()),
+                };
+                if value < min || value > max {
+                    return err(format!("`{}` is out of range for `{}`", text, name));
+                }
+                Ok(Literal::Integer(value))
+            }
+            (Type::Float32 | Type::Float64, LiteralKind::Number(text)) => {
+                let value = match text.replace('_', "").parse::<f64>() {
+                    Ok(value) => value,
+                    Err(_) => return err(format!("invalid number `{}`", text)),
+                };
+                let finite = match ty {
+                    Type::Float32 => (value as f32).is_finite(),
+                    _ => value.is_finite(),
+                };
+                if !finite {
+                    return err(format!("`{}` is out of range for `{}`", text, name));
+                }
+                Ok(Literal::Float(value))
+            }
+            (Type::Char, LiteralKind::String(s)) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(Literal::Char(c)),
+                    _ => err("a `char` must be a string of exactly one character".to_string()),
+                }
+            }
+            (Type::String, LiteralKind::String(s)) => Ok(Literal::String(s.clone())),
+            _ => err(format!("expected a value of type `{}`", name)),
+        }
+    }
+
+    fn resolve_resource(&mut self, resource: &Resource<'_>) -> Result<()> {
+        let id = self.type_lookup[&*resource.name.name];
+        let mut names = HashSet::new();
+        for func in resource.funcs.iter() {
+            if !names.insert(&func.name.name) {
+                return Err(Error {
+                    span: func.name.span,
+                    msg: format!("{:?} defined twice", func.name.name),
+                }
+                .into());
+            }
+            // Every resource has an implicit `drop` function which destroys
+            // it, see `Interface::resource_drop`.
+            if func.name.name == "drop" {
+                return Err(Error {
+                    span: func.name.span,
+                    msg: "`drop` is reserved for the resource's destructor".to_string(),
+                }
+                .into());
+            }
+            let docs = self.docs(&func.docs)?;
+            let mut params = Vec::new();
+            let mut defaults = Vec::new();
+            if func.kind == ResourceFuncKind::Method {
+                let ty = self.anon_type_def(TypeDef {
+                    kind: TypeDefKind::Handle(Handle::Borrow(id)),
+                    name: None,
+                    docs: Docs::default(),
+                    attrs: Attributes::default(),
+                    foreign_module: None,
+                    refinement: None,
+                    span: None,
+                });
+                params.push(("self".to_string(), ty));
+                defaults.push(None);
+            }
+            let (rest, rest_defaults) = self.resolve_params(&func.params)?;
+            params.extend(rest);
+            defaults.extend(rest_defaults);
+            let name = func.name.name.to_string();
+            let (kind, results) = match func.kind {
+                ResourceFuncKind::Method => (
+                    FunctionKind::Method { resource: id, name },
+                    self.resolve_results(&func.results)?,
+                ),
+                ResourceFuncKind::Static => (
+                    FunctionKind::Static { resource: id, name },
+                    self.resolve_results(&func.results)?,
+                ),
+                ResourceFuncKind::Constructor => {
+                    let ty = self.anon_type_def(TypeDef {
+                        kind: TypeDefKind::Handle(Handle::Own(id)),
+                        name: None,
+                        docs: Docs::default(),
+                        attrs: Attributes::default(),
+                        foreign_module: None,
+                        refinement: None,
+                        span: None,
+                    });
+                    (
+                        FunctionKind::Constructor { resource: id },
+                        Results::Anon(ty),
+                    )
+                }
+            };
+            self.validate_results_not_borrowed(func.name.span, &results)?;
+            self.functions.push(Function {
+                docs,
+                attrs: self.attrs(&func.docs)?,
+                name: format!("{}::{}", resource.name.name, func.name.name),
+                kind,
+                is_async: func.is_async,
+                params,
+                defaults,
+                results,
+                span: Some(func.name.span),
+            });
         }
         Ok(())
     }
 
-    fn resolve_params(&mut self, params: &ParamList<'_>) -> Result<Params> {
-        params
-            .iter()
-            .map(|(name, ty)| Ok((name.name.to_string(), self.resolve_type(ty)?)))
-            .collect::<Result<_>>()
+    fn resolve_params(&mut self, params: &ParamList<'_>) -> Result<(Params, Vec<Option<Literal>>)> {
+        let mut types = Vec::new();
+        let mut defaults = Vec::new();
+        for param in params {
+            let ty = self.resolve_type(&param.ty)?;
+            types.push((param.name.name.to_string(), ty));
+            defaults.push(match &param.default {
+                Some(literal) => Some(self.resolve_literal(ty, literal)?),
+                None => None,
+            });
+        }
+        Ok((types, defaults))
     }
 
     fn resolve_results(&mut self, results: &ResultList<'_>) -> Result<Results> {
         match results {
-            ResultList::Named(rs) => Ok(Results::Named(self.resolve_params(rs)?)),
+            ResultList::Named(rs) => Ok(Results::Named(self.resolve_params(rs)?.0)),
             ResultList::Anon(ty) => Ok(Results::Anon(self.resolve_type(ty)?)),
         }
     }
 
+    /// Validates that `results` don't contain any borrowed handles, as a
+    /// borrow is only valid for the duration of a call and so can't outlive
+    /// the call which returns it.
+    fn validate_results_not_borrowed(&self, span: Span, results: &Results) -> Result<()> {
+        let mut visited = HashSet::new();
+        let mut stack = results.iter_types().copied().collect::<Vec<_>>();
+        while let Some(ty) = stack.pop() {
+            let id = match ty {
+                Type::Id(id) if visited.insert(id) => id,
+                _ => continue,
+            };
+            match &self.types[id].kind {
+                TypeDefKind::Handle(Handle::Borrow(_)) => {
+                    return Err(Error {
+                        span,
+                        msg: "borrowed handles cannot be returned from functions".to_string(),
+                    }
+                    .into())
+                }
+                TypeDefKind::Type(t) | TypeDefKind::List(t) | TypeDefKind::Option(t) => {
+                    stack.push(*t)
+                }
+                TypeDefKind::Record(r) => stack.extend(r.fields.iter().map(|f| f.ty)),
+                TypeDefKind::Tuple(t) => stack.extend(t.types.iter().copied()),
+                TypeDefKind::Variant(v) => stack.extend(v.cases.iter().filter_map(|c| c.ty)),
+                TypeDefKind::Union(u) => stack.extend(u.cases.iter().map(|c| c.ty)),
+                TypeDefKind::Result(r) => stack.extend(r.ok.into_iter().chain(r.err)),
+                TypeDefKind::Future(t) => stack.extend(*t),
+                TypeDefKind::Stream(s) => stack.extend(s.element.into_iter().chain(s.end)),
+                TypeDefKind::Flags(_)
+                | TypeDefKind::Enum(_)
+                | TypeDefKind::Resource
+                | TypeDefKind::Handle(Handle::Own(_)) => {}
+            }
+        }
+        Ok(())
+    }
+
     fn validate_type_not_recursive(
         &self,
         span: Span,
@@ -573,13 +1298,15 @@ impl Resolver {
         if !visiting.insert(ty) {
             return Err(Error {
                 span,
-                msg: "type can recursively refer to itself".to_string(),
+                msg: "type can recursively refer to itself without going through a `list` or \
+                      `option`"
+                    .to_string(),
             }
             .into());
         }
 
         match &self.types[ty].kind {
-            TypeDefKind::List(Type::Id(id)) | TypeDefKind::Type(Type::Id(id)) => {
+            TypeDefKind::Type(Type::Id(id)) => {
                 self.validate_type_not_recursive(span, *id, visiting, valid)?
             }
             TypeDefKind::Variant(v) => {
@@ -604,11 +1331,10 @@ impl Resolver {
                 }
             }
 
-            TypeDefKind::Option(t) => {
-                if let Type::Id(id) = *t {
-                    self.validate_type_not_recursive(span, id, visiting, valid)?
-                }
-            }
+            // Lists and options are the types a value may recursively refer
+            // to itself through, as lists are stored out of line and options
+            // are boxed when they're recursive.
+            TypeDefKind::List(_) | TypeDefKind::Option(_) => {}
             TypeDefKind::Result(r) => {
                 if let Some(Type::Id(id)) = r.ok {
                     self.validate_type_not_recursive(span, id, visiting, valid)?
@@ -639,13 +1365,294 @@ impl Resolver {
             }
 
             TypeDefKind::Flags(_)
-            | TypeDefKind::List(_)
             | TypeDefKind::Type(_)
-            | TypeDefKind::Enum(_) => {}
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Handle(_) => {}
         }
 
         valid.insert(ty);
         visiting.remove(&ty);
         Ok(())
     }
+
+    /// Validates that if `ty` is recursive then the recursion goes through a
+    /// record, variant or union, since most languages can't express a type
+    /// alias which refers to itself.
+    fn validate_recursion_is_nominal(&self, span: Span, ty: TypeId) -> Result<()> {
+        let mut visited = HashSet::new();
+        let mut stack = vec![ty];
+        while let Some(id) = stack.pop() {
+            let contained = match &self.types[id].kind {
+                TypeDefKind::Type(t) | TypeDefKind::List(t) | TypeDefKind::Option(t) => vec![*t],
+                TypeDefKind::Tuple(t) => t.types.clone(),
+                TypeDefKind::Result(r) => r.ok.into_iter().chain(r.err).collect(),
+                _ => continue,
+            };
+            for t in contained {
+                if let Type::Id(t) = t {
+                    if t == ty {
+                        return Err(Error {
+                            span,
+                            msg: "type can only refer to itself through a record, variant or union"
+                                .to_string(),
+                        }
+                        .into());
+                    }
+                    if visited.insert(t) {
+                        stack.push(t);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves the items of a file which defines a world.
+///
+/// Such a file may only contain named `interface` blocks, which the world can
+/// refer to, and exactly one `world` item.
+pub(super) fn resolve_world(
+    items: &[Item<'_>],
+    package: Option<&PackageName>,
+    deps: &HashMap<String, Interface>,
+) -> Result<World> {
+    let mut errors = Vec::new();
+    let mut interfaces = HashMap::new();
+    let mut world = None;
+    for item in items {
+        match item {
+            Item::Interface(i) => {
+                let iface =
+                    Resolver::default().resolve(&i.name.name, &i.items, package, deps, &interfaces);
+                let iface = match collect_error(&mut errors, iface)? {
+                    Some(iface) => iface,
+                    None => continue,
+                };
+                if interfaces.insert(i.name.name.to_string(), iface).is_some() {
+                    errors.push(Error {
+                        span: i.name.span,
+                        msg: format!("interface `{}` defined twice", i.name.name),
+                    });
+                }
+            }
+            Item::World(w) => {
+                if world.is_some() {
+                    errors.push(Error {
+                        span: w.name.span,
+                        msg: "only one world may be defined per file".to_string(),
+                    });
+                    continue;
+                }
+                world = Some(w);
+            }
+            Item::Use(u) => errors.push(Error {
+                span: u.from[0].span,
+                msg: "`use` must be placed inside of an interface in a world file".to_string(),
+            }),
+            Item::Include(i) => errors.push(Error {
+                span: i.target.from[0].span,
+                msg: "`include` must be placed inside of an interface in a world file".to_string(),
+            }),
+            Item::TypeDef(super::TypeDef { name, .. })
+            | Item::Value(Value { name, .. })
+            | Item::Resource(Resource { name, .. }) => errors.push(Error {
+                span: name.span,
+                msg: "types and functions must be placed inside of an interface in a world file"
+                    .to_string(),
+            }),
+        }
+    }
+    check_errors(errors)?;
+
+    let w = match world {
+        Some(w) => w,
+        None => {
+            return Err(Error {
+                span: Span { start: 0, end: 0 },
+                msg: "no `world` defined in file".to_string(),
+            }
+            .into())
+        }
+    };
+
+    let resolve_kind = |name: &str, kind: &ExternKind<'_>| -> Result<Interface> {
+        match kind {
+            ExternKind::Id(id) => match interfaces.get(&*id.name) {
+                Some(iface) => Ok(iface.clone()),
+                None => Err(Error {
+                    span: id.span,
+                    msg: format!("no interface named `{}`", id.name),
+                }
+                .into()),
+            },
+            ExternKind::Interface(_, items) => {
+                Resolver::default().resolve(name, items, package, deps, &interfaces)
+            }
+        }
+    };
+
+    let mut ret = World {
+        name: w.name.name.to_string(),
+        docs: Resolver::default().docs(&w.docs)?,
+        ..World::default()
+    };
+    let mut errors = Vec::new();
+    let mut names = HashSet::new();
+    let mut has_default = false;
+    for item in w.items.iter() {
+        match item {
+            WorldItem::Import(import) => {
+                if !names.insert(("import", &*import.name.name)) {
+                    errors.push(Error {
+                        span: import.name.span,
+                        msg: format!("import `{}` defined twice", import.name.name),
+                    });
+                    continue;
+                }
+                let iface = resolve_kind(&import.name.name, &import.kind);
+                if let Some(mut iface) = collect_error(&mut errors, iface)? {
+                    iface.name = import.name.name.to_string();
+                    ret.imports.insert(iface.name.clone(), iface);
+                }
+            }
+            WorldItem::Export(export) => {
+                if !names.insert(("export", &*export.name.name)) {
+                    errors.push(Error {
+                        span: export.name.span,
+                        msg: format!("export `{}` defined twice", export.name.name),
+                    });
+                    continue;
+                }
+                let iface = resolve_kind(&export.name.name, &export.kind);
+                if let Some(mut iface) = collect_error(&mut errors, iface)? {
+                    iface.name = export.name.name.to_string();
+                    iface.module = Some(iface.name.clone());
+                    ret.exports.insert(iface.name.clone(), iface);
+                }
+            }
+            WorldItem::ExportDefault(kind) => {
+                if has_default {
+                    let span = match kind {
+                        ExternKind::Id(id) => id.span,
+                        ExternKind::Interface(span, _) => *span,
+                    };
+                    errors.push(Error {
+                        span,
+                        msg: "default export specified twice".to_string(),
+                    });
+                    continue;
+                }
+                has_default = true;
+                ret.default = collect_error(&mut errors, resolve_kind(&w.name.name, kind))?;
+            }
+        }
+    }
+    check_errors(errors)?;
+    Ok(ret)
+}
+
+/// Adds the included `item`, named `name`, to `items` unless an identical
+/// item is already there, as happens when an interface is included by way of
+/// more than one other.
+///
+/// Returns `false` if a different value of the same name is already defined.
+fn include_item<T: PartialEq>(
+    items: &mut Vec<T>,
+    values: &mut HashSet<String>,
+    name: &str,
+    item: T,
+) -> bool {
+    if values.insert(name.to_string()) {
+        items.push(item);
+        return true;
+    }
+    items.contains(&item)
+}
+
+/// Returns the interface that `u` refers to, which is either one defined
+/// earlier in the same file, as found in `scope`, or a dependency.
+fn lookup_use<'a>(
+    u: &Use<'_>,
+    package: Option<&PackageName>,
+    deps: &'a HashMap<String, Interface>,
+    scope: &'a HashMap<String, Interface>,
+) -> Result<&'a Interface> {
+    let local = match u.package {
+        Some(_) => None,
+        None => scope.get(&*u.from[0].name),
+    };
+    // Every dependency is loaded before resolving other than the interfaces
+    // defined in this same file, so one which is missing is an interface of
+    // this file used before its definition.
+    let mut dep = match local.or_else(|| deps.get(&u.dependency_id(package))) {
+        Some(dep) => dep,
+        None => {
+            return Err(Error {
+                span: u.from[0].span,
+                msg: format!(
+                    "interface `{}` must be defined before it's used",
+                    u.from[0].name
+                ),
+            }
+            .into())
+        }
+    };
+    let mut prev = &*u.from[0].name;
+    for name in u.from[1..].iter() {
+        dep = match dep.interface_lookup.get(&*name.name) {
+            Some(i) => &dep.interfaces[*i],
+            None => {
+                return Err(Error {
+                    span: name.span,
+                    msg: format!("`{}` not defined in `{}`", name.name, prev),
+                }
+                .into())
+            }
+        };
+        prev = &*name.name;
+    }
+    Ok(dep)
+}
+
+/// Returns the name of the primitive type `ty` as it's written in wit.
+fn primitive_name(ty: Type) -> &'static str {
+    match ty {
+        Type::Bool => "bool",
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::S8 => "s8",
+        Type::S16 => "s16",
+        Type::S32 => "s32",
+        Type::S64 => "s64",
+        Type::Float32 => "float32",
+        Type::Float64 => "float64",
+        Type::Char => "char",
+        Type::String => "string",
+        Type::Id(_) => unreachable!(),
+    }
+}
+
+/// Parses the text of a number token as an integer, allowing `_` separators
+/// and `0x`, `0o` and `0b` prefixes.
+fn parse_integer(text: &str) -> Option<i128> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (radix, digits) = match text.get(..2) {
+        Some("0x") => (16, &text[2..]),
+        Some("0o") => (8, &text[2..]),
+        Some("0b") => (2, &text[2..]),
+        _ => (10, text),
+    };
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || digits.starts_with('+') {
+        return None;
+    }
+    let value = i128::from_str_radix(&digits, radix).ok()?;
+    Some(if negative { -value } else { value })
 }
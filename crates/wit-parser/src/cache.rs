@@ -0,0 +1,148 @@
+use crate::{
+    read_file, DependencyGraph, Interface, Loader, PackageName, ParseOptions, Span, World,
+};
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Parsed interfaces shared between the parses of a [`WitCache`], keyed by
+/// the file they were loaded from and the package they belong to, if any.
+pub(crate) type CachedInterfaces = HashMap<(PathBuf, Option<PackageName>), CachedInterface>;
+
+#[derive(Debug)]
+pub(crate) struct CachedInterface {
+    /// The hash of the contents of the file.
+    pub hash: u64,
+    /// The hash of the contents of the file combined with the fingerprints
+    /// of all of its dependencies when it was parsed.
+    pub fingerprint: u64,
+    pub uses: Vec<UseSite>,
+    pub interface: Interface,
+}
+
+/// The interface a `use` statement refers to.
+#[derive(Debug, Clone)]
+pub(crate) struct UseSite {
+    pub package: Option<PackageName>,
+    pub name: String,
+    /// The identifier of the interface, as in [`crate::Dependency::id`].
+    pub id: String,
+    /// The span of the interface's name in the `use` statement.
+    pub span: Span,
+}
+
+pub(crate) fn hash(contents: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A session for parsing many files which `use` the same interfaces.
+///
+/// Every interface loaded while parsing a file is remembered along with a
+/// hash of its contents, so parsing another file which uses it, or the same
+/// file again, only parses it again if it or one of its own dependencies
+/// has changed in its [`WitSources`](crate::WitSources).
+///
+/// The cache also records every file it has read, for build scripts to pass
+/// to `cargo:rerun-if-changed`.
+#[derive(Debug, Default)]
+pub struct WitCache {
+    options: ParseOptions,
+    interfaces: CachedInterfaces,
+    files: BTreeSet<PathBuf>,
+}
+
+impl WitCache {
+    /// Creates an empty cache which resolves `use` statements according to
+    /// `options`.
+    pub fn new(options: ParseOptions) -> WitCache {
+        WitCache {
+            options,
+            ..WitCache::default()
+        }
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Parses the interface in the file at `path` in the same manner as
+    /// [`Interface::parse_file_with`].
+    pub fn parse_file(&mut self, path: impl AsRef<Path>) -> Result<(Interface, DependencyGraph)> {
+        let path = path.as_ref();
+        // Recorded up front so a file which can't be read is still listed.
+        self.files.insert(path.to_path_buf());
+        let contents = read_file(&self.options.sources, path)?;
+        self.parse_contents(path, &contents)
+    }
+
+    /// Parses `contents` as the contents of the interface in the file at
+    /// `path` in the same manner as [`Interface::parse_contents_with`].
+    pub fn parse_contents(
+        &mut self,
+        path: impl AsRef<Path>,
+        contents: &str,
+    ) -> Result<(Interface, DependencyGraph)> {
+        self.parse(path.as_ref(), |loader| {
+            Ok(loader.parse_interface(path.as_ref(), contents, None)?.0)
+        })
+    }
+
+    /// Parses the world in the file at `path` in the same manner as
+    /// [`World::parse_file_with`].
+    pub fn parse_world_file(&mut self, path: impl AsRef<Path>) -> Result<(World, DependencyGraph)> {
+        let path = path.as_ref();
+        self.files.insert(path.to_path_buf());
+        let contents = read_file(&self.options.sources, path)?;
+        self.parse_world_contents(path, &contents)
+    }
+
+    /// Parses `contents` as the contents of the world in the file at `path`
+    /// in the same manner as [`World::parse_contents_with`].
+    pub fn parse_world_contents(
+        &mut self,
+        path: impl AsRef<Path>,
+        contents: &str,
+    ) -> Result<(World, DependencyGraph)> {
+        self.parse(path.as_ref(), |loader| {
+            let (world, _) =
+                loader.parse(path.as_ref(), contents, None, |_name, package, ast, map| {
+                    ast.resolve_world(package, map)
+                })?;
+            Ok(world)
+        })
+    }
+
+    /// Returns every file read by the parses of this cache so far, sorted.
+    ///
+    /// This includes the files given to `parse_*` as well as every
+    /// dependency loaded for them, even by parses which failed.
+    pub fn files(&self) -> impl Iterator<Item = &Path> + '_ {
+        self.files.iter().map(|p| p.as_path())
+    }
+
+    /// Runs `parse` with a loader for the root file `path` which shares
+    /// this cache, recording the files it reads whether or not it succeeds.
+    fn parse<T>(
+        &mut self,
+        path: &Path,
+        parse: impl FnOnce(&mut Loader<'_>) -> Result<T>,
+    ) -> Result<(T, DependencyGraph)> {
+        self.files.insert(path.to_path_buf());
+        let parent = path.parent().unwrap();
+        let options = &self.options;
+        let files = &mut self.files;
+        let mut load = |package: Option<&PackageName>, name: &str| {
+            let (path, contents) = options.load(parent, package, name)?;
+            files.insert(path.clone());
+            Ok((path, contents))
+        };
+        let mut loader = Loader::new(&mut load, Some(&mut self.interfaces));
+        let result = parse(&mut loader);
+        let graph = DependencyGraph::new(loader.nodes);
+        Ok((result?, graph))
+    }
+}
@@ -0,0 +1,739 @@
+//! Checking whether a new version of an interface is compatible with an old
+//! one.
+//!
+//! Changes are judged from the point of view of a component which imports
+//! the old version of an interface and is then linked against an
+//! implementation of the new version. A change is breaking if such a
+//! component may no longer link, or may be handed values it doesn't know how
+//! to interpret.
+
+use crate::abi::AbiVariant;
+use crate::{
+    Function, FunctionKind, Handle, Interface, Literal, Params, Results, SizeAlign, Type,
+    TypeDefKind, TypeId,
+};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Whether a [`Change`] breaks components built against the old version of
+/// an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Compatibility {
+    Compatible,
+    Breaking,
+}
+
+impl Compatibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compatibility::Compatible => "compatible",
+            Compatibility::Breaking => "breaking",
+        }
+    }
+}
+
+/// A difference between two versions of an interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub compatibility: Compatibility,
+    /// The item which changed, such as ``function `foo` ``.
+    pub item: String,
+    pub message: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.compatibility.as_str(),
+            self.item,
+            self.message
+        )
+    }
+}
+
+/// All of the differences between two versions of an interface, as returned
+/// by [`compare`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub changes: Vec<Change>,
+}
+
+impl Changes {
+    pub fn is_breaking(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|c| c.compatibility == Compatibility::Breaking)
+    }
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the `old` and `new` versions of an interface, returning every
+/// change between them along with whether it's breaking.
+///
+/// Types, functions, globals and constants are matched up by name.
+/// Documentation is ignored.
+///
+/// Constants and default values are compiled into the bindings on either
+/// side rather than passed between components, so changes to them are
+/// never breaking.
+pub fn compare(old: &Interface, new: &Interface) -> Changes {
+    let mut old_sizes = SizeAlign::default();
+    old_sizes.fill(old);
+    let mut new_sizes = SizeAlign::default();
+    new_sizes.fill(new);
+    let mut cx = Compare {
+        old,
+        new,
+        old_sizes,
+        new_sizes,
+        returned: HashSet::new(),
+        changes: Vec::new(),
+    };
+    for func in old.functions.iter() {
+        for ty in func.results.iter_types() {
+            cx.mark_returned(ty);
+        }
+    }
+    for global in old.globals.iter() {
+        cx.mark_returned(&global.ty);
+    }
+    cx.types();
+    cx.functions();
+    cx.globals();
+    cx.constants();
+    Changes {
+        changes: cx.changes,
+    }
+}
+
+struct Compare<'a> {
+    old: &'a Interface,
+    new: &'a Interface,
+    old_sizes: SizeAlign,
+    new_sizes: SizeAlign,
+    /// The types of `old` which may be handed to a component importing it,
+    /// as they're reachable from a function result or global.
+    returned: HashSet<TypeId>,
+    changes: Vec<Change>,
+}
+
+impl Compare<'_> {
+    fn compatible(&mut self, item: &str, message: impl Into<String>) {
+        self.push(Compatibility::Compatible, item, message.into());
+    }
+
+    fn breaking(&mut self, item: &str, message: impl Into<String>) {
+        self.push(Compatibility::Breaking, item, message.into());
+    }
+
+    fn push(&mut self, compatibility: Compatibility, item: &str, message: String) {
+        self.changes.push(Change {
+            compatibility,
+            item: item.to_string(),
+            message,
+        });
+    }
+
+    fn mark_returned(&mut self, ty: &Type) {
+        let id = match ty {
+            Type::Id(id) => *id,
+            _ => return,
+        };
+        if !self.returned.insert(id) {
+            return;
+        }
+        match &self.old.types[id].kind {
+            TypeDefKind::Record(r) => {
+                for field in r.fields.iter() {
+                    self.mark_returned(&field.ty);
+                }
+            }
+            TypeDefKind::Tuple(t) => {
+                for ty in t.types.iter() {
+                    self.mark_returned(ty);
+                }
+            }
+            TypeDefKind::Variant(v) => {
+                for ty in v.cases.iter().filter_map(|c| c.ty.as_ref()) {
+                    self.mark_returned(ty);
+                }
+            }
+            TypeDefKind::Union(u) => {
+                for case in u.cases.iter() {
+                    self.mark_returned(&case.ty);
+                }
+            }
+            TypeDefKind::Result(r) => {
+                for ty in r.ok.iter().chain(&r.err) {
+                    self.mark_returned(ty);
+                }
+            }
+            TypeDefKind::Stream(s) => {
+                for ty in s.element.iter().chain(&s.end) {
+                    self.mark_returned(ty);
+                }
+            }
+            TypeDefKind::Future(Some(ty))
+            | TypeDefKind::Option(ty)
+            | TypeDefKind::List(ty)
+            | TypeDefKind::Type(ty) => self.mark_returned(ty),
+            TypeDefKind::Handle(h) => self.mark_returned(&Type::Id(h.resource())),
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Future(None)
+            | TypeDefKind::Resource => {}
+        }
+    }
+
+    fn types(&mut self) {
+        for (old_id, def) in self.old.types.iter() {
+            let name = match &def.name {
+                Some(name) => name,
+                None => continue,
+            };
+            let item = format!("type `{}`", name);
+            match self.new.type_lookup.get(name) {
+                Some(new_id) => self.typedef(&item, old_id, *new_id),
+                None => self.breaking(&item, "removed"),
+            }
+        }
+        for (_, def) in self.new.types.iter() {
+            if let Some(name) = &def.name {
+                if !self.old.type_lookup.contains_key(name) {
+                    self.compatible(&format!("type `{}`", name), "added");
+                }
+            }
+        }
+    }
+
+    fn typedef(&mut self, item: &str, old_id: TypeId, new_id: TypeId) {
+        let (old, new) = (self.old, self.new);
+        // Either side may rely on values being within the old refinement, so
+        // any change to it breaks someone.
+        let refinements = (&old.types[old_id].refinement, &new.types[new_id].refinement);
+        let message = match refinements {
+            (Some(a), Some(b)) if a != b => Some(format!(
+                "refinement changed from `where {}` to `where {}`",
+                a, b
+            )),
+            (Some(a), None) => Some(format!("refinement `where {}` removed", a)),
+            (None, Some(b)) => Some(format!("refinement `where {}` added", b)),
+            _ => None,
+        };
+        if let Some(message) = message {
+            self.breaking(item, message);
+        }
+        match (&old.types[old_id].kind, &new.types[new_id].kind) {
+            (TypeDefKind::Record(a), TypeDefKind::Record(b)) => {
+                // Records have a fixed layout, so adding a field is as much of
+                // a problem as removing one.
+                let a_names = a.fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+                let b_names = b.fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+                let extension = Some("which changes the layout of the record");
+                self.members(item, "field", &a_names, &b_names, extension);
+                for fa in a.fields.iter() {
+                    if let Some(fb) = b.fields.iter().find(|f| f.name == fa.name) {
+                        if !self.same(&fa.ty, &fb.ty) {
+                            let message = format!(
+                                "field `{}` changed type from `{}` to `{}`",
+                                fa.name,
+                                describe(old, &fa.ty),
+                                describe(new, &fb.ty),
+                            );
+                            self.breaking(item, message);
+                        }
+                        let what = format!("field `{}`", fa.name);
+                        self.default(item, &what, fa.default.as_ref(), fb.default.as_ref());
+                    }
+                }
+            }
+            (TypeDefKind::Flags(a), TypeDefKind::Flags(b)) => {
+                let a_names = a.flags.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+                let b_names = b.flags.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+                let extension = self.extension(old_id, new_id);
+                self.members(item, "flag", &a_names, &b_names, extension);
+            }
+            (TypeDefKind::Enum(a), TypeDefKind::Enum(b)) => {
+                let a_names = a.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+                let b_names = b.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+                let extension = self.extension(old_id, new_id);
+                self.members(item, "case", &a_names, &b_names, extension);
+            }
+            (TypeDefKind::Variant(a), TypeDefKind::Variant(b)) => {
+                let a_names = a.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+                let b_names = b.cases.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+                let extension = self.extension(old_id, new_id);
+                self.members(item, "case", &a_names, &b_names, extension);
+                for ca in a.cases.iter() {
+                    if let Some(cb) = b.cases.iter().find(|c| c.name == ca.name) {
+                        if !self.same_optional(ca.ty.as_ref(), cb.ty.as_ref()) {
+                            let message = format!(
+                                "case `{}` changed payload from `{}` to `{}`",
+                                ca.name,
+                                describe_optional(old, ca.ty.as_ref()),
+                                describe_optional(new, cb.ty.as_ref()),
+                            );
+                            self.breaking(item, message);
+                        }
+                    }
+                }
+            }
+            (TypeDefKind::Union(a), TypeDefKind::Union(b)) => {
+                for (i, (ca, cb)) in a.cases.iter().zip(&b.cases).enumerate() {
+                    if !self.same(&ca.ty, &cb.ty) {
+                        let message = format!(
+                            "case {} changed type from `{}` to `{}`",
+                            i,
+                            describe(old, &ca.ty),
+                            describe(new, &cb.ty),
+                        );
+                        self.breaking(item, message);
+                    }
+                }
+                for case in a.cases.iter().skip(b.cases.len()) {
+                    let message = format!("case `{}` removed", describe(old, &case.ty));
+                    self.breaking(item, message);
+                }
+                let extension = self.extension(old_id, new_id);
+                for case in b.cases.iter().skip(a.cases.len()) {
+                    let added = format!("case `{}` added", describe(new, &case.ty));
+                    self.added(item, added, extension);
+                }
+            }
+            (TypeDefKind::Resource, TypeDefKind::Resource) => {}
+            (a, b) => {
+                if !self.same_kind(a, b) {
+                    let message = format!(
+                        "changed from `{}` to `{}`",
+                        describe_kind(old, a),
+                        describe_kind(new, b),
+                    );
+                    self.breaking(item, message);
+                }
+            }
+        }
+    }
+
+    /// Returns why adding flags or cases to the end of the type `old_id` is
+    /// breaking, if it is.
+    ///
+    /// Components built against the old version only ever pass the flags
+    /// and cases they know of, so adding more is fine as long as the layout
+    /// of the type is unchanged and the new ones are never handed back to
+    /// such components.
+    fn extension(&self, old_id: TypeId, new_id: TypeId) -> Option<&'static str> {
+        let (old, new) = (Type::Id(old_id), Type::Id(new_id));
+        if self.returned.contains(&old_id) {
+            Some("which may be returned to components which don't know of it")
+        } else if self.old_sizes.size(&old) != self.new_sizes.size(&new)
+            || self.old_sizes.align(&old) != self.new_sizes.align(&new)
+        {
+            Some("which changes the size of the type")
+        } else {
+            None
+        }
+    }
+
+    /// Reports the changes between the `old` and `new` names of the fields,
+    /// flags or cases of a type.
+    ///
+    /// `extension` is why adding to the end of the list is breaking, if it
+    /// is.
+    fn members(
+        &mut self,
+        item: &str,
+        what: &str,
+        old: &[&str],
+        new: &[&str],
+        extension: Option<&str>,
+    ) {
+        // A name which is replaced by an unknown name at the same position
+        // is considered renamed, rather than one being removed and another
+        // added.
+        let renamed = |i: usize| match (old.get(i), new.get(i)) {
+            (Some(a), Some(b)) => !new.contains(a) && !old.contains(b),
+            _ => false,
+        };
+        for (i, name) in old.iter().enumerate() {
+            match new.iter().position(|n| n == name) {
+                Some(j) if i == j => {}
+                Some(_) => self.breaking(item, format!("{} `{}` moved", what, name)),
+                None if renamed(i) => {
+                    let message = format!("{} `{}` renamed to `{}`", what, name, new[i]);
+                    self.breaking(item, message);
+                }
+                None => self.breaking(item, format!("{} `{}` removed", what, name)),
+            }
+        }
+        for (i, name) in new.iter().enumerate() {
+            if old.contains(name) || renamed(i) {
+                continue;
+            }
+            let added = format!("{} `{}` added", what, name);
+            if i < old.len() {
+                self.breaking(item, added);
+            } else {
+                self.added(item, added, extension);
+            }
+        }
+    }
+
+    fn added(&mut self, item: &str, added: String, extension: Option<&str>) {
+        match extension {
+            Some(reason) => self.breaking(item, format!("{}, {}", added, reason)),
+            None => self.compatible(item, added),
+        }
+    }
+
+    fn functions(&mut self) {
+        let (old, new) = (self.old, self.new);
+        for a in old.functions.iter() {
+            let item = format!("function `{}`", a.name);
+            let b = match new.functions.iter().find(|f| f.name == a.name) {
+                Some(b) => b,
+                None => {
+                    self.breaking(&item, "removed");
+                    continue;
+                }
+            };
+            let before = self.changes.len();
+            self.function(&item, a, b);
+            let breaking = self.changes[before..]
+                .iter()
+                .any(|c| c.compatibility == Compatibility::Breaking);
+
+            // Changes to the types used by the function, which are reported
+            // along with those types, may still change how it's called.
+            if !breaking
+                && old.wasm_signature(AbiVariant::GuestImport, a)
+                    != new.wasm_signature(AbiVariant::GuestImport, b)
+            {
+                self.breaking(&item, "changed its canonical ABI signature");
+            }
+        }
+        for f in new.functions.iter() {
+            if !old.functions.iter().any(|g| g.name == f.name) {
+                self.compatible(&format!("function `{}`", f.name), "added");
+            }
+        }
+    }
+
+    fn function(&mut self, item: &str, a: &Function, b: &Function) {
+        let same_kind = match (&a.kind, &b.kind) {
+            (FunctionKind::Freestanding, FunctionKind::Freestanding) => true,
+            (
+                FunctionKind::Method { resource: x, .. },
+                FunctionKind::Method { resource: y, .. },
+            )
+            | (
+                FunctionKind::Static { resource: x, .. },
+                FunctionKind::Static { resource: y, .. },
+            )
+            | (
+                FunctionKind::Constructor { resource: x },
+                FunctionKind::Constructor { resource: y },
+            ) => self.same(&Type::Id(*x), &Type::Id(*y)),
+            _ => false,
+        };
+        if !same_kind {
+            let message = format!(
+                "changed from {} to {}",
+                describe_function_kind(&a.kind),
+                describe_function_kind(&b.kind),
+            );
+            self.breaking(item, message);
+        }
+
+        self.params(item, "parameter", &a.params, &b.params);
+        if a.params.len() == b.params.len() {
+            for ((name, _), (x, y)) in a.params.iter().zip(a.defaults.iter().zip(&b.defaults)) {
+                let what = format!("parameter `{}`", name);
+                self.default(item, &what, x.as_ref(), y.as_ref());
+            }
+        }
+        match (&a.results, &b.results) {
+            (Results::Named(x), Results::Named(y)) => self.params(item, "result", x, y),
+            (Results::Anon(x), Results::Anon(y)) => {
+                if !self.same(x, y) {
+                    let message = format!(
+                        "result changed type from `{}` to `{}`",
+                        describe(self.old, x),
+                        describe(self.new, y),
+                    );
+                    self.breaking(item, message);
+                }
+            }
+            (x, y) => {
+                let message = format!(
+                    "results changed from `{}` to `{}`",
+                    describe_results(self.old, x),
+                    describe_results(self.new, y),
+                );
+                self.breaking(item, message);
+            }
+        }
+
+        // Async functions share the canonical ABI of synchronous ones.
+        match (a.is_async, b.is_async) {
+            (false, true) => self.compatible(item, "became async"),
+            (true, false) => self.compatible(item, "no longer async"),
+            _ => {}
+        }
+    }
+
+    fn params(&mut self, item: &str, what: &str, a: &Params, b: &Params) {
+        if a.len() != b.len() {
+            let message = format!(
+                "{}s changed from `({})` to `({})`",
+                what,
+                describe_params(self.old, a),
+                describe_params(self.new, b),
+            );
+            self.breaking(item, message);
+            return;
+        }
+        for ((x_name, x), (y_name, y)) in a.iter().zip(b) {
+            if x_name != y_name {
+                let message = format!("{} `{}` renamed to `{}`", what, x_name, y_name);
+                self.breaking(item, message);
+            }
+            if !self.same(x, y) {
+                let message = format!(
+                    "{} `{}` changed type from `{}` to `{}`",
+                    what,
+                    x_name,
+                    describe(self.old, x),
+                    describe(self.new, y),
+                );
+                self.breaking(item, message);
+            }
+        }
+    }
+
+    fn default(&mut self, item: &str, what: &str, a: Option<&Literal>, b: Option<&Literal>) {
+        let message = match (a, b) {
+            (Some(x), Some(y)) if x != y => {
+                format!("{} default changed from `{}` to `{}`", what, x, y)
+            }
+            (Some(x), None) => format!("{} default of `{}` removed", what, x),
+            (None, Some(y)) => format!("{} default of `{}` added", what, y),
+            _ => return,
+        };
+        self.compatible(item, message);
+    }
+
+    fn constants(&mut self) {
+        let (old, new) = (self.old, self.new);
+        for a in old.constants.iter() {
+            let item = format!("constant `{}`", a.name);
+            match new.constants.iter().find(|c| c.name == a.name) {
+                Some(b) => {
+                    if !self.same(&a.ty, &b.ty) {
+                        let message = format!(
+                            "changed type from `{}` to `{}`",
+                            describe(old, &a.ty),
+                            describe(new, &b.ty),
+                        );
+                        self.compatible(&item, message);
+                    } else if a.value != b.value {
+                        let message = format!("changed from `{}` to `{}`", a.value, b.value);
+                        self.compatible(&item, message);
+                    }
+                }
+                None => self.compatible(&item, "removed"),
+            }
+        }
+        for c in new.constants.iter() {
+            if !old.constants.iter().any(|a| a.name == c.name) {
+                self.compatible(&format!("constant `{}`", c.name), "added");
+            }
+        }
+    }
+
+    fn globals(&mut self) {
+        let (old, new) = (self.old, self.new);
+        for a in old.globals.iter() {
+            let item = format!("global `{}`", a.name);
+            match new.globals.iter().find(|g| g.name == a.name) {
+                Some(b) => {
+                    if !self.same(&a.ty, &b.ty) {
+                        let message = format!(
+                            "changed type from `{}` to `{}`",
+                            describe(old, &a.ty),
+                            describe(new, &b.ty),
+                        );
+                        self.breaking(&item, message);
+                    }
+                }
+                None => self.breaking(&item, "removed"),
+            }
+        }
+        for g in new.globals.iter() {
+            if !old.globals.iter().any(|a| a.name == g.name) {
+                self.compatible(&format!("global `{}`", g.name), "added");
+            }
+        }
+    }
+
+    /// Returns whether `a`, a type of the old interface, is the same as `b`,
+    /// a type of the new one.
+    ///
+    /// Named types are the same if their names are, as changes to their
+    /// definitions are reported separately.
+    fn same(&self, a: &Type, b: &Type) -> bool {
+        match (a, b) {
+            (Type::Id(a), Type::Id(b)) => {
+                let (a, b) = (&self.old.types[*a], &self.new.types[*b]);
+                match (&a.name, &b.name) {
+                    (Some(x), Some(y)) => x == y,
+                    (None, None) => self.same_kind(&a.kind, &b.kind),
+                    _ => false,
+                }
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    fn same_optional(&self, a: Option<&Type>, b: Option<&Type>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => self.same(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn same_kind(&self, a: &TypeDefKind, b: &TypeDefKind) -> bool {
+        match (a, b) {
+            (TypeDefKind::Type(a), TypeDefKind::Type(b))
+            | (TypeDefKind::List(a), TypeDefKind::List(b))
+            | (TypeDefKind::Option(a), TypeDefKind::Option(b)) => self.same(a, b),
+            (TypeDefKind::Tuple(a), TypeDefKind::Tuple(b)) => {
+                a.types.len() == b.types.len()
+                    && a.types.iter().zip(&b.types).all(|(a, b)| self.same(a, b))
+            }
+            (TypeDefKind::Result(a), TypeDefKind::Result(b)) => {
+                self.same_optional(a.ok.as_ref(), b.ok.as_ref())
+                    && self.same_optional(a.err.as_ref(), b.err.as_ref())
+            }
+            (TypeDefKind::Future(a), TypeDefKind::Future(b)) => {
+                self.same_optional(a.as_ref(), b.as_ref())
+            }
+            (TypeDefKind::Stream(a), TypeDefKind::Stream(b)) => {
+                self.same_optional(a.element.as_ref(), b.element.as_ref())
+                    && self.same_optional(a.end.as_ref(), b.end.as_ref())
+            }
+            (TypeDefKind::Handle(Handle::Own(a)), TypeDefKind::Handle(Handle::Own(b)))
+            | (TypeDefKind::Handle(Handle::Borrow(a)), TypeDefKind::Handle(Handle::Borrow(b))) => {
+                self.same(&Type::Id(*a), &Type::Id(*b))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Returns how `ty` is written in a wit file.
+pub(crate) fn describe(iface: &Interface, ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::Float32 => "float32".to_string(),
+        Type::Float64 => "float64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::Id(id) => match &iface.types[*id].name {
+            Some(name) => name.clone(),
+            None => describe_kind(iface, &iface.types[*id].kind),
+        },
+    }
+}
+
+fn describe_optional(iface: &Interface, ty: Option<&Type>) -> String {
+    match ty {
+        Some(ty) => describe(iface, ty),
+        None => "_".to_string(),
+    }
+}
+
+fn describe_kind(iface: &Interface, kind: &TypeDefKind) -> String {
+    let optional = |ty: Option<&Type>| describe_optional(iface, ty);
+    match kind {
+        TypeDefKind::Type(ty) => describe(iface, ty),
+        TypeDefKind::List(ty) => format!("list<{}>", describe(iface, ty)),
+        TypeDefKind::Option(ty) => format!("option<{}>", describe(iface, ty)),
+        TypeDefKind::Tuple(t) => {
+            let types = t.types.iter().map(|ty| describe(iface, ty));
+            format!("tuple<{}>", types.collect::<Vec<_>>().join(", "))
+        }
+        TypeDefKind::Result(r) => match (&r.ok, &r.err) {
+            (None, None) => "result".to_string(),
+            (ok, None) => format!("result<{}>", optional(ok.as_ref())),
+            (ok, err) => format!(
+                "result<{}, {}>",
+                optional(ok.as_ref()),
+                optional(err.as_ref())
+            ),
+        },
+        TypeDefKind::Future(None) => "future".to_string(),
+        TypeDefKind::Future(Some(ty)) => format!("future<{}>", describe(iface, ty)),
+        TypeDefKind::Stream(s) => match (&s.element, &s.end) {
+            (None, None) => "stream".to_string(),
+            (element, None) => format!("stream<{}>", optional(element.as_ref())),
+            (element, end) => format!(
+                "stream<{}, {}>",
+                optional(element.as_ref()),
+                optional(end.as_ref())
+            ),
+        },
+        TypeDefKind::Handle(Handle::Own(id)) => describe(iface, &Type::Id(*id)),
+        TypeDefKind::Handle(Handle::Borrow(id)) => {
+            format!("borrow<{}>", describe(iface, &Type::Id(*id)))
+        }
+        TypeDefKind::Record(_) => "record".to_string(),
+        TypeDefKind::Flags(_) => "flags".to_string(),
+        TypeDefKind::Variant(_) => "variant".to_string(),
+        TypeDefKind::Enum(_) => "enum".to_string(),
+        TypeDefKind::Union(_) => "union".to_string(),
+        TypeDefKind::Resource => "resource".to_string(),
+    }
+}
+
+fn describe_params(iface: &Interface, params: &Params) -> String {
+    params
+        .iter()
+        .map(|(name, ty)| format!("{}: {}", name, describe(iface, ty)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn describe_results(iface: &Interface, results: &Results) -> String {
+    match results {
+        Results::Named(params) => format!("({})", describe_params(iface, params)),
+        Results::Anon(ty) => describe(iface, ty),
+    }
+}
+
+fn describe_function_kind(kind: &FunctionKind) -> &'static str {
+    match kind {
+        FunctionKind::Freestanding => "a freestanding function",
+        FunctionKind::Method { .. } => "a method",
+        FunctionKind::Static { .. } => "a static function",
+        FunctionKind::Constructor { .. } => "a constructor",
+    }
+}
@@ -0,0 +1,307 @@
+use crate::Span;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A message about a location within a wit file, such as a parse error.
+///
+/// Diagnostics are rendered in the style of `rustc`, with the source line of
+/// each location shown alongside the message:
+///
+/// ```text
+/// error: no type named `foo`
+///      --> example.wit:3:10
+///       |
+///     3 | type x = foo
+///       |          ^^^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The file the diagnostic is reported in.
+    pub file: PathBuf,
+    /// The primary location, in bytes within the contents of `file`, that the
+    /// diagnostic is about.
+    pub span: Span,
+    /// Secondary locations within `file` relevant to the diagnostic.
+    pub labels: Vec<Label>,
+    /// Extra information shown after the source snippets.
+    pub notes: Vec<String>,
+    source: Arc<str>,
+}
+
+/// A secondary location attached to a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A line and column within a file, both starting at 1.
+///
+/// Columns are measured in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Diagnostic {
+    /// Creates a new error at `span` within `source`, the contents of
+    /// `file`.
+    pub fn error(
+        file: impl AsRef<Path>,
+        source: &str,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            file: file.as_ref().to_path_buf(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            source: source.into(),
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Returns the contents of the file this diagnostic was reported in.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the line and column of the byte offset `pos` within this
+    /// diagnostic's file.
+    pub fn location(&self, pos: u32) -> Location {
+        let pos = pos as usize;
+        let mut cur = 0;
+        // Use split_terminator instead of lines so that if there is a `\r`,
+        // it is included in the offset calculation. The `+1` values below
+        // account for the `\n`.
+        for (i, line) in self.source.split_terminator('\n').enumerate() {
+            if cur + line.len() + 1 > pos {
+                return Location {
+                    line: i + 1,
+                    column: pos - cur + 1,
+                };
+            }
+            cur += line.len() + 1;
+        }
+        Location {
+            line: self.source.lines().count() + 1,
+            column: 1,
+        }
+    }
+
+    fn render(&self, dst: &mut String) {
+        let start = self.location(self.span.start);
+        write!(
+            dst,
+            "{}: {}\n     --> {}:{}:{}\n      |",
+            self.severity.as_str(),
+            self.message,
+            self.file.display(),
+            start.line,
+            start.column,
+        )
+        .unwrap();
+        let mut snippets = vec![(self.span, '^', "")];
+        for label in self.labels.iter() {
+            snippets.push((label.span, '-', &label.message));
+        }
+        snippets.sort_by_key(|(span, ..)| span.start);
+        for (span, marker, message) in snippets {
+            self.render_snippet(dst, span, marker, message);
+        }
+        for note in self.notes.iter() {
+            write!(dst, "\n      = note: {}", note).unwrap();
+        }
+    }
+
+    /// Renders the line containing `span` followed by a line underlining
+    /// `span` with `marker` and then `message`.
+    fn render_snippet(&self, dst: &mut String, span: Span, marker: char, message: &str) {
+        let start = self.location(span.start);
+        let snippet = self.source.lines().nth(start.line - 1).unwrap_or("");
+        let width = self
+            .source
+            .get(span.start as usize..span.end as usize)
+            .and_then(|s| s.lines().next())
+            .map(|s| s.chars().count())
+            .unwrap_or(0)
+            .max(1);
+        let indent = snippet
+            .get(..start.column - 1)
+            .map(|s| s.chars().count())
+            .unwrap_or(start.column - 1);
+        write!(
+            dst,
+            "\n {:4} | {}\n      | {}{}",
+            start.line,
+            snippet,
+            " ".repeat(indent),
+            marker.to_string().repeat(width),
+        )
+        .unwrap();
+        if !message.is_empty() {
+            write!(dst, " {}", message).unwrap();
+        }
+    }
+
+    fn render_json(&self, dst: &mut String) {
+        dst.push('{');
+        write!(dst, "\"severity\":\"{}\",", self.severity.as_str()).unwrap();
+        dst.push_str("\"message\":");
+        json_str(dst, &self.message);
+        dst.push_str(",\"file\":");
+        json_str(dst, &self.file.display().to_string());
+        dst.push_str(",\"range\":");
+        self.render_json_range(dst, self.span);
+        dst.push_str(",\"labels\":[");
+        for (i, label) in self.labels.iter().enumerate() {
+            if i > 0 {
+                dst.push(',');
+            }
+            dst.push_str("{\"message\":");
+            json_str(dst, &label.message);
+            dst.push_str(",\"range\":");
+            self.render_json_range(dst, label.span);
+            dst.push('}');
+        }
+        dst.push_str("],\"notes\":[");
+        for (i, note) in self.notes.iter().enumerate() {
+            if i > 0 {
+                dst.push(',');
+            }
+            json_str(dst, note);
+        }
+        dst.push_str("]}");
+    }
+
+    fn render_json_range(&self, dst: &mut String, span: Span) {
+        let start = self.location(span.start);
+        let end = self.location(span.end);
+        write!(
+            dst,
+            "{{\"start\":{{\"offset\":{},\"line\":{},\"column\":{}}},\
+             \"end\":{{\"offset\":{},\"line\":{},\"column\":{}}}}}",
+            span.start, start.line, start.column, span.end, end.line, end.column,
+        )
+        .unwrap();
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        self.render(&mut s);
+        f.write_str(&s)
+    }
+}
+
+/// All of the diagnostics produced while parsing a file.
+///
+/// This is the error returned by parsing functions such as
+/// [`Interface::parse_file`](crate::Interface::parse_file) when a file
+/// doesn't parse, and may be retrieved from the returned `anyhow::Error`
+/// with `downcast_ref`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+
+    /// Renders these diagnostics as a JSON array for consumption by editors
+    /// and other tools.
+    ///
+    /// Each element is an object with `severity`, `message`, `file`,
+    /// `range`, `labels` and `notes` fields. Ranges have a `start` and `end`
+    /// which each hold a byte `offset` as well as a `line` and `column`, both
+    /// starting at 1.
+    pub fn to_json(&self) -> String {
+        let mut dst = String::from("[");
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                dst.push(',');
+            }
+            diagnostic.render_json(&mut dst);
+        }
+        dst.push(']');
+        dst
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(diagnostic: Diagnostic) -> Diagnostics {
+        Diagnostics {
+            diagnostics: vec![diagnostic],
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n\n")?;
+            }
+            diagnostic.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+fn json_str(dst: &mut String, s: &str) {
+    dst.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => dst.push_str("\\\""),
+            '\\' => dst.push_str("\\\\"),
+            '\n' => dst.push_str("\\n"),
+            '\r' => dst.push_str("\\r"),
+            '\t' => dst.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(dst, "\\u{:04x}", c as u32).unwrap(),
+            c => dst.push(c),
+        }
+    }
+    dst.push('"');
+}
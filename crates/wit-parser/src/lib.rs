@@ -1,14 +1,31 @@
 use anyhow::{anyhow, bail, Context, Result};
 use id_arena::{Arena, Id};
-use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag};
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use indexmap::IndexMap;
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
 use std::path::{Path, PathBuf};
 
 pub mod abi;
-mod ast;
+pub mod ast;
+mod cache;
+pub mod compat;
+mod diagnostics;
+mod packages;
+#[cfg(feature = "serde")]
+mod serialize;
 mod sizealign;
+mod sources;
+pub use ast::Span;
+pub use cache::WitCache;
+use cache::{CachedInterface, CachedInterfaces, UseSite};
+pub use diagnostics::*;
+pub use packages::*;
 pub use sizealign::*;
+pub use sources::WitSources;
 
 /// Checks if the given string is a legal identifier in wit.
 pub fn validate_id(s: &str) -> Result<()> {
@@ -16,7 +33,46 @@ pub fn validate_id(s: &str) -> Result<()> {
     Ok(())
 }
 
+/// A parsed and resolved interface.
+///
+/// # JSON
+///
+/// With the `serde` feature enabled an interface can be serialized, and its
+/// JSON representation is kept stable for tools written in other languages:
+///
+/// * `types` lists every type, named or anonymous, where the type with id
+///   `n` is at index `n`. Each has `docs`, `kind`, `name` and
+///   `foreign_module`, the latter two being `null` if unset. Aliases with a
+///   `where` clause also have a `refinement`, such as
+///   `{"range": {"start": {"integer": 1}, "end": null, "inclusive": false}}`
+///   or `{"length": {...}}`.
+/// * A type reference is either the name of a primitive, such as `"u32"` or
+///   `"string"`, or `{"id": n}` referring to an entry of `types`.
+/// * A `kind` is `"resource"` or an object with a single key naming the
+///   kind, such as `{"list": <type>}`, `{"option": <type>}`,
+///   `{"type": <type>}` for aliases, `{"record": {"fields": [...]}}` or
+///   `{"handle": {"own": n}}`. Fields and cases have `docs`, `name` and
+///   `type`, and optional types are `null` when absent. Fields also have a
+///   `default` literal, or `null`.
+/// * `functions` have `docs`, `name`, `kind`, `async`, `params`, `defaults`
+///   and `results`. `params` is a list of `[name, type]` pairs, `defaults`
+///   holds a literal or `null` for each parameter and `results` is either
+///   `{"named": [[name, type], ...]}` or `{"anon": <type>}`. A `kind` is
+///   `"freestanding"`, `{"method": {"resource": n, "name": ...}}`,
+///   `{"static": {...}}` or `{"constructor": {"resource": n}}`.
+/// * `constants` have `docs`, `name`, `type` and `value`.
+/// * A literal is an object with a single key naming its kind: `bool`,
+///   `integer`, `float`, `char` or `string`, such as `{"integer": 4096}`.
+/// * `docs` are a string, or `null` if there are none.
+/// * Types, fields, cases and functions have `attrs` if any attributes were
+///   written on them, which lists objects with a `name` and `args`, each
+///   arg having a `key`, or `null`, and a literal `value`. Deprecation and
+///   `@since` versions are only found there, rather than in `docs`.
+///
+/// The `type_lookup` and `interface_lookup` maps are left out, as they can
+/// be rebuilt from the names of `types` and `interfaces`.
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Interface {
     pub name: String,
     /// The module name to use for bindings generation.
@@ -27,44 +83,126 @@ pub struct Interface {
     /// name of `<module>#<name>` for exports or an import module
     /// name of `<module>` for imports.
     pub module: Option<String>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::arena"))]
     pub types: Arena<TypeDef>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub type_lookup: HashMap<String, TypeId>,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::arena"))]
     pub interfaces: Arena<Interface>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub interface_lookup: HashMap<String, InterfaceId>,
     pub functions: Vec<Function>,
     pub globals: Vec<Global>,
+    pub constants: Vec<Constant>,
+}
+
+/// A world describes the complete set of interfaces that a component
+/// imports and exports.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct World {
+    pub name: String,
+    pub docs: Docs,
+    /// Interfaces imported by this world, keyed by their import name.
+    pub imports: IndexMap<String, Interface>,
+    /// Interfaces exported by this world under a name, keyed by that name.
+    ///
+    /// Each of these interfaces has its `module` set to its export name.
+    pub exports: IndexMap<String, Interface>,
+    /// The interface, if any, whose functions are exported directly from
+    /// the component rather than under a name.
+    pub default: Option<Interface>,
 }
 
 pub type TypeId = Id<TypeDef>;
 pub type InterfaceId = Id<Interface>;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TypeDef {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
     pub kind: TypeDefKind,
     pub name: Option<String>,
     /// `None` if this type is originally declared in this instance or
     /// otherwise `Some` if it was originally defined in a different module.
     pub foreign_module: Option<String>,
+    /// The `where` clause of a `type` alias, which is `None` for all other
+    /// types.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub refinement: Option<Refinement>,
+    /// The name of this type in the source it was parsed from.
+    ///
+    /// This is `None` for anonymous types, for types copied from another
+    /// interface, which may be in a different file, and for interfaces which
+    /// weren't parsed from source.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub span: Option<Span>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum TypeDefKind {
     Record(Record),
     Flags(Flags),
     Tuple(Tuple),
     Variant(Variant),
     Enum(Enum),
+    /// An optional value.
+    ///
+    /// Options which are recursive, see `Interface::is_recursive`, are
+    /// boxed: rather than storing their payload inline they are
+    /// represented as a pointer to it, which is zero for `none`.
     Option(Type),
     Result(Result_),
     Union(Union),
     List(Type),
     Future(Option<Type>),
     Stream(Stream),
+    /// A resource type, an opaque object which can only be referred to
+    /// through a `Handle`.
+    Resource,
+    Handle(Handle),
     Type(Type),
 }
 
+/// A handle to a resource, which is represented as an index at runtime.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Handle {
+    /// An owned handle; ownership of the resource is transferred along with
+    /// the handle.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::id"))]
+    Own(TypeId),
+    /// A borrowed handle, which is only valid for the duration of the call
+    /// it's passed to.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::id"))]
+    Borrow(TypeId),
+}
+
+impl Handle {
+    /// Returns the resource type this handle refers to.
+    pub fn resource(&self) -> TypeId {
+        match self {
+            Handle::Own(id) | Handle::Borrow(id) => *id,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum Type {
     Bool,
     U8,
@@ -79,6 +217,7 @@ pub enum Type {
     Float64,
     Char,
     String,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::id"))]
     Id(TypeId),
 }
 
@@ -91,25 +230,36 @@ pub enum Int {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Record {
     pub fields: Vec<Field>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Field {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub ty: Type,
+    /// The value of this field when it isn't otherwise given, if any.
+    pub default: Option<Literal>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Flags {
     pub flags: Vec<Flag>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Flag {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
     pub name: String,
 }
 
@@ -141,19 +291,25 @@ impl FlagsRepr {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Tuple {
     pub types: Vec<Type>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Variant {
     pub cases: Vec<Case>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Case {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub ty: Option<Type>,
 }
 
@@ -169,13 +325,17 @@ impl Variant {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Enum {
     pub cases: Vec<EnumCase>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct EnumCase {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
     pub name: String,
 }
 
@@ -191,19 +351,25 @@ impl Enum {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Result_ {
     pub ok: Option<Type>,
     pub err: Option<Type>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Union {
     pub cases: Vec<UnionCase>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct UnionCase {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub ty: Type,
 }
 
@@ -219,26 +385,290 @@ impl Union {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Stream {
     pub element: Option<Type>,
     pub end: Option<Type>,
 }
 
 #[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
 pub struct Docs {
     pub contents: Option<String>,
+    /// Set by a `@deprecated` attribute on the item.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub deprecated: Option<Deprecated>,
+    /// The version the item was added in, set by a `@since("x.y.z")`
+    /// attribute.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub since: Option<String>,
 }
 
+impl Docs {
+    /// Returns whether there's nothing to document, neither contents nor
+    /// deprecation or version metadata.
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_none() && self.deprecated.is_none() && self.since.is_none()
+    }
+}
+
+/// Why an item is deprecated, from `@deprecated`, `@deprecated("note")` or
+/// `@deprecated(note = "...", since = "x.y.z")`.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Deprecated {
+    pub since: Option<String>,
+    pub note: Option<String>,
+}
+
+/// The attributes written before an item, such as `@rust(derive = "Hash")`.
+///
+/// Attributes don't change the meaning of an interface. They're hints for
+/// generators, which look up the ones they understand and ignore the rest.
+#[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct Attributes {
+    pub attrs: Vec<Attribute>,
+}
+
+impl Attributes {
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    /// Returns the first attribute named `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&Attribute> {
+        self.attrs.iter().find(|a| a.name == name)
+    }
+
+    /// Returns all attributes named `name` in the order they were written.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Attribute> + 'a {
+        self.attrs.iter().filter(move |a| a.name == name)
+    }
+
+    /// Returns the value of `key` in the first attribute named `name` which
+    /// has it, such as `"Hash"` for `lookup("rust", "derive")`.
+    pub fn lookup(&self, name: &str, key: &str) -> Option<&Literal> {
+        self.attrs
+            .iter()
+            .filter(|a| a.name == name)
+            .find_map(|a| a.arg(key))
+    }
+}
+
+/// An attribute, written `@name` or `@name(args...)`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<AttributeArg>,
+}
+
+impl Attribute {
+    /// Returns the value of the argument named `key`, if it was given.
+    pub fn arg(&self, key: &str) -> Option<&Literal> {
+        self.args
+            .iter()
+            .find(|arg| arg.key.as_deref() == Some(key))
+            .map(|arg| &arg.value)
+    }
+
+    /// Returns the first argument without a key, such as the message of
+    /// `@deprecated("use x")`.
+    pub fn value(&self) -> Option<&Literal> {
+        self.args
+            .iter()
+            .find(|arg| arg.key.is_none())
+            .map(|arg| &arg.value)
+    }
+}
+
+impl fmt::Display for Attribute {
+    /// Writes this attribute as it would be written in a wit file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        f.write_str("(")?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match &arg.key {
+                Some(key) => write!(f, "{} = {}", key, arg.value)?,
+                None => write!(f, "{}", arg.value)?,
+            }
+        }
+        f.write_str(")")
+    }
+}
+
+/// An argument of an attribute, either `value` or `key = value`.
+///
+/// A key written on its own, as in `@rust(error)`, has the value `true`.
+/// Numbers are `Integer`s if they're written as one and `Float`s otherwise,
+/// and attributes never have `Char` values.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AttributeArg {
+    pub key: Option<String>,
+    pub value: Literal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Global {
     pub docs: Docs,
     pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub ty: Type,
+}
+
+/// A constant declared with `const name: ty = value`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Constant {
+    pub docs: Docs,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub ty: Type,
+    pub value: Literal,
+}
+
+/// The value of a constant or a default, which is always of a primitive
+/// type.
+///
+/// Values have been checked against their type, so an `Integer` is within
+/// the range of its integer type and a `Char` is only used for `char`s.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Literal {
+    Bool(bool),
+    Integer(i128),
+    Float(f64),
+    Char(char),
+    String(String),
+}
+
+impl Literal {
+    /// Returns the value of an `Integer` or `Float`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Literal::Integer(i) => Some(*i as f64),
+            Literal::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    /// Writes this literal as it would be written in a wit file.
+    ///
+    /// The escapes used by strings are also valid in most languages'
+    /// string literals.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Integer(i) => write!(f, "{}", i),
+            Literal::Float(x) => write!(f, "{:?}", x),
+            Literal::Char(c) => write_quoted(f, c.encode_utf8(&mut [0; 4])),
+            Literal::String(s) => write_quoted(f, s),
+        }
+    }
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// A constraint on the values of a `type` alias, written after `where` as in
+/// `type port = u16 where 1..=65535`.
+///
+/// Generators check refinements when lifting values, as values coming from
+/// the other side of the boundary can't be trusted to satisfy them.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Refinement {
+    /// The value of an integer or floating point type is within the range.
+    Range(Range),
+    /// The length of a string, in bytes, or of a list is within the range,
+    /// written `len <= max`, `len >= min` or `len in min..=max`. Its bounds
+    /// are always `Integer`s.
+    Length(Range),
+}
+
+impl fmt::Display for Refinement {
+    /// Writes this refinement as it would be written after `where`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refinement::Range(range) => write!(f, "{}", range),
+            Refinement::Length(range) => match range {
+                Range {
+                    start: None,
+                    end: Some(end),
+                    inclusive: true,
+                } => write!(f, "len <= {}", end),
+                Range {
+                    start: Some(start),
+                    end: None,
+                    ..
+                } => write!(f, "len >= {}", start),
+                range => write!(f, "len in {}", range),
+            },
+        }
+    }
+}
+
+/// A range of values, either of whose bounds may be left out.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Range {
+    pub start: Option<Literal>,
+    pub end: Option<Literal>,
+    /// Whether `end` is itself within the range, as in `1..=10`.
+    pub inclusive: bool,
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = &self.start {
+            write!(f, "{}", start)?;
+        }
+        f.write_str(if self.inclusive { "..=" } else { ".." })?;
+        if let Some(end) = &self.end {
+            write!(f, "{}", end)?;
+        }
+        Ok(())
+    }
 }
 
 pub type Params = Vec<(String, Type)>;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum Results {
     Named(Params),
     Anon(Type),
@@ -291,52 +721,215 @@ impl Results {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Function {
     pub docs: Docs,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Attributes::is_empty"))]
+    pub attrs: Attributes,
     pub name: String,
     pub kind: FunctionKind,
+    /// Whether this function was declared `async`.
+    ///
+    /// Async functions have the same canonical ABI as synchronous ones; the
+    /// flag instructs generators to produce bindings which suspend the caller
+    /// until the callee completes, e.g. an `async fn` in Rust or a `Promise`
+    /// in JS.
+    #[cfg_attr(feature = "serde", serde(rename = "async"))]
+    pub is_async: bool,
     pub params: Params,
+    /// The default value of each parameter, if any, in the same order as
+    /// `params`.
+    pub defaults: Vec<Option<Literal>>,
     pub results: Results,
+    /// The name of this function in the source it was parsed from, with the
+    /// same caveats as `TypeDef::span`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub span: Option<Span>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
 pub enum FunctionKind {
     Freestanding,
+    /// A method of `resource`, whose first parameter is `self`, a borrowed
+    /// handle to the resource.
+    Method {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::id"))]
+        resource: TypeId,
+        name: String,
+    },
+    /// A function scoped to `resource` which doesn't take `self`.
+    Static {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::id"))]
+        resource: TypeId,
+        name: String,
+    },
+    /// The constructor of `resource`, which returns an owned handle to a new
+    /// resource.
+    Constructor {
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize::id"))]
+        resource: TypeId,
+    },
 }
 
 impl Function {
+    /// Returns the name of this function within its scope, which for
+    /// resource functions excludes the `resource::` prefix of `name`.
     pub fn item_name(&self) -> &str {
         match &self.kind {
             FunctionKind::Freestanding => &self.name,
+            FunctionKind::Method { name, .. } | FunctionKind::Static { name, .. } => name,
+            FunctionKind::Constructor { .. } => "constructor",
+        }
+    }
+
+    /// Returns the resource this function is associated with, if any.
+    pub fn resource(&self) -> Option<TypeId> {
+        match &self.kind {
+            FunctionKind::Freestanding => None,
+            FunctionKind::Method { resource, .. }
+            | FunctionKind::Static { resource, .. }
+            | FunctionKind::Constructor { resource } => Some(*resource),
         }
     }
 }
 
-fn unwrap_md(contents: &str) -> String {
-    let mut wit = String::new();
-    let mut last_pos = 0;
+/// Formats `contents`, the contents of the wit file `filename`, in the
+/// canonical style.
+///
+/// Comments, including doc comments, are kept as they're written while
+/// indentation, spacing, blank lines and trailing commas are normalized.
+/// Returns an error if `contents` doesn't parse.
+pub fn format(filename: impl AsRef<Path>, contents: &str) -> Result<String> {
+    let filename = filename.as_ref();
+    if is_md(filename) {
+        bail!("formatting markdown files is not supported");
+    }
+    ast::format(contents).map_err(|e| ast::to_diagnostics(e, filename, contents))
+}
+
+/// Extracts the wit code of a markdown file, which interleaves
+/// ```` ```wit ```` code blocks with prose.
+///
+/// Code is kept at the same line and column as in `contents`, with
+/// everything else replaced by blank lines, so diagnostics point into the
+/// markdown file. Code blocks in other languages are prose, as are those
+/// marked `ignore`, as in ```` ```wit,ignore ````, which are typically
+/// examples.
+///
+/// The prose before a code block, since the previous code block or heading,
+/// is the documentation of the first item in the block. It becomes doc
+/// comments on the lines it was written on, ahead of any doc comments the
+/// item has in the block itself.
+pub fn unwrap_md(contents: &str) -> String {
+    let mut wit = Unwrapped {
+        contents,
+        wit: String::new(),
+        line: 0,
+        col: 0,
+    };
+    // Where the prose which documents the next code block starts.
+    let mut prose_start = 0;
     let mut in_wit_code_block = false;
-    Parser::new_ext(contents, Options::empty())
-        .into_offset_iter()
-        .for_each(|(event, range)| match (event, range) {
-            (Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(CowStr::Borrowed("wit")))), _) => {
-                in_wit_code_block = true;
-            }
-            (Event::Text(text), range) if in_wit_code_block => {
-                // Ensure that offsets are correct by inserting newlines to
-                // cover the Markdown content outside of wit code blocks.
-                for _ in contents[last_pos..range.start].lines() {
-                    wit.push_str("\n");
+    for (event, range) in Parser::new_ext(contents, Options::empty()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) if is_wit_block(&info) => {
+                // Separate the prose from the block's own doc comments.
+                let mut code = contents[range.clone()].lines().skip(1);
+                let first = code.find(|l| !l.trim().is_empty());
+                if wit.push_docs(prose_start, range.start)
+                    && matches!(first, Some(l) if l.trim().starts_with("///"))
+                {
+                    wit.push(range.start, "///");
                 }
-                wit.push_str(&text);
-                last_pos = range.end;
+                in_wit_code_block = true;
             }
-            (Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(CowStr::Borrowed("wit")))), _) => {
+            Event::Text(_) if in_wit_code_block => wit.push(range.start, &contents[range]),
+            Event::End(Tag::CodeBlock(_)) if in_wit_code_block => {
                 in_wit_code_block = false;
+                prose_start = range.end;
             }
+            Event::Start(Tag::Heading(..)) => prose_start = range.end,
             _ => {}
-        });
-    wit
+        }
+    }
+    wit.wit
+}
+
+/// Returns whether a code block with the info string `info` contains wit
+/// code, as for ```` ```wit ```` but not ```` ```wit,ignore ````.
+fn is_wit_block(info: &str) -> bool {
+    let mut words = info
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|w| !w.is_empty());
+    words.next() == Some("wit") && words.all(|w| w != "ignore")
+}
+
+/// The wit code extracted from a markdown file by [`unwrap_md`].
+struct Unwrapped<'a> {
+    contents: &'a str,
+    wit: String,
+    /// The line and column, in chars, of the end of `wit`.
+    line: usize,
+    col: usize,
+}
+
+impl Unwrapped<'_> {
+    /// Pushes `text` at the same line and column as `offset` in the
+    /// markdown file.
+    fn push(&mut self, offset: usize, text: &str) {
+        let before = &self.contents[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count();
+        let col = before[line_start..].chars().count();
+        while self.line < line {
+            self.wit.push('\n');
+            self.line += 1;
+            self.col = 0;
+        }
+        while self.col < col {
+            self.wit.push(' ');
+            self.col += 1;
+        }
+        self.wit.push_str(text);
+        match text.rfind('\n') {
+            Some(i) => {
+                self.line += text.matches('\n').count();
+                self.col = text[i + 1..].chars().count();
+            }
+            None => self.col += text.chars().count(),
+        }
+    }
+
+    /// Pushes the prose between `start` and `end` as doc comments, leaving
+    /// out blank lines around it. Returns whether there was any.
+    fn push_docs(&mut self, start: usize, end: usize) -> bool {
+        let prose = &self.contents[start..end];
+        let mut lines = Vec::new();
+        let mut offset = start;
+        for line in prose.split_inclusive('\n') {
+            lines.push((offset, line.trim_end()));
+            offset += line.len();
+        }
+        // The line of the code block's fence is never part of the prose.
+        if !prose.ends_with('\n') {
+            lines.pop();
+        }
+        let first = lines.iter().position(|(_, l)| !l.trim().is_empty());
+        let last = lines.iter().rposition(|(_, l)| !l.trim().is_empty());
+        let (first, last) = match (first, last) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return false,
+        };
+        for (offset, line) in &lines[first..=last] {
+            self.push(*offset, &format!("/// {}", line.trim()));
+        }
+        true
+    }
 }
 
 impl Interface {
@@ -347,94 +940,62 @@ impl Interface {
     }
 
     pub fn parse_file(path: impl AsRef<Path>) -> Result<Interface> {
-        let path = path.as_ref();
-        let parent = path.parent().unwrap();
-        let contents = std::fs::read_to_string(&path)
-            .with_context(|| format!("failed to read: {}", path.display()))?;
-        Interface::parse_with(path, &contents, |path| load_fs(parent, path))
+        Ok(Interface::parse_file_with(path, &ParseOptions::default())?.0)
+    }
+
+    /// Parses the interface in the file at `path`, resolving any `use`
+    /// statements according to `options`.
+    ///
+    /// Returns the interface along with the graph of all files it depends on.
+    pub fn parse_file_with(
+        path: impl AsRef<Path>,
+        options: &ParseOptions,
+    ) -> Result<(Interface, DependencyGraph)> {
+        parse_file(path.as_ref(), options, resolve_interface)
+    }
+
+    /// Parses `contents` as the contents of the interface in the file at
+    /// `path`, resolving any `use` statements according to `options`.
+    ///
+    /// This is [`Interface::parse_file_with`] for files which have not been
+    /// saved, such as those being edited.
+    pub fn parse_contents_with(
+        path: impl AsRef<Path>,
+        contents: &str,
+        options: &ParseOptions,
+    ) -> Result<(Interface, DependencyGraph)> {
+        parse_contents(path.as_ref(), contents, options, resolve_interface)
     }
 
+    /// Parses `contents` as the interface in `filename`, using `load` to
+    /// load the dependencies of `use` statements.
+    ///
+    /// `load` is given the identifier of the interface to load, which is
+    /// either a plain name such as `foo` or, for interfaces within a package,
+    /// a qualified name such as `ns:pkg/foo@1.0.0`.
     pub fn parse_with(
         filename: impl AsRef<Path>,
         contents: &str,
         mut load: impl FnMut(&str) -> Result<(PathBuf, String)>,
     ) -> Result<Interface> {
-        Interface::_parse_with(
+        let mut load = |package: Option<&PackageName>, name: &str| match package {
+            Some(package) => load(&package.interface_id(name)),
+            None => load(name),
+        };
+        let (ret, _) = Loader::new(&mut load, None).parse(
             filename.as_ref(),
             contents,
-            &mut load,
-            &mut HashSet::new(),
-            &mut HashMap::new(),
-        )
-    }
-
-    fn _parse_with(
-        filename: &Path,
-        contents: &str,
-        load: &mut dyn FnMut(&str) -> Result<(PathBuf, String)>,
-        visiting: &mut HashSet<PathBuf>,
-        map: &mut HashMap<String, Interface>,
-    ) -> Result<Interface> {
-        let name = filename
-            .file_name()
-            .context("wit path must end in a file name")?
-            .to_str()
-            .context("wit filename must be valid unicode")?
-            // TODO: replace with `file_prefix` if/when that gets stabilized.
-            .split(".")
-            .next()
-            .unwrap();
-        let mut contents = contents;
-
-        // If we have a ".md" file, it's a wit file wrapped in a markdown file;
-        // parse the markdown to extract the `wit` code blocks.
-        let md_contents;
-        if filename.extension().and_then(|s| s.to_str()) == Some("md") {
-            md_contents = unwrap_md(contents);
-            contents = &md_contents[..];
-        }
-
-        // Parse the `contents `into an AST
-        let ast = match ast::Ast::parse(contents) {
-            Ok(ast) => ast,
-            Err(mut e) => {
-                let file = filename.display().to_string();
-                ast::rewrite_error(&mut e, &file, contents);
-                return Err(e);
-            }
-        };
-
-        // Load up any modules into our `map` that have not yet been parsed.
-        if !visiting.insert(filename.to_path_buf()) {
-            bail!("file `{}` recursively imports itself", filename.display())
-        }
-        for item in ast.items.iter() {
-            let u = match item {
-                ast::Item::Use(u) => u,
-                _ => continue,
-            };
-            if map.contains_key(&*u.from[0].name) {
-                continue;
-            }
-            let (filename, contents) = load(&u.from[0].name)
-                // TODO: insert context here about `u.name.span` and `filename`
-                ?;
-            let instance = Interface::_parse_with(&filename, &contents, load, visiting, map)?;
-            map.insert(u.from[0].name.to_string(), instance);
-        }
-        visiting.remove(filename);
-
-        // and finally resolve everything into our final instance
-        match ast.resolve(name, map) {
-            Ok(i) => Ok(i),
-            Err(mut e) => {
-                let file = filename.display().to_string();
-                ast::rewrite_error(&mut e, &file, contents);
-                Err(e)
-            }
-        }
+            None,
+            resolve_interface,
+        )?;
+        Ok(ret)
     }
 
+    /// Returns all types such that each comes after the types it contains.
+    ///
+    /// The exception is recursive types, where the element of a recursive
+    /// list or payload of a recursive option may come after the list or
+    /// option since it's stored out of line.
     pub fn topological_types(&self) -> Vec<TypeId> {
         let mut ret = Vec::new();
         let mut visited = HashSet::new();
@@ -449,8 +1010,10 @@ impl Interface {
             return;
         }
         match &self.types[id].kind {
-            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) => {}
+            TypeDefKind::Flags(_) | TypeDefKind::Enum(_) | TypeDefKind::Resource => {}
+            TypeDefKind::List(_) | TypeDefKind::Option(_) if self.is_recursive(id) => {}
             TypeDefKind::Type(t) | TypeDefKind::List(t) => self.topo_visit_ty(t, list, visited),
+            TypeDefKind::Handle(h) => self.topo_visit(h.resource(), list, visited),
             TypeDefKind::Record(r) => {
                 for f in r.fields.iter() {
                     self.topo_visit_ty(&f.ty, list, visited);
@@ -520,6 +1083,8 @@ impl Interface {
 
             Type::Bool | Type::Char | Type::String => false,
 
+            // Refined values must be checked one by one.
+            Type::Id(id) if self.types[*id].refinement.is_some() => false,
             Type::Id(id) => match &self.types[*id].kind {
                 TypeDefKind::List(_)
                 | TypeDefKind::Variant(_)
@@ -528,6 +1093,8 @@ impl Interface {
                 | TypeDefKind::Result(_)
                 | TypeDefKind::Future(_)
                 | TypeDefKind::Stream(_)
+                | TypeDefKind::Resource
+                | TypeDefKind::Handle(_)
                 | TypeDefKind::Union(_) => false,
                 TypeDefKind::Type(t) => self.all_bits_valid(t),
                 TypeDefKind::Record(r) => r.fields.iter().all(|f| self.all_bits_valid(&f.ty)),
@@ -541,6 +1108,110 @@ impl Interface {
         }
     }
 
+    /// Returns whether a value of `ty` contains any refined types whose
+    /// `where` clauses must be checked.
+    pub fn has_refinements(&self, ty: &Type) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![*ty];
+        while let Some(ty) = stack.pop() {
+            let id = match ty {
+                Type::Id(id) if visited.insert(id) => id,
+                _ => continue,
+            };
+            if self.types[id].refinement.is_some() {
+                return true;
+            }
+            match &self.types[id].kind {
+                // Values within futures and streams are checked as they
+                // arrive rather than along with the future or stream.
+                TypeDefKind::Future(_) | TypeDefKind::Stream(_) => {}
+                _ => stack.extend(self.contained_types(id)),
+            }
+        }
+        false
+    }
+
+    /// Returns whether a value of the type `id` may contain another value of
+    /// the same type.
+    ///
+    /// Recursion always goes through a `list` or an `option`, and the
+    /// options which are part of a recursive type are boxed.
+    pub fn is_recursive(&self, id: TypeId) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = self.contained_types(id);
+        while let Some(ty) = stack.pop() {
+            if let Type::Id(other) = ty {
+                if other == id {
+                    return true;
+                }
+                if visited.insert(other) {
+                    stack.extend(self.contained_types(other));
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the types directly contained within values of the type `id`.
+    pub fn contained_types(&self, id: TypeId) -> Vec<Type> {
+        match &self.types[id].kind {
+            TypeDefKind::Type(t) | TypeDefKind::List(t) | TypeDefKind::Option(t) => vec![*t],
+            TypeDefKind::Record(r) => r.fields.iter().map(|f| f.ty).collect(),
+            TypeDefKind::Tuple(t) => t.types.clone(),
+            TypeDefKind::Variant(v) => v.cases.iter().filter_map(|c| c.ty).collect(),
+            TypeDefKind::Union(u) => u.cases.iter().map(|c| c.ty).collect(),
+            TypeDefKind::Result(r) => r.ok.into_iter().chain(r.err).collect(),
+            TypeDefKind::Future(t) => t.iter().copied().collect(),
+            TypeDefKind::Stream(s) => s.element.into_iter().chain(s.end).collect(),
+            TypeDefKind::Flags(_)
+            | TypeDefKind::Enum(_)
+            | TypeDefKind::Resource
+            | TypeDefKind::Handle(_) => Vec::new(),
+        }
+    }
+
+    /// Returns `ty` with any type aliases resolved.
+    ///
+    /// The types of constants and defaults are always primitive types once
+    /// their aliases are resolved.
+    pub fn unalias(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Id(id) => match &self.types[*id].kind {
+                TypeDefKind::Type(t) => self.unalias(t),
+                _ => *ty,
+            },
+            _ => *ty,
+        }
+    }
+
+    /// Returns the destructor of `resource`, which every resource
+    /// implicitly has in addition to the functions declared for it.
+    ///
+    /// The destructor is named `{resource}::drop` and takes the index of the
+    /// owned handle to drop. Handles are passed across the component
+    /// boundary as `u32` indices into a table of resources kept by the
+    /// side which implements the resource.
+    pub fn resource_drop(&self, resource: TypeId) -> Function {
+        let name = self.types[resource]
+            .name
+            .as_ref()
+            .expect("resources are always named");
+        Function {
+            docs: Docs::default(),
+            attrs: Attributes::default(),
+            name: format!("{}::drop", name),
+            kind: FunctionKind::Static {
+                resource,
+                name: "drop".to_string(),
+            },
+            is_async: false,
+            params: vec![("self".to_string(), Type::U32)],
+            defaults: vec![None],
+            results: Results::Named(Vec::new()),
+            span: None,
+        }
+    }
+
     pub fn get_variant(&self, ty: &Type) -> Option<&Variant> {
         if let Type::Id(id) = ty {
             match &self.types[*id].kind {
@@ -553,17 +1224,385 @@ impl Interface {
     }
 }
 
-fn load_fs(root: &Path, name: &str) -> Result<(PathBuf, String)> {
+impl World {
+    pub fn parse(name: &str, input: &str) -> Result<World> {
+        World::parse_with(name, input, |f| {
+            Err(anyhow!("cannot load submodule `{}`", f))
+        })
+    }
+
+    pub fn parse_file(path: impl AsRef<Path>) -> Result<World> {
+        Ok(World::parse_file_with(path, &ParseOptions::default())?.0)
+    }
+
+    /// Parses the world in the file at `path`, resolving any `use`
+    /// statements according to `options`.
+    ///
+    /// Returns the world along with the graph of all files it depends on.
+    pub fn parse_file_with(
+        path: impl AsRef<Path>,
+        options: &ParseOptions,
+    ) -> Result<(World, DependencyGraph)> {
+        parse_file(path.as_ref(), options, |_name, package, ast, map| {
+            ast.resolve_world(package, map)
+        })
+    }
+
+    /// Parses `contents` as the contents of the world in the file at `path`
+    /// in the same manner as [`Interface::parse_contents_with`].
+    pub fn parse_contents_with(
+        path: impl AsRef<Path>,
+        contents: &str,
+        options: &ParseOptions,
+    ) -> Result<(World, DependencyGraph)> {
+        parse_contents(
+            path.as_ref(),
+            contents,
+            options,
+            |_name, package, ast, map| ast.resolve_world(package, map),
+        )
+    }
+
+    /// Parses `contents` as the world in `filename`, using `load` to load
+    /// the dependencies of `use` statements in the same manner as
+    /// [`Interface::parse_with`].
+    pub fn parse_with(
+        filename: impl AsRef<Path>,
+        contents: &str,
+        mut load: impl FnMut(&str) -> Result<(PathBuf, String)>,
+    ) -> Result<World> {
+        let mut load = |package: Option<&PackageName>, name: &str| match package {
+            Some(package) => load(&package.interface_id(name)),
+            None => load(name),
+        };
+        let (ret, _) = Loader::new(&mut load, None).parse(
+            filename.as_ref(),
+            contents,
+            None,
+            |_name, package, ast, map| ast.resolve_world(package, map),
+        )?;
+        Ok(ret)
+    }
+}
+
+fn resolve_interface(
+    name: &str,
+    package: Option<&PackageName>,
+    ast: &ast::Ast<'_>,
+    map: &HashMap<String, Interface>,
+) -> Result<Interface> {
+    ast.resolve(name, package, map)
+}
+
+fn parse_file<T>(
+    path: &Path,
+    options: &ParseOptions,
+    resolve: ResolveFn<T>,
+) -> Result<(T, DependencyGraph)> {
+    let contents = read_file(&options.sources, path)?;
+    parse_contents(path, &contents, options, resolve)
+}
+
+fn read_file(sources: &WitSources, path: &Path) -> Result<String> {
+    sources
+        .read(path)
+        .with_context(|| format!("failed to read: {}", path.display()))
+}
+
+fn parse_contents<T>(
+    path: &Path,
+    contents: &str,
+    options: &ParseOptions,
+    resolve: ResolveFn<T>,
+) -> Result<(T, DependencyGraph)> {
+    let parent = path.parent().unwrap();
+    let mut load = |package: Option<&PackageName>, name: &str| options.load(parent, package, name);
+    let mut loader = Loader::new(&mut load, None);
+    let (ret, _) = loader.parse(path, contents, None, resolve)?;
+    Ok((ret, DependencyGraph::new(loader.nodes)))
+}
+
+/// Resolves the AST of a file, given the name of the file and the package it
+/// belongs to, if any.
+type ResolveFn<T> =
+    fn(&str, Option<&PackageName>, &ast::Ast<'_>, &HashMap<String, Interface>) -> Result<T>;
+
+/// Loads the file for an interface, given the package it belongs to, if any,
+/// and its name.
+type LoadFn<'a> = dyn FnMut(Option<&PackageName>, &str) -> Result<(PathBuf, String)> + 'a;
+
+/// State shared by all of the files loaded while parsing a single root file.
+struct Loader<'a> {
+    load: &'a mut LoadFn<'a>,
+    cache: Option<&'a mut CachedInterfaces>,
+    visiting: HashSet<PathBuf>,
+    map: HashMap<String, Interface>,
+    /// The fingerprint of each interface in `map`, which changes whenever
+    /// the file of the interface or of any of its dependencies does.
+    fingerprints: HashMap<String, u64>,
+    nodes: BTreeMap<String, Dependency>,
+    /// The version of each package requested so far, keyed by its
+    /// unversioned name, along with where it was first requested.
+    versions: HashMap<String, (Option<String>, PathBuf, Span)>,
+}
+
+impl<'a> Loader<'a> {
+    fn new(load: &'a mut LoadFn<'a>, cache: Option<&'a mut CachedInterfaces>) -> Loader<'a> {
+        Loader {
+            load,
+            cache,
+            visiting: HashSet::new(),
+            map: HashMap::new(),
+            fingerprints: HashMap::new(),
+            nodes: BTreeMap::new(),
+            versions: HashMap::new(),
+        }
+    }
+
+    /// Parses and resolves `contents`, the contents of `filename`, which
+    /// belongs to `package` if it was loaded as part of one.
+    ///
+    /// Returns the result of `resolve` along with the `use` statements of
+    /// this file.
+    fn parse<T>(
+        &mut self,
+        filename: &Path,
+        contents: &str,
+        package: Option<&PackageName>,
+        resolve: ResolveFn<T>,
+    ) -> Result<(T, Vec<UseSite>)> {
+        let name = filename
+            .file_name()
+            .context("wit path must end in a file name")?
+            .to_str()
+            .context("wit filename must be valid unicode")?
+            // TODO: replace with `file_prefix` if/when that gets stabilized.
+            .split(".")
+            .next()
+            .unwrap();
+        let mut contents = contents;
+
+        // If we have a ".md" file, it's a wit file wrapped in a markdown file;
+        // parse the markdown to extract the `wit` code blocks.
+        let md_contents;
+        if is_md(filename) {
+            md_contents = unwrap_md(contents);
+            contents = &md_contents[..];
+        }
+
+        // Parse the `contents `into an AST
+        let ast = match ast::Ast::parse(contents) {
+            Ok(ast) => ast,
+            Err(e) => return Err(ast::to_diagnostics(e, filename, contents)),
+        };
+
+        // Load up any modules into our `map` that have not yet been parsed.
+        self.visiting.insert(filename.to_path_buf());
+        let mut uses = Vec::new();
+        let result = ast.for_each_use(|u| {
+            let site = UseSite {
+                package: u.target_package(package),
+                name: u.from[0].name.to_string(),
+                id: u.dependency_id(package),
+                span: u.target_span(),
+            };
+            self.load_use(filename, contents, &site)?;
+            uses.push(site);
+            Ok(())
+        });
+        if let Err(e) = result {
+            return Err(ast::to_diagnostics(e, filename, contents));
+        }
+        self.visiting.remove(filename);
+
+        // and finally resolve everything into our final instance
+        match resolve(name, package, &ast, &self.map) {
+            Ok(i) => Ok((i, uses)),
+            Err(e) => Err(ast::to_diagnostics(e, filename, contents)),
+        }
+    }
+
+    /// Parses and resolves the interface in `filename` in the same manner as
+    /// [`Loader::parse`].
+    ///
+    /// With a cache, the interface is only parsed again if its file or the
+    /// file of one of its dependencies has changed since it was cached.
+    /// Returns the interface, the `use` statements of its file and its
+    /// fingerprint.
+    fn parse_interface(
+        &mut self,
+        filename: &Path,
+        contents: &str,
+        package: Option<&PackageName>,
+    ) -> Result<(Interface, Vec<UseSite>, u64)> {
+        let hash = cache::hash(contents);
+        let key = (filename.to_path_buf(), package.cloned());
+        let cached_uses = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key))
+            .filter(|cached| cached.hash == hash)
+            .map(|cached| cached.uses.clone());
+        if let Some(uses) = cached_uses {
+            // The dependencies are loaded, and checked against the cache in
+            // turn, just as they would be if this file were parsed.
+            let md_contents;
+            let mut contents = contents;
+            if is_md(filename) {
+                md_contents = unwrap_md(contents);
+                contents = &md_contents[..];
+            }
+            self.visiting.insert(filename.to_path_buf());
+            for site in uses.iter() {
+                self.load_use(filename, contents, site)?;
+            }
+            self.visiting.remove(filename);
+
+            let fingerprint = self.fingerprint(hash, &uses);
+            let cached = &self.cache.as_ref().unwrap()[&key];
+            if cached.fingerprint == fingerprint {
+                return Ok((cached.interface.clone(), uses, fingerprint));
+            }
+        }
+
+        let (interface, uses) = self.parse(filename, contents, package, resolve_interface)?;
+        let fingerprint = self.fingerprint(hash, &uses);
+        if let Some(cache) = &mut self.cache {
+            cache.insert(
+                key,
+                CachedInterface {
+                    hash,
+                    fingerprint,
+                    uses: uses.clone(),
+                    interface: interface.clone(),
+                },
+            );
+        }
+        Ok((interface, uses, fingerprint))
+    }
+
+    /// Loads the interface used by `site` in `filename`, unless it has
+    /// already been loaded.
+    fn load_use(&mut self, filename: &Path, contents: &str, site: &UseSite) -> Result<()> {
+        if let Some(target) = &site.package {
+            self.check_version(filename, contents, site.span, target)?;
+        }
+        if self.map.contains_key(&site.id) {
+            return Ok(());
+        }
+        let (path, dep_contents) = match (self.load)(site.package.as_ref(), &site.name) {
+            Ok(pair) => pair,
+            // Errors locating a package already describe where it was
+            // searched for.
+            Err(e) if site.package.is_some() => {
+                let d = Diagnostic::error(filename, contents, site.span, e.to_string());
+                return Err(Diagnostics::from(d).into());
+            }
+            Err(e) => {
+                let msg = format!("failed to load `{}`", site.id);
+                let d = Diagnostic::error(filename, contents, site.span, msg)
+                    .with_note(format!("{:#}", e));
+                return Err(Diagnostics::from(d).into());
+            }
+        };
+        if self.visiting.contains(&path) {
+            let msg = format!("file `{}` recursively imports itself", path.display());
+            let d = Diagnostic::error(filename, contents, site.span, msg);
+            return Err(Diagnostics::from(d).into());
+        }
+        let (instance, uses, fingerprint) =
+            self.parse_interface(&path, &dep_contents, site.package.as_ref())?;
+        self.map.insert(site.id.clone(), instance);
+        self.fingerprints.insert(site.id.clone(), fingerprint);
+        self.nodes.insert(
+            site.id.clone(),
+            Dependency {
+                id: site.id.clone(),
+                package: site.package.clone(),
+                path,
+                dependencies: uses
+                    .into_iter()
+                    .map(|u| u.id)
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Combines the hash of the contents of a file with the fingerprints of
+    /// the interfaces it uses, all of which must have been loaded.
+    fn fingerprint(&self, hash: u64, uses: &[UseSite]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hash.hash(&mut hasher);
+        for site in uses {
+            self.fingerprints[&site.id].hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Checks that `package`, requested at `span` in `filename`, is
+    /// requested at the same version as every other request for that
+    /// package.
+    fn check_version(
+        &mut self,
+        filename: &Path,
+        contents: &str,
+        span: Span,
+        package: &PackageName,
+    ) -> Result<()> {
+        let describe = |version: &Option<String>| match version {
+            Some(v) => format!("version `{}`", v),
+            None => "no version".to_string(),
+        };
+        let key = package.unversioned();
+        let (version, first_file, first_span) = match self.versions.get(&key) {
+            Some(first) => first,
+            None => {
+                self.versions
+                    .insert(key, (package.version.clone(), filename.to_path_buf(), span));
+                return Ok(());
+            }
+        };
+        if *version == package.version {
+            return Ok(());
+        }
+        let msg = format!(
+            "package `{}` is requested with {} here but with {} elsewhere",
+            key,
+            describe(&package.version),
+            describe(version),
+        );
+        let mut d = Diagnostic::error(filename, contents, span, msg);
+        if first_file == filename {
+            d = d.with_label(*first_span, format!("{} requested here", describe(version)));
+        } else {
+            d = d.with_note(format!(
+                "{} is requested by `{}`",
+                describe(version),
+                first_file.display()
+            ));
+        }
+        Err(Diagnostics::from(d).into())
+    }
+}
+
+fn is_md(filename: &Path) -> bool {
+    filename.extension().and_then(|s| s.to_str()) == Some("md")
+}
+
+pub(crate) fn load_fs(sources: &WitSources, root: &Path, name: &str) -> Result<(PathBuf, String)> {
     let wit = root.join(name).with_extension("wit");
 
     // Attempt to read a ".wit" file.
-    match fs::read_to_string(&wit) {
+    match sources.read(&wit) {
         Ok(contents) => Ok((wit, contents)),
 
         // If no such file was found, attempt to read a ".wit.md" file.
         Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
             let wit_md = wit.with_extension("wit.md");
-            match fs::read_to_string(&wit_md) {
+            match sources.read(&wit_md) {
                 Ok(contents) => Ok((wit_md, contents)),
                 Err(_err) => Err(err.into()),
             }
@@ -572,3 +1611,28 @@ fn load_fs(root: &Path, name: &str) -> Result<(PathBuf, String)> {
         Err(err) => return Err(err.into()),
     }
 }
+
+pub(crate) fn load_package(
+    root: &Path,
+    options: &ParseOptions,
+    package: &PackageName,
+    name: &str,
+) -> Result<(PathBuf, String)> {
+    let dir = match options.find_package(root, package) {
+        Some(dir) => dir,
+        None => bail!(
+            "package `{}` not found, searched in: {}",
+            package,
+            options.describe_search_paths(root)
+        ),
+    };
+    load_fs(&options.sources, &dir, name).map_err(|e| match e.downcast_ref::<io::Error>() {
+        Some(io) if io.kind() == io::ErrorKind::NotFound => anyhow!(
+            "interface `{}` not found in package `{}` at `{}`",
+            name,
+            package,
+            dir.display()
+        ),
+        _ => e,
+    })
+}
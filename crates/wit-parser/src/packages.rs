@@ -0,0 +1,182 @@
+use crate::WitSources;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A package of interfaces referred to by a `use` statement, such as the
+/// `wasi:io` of `use { stream } from wasi:io/streams@0.2.0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackageName {
+    pub namespace: Option<String>,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl PackageName {
+    /// Returns the identifier of the interface `interface` within this
+    /// package, for example `wasi:io/streams@0.2.0`.
+    pub fn interface_id(&self, interface: &str) -> String {
+        let mut ret = self.unversioned();
+        ret.push('/');
+        ret.push_str(interface);
+        if let Some(version) = &self.version {
+            ret.push('@');
+            ret.push_str(version);
+        }
+        ret
+    }
+
+    /// Returns this package's name without its version, for example
+    /// `wasi:io`.
+    pub fn unversioned(&self) -> String {
+        match &self.namespace {
+            Some(ns) => format!("{}:{}", ns, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Returns the directories, relative to a search root, in which this
+    /// package may be found.
+    ///
+    /// A directory suffixed with the package's version (`wasi/io@0.2.0`) takes
+    /// precedence over an unversioned one (`wasi/io`).
+    fn candidate_dirs(&self, root: &Path) -> Vec<PathBuf> {
+        let mut base = root.to_path_buf();
+        if let Some(ns) = &self.namespace {
+            base.push(ns);
+        }
+        let mut ret = Vec::new();
+        if let Some(version) = &self.version {
+            ret.push(base.join(format!("{}@{}", self.name, version)));
+        }
+        ret.push(base.join(&self.name));
+        ret
+    }
+}
+
+impl fmt::Display for PackageName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.unversioned())?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", version)?;
+        }
+        Ok(())
+    }
+}
+
+/// Configuration for how `use` statements are resolved when parsing files
+/// from a [`WitSources`], by default the filesystem.
+///
+/// Interfaces referred to by a plain name (`use { x } from foo`) are always
+/// loaded from next to the file being parsed. Interfaces within a package
+/// (`use { x } from ns:pkg/foo@1.0.0`) are searched for in the `deps`
+/// directory next to the root file and then in each of `search_paths`, in
+/// order.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub search_paths: Vec<PathBuf>,
+    /// Where all files, including the root file, are read from.
+    pub sources: WitSources,
+}
+
+impl ParseOptions {
+    /// Loads the file defining the interface `name`, which is within
+    /// `package` if given, for a root file in the directory `root`.
+    ///
+    /// Returns the path of the file along with its contents.
+    pub fn load(
+        &self,
+        root: &Path,
+        package: Option<&PackageName>,
+        name: &str,
+    ) -> Result<(PathBuf, String)> {
+        match package {
+            Some(package) => crate::load_package(root, self, package, name),
+            None => crate::load_fs(&self.sources, root, name),
+        }
+    }
+
+    /// Returns the directory containing `package`, given the directory of
+    /// the root file being parsed.
+    pub(crate) fn find_package(&self, root: &Path, package: &PackageName) -> Option<PathBuf> {
+        let deps = root.join("deps");
+        std::iter::once(&deps)
+            .chain(self.search_paths.iter())
+            .flat_map(|dir| package.candidate_dirs(dir))
+            .find(|dir| self.sources.is_dir(dir))
+    }
+
+    /// Returns a human readable list of the directories searched for
+    /// packages, for use in error messages.
+    pub(crate) fn describe_search_paths(&self, root: &Path) -> String {
+        std::iter::once(root.join("deps"))
+            .chain(self.search_paths.iter().cloned())
+            .map(|p| format!("`{}`", p.display()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The set of files loaded to resolve the `use` statements of a parse.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DependencyGraph {
+    /// Every dependency in topological order: each entry comes after all of
+    /// the dependencies it uses itself. Otherwise entries are ordered by
+    /// their `id`, so the same set of files always produces the same graph.
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    /// The unique identifier of this dependency: `ns:pkg/iface@version` for
+    /// interfaces within a package or the name of the file otherwise.
+    pub id: String,
+    /// The package this interface belongs to, if any.
+    pub package: Option<PackageName>,
+    /// The file this interface was loaded from.
+    pub path: PathBuf,
+    /// The `id`s of the dependencies used directly by this one, sorted.
+    pub dependencies: Vec<String>,
+}
+
+impl DependencyGraph {
+    pub(crate) fn new(mut nodes: BTreeMap<String, Dependency>) -> DependencyGraph {
+        let ids = nodes.keys().cloned().collect::<Vec<_>>();
+        let mut visited = HashSet::new();
+        let mut dependencies = Vec::new();
+        for id in ids {
+            visit(&id, &mut nodes, &mut visited, &mut dependencies);
+        }
+        return DependencyGraph { dependencies };
+
+        fn visit(
+            id: &str,
+            nodes: &mut BTreeMap<String, Dependency>,
+            visited: &mut HashSet<String>,
+            list: &mut Vec<Dependency>,
+        ) {
+            if !visited.insert(id.to_string()) {
+                return;
+            }
+            let node = match nodes.remove(id) {
+                Some(node) => node,
+                None => return,
+            };
+            for dep in node.dependencies.iter() {
+                visit(dep, nodes, visited, list);
+            }
+            list.push(node);
+        }
+    }
+
+    /// Returns every package in this graph along with the version of it
+    /// that was used.
+    pub fn packages(&self) -> BTreeMap<String, Option<&str>> {
+        self.dependencies
+            .iter()
+            .filter_map(|d| d.package.as_ref())
+            .map(|p| (p.unversioned(), p.version.as_deref()))
+            .collect()
+    }
+}
@@ -0,0 +1,19 @@
+//! Helpers for serializing the types of `id_arena`, which don't implement
+//! `Serialize` themselves.
+
+use id_arena::{Arena, Id};
+use serde::{Serialize, Serializer};
+
+/// Serializes an id as its index within its arena.
+pub fn id<T, S: Serializer>(id: &Id<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(id.index() as u64)
+}
+
+/// Serializes an arena as a list of its items, so that the item with id `n`
+/// is at index `n` of the list.
+pub fn arena<T: Serialize, S: Serializer>(
+    arena: &Arena<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(arena.iter().map(|(_, item)| item))
+}
@@ -1,4 +1,4 @@
-use crate::{FlagsRepr, Int, Interface, Type, TypeDef, TypeDefKind};
+use crate::{FlagsRepr, Int, Interface, Type, TypeDefKind, TypeId};
 
 #[derive(Default)]
 pub struct SizeAlign {
@@ -9,13 +9,13 @@ impl SizeAlign {
     pub fn fill(&mut self, iface: &Interface) {
         self.map = vec![(0, 0); iface.types.len()];
         for ty in iface.topological_types() {
-            let pair = self.calculate(&iface.types[ty]);
+            let pair = self.calculate(iface, ty);
             self.map[ty.index()] = pair;
         }
     }
 
-    fn calculate(&self, ty: &TypeDef) -> (usize, usize) {
-        match &ty.kind {
+    fn calculate(&self, iface: &Interface, id: TypeId) -> (usize, usize) {
+        match &iface.types[id].kind {
             TypeDefKind::Type(t) => (self.size(t), self.align(t)),
             TypeDefKind::List(_) => (8, 4),
             TypeDefKind::Record(r) => self.record(r.fields.iter().map(|f| &f.ty)),
@@ -27,6 +27,9 @@ impl SizeAlign {
             },
             TypeDefKind::Variant(v) => self.variant(v.tag(), v.cases.iter().map(|c| c.ty.as_ref())),
             TypeDefKind::Enum(e) => self.variant(e.tag(), []),
+            // Recursive options are boxed, represented as a pointer to their
+            // payload.
+            TypeDefKind::Option(_) if iface.is_recursive(id) => (4, 4),
             TypeDefKind::Option(t) => self.variant(Int::U8, [Some(t)]),
             TypeDefKind::Result(r) => self.variant(Int::U8, [r.ok.as_ref(), r.err.as_ref()]),
             TypeDefKind::Union(u) => self.variant(u.tag(), u.cases.iter().map(|c| Some(&c.ty))),
@@ -34,6 +37,9 @@ impl SizeAlign {
             TypeDefKind::Future(_) => (4, 4),
             // A stream is represented as an index.
             TypeDefKind::Stream(_) => (4, 4),
+            // Resources are only ever passed around as handles, which are
+            // represented as an index.
+            TypeDefKind::Resource | TypeDefKind::Handle(_) => (4, 4),
         }
     }
 
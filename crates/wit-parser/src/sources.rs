@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the files of a wit tree are read from.
+///
+/// By default files are read from the filesystem. Files can also be
+/// provided in memory, for tools which can't access a filesystem such as
+/// those running in a browser, in which case `use` statements are resolved
+/// against the in-memory files exactly as they would be against files on
+/// disk:
+///
+/// ```
+/// use wit_parser::{Interface, ParseOptions, WitSources};
+///
+/// let mut sources = WitSources::memory();
+/// sources.insert("types.wit", "type id = u32");
+/// sources.insert("api.wit", "use { id } from types\nget: func() -> id");
+/// let options = ParseOptions {
+///     sources,
+///     ..ParseOptions::default()
+/// };
+/// let (iface, _) = Interface::parse_file_with("api.wit", &options).unwrap();
+/// assert_eq!(iface.functions[0].name, "get");
+/// ```
+#[derive(Debug, Clone)]
+pub struct WitSources {
+    files: BTreeMap<PathBuf, String>,
+    fs: bool,
+}
+
+impl WitSources {
+    /// Sources which are read from the filesystem, except for any files
+    /// [inserted](WitSources::insert) later which take precedence over the
+    /// files on disk.
+    pub fn fs() -> WitSources {
+        WitSources {
+            files: BTreeMap::new(),
+            fs: true,
+        }
+    }
+
+    /// Sources which consist only of the files [inserted](WitSources::insert)
+    /// and never touch the filesystem.
+    pub fn memory() -> WitSources {
+        WitSources {
+            files: BTreeMap::new(),
+            fs: false,
+        }
+    }
+
+    /// Adds the file at `path` with `contents`, replacing any file
+    /// previously added at `path`.
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<String>) {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    /// Returns the paths of the files added in memory, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> + '_ {
+        self.files.keys().map(|p| p.as_path())
+    }
+
+    /// Reads the file at `path`.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if there is no
+    /// such file.
+    pub fn read(&self, path: &Path) -> io::Result<String> {
+        if let Some(contents) = self.files.get(path) {
+            return Ok(contents.clone());
+        }
+        if self.fs {
+            return fs::read_to_string(path);
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no file `{}` in the provided sources", path.display()),
+        ))
+    }
+
+    /// Returns whether `path` is a directory, that is whether any file is
+    /// contained within it.
+    pub fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+            || (self.fs && path.is_dir())
+    }
+}
+
+impl Default for WitSources {
+    fn default() -> WitSources {
+        WitSources::fs()
+    }
+}
+
+impl<P: Into<PathBuf>, S: Into<String>> FromIterator<(P, S)> for WitSources {
+    /// Collects in-memory sources, as with [`WitSources::memory`].
+    fn from_iter<I: IntoIterator<Item = (P, S)>>(iter: I) -> WitSources {
+        let mut sources = WitSources::memory();
+        for (path, contents) in iter {
+            sources.insert(path, contents);
+        }
+        sources
+    }
+}
@@ -0,0 +1,71 @@
+//! Golden tests of the instructions `wit_parser::abi` generates, recorded as
+//! text for each function of every `tests/abi/*.wit` file on each side of a
+//! call. Run with `BLESS=1` to update the `*.wit.abi` files.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use wit_parser::abi::{record, AbiVariant, LiftLower};
+use wit_parser::Interface;
+
+const SIDES: &[(&str, AbiVariant, LiftLower)] = &[
+    (
+        "guest-import",
+        AbiVariant::GuestImport,
+        LiftLower::LowerArgsLiftResults,
+    ),
+    (
+        "guest-export",
+        AbiVariant::GuestExport,
+        LiftLower::LiftArgsLowerResults,
+    ),
+    (
+        "host-import",
+        AbiVariant::GuestImport,
+        LiftLower::LiftArgsLowerResults,
+    ),
+    (
+        "host-export",
+        AbiVariant::GuestExport,
+        LiftLower::LowerArgsLiftResults,
+    ),
+];
+
+#[test]
+fn abi() {
+    let mut tests = fs::read_dir("tests/abi")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("wit"))
+        .collect::<Vec<_>>();
+    tests.sort();
+    assert!(!tests.is_empty());
+
+    for test in tests {
+        let iface = Interface::parse_file(&test).unwrap();
+        let mut actual = String::new();
+        for func in iface.functions.iter() {
+            for (side, variant, lift_lower) in SIDES {
+                actual.push_str(&format!("// {} ({})\n", func.name, side));
+                actual.push_str(&record(&iface, *variant, *lift_lower, func));
+                actual.push('\n');
+            }
+        }
+        check(&test.with_extension("wit.abi"), &actual);
+    }
+}
+
+fn check(expected: &Path, actual: &str) {
+    if env::var_os("BLESS").is_some() {
+        fs::write(expected, actual).unwrap();
+        return;
+    }
+    let contents = fs::read_to_string(expected)
+        .unwrap_or_else(|_| panic!("failed to read {:?}, run with BLESS=1", expected));
+    assert_eq!(
+        contents.replace("\r\n", "\n"),
+        actual,
+        "{:?} is out of date, run with BLESS=1",
+        expected
+    );
+}
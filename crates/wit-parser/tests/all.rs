@@ -77,6 +77,10 @@ fn find_tests() -> Vec<PathBuf> {
         for f in path.read_dir().unwrap() {
             let f = f.unwrap();
             if f.file_type().unwrap().is_dir() {
+                // `deps` directories hold packages used by other tests.
+                if f.file_name() == "deps" {
+                    continue;
+                }
                 find_tests(&f.path(), tests);
                 continue;
             }
@@ -99,12 +103,28 @@ impl Runner<'_> {
     fn run(&mut self, test: &Path, contents: &[u8]) -> Result<()> {
         let contents = str::from_utf8(contents)?;
 
-        let result = Interface::parse_file(test);
+        // Files named `world-*` define a world rather than an interface.
+        let is_world = matches!(
+            test.file_name().and_then(|s| s.to_str()),
+            Some(s) if s.starts_with("world-")
+        );
+        let options = ParseOptions::default();
+        let result = if is_world {
+            World::parse_file_with(test, &options)
+                .map(|(w, g)| with_packages(world_to_json(&w), &g))
+        } else {
+            Interface::parse_file_with(test, &options).map(|(i, g)| with_packages(to_json(&i), &g))
+        };
 
         let result = if contents.contains("// parse-fail") {
             match result {
                 Ok(_) => bail!("expected test to not parse but it did"),
                 Err(mut e) => {
+                    // Diagnostics must also render as valid JSON for editors.
+                    if let Some(d) = e.downcast_ref::<Diagnostics>() {
+                        serde_json::from_str::<serde_json::Value>(&d.to_json())
+                            .context("diagnostics rendered as invalid JSON")?;
+                    }
                     if let Some(err) = e.downcast_mut::<io::Error>() {
                         *err = io::Error::new(
                             io::ErrorKind::Other,
@@ -115,8 +135,11 @@ impl Runner<'_> {
                 }
             }
         } else {
-            let instance = result?;
-            to_json(&instance)
+            let result = result?;
+            if test.extension() == Some(OsStr::new("wit")) {
+                self.check_format(test, contents, is_world, &options, &result)?;
+            }
+            result
         };
 
         // "foo.wit" => "foo.wit.result"
@@ -160,11 +183,111 @@ impl Runner<'_> {
         }
     }
 
+    /// Checks that formatting `contents` is idempotent and doesn't change
+    /// what it parses to, and for tests within `fmt` directories that the
+    /// output matches the test's `.wit.formatted` file.
+    fn check_format(
+        &self,
+        test: &Path,
+        contents: &str,
+        is_world: bool,
+        options: &ParseOptions,
+        expected: &str,
+    ) -> Result<()> {
+        let formatted = format(test, contents).context("failed to format")?;
+        let reformatted = format(test, &formatted).context("failed to format formatted output")?;
+        if formatted != reformatted {
+            bail!(
+                "formatting is not idempotent, formatted once:\n{}\nformatted twice:\n{}",
+                formatted,
+                reformatted
+            );
+        }
+        let result = if is_world {
+            World::parse_contents_with(test, &formatted, options)
+                .map(|(w, g)| with_packages(world_to_json(&w), &g))
+        } else {
+            Interface::parse_contents_with(test, &formatted, options)
+                .map(|(i, g)| with_packages(to_json(&i), &g))
+        };
+        let result = result.context("formatted output failed to parse")?;
+        if result != expected {
+            bail!(
+                "formatted output parsed differently, formatted:\n{}",
+                formatted
+            );
+        }
+
+        if test.parent().and_then(|p| p.file_name()) != Some(OsStr::new("fmt")) {
+            return Ok(());
+        }
+        let formatted_file = test.with_extension("wit.formatted");
+        if env::var_os("BLESS").is_some() {
+            fs::write(&formatted_file, &formatted)?;
+        } else {
+            let expected = fs::read_to_string(&formatted_file).context(format!(
+                "failed to read formatting expectation file {:?}\nthis can be fixed with BLESS=1",
+                formatted_file
+            ))?;
+            if expected.replace("\r\n", "\n") != formatted {
+                bail!(
+                    "failed formatting: expected `{:?}` but found `{:?}`",
+                    expected,
+                    formatted
+                );
+            }
+        }
+        self.bump_ntests();
+        Ok(())
+    }
+
     fn bump_ntests(&self) {
         self.ntests.fetch_add(1, SeqCst);
     }
 }
 
+/// Records the version of each package that `json` was resolved against, if
+/// any.
+fn with_packages(json: String, graph: &DependencyGraph) -> String {
+    let packages = graph.packages();
+    if packages.is_empty() {
+        return json;
+    }
+    let mut json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    json["packages"] = serde_json::to_value(&packages).unwrap();
+    serde_json::to_string_pretty(&json).unwrap()
+}
+
+fn world_to_json(w: &World) -> String {
+    #[derive(Serialize)]
+    struct World {
+        name: String,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        imports: Vec<(String, serde_json::Value)>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        exports: Vec<(String, serde_json::Value)>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        default: Option<serde_json::Value>,
+    }
+
+    let json = |i: &Interface| serde_json::from_str(&to_json(i)).unwrap();
+    let world = World {
+        name: w.name.clone(),
+        imports: w
+            .imports
+            .iter()
+            .map(|(n, i)| (n.clone(), json(i)))
+            .collect(),
+        exports: w
+            .exports
+            .iter()
+            .map(|(n, i)| (n.clone(), json(i)))
+            .collect(),
+        default: w.default.as_ref().map(json),
+    };
+    serde_json::to_string_pretty(&world).unwrap()
+}
+
 fn to_json(i: &Interface) -> String {
     #[derive(Serialize)]
     struct Interface {
@@ -174,15 +297,10 @@ fn to_json(i: &Interface) -> String {
         functions: Vec<Function>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
         globals: Vec<Global>,
-    }
-
-    #[derive(Serialize)]
-    struct Resource {
-        name: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        supertype: Option<String>,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        foreign_module: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        constants: Vec<Constant>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        interfaces: Vec<(String, serde_json::Value)>,
     }
 
     #[derive(Serialize)]
@@ -194,6 +312,14 @@ fn to_json(i: &Interface) -> String {
         ty: Type,
         #[serde(skip_serializing_if = "Option::is_none")]
         foreign_module: Option<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        attrs: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deprecated: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        since: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        refinement: Option<String>,
     }
 
     #[derive(Serialize)]
@@ -202,6 +328,8 @@ fn to_json(i: &Interface) -> String {
         Primitive(String),
         Record {
             fields: Vec<(String, String)>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            defaults: Vec<(String, String)>,
         },
         Flags {
             flags: Vec<String>,
@@ -229,13 +357,26 @@ fn to_json(i: &Interface) -> String {
         Union {
             cases: Vec<String>,
         },
+        Resource {},
+        Own(String),
+        Borrow(String),
     }
 
     #[derive(Serialize)]
     struct Function {
         name: String,
+        #[serde(rename = "async", skip_serializing_if = "std::ops::Not::not")]
+        is_async: bool,
         params: Vec<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        defaults: Vec<(String, String)>,
         results: Vec<String>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        attrs: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deprecated: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        since: Option<String>,
     }
 
     #[derive(Serialize)]
@@ -244,6 +385,13 @@ fn to_json(i: &Interface) -> String {
         ty: String,
     }
 
+    #[derive(Serialize)]
+    struct Constant {
+        name: String,
+        ty: String,
+        value: String,
+    }
+
     let types = i
         .types
         .iter()
@@ -252,6 +400,10 @@ fn to_json(i: &Interface) -> String {
             name: r.name.clone(),
             ty: translate_typedef(r),
             foreign_module: r.foreign_module.clone(),
+            attrs: translate_typedef_attrs(r),
+            deprecated: translate_deprecated(&r.docs),
+            since: r.docs.since.clone(),
+            refinement: r.refinement.as_ref().map(|r| r.to_string()),
         })
         .collect::<Vec<_>>();
     let functions = i
@@ -259,12 +411,22 @@ fn to_json(i: &Interface) -> String {
         .iter()
         .map(|f| Function {
             name: f.name.clone(),
+            is_async: f.is_async,
             params: f.params.iter().map(|(_, ty)| translate_type(ty)).collect(),
+            defaults: f
+                .params
+                .iter()
+                .zip(&f.defaults)
+                .filter_map(|((name, _), d)| Some((name.clone(), d.as_ref()?.to_string())))
+                .collect(),
             results: f
                 .results
                 .iter_types()
                 .map(|ty| translate_type(ty))
                 .collect(),
+            attrs: f.attrs.attrs.iter().map(|a| a.to_string()).collect(),
+            deprecated: translate_deprecated(&f.docs),
+            since: f.docs.since.clone(),
         })
         .collect::<Vec<_>>();
     let globals = i
@@ -276,13 +438,76 @@ fn to_json(i: &Interface) -> String {
         })
         .collect::<Vec<_>>();
 
+    let constants = i
+        .constants
+        .iter()
+        .map(|c| Constant {
+            name: c.name.clone(),
+            ty: translate_type(&c.ty),
+            value: c.value.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    let interfaces = i
+        .interfaces
+        .iter()
+        .map(|(_, i)| (i.name.clone(), serde_json::from_str(&to_json(i)).unwrap()))
+        .collect();
     let iface = Interface {
         types,
         functions,
         globals,
+        constants,
+        interfaces,
     };
     return serde_json::to_string_pretty(&iface).unwrap();
 
+    /// Describes the deprecation of an item as `since <version>: <note>`,
+    /// leaving out whichever parts weren't given.
+    fn translate_deprecated(docs: &wit_parser::Docs) -> Option<String> {
+        let deprecated = docs.deprecated.as_ref()?;
+        let mut ret = String::new();
+        if let Some(since) = &deprecated.since {
+            ret.push_str(&format!("since {}", since));
+        }
+        if let Some(note) = &deprecated.note {
+            if !ret.is_empty() {
+                ret.push_str(": ");
+            }
+            ret.push_str(note);
+        }
+        Some(ret)
+    }
+
+    /// Returns the attributes of `ty` and then those of its members, which
+    /// are prefixed with the member's name.
+    fn translate_typedef_attrs(ty: &wit_parser::TypeDef) -> Vec<String> {
+        let mut members = Vec::new();
+        match &ty.kind {
+            TypeDefKind::Record(r) => {
+                members.extend(r.fields.iter().map(|f| (f.name.clone(), &f.attrs)))
+            }
+            TypeDefKind::Flags(f) => {
+                members.extend(f.flags.iter().map(|f| (f.name.clone(), &f.attrs)))
+            }
+            TypeDefKind::Variant(v) => {
+                members.extend(v.cases.iter().map(|c| (c.name.clone(), &c.attrs)))
+            }
+            TypeDefKind::Enum(e) => {
+                members.extend(e.cases.iter().map(|c| (c.name.clone(), &c.attrs)))
+            }
+            TypeDefKind::Union(u) => {
+                members.extend(u.cases.iter().map(|c| (translate_type(&c.ty), &c.attrs)))
+            }
+            _ => {}
+        }
+        let own = ty.attrs.attrs.iter().map(|a| a.to_string());
+        let members = members
+            .into_iter()
+            .flat_map(|(name, attrs)| attrs.attrs.iter().map(move |a| format!("{}: {}", name, a)));
+        own.chain(members).collect()
+    }
+
     fn translate_typedef(ty: &wit_parser::TypeDef) -> Type {
         match &ty.kind {
             TypeDefKind::Type(t) => Type::Primitive(translate_type(t)),
@@ -292,6 +517,11 @@ fn to_json(i: &Interface) -> String {
                     .iter()
                     .map(|f| (f.name.clone(), translate_type(&f.ty)))
                     .collect(),
+                defaults: r
+                    .fields
+                    .iter()
+                    .filter_map(|f| Some((f.name.clone(), f.default.as_ref()?.to_string())))
+                    .collect(),
             },
             TypeDefKind::Tuple(t) => Type::Tuple {
                 types: t.types.iter().map(|ty| translate_type(ty)).collect(),
@@ -323,6 +553,9 @@ fn to_json(i: &Interface) -> String {
             TypeDefKind::Union(u) => Type::Union {
                 cases: u.cases.iter().map(|c| translate_type(&c.ty)).collect(),
             },
+            TypeDefKind::Resource => Type::Resource {},
+            TypeDefKind::Handle(Handle::Own(r)) => Type::Own(format!("type-{}", r.index())),
+            TypeDefKind::Handle(Handle::Borrow(r)) => Type::Borrow(format!("type-{}", r.index())),
         }
     }
 
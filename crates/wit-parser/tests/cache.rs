@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use wit_parser::{ParseOptions, WitCache};
+
+/// Creates an empty directory for the test `name`.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("cache")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn type_names(iface: &wit_parser::Interface) -> Vec<&str> {
+    iface
+        .types
+        .iter()
+        .filter_map(|(_, ty)| ty.name.as_deref())
+        .collect()
+}
+
+#[test]
+fn shared_dependencies() {
+    let dir = test_dir("shared_dependencies");
+    fs::write(dir.join("base.wit"), "type id = u32").unwrap();
+    fs::write(
+        dir.join("types.wit"),
+        "use { id } from base\nrecord thing { id: id }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("a.wit"),
+        "use { thing } from types\na: func(t: thing)",
+    )
+    .unwrap();
+    fs::write(dir.join("b.wit"), "use { id } from base\nb: func(i: id)").unwrap();
+
+    let mut cache = WitCache::new(ParseOptions::default());
+    let (a, graph) = cache.parse_file(dir.join("a.wit")).unwrap();
+    assert_eq!(type_names(&a), ["id", "thing"]);
+    let ids = graph
+        .dependencies
+        .iter()
+        .map(|d| &d.id[..])
+        .collect::<Vec<_>>();
+    assert_eq!(ids, ["base", "types"]);
+
+    let (b, graph) = cache.parse_file(dir.join("b.wit")).unwrap();
+    assert_eq!(type_names(&b), ["id"]);
+    assert_eq!(graph.dependencies.len(), 1);
+
+    let files = cache.files().collect::<Vec<_>>();
+    let expected = ["a.wit", "b.wit", "base.wit", "types.wit"]
+        .iter()
+        .map(|f| dir.join(f))
+        .collect::<Vec<_>>();
+    assert_eq!(files, expected);
+}
+
+#[test]
+fn invalidation() {
+    let dir = test_dir("invalidation");
+    fs::write(dir.join("base.wit"), "type id = u32").unwrap();
+    fs::write(
+        dir.join("types.wit"),
+        "use { id } from base\nrecord thing { id: id }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("root.wit"),
+        "use { thing } from types\nf: func(t: thing)",
+    )
+    .unwrap();
+
+    let mut cache = WitCache::new(ParseOptions::default());
+    cache.parse_file(dir.join("root.wit")).unwrap();
+
+    // A change to a transitive dependency is seen even though the file in
+    // between is unchanged.
+    fs::write(dir.join("base.wit"), "type id = string").unwrap();
+    let (root, _) = cache.parse_file(dir.join("root.wit")).unwrap();
+    let id = root
+        .types
+        .iter()
+        .find(|(_, t)| t.name.as_deref() == Some("id"));
+    assert!(matches!(
+        id.unwrap().1.kind,
+        wit_parser::TypeDefKind::Type(wit_parser::Type::String)
+    ));
+
+    // Errors in dependencies are reported rather than the cached interface
+    // being used.
+    fs::write(dir.join("base.wit"), "type other = u32").unwrap();
+    let err = cache.parse_file(dir.join("root.wit")).unwrap_err();
+    assert!(format!("{:?}", err).contains("id"), "{:?}", err);
+
+    fs::write(dir.join("base.wit"), "type id = u8").unwrap();
+    cache.parse_file(dir.join("root.wit")).unwrap();
+}
@@ -0,0 +1,263 @@
+use wit_parser::compat::compare;
+use wit_parser::Interface;
+
+/// Returns each change between `old` and `new` as it's displayed.
+fn changes(old: &str, new: &str) -> Vec<String> {
+    let old = Interface::parse("old", old).unwrap();
+    let new = Interface::parse("new", new).unwrap();
+    let changes = compare(&old, &new);
+    assert_eq!(
+        changes.is_breaking(),
+        changes
+            .changes
+            .iter()
+            .any(|c| c.to_string().starts_with("breaking"))
+    );
+    changes.changes.iter().map(|c| c.to_string()).collect()
+}
+
+#[test]
+fn unchanged() {
+    let wit = "
+        /// Docs don't matter.
+        record point { x: u32, y: u32 }
+        variant shape { circle(float32), square(point) }
+        area: func(s: shape) -> float32
+    ";
+    let without_docs = wit.replace("/// Docs don't matter.", "");
+    assert!(changes(wit, &without_docs).is_empty());
+}
+
+#[test]
+fn functions() {
+    assert_eq!(
+        changes(
+            "
+                a: func(x: u32)
+                b: func()
+                c: func(x: u32) -> string
+                d: func(x: u32)
+            ",
+            "
+                a: func(x: u32)
+                c: func(x: u64) -> list<u8>
+                d: func(y: u32)
+                e: func()
+            ",
+        ),
+        [
+            "breaking: function `b`: removed",
+            "breaking: function `c`: parameter `x` changed type from `u32` to `u64`",
+            "breaking: function `c`: result changed type from `string` to `list<u8>`",
+            "breaking: function `d`: parameter `x` renamed to `y`",
+            "compatible: function `e`: added",
+        ]
+    );
+    assert_eq!(
+        changes("a: func(x: u32)", "a: func(x: u32, y: u32)"),
+        ["breaking: function `a`: parameters changed from `(x: u32)` to `(x: u32, y: u32)`"]
+    );
+    assert_eq!(
+        changes("a: func()", "a: async func()"),
+        ["compatible: function `a`: became async"]
+    );
+}
+
+#[test]
+fn records() {
+    assert_eq!(
+        changes(
+            "
+                record a { x: u32, y: u32 }
+                record b { x: u32 }
+                record c { x: u32, y: u32 }
+                record d { x: u32 }
+            ",
+            "
+                record a { x: u32 }
+                record b { x: u32, y: u32 }
+                record c { y: u32, x: u32 }
+                record d { x: string }
+            ",
+        ),
+        [
+            "breaking: type `a`: field `y` removed",
+            "breaking: type `b`: field `y` added, which changes the layout of the record",
+            "breaking: type `c`: field `x` moved",
+            "breaking: type `c`: field `y` moved",
+            "breaking: type `d`: field `x` changed type from `u32` to `string`",
+        ]
+    );
+}
+
+#[test]
+fn flags() {
+    assert_eq!(
+        changes(
+            "
+                flags a { x, y }
+                flags b { x, y }
+                flags c { x, y }
+                f: func(a: a, b: b) -> c
+            ",
+            "
+                flags a { x, z }
+                flags b { x, y, z }
+                flags c { x, y, z }
+                f: func(a: a, b: b) -> c
+            ",
+        ),
+        [
+            "breaking: type `a`: flag `y` renamed to `z`",
+            "compatible: type `b`: flag `z` added",
+            "breaking: type `c`: flag `z` added, which may be returned to components which don't know of it",
+        ]
+    );
+
+    // Enough flags to no longer fit in a byte change the layout.
+    let old = "flags a { a0, a1, a2, a3, a4, a5, a6, a7 }";
+    let new = "flags a { a0, a1, a2, a3, a4, a5, a6, a7, a8 }";
+    assert_eq!(
+        changes(old, new),
+        ["breaking: type `a`: flag `a8` added, which changes the size of the type"]
+    );
+}
+
+#[test]
+fn variants() {
+    assert_eq!(
+        changes(
+            "
+                variant a { x(u32), y }
+                variant b { x(u32), y }
+                enum c { x, y }
+                union d { u32, string }
+                f: func(a: a) -> b
+                g: func(c: c, d: d)
+            ",
+            "
+                variant a { x(u32), y, z(u8) }
+                variant b { x(u32), y, z }
+                enum c { x }
+                union d { u32, string, u8 }
+                f: func(a: a) -> b
+                g: func(c: c, d: d)
+            ",
+        ),
+        [
+            "compatible: type `a`: case `z` added",
+            "breaking: type `b`: case `z` added, which may be returned to components which don't know of it",
+            "breaking: type `c`: case `y` removed",
+            "compatible: type `d`: case `u8` added",
+        ]
+    );
+    assert_eq!(
+        changes("variant a { x(u32) }", "variant a { x(u64) }"),
+        ["breaking: type `a`: case `x` changed payload from `u32` to `u64`"]
+    );
+}
+
+#[test]
+fn canonical_abi() {
+    // Functions which are otherwise unchanged still break if the types they
+    // use are passed differently.
+    assert_eq!(
+        changes(
+            "
+                variant a { x(u32), y(float32) }
+                f: func(a: a)
+            ",
+            "
+                variant a { x(u32), y(float32), z(float64) }
+                f: func(a: a)
+            ",
+        ),
+        [
+            "breaking: type `a`: case `z` added, which changes the size of the type",
+            "breaking: function `f`: changed its canonical ABI signature",
+        ]
+    );
+}
+
+#[test]
+fn types() {
+    assert_eq!(
+        changes(
+            "
+                type a = u32
+                type b = list<u32>
+                record c {}
+                type d = u32
+            ",
+            "
+                type a = u64
+                type b = list<u32>
+                variant c { x }
+                type e = u32
+            ",
+        ),
+        [
+            "breaking: type `a`: changed from `u32` to `u64`",
+            "breaking: type `c`: changed from `record` to `variant`",
+            "breaking: type `d`: removed",
+            "compatible: type `e`: added",
+        ]
+    );
+}
+
+#[test]
+fn refinements() {
+    assert_eq!(
+        changes(
+            "
+                type a = u32 where 1..10
+                type b = u32
+                type c = string where len <= 8
+                type d = u32 where 1..
+            ",
+            "
+                type a = u32 where 1..=10
+                type b = u32 where ..10
+                type c = string
+                type d = u32 where 1..
+            ",
+        ),
+        [
+            "breaking: type `a`: refinement changed from `where 1..10` to `where 1..=10`",
+            "breaking: type `b`: refinement `where ..10` added",
+            "breaking: type `c`: refinement `where len <= 8` removed",
+        ]
+    );
+}
+
+#[test]
+fn constants_and_defaults() {
+    assert_eq!(
+        changes(
+            "
+                const a: u32 = 1
+                const b: string = \"x\"
+                const c: u32 = 1
+                record r { x: u32 = 1, y: u32 }
+                f: func(x: u32 = 1, y: bool)
+            ",
+            "
+                const a: u32 = 2
+                const c: u64 = 1
+                const d: bool = true
+                record r { x: u32, y: u32 = 2 }
+                f: func(x: u32 = 3, y: bool = false)
+            ",
+        ),
+        [
+            "compatible: type `r`: field `x` default of `1` removed",
+            "compatible: type `r`: field `y` default of `2` added",
+            "compatible: function `f`: parameter `x` default changed from `1` to `3`",
+            "compatible: function `f`: parameter `y` default of `false` added",
+            "compatible: constant `a`: changed from `1` to `2`",
+            "compatible: constant `b`: removed",
+            "compatible: constant `c`: changed type from `u32` to `u64`",
+            "compatible: constant `d`: added",
+        ]
+    );
+}
@@ -0,0 +1,304 @@
+//! Round trips values through `wit_parser::abi::Interpreter`, lowering them
+//! on one side of a call and lifting them on the other, for both imports and
+//! exports.
+
+use wit_parser::abi::{AbiVariant, Interpreter, Memory, Value, WasmValue};
+use wit_parser::Interface;
+
+const WIT: &str = "
+record point {
+  x: s32,
+  y: float64,
+}
+
+flags many {
+  f0, f1, f2, f3, f4, f5, f6, f7, f8, f9,
+  f10, f11, f12, f13, f14, f15, f16, f17, f18, f19,
+  f20, f21, f22, f23, f24, f25, f26, f27, f28, f29,
+  f30, f31, f32, f33, f34, f35,
+}
+
+variant mixed {
+  int(s64),
+  float(float32),
+  text(string),
+  nothing,
+}
+
+enum color {
+  red,
+  green,
+}
+
+union number {
+  u8,
+  float64,
+}
+
+type port = u16 where 1..=65535
+
+variant tree {
+  leaf(u32),
+  node(list<tree>),
+}
+
+record linked-list {
+  value: string,
+  next: option<linked-list>,
+}
+
+resource blob
+
+scalars: func(a: bool, b: s8, c: u16, d: s64, e: float32, f: char) -> tuple<u64, float64>
+strings: func(a: string, b: list<string>) -> string
+lists: func(a: list<u8>, b: list<char>, c: list<point>, d: list<list<mixed>>) -> list<point>
+aggregates: func(a: point, b: many, c: color, d: number) -> many
+variants: func(a: mixed, b: option<string>) -> result<mixed, string>
+refined: func(p: port) -> port
+recursive: func(t: tree, l: linked-list) -> linked-list
+handles: func(b: blob) -> blob
+wide: func(
+  a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64, a7: u64,
+  a8: u64, a9: u64, a10: u64, a11: u64, a12: u64, a13: u64, a14: u64, a15: string,
+) -> list<u64>
+";
+
+fn point(x: i32, y: f64) -> Value {
+    Value::Record(vec![Value::S32(x), Value::Float64(y)])
+}
+
+fn text(s: &str) -> Value {
+    Value::String(s.to_string())
+}
+
+fn some(val: Value) -> Option<Box<Value>> {
+    Some(Box::new(val))
+}
+
+fn leaf(x: u32) -> Value {
+    Value::Variant(0, some(Value::U32(x)))
+}
+
+fn linked(values: &[&str]) -> Value {
+    values
+        .iter()
+        .rev()
+        .fold(None, |next, value| {
+            Some(Value::Record(vec![
+                text(value),
+                Value::Option(next.map(Box::new)),
+            ]))
+        })
+        .unwrap()
+}
+
+/// Returns the arguments and results of a call to each function.
+fn calls() -> Vec<(&'static str, Vec<Value>, Vec<Value>)> {
+    let mut flags = vec![false; 36];
+    flags[1] = true;
+    flags[33] = true;
+    vec![
+        (
+            "scalars",
+            vec![
+                Value::Bool(true),
+                Value::S8(-3),
+                Value::U16(65535),
+                Value::S64(i64::MIN),
+                Value::Float32(1.5),
+                Value::Char('☃'),
+            ],
+            vec![Value::Tuple(vec![
+                Value::U64(u64::MAX),
+                Value::Float64(-0.25),
+            ])],
+        ),
+        (
+            "strings",
+            vec![text("hello"), Value::List(vec![text(""), text("wörld")])],
+            vec![text("")],
+        ),
+        (
+            "lists",
+            vec![
+                Value::List(vec![Value::U8(0), Value::U8(255)]),
+                Value::List(vec![Value::Char('a'), Value::Char('𝄞')]),
+                Value::List(vec![point(1, 2.0), point(-1, 0.5)]),
+                Value::List(vec![
+                    Value::List(vec![]),
+                    Value::List(vec![
+                        Value::Variant(2, some(text("x"))),
+                        Value::Variant(3, None),
+                    ]),
+                ]),
+            ],
+            vec![Value::List(vec![point(7, 8.5)])],
+        ),
+        (
+            "aggregates",
+            vec![
+                point(i32::MIN, f64::MAX),
+                Value::Flags(flags.clone()),
+                Value::Enum(1),
+                Value::Union(1, Box::new(Value::Float64(3.25))),
+            ],
+            vec![Value::Flags(flags)],
+        ),
+        (
+            "variants",
+            vec![
+                Value::Variant(1, some(Value::Float32(-2.5))),
+                Value::Option(some(text("some"))),
+            ],
+            vec![Value::Result(Ok(some(Value::Variant(
+                0,
+                some(Value::S64(-1)),
+            ))))],
+        ),
+        ("refined", vec![Value::U16(80)], vec![Value::U16(443)]),
+        (
+            "recursive",
+            vec![
+                Value::Variant(
+                    1,
+                    some(Value::List(vec![
+                        leaf(1),
+                        Value::Variant(1, some(Value::List(vec![leaf(2)]))),
+                    ])),
+                ),
+                linked(&["a", "b", "c"]),
+            ],
+            vec![linked(&["d"])],
+        ),
+        ("handles", vec![Value::Handle(3)], vec![Value::Handle(4)]),
+        (
+            "wide",
+            (0..15).map(Value::U64).chain([text("last")]).collect(),
+            vec![Value::List(vec![Value::U64(1), Value::U64(2)])],
+        ),
+    ]
+}
+
+#[test]
+fn guest_import() {
+    let iface = Interface::parse("interpreter.wit", WIT).unwrap();
+    let interp = Interpreter::new(&iface);
+    for (name, args, results) in calls() {
+        let func = iface.functions.iter().find(|f| f.name == name).unwrap();
+        let mut memory = Memory::new();
+        let lifted = interp
+            .call_wasm(
+                AbiVariant::GuestImport,
+                func,
+                &args,
+                &mut memory,
+                |params, memory| {
+                    interp.call_interface(AbiVariant::GuestImport, func, params, memory, |lifted| {
+                        assert_eq!(lifted, args, "{}", name);
+                        Ok(results.clone())
+                    })
+                },
+            )
+            .unwrap();
+        assert_eq!(lifted, results, "{}", name);
+        assert_eq!(memory.live_allocations().count(), 0, "{}", name);
+    }
+}
+
+#[test]
+fn guest_export() {
+    let iface = Interface::parse("interpreter.wit", WIT).unwrap();
+    let interp = Interpreter::new(&iface);
+    for (name, args, results) in calls() {
+        let func = iface.functions.iter().find(|f| f.name == name).unwrap();
+        let mut memory = Memory::new();
+        let mut returned = Vec::new();
+        let lifted = interp
+            .call_wasm(
+                AbiVariant::GuestExport,
+                func,
+                &args,
+                &mut memory,
+                |params, memory| {
+                    returned = interp.call_interface(
+                        AbiVariant::GuestExport,
+                        func,
+                        params,
+                        memory,
+                        |lifted| {
+                            assert_eq!(lifted, args, "{}", name);
+                            Ok(results.clone())
+                        },
+                    )?;
+                    Ok(returned.clone())
+                },
+            )
+            .unwrap();
+        assert_eq!(lifted, results, "{}", name);
+        interp.post_return(func, &returned, &mut memory).unwrap();
+        assert_eq!(memory.live_allocations().count(), 0, "{}", name);
+    }
+}
+
+#[test]
+fn invalid() {
+    let iface = Interface::parse("interpreter.wit", WIT).unwrap();
+    let interp = Interpreter::new(&iface);
+    let func = |name| iface.functions.iter().find(|f| f.name == name).unwrap();
+    let lift = |name, params: &[WasmValue]| {
+        interp
+            .call_interface(
+                AbiVariant::GuestExport,
+                func(name),
+                params,
+                &mut Memory::new(),
+                |_| unreachable!(),
+            )
+            .unwrap_err()
+            .to_string()
+    };
+
+    let mut scalars = vec![
+        WasmValue::I32(2),
+        WasmValue::I32(0),
+        WasmValue::I32(0),
+        WasmValue::I64(0),
+        WasmValue::F32(0.0),
+        WasmValue::I32(0),
+    ];
+    assert_eq!(lift("scalars", &scalars), "invalid bool 2");
+    scalars[0] = WasmValue::I32(1);
+    scalars[5] = WasmValue::I32(0xd800);
+    assert_eq!(lift("scalars", &scalars), "invalid char 0xd800");
+
+    assert_eq!(
+        lift("refined", &[WasmValue::I32(0)]),
+        "U16(0) doesn't satisfy `where 1..=65535`"
+    );
+    assert_eq!(
+        lift(
+            "variants",
+            &[
+                WasmValue::I32(4),
+                WasmValue::I64(0),
+                WasmValue::I32(0),
+                WasmValue::I32(0),
+                WasmValue::I32(0),
+                WasmValue::I32(0),
+            ]
+        ),
+        "invalid discriminant 4"
+    );
+    assert_eq!(
+        lift(
+            "strings",
+            &[
+                WasmValue::I32(4096),
+                WasmValue::I32(1),
+                WasmValue::I32(0),
+                WasmValue::I32(0)
+            ]
+        ),
+        "out of bounds access of 1 bytes at 0x1000"
+    );
+}
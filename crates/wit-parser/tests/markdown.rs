@@ -0,0 +1,51 @@
+use std::path::Path;
+use wit_parser::{Interface, ParseOptions};
+
+fn parse(name: &str) -> Interface {
+    let path = Path::new("tests/ui").join(name);
+    Interface::parse_file_with(path, &ParseOptions::default())
+        .unwrap()
+        .0
+}
+
+fn docs<'a>(iface: &'a Interface, name: &str) -> &'a str {
+    let docs = match iface.type_lookup.get(name) {
+        Some(id) => &iface.types[*id].docs,
+        None => {
+            &iface
+                .functions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap()
+                .docs
+        }
+    };
+    docs.contents.as_deref().unwrap_or("")
+}
+
+#[test]
+fn prose_documents_the_next_item() {
+    let iface = parse("literate.wit.md");
+    assert_eq!(
+        docs(&iface, "point"),
+        "A point on the plane.\n\nCoordinates are in pixels.\n\nBoth are non-negative.\n"
+    );
+    assert_eq!(
+        docs(&iface, "move"),
+        "An example of using it, which isn't part of the interface:\n\n\
+         ```wit,ignore\n\
+         origin: func() -> point { x: 0, y: 0 }\n\
+         ```\n\n\
+         Moves a point.\n"
+    );
+    assert_eq!(
+        docs(&iface, "distance"),
+        "- Code blocks nested in lists keep their columns:\n"
+    );
+}
+
+#[test]
+fn headings_end_prose() {
+    let iface = parse("import-me-too.wit.md");
+    assert_eq!(docs(&iface, "foo"), "This is foo.\n");
+}
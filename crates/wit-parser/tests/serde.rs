@@ -0,0 +1,127 @@
+#![cfg(feature = "serde")]
+
+use serde_json::json;
+use wit_parser::Interface;
+
+#[test]
+fn json_schema() {
+    let iface = Interface::parse(
+        "example",
+        "
+            /// A point.
+            @rust(derive = \"Hash\")
+            record point { x: u32 = 1, y: option<float64> }
+            resource blob {
+                size: func() -> u64
+            }
+            variant shape { circle(float32), none }
+            type shapes = list<shape>
+
+            const max-points: u32 = 10
+
+            area: async func(s: shape, scale: float32 = 1.5) -> float32
+            points: func() -> (a: point, b: point)
+        ",
+    )
+    .unwrap();
+    let actual = serde_json::to_value(&iface).unwrap();
+    let expected = json!({
+        "name": "example",
+        "module": null,
+        "types": [
+            {
+                "name": "point",
+                "docs": "A point.\n",
+                "attrs": [
+                    {"name": "rust", "args": [{"key": "derive", "value": {"string": "Hash"}}]},
+                ],
+                "kind": {
+                    "record": {
+                        "fields": [
+                            {"name": "x", "docs": null, "type": "u32", "default": {"integer": 1}},
+                            {"name": "y", "docs": null, "type": {"id": 4}, "default": null},
+                        ],
+                    },
+                },
+                "foreign_module": null,
+            },
+            {
+                "name": "blob",
+                "docs": null,
+                "kind": "resource",
+                "foreign_module": null,
+            },
+            {
+                "name": "shape",
+                "docs": null,
+                "kind": {
+                    "variant": {
+                        "cases": [
+                            {"name": "circle", "docs": null, "type": "float32"},
+                            {"name": "none", "docs": null, "type": null},
+                        ],
+                    },
+                },
+                "foreign_module": null,
+            },
+            {
+                "name": "shapes",
+                "docs": null,
+                "kind": {"list": {"id": 2}},
+                "foreign_module": null,
+            },
+            {
+                "name": null,
+                "docs": null,
+                "kind": {"option": "float64"},
+                "foreign_module": null,
+            },
+            {
+                "name": null,
+                "docs": null,
+                "kind": {"handle": {"borrow": 1}},
+                "foreign_module": null,
+            },
+        ],
+        "interfaces": [],
+        "functions": [
+            {
+                "name": "blob::size",
+                "docs": null,
+                "kind": {"method": {"resource": 1, "name": "size"}},
+                "async": false,
+                "params": [["self", {"id": 5}]],
+                "defaults": [null],
+                "results": {"anon": "u64"},
+            },
+            {
+                "name": "area",
+                "docs": null,
+                "kind": "freestanding",
+                "async": true,
+                "params": [["s", {"id": 2}], ["scale", "float32"]],
+                "defaults": [null, {"float": 1.5}],
+                "results": {"anon": "float32"},
+            },
+            {
+                "name": "points",
+                "docs": null,
+                "kind": "freestanding",
+                "async": false,
+                "params": [],
+                "defaults": [],
+                "results": {"named": [["a", {"id": 0}], ["b", {"id": 0}]]},
+            },
+        ],
+        "globals": [],
+        "constants": [
+            {"name": "max-points", "docs": null, "type": "u32", "value": {"integer": 10}},
+        ],
+    });
+    assert_eq!(
+        actual,
+        expected,
+        "{}",
+        serde_json::to_string_pretty(&actual).unwrap()
+    );
+}
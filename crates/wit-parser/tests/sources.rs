@@ -0,0 +1,80 @@
+use wit_parser::{Interface, ParseOptions, WitCache, WitSources};
+
+fn options(sources: WitSources) -> ParseOptions {
+    ParseOptions {
+        search_paths: vec!["registry".into()],
+        sources,
+    }
+}
+
+#[test]
+fn memory() {
+    let sources = [
+        (
+            "app/api.wit",
+            "
+                use { id } from types
+                use { input-stream } from wasi:io/streams@0.2.0
+                get: func(s: input-stream) -> id
+            ",
+        ),
+        ("app/types.wit", "type id = u32"),
+        (
+            "registry/wasi/io@0.2.0/streams.wit",
+            "resource input-stream",
+        ),
+    ]
+    .into_iter()
+    .collect::<WitSources>();
+
+    let (iface, graph) =
+        Interface::parse_file_with("app/api.wit", &options(sources.clone())).unwrap();
+    assert_eq!(iface.functions[0].name, "get");
+    let paths = graph
+        .dependencies
+        .iter()
+        .map(|d| d.path.to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        ["app/types.wit", "registry/wasi/io@0.2.0/streams.wit"]
+    );
+
+    let mut cache = WitCache::new(options(sources));
+    cache.parse_file("app/api.wit").unwrap();
+    assert_eq!(cache.files().count(), 3);
+}
+
+#[test]
+fn memory_only() {
+    // Files in memory never fall back to the filesystem, even for paths which
+    // exist on disk.
+    let sources = [("tests/ui/root.wit", "use { x } from import-me")]
+        .into_iter()
+        .collect::<WitSources>();
+    let err = Interface::parse_file_with("tests/ui/root.wit", &options(sources)).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("failed to load `import-me`"),
+        "{:?}",
+        err
+    );
+    let err = Interface::parse_file_with("Cargo.toml", &options(WitSources::memory())).unwrap_err();
+    assert!(
+        format!("{:?}", err).contains("failed to read: Cargo.toml"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn overlay() {
+    // Files inserted into filesystem sources take precedence over the disk.
+    let mut sources = WitSources::fs();
+    sources.insert("tests/ui/types.wit", "type id = u32");
+    sources.insert(
+        "tests/ui/root.wit",
+        "use { id } from types\nf: func() -> id",
+    );
+    let (iface, _) = Interface::parse_file_with("tests/ui/root.wit", &options(sources)).unwrap();
+    assert_eq!(iface.types.iter().count(), 1);
+}
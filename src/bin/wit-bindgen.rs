@@ -1,7 +1,13 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
-use std::path::PathBuf;
-use wit_bindgen_core::{wit_parser, Files, Generator};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use wit_bindgen_core::lint::Naming;
+use wit_bindgen_core::registry::{Options, Registry};
+use wit_bindgen_core::{wit_parser, Files, Generator, UnsupportedFeature};
+use wit_parser::abi::{AbiVariant, LiftLower};
 use wit_parser::Interface;
 
 /// Helper for passing VERSION to opt.
@@ -34,6 +40,151 @@ enum Category {
         #[clap(flatten)]
         world: World,
     },
+    /// Generates bindings with the named generator, which is either one of
+    /// those printed by `wit-bindgen list` or an external executable named
+    /// `wit-bindgen-<generator>` found on `PATH`.
+    ///
+    /// External generators read a JSON object from stdin with the
+    /// `imports` and `exports` interfaces, in the form printed by
+    /// `wit-bindgen json`, and the `options` given to them, mapping names to
+    /// either `true` for flags or a string. They print a JSON object mapping
+    /// the names of the files to generate to their contents.
+    Gen {
+        /// The name of the generator.
+        generator: String,
+
+        /// An option for the generator, written as `name` for flags or
+        /// `name=value` otherwise. Can be specified multiple times.
+        #[clap(long = "option", short = 'O')]
+        options: Vec<String>,
+
+        #[clap(flatten)]
+        common: Common,
+        #[clap(flatten)]
+        world: World,
+    },
+    /// Lists the built-in generators for `wit-bindgen gen` and their options.
+    ///
+    /// The JS host generator isn't among them, as it takes a component
+    /// rather than `*.wit` files. It's run with `wit-bindgen host js`.
+    List,
+    /// Formats `*.wit` files in place in the canonical style.
+    Fmt {
+        /// The `*.wit` files to format.
+        #[clap(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Don't write anything, instead exiting with an error if any of the
+        /// files aren't already formatted.
+        #[clap(long)]
+        check: bool,
+    },
+    /// Lists the changes between two versions of a `*.wit` interface,
+    /// exiting with an error if any of them would break components built
+    /// against the old version.
+    Compat {
+        /// The old version of the interface.
+        old: PathBuf,
+        /// The new version of the interface.
+        new: PathBuf,
+
+        /// Additional directory to search for packages referenced by `use`
+        /// statements. Can be specified multiple times.
+        #[clap(long = "search-path", short = 'L')]
+        search_paths: Vec<PathBuf>,
+    },
+    /// Reports the names in a `*.wit` interface which generated bindings
+    /// rename, or which become reserved words or collide once converted to
+    /// identifiers, exiting with an error if any of them can't be used.
+    Lint {
+        /// The `*.wit` interface to check.
+        wit: PathBuf,
+
+        /// A language to check names against. Can be specified multiple
+        /// times, and defaults to every language.
+        #[clap(long = "language", short = 'l', value_enum)]
+        languages: Vec<Language>,
+
+        /// Additional directory to search for packages referenced by `use`
+        /// statements. Can be specified multiple times.
+        #[clap(long = "search-path", short = 'L')]
+        search_paths: Vec<PathBuf>,
+    },
+    /// Prints the instructions which bindings for the functions of a `*.wit`
+    /// interface are generated from, for debugging how values are lifted and
+    /// lowered.
+    Abi {
+        /// The `*.wit` interface to print.
+        wit: PathBuf,
+
+        /// The function to print, defaulting to every function.
+        #[clap(long)]
+        func: Option<String>,
+
+        /// Which side of the call to print the bindings for.
+        #[clap(long, value_enum, default_value = "guest-import")]
+        variant: AbiSide,
+
+        /// Additional directory to search for packages referenced by `use`
+        /// statements. Can be specified multiple times.
+        #[clap(long = "search-path", short = 'L')]
+        search_paths: Vec<PathBuf>,
+    },
+    /// Prints the parsed and resolved form of a `*.wit` interface as JSON.
+    ///
+    /// The schema is documented on `wit_parser::Interface`.
+    Json {
+        /// The `*.wit` interface to print.
+        wit: PathBuf,
+
+        /// Additional directory to search for packages referenced by `use`
+        /// statements. Can be specified multiple times.
+        #[clap(long = "search-path", short = 'L')]
+        search_paths: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Language {
+    Rust,
+    Python,
+    Java,
+    Js,
+}
+
+/// A side of a call between a guest and a host, for `wit-bindgen abi`.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum AbiSide {
+    /// A guest calling a function it imports.
+    GuestImport,
+    /// A guest implementing a function it exports.
+    GuestExport,
+    /// A host implementing a function guests import.
+    HostImport,
+    /// A host calling a function a guest exports.
+    HostExport,
+}
+
+impl AbiSide {
+    fn abi(&self) -> (AbiVariant, LiftLower) {
+        match self {
+            AbiSide::GuestImport => (AbiVariant::GuestImport, LiftLower::LowerArgsLiftResults),
+            AbiSide::GuestExport => (AbiVariant::GuestExport, LiftLower::LiftArgsLowerResults),
+            AbiSide::HostImport => (AbiVariant::GuestImport, LiftLower::LiftArgsLowerResults),
+            AbiSide::HostExport => (AbiVariant::GuestExport, LiftLower::LowerArgsLiftResults),
+        }
+    }
+}
+
+impl Language {
+    fn naming(&self) -> &'static dyn Naming {
+        match self {
+            Language::Rust => &wit_bindgen_gen_rust_lib::RustNaming,
+            Language::Python => &wit_bindgen_gen_host_wasmtime_py::PyNaming,
+            Language::Java => &wit_bindgen_gen_guest_teavm_java::JavaNaming,
+            Language::Js => &wit_bindgen_gen_host_js::JsNaming,
+        }
+    }
 }
 
 #[derive(Debug, Parser)]
@@ -112,6 +263,17 @@ struct World {
     /// specified multiple times.
     #[clap(long = "export", short)]
     exports: Vec<PathBuf>,
+
+    /// Generate bindings for all imports and exports of the `world` defined
+    /// in the given `*.wit` file.
+    #[clap(long = "world", short)]
+    world: Option<PathBuf>,
+
+    /// Additional directory to search for packages referenced by `use`
+    /// statements, after the `deps` directory next to each `*.wit` file. Can
+    /// be specified multiple times.
+    #[clap(long = "search-path", short = 'L')]
+    search_paths: Vec<PathBuf>,
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -119,26 +281,76 @@ struct Common {
     /// Where to place output files
     #[clap(long = "out-dir")]
     out_dir: Option<PathBuf>,
+
+    /// Print errors in `*.wit` files as JSON, for consumption by editors and
+    /// other tools.
+    #[clap(long = "json-diagnostics")]
+    json_diagnostics: bool,
 }
 
 impl Opt {
-    fn common(&self) -> &Common {
-        match &self.category {
+    fn common(&self) -> Option<&Common> {
+        let common = match &self.category {
             Category::Guest(GuestGenerator::Rust { common, .. })
             | Category::Guest(GuestGenerator::C { common, .. })
             | Category::Guest(GuestGenerator::TeavmJava { common, .. })
             | Category::Host(HostGenerator::WasmtimeRust { common, .. })
             | Category::Host(HostGenerator::WasmtimePy { common, .. })
             | Category::Host(HostGenerator::Js { common, .. })
-            | Category::Markdown { common, .. } => common,
-        }
+            | Category::Markdown { common, .. }
+            | Category::Gen { common, .. } => common,
+            Category::List
+            | Category::Fmt { .. }
+            | Category::Compat { .. }
+            | Category::Lint { .. }
+            | Category::Abi { .. }
+            | Category::Json { .. } => return None,
+        };
+        Some(common)
     }
 }
 
 fn main() -> Result<()> {
     let opt: Opt = Opt::parse();
-    let common = opt.common().clone();
+    let json_diagnostics = opt.common().map_or(false, |c| c.json_diagnostics);
+    match run(opt) {
+        Err(e) if json_diagnostics => match e.downcast_ref::<wit_parser::Diagnostics>() {
+            Some(diagnostics) => {
+                println!("{}", diagnostics.to_json());
+                std::process::exit(1);
+            }
+            None => Err(e),
+        },
+        result => result,
+    }
+}
 
+fn run(opt: Opt) -> Result<()> {
+    let common = match opt.common() {
+        Some(common) => common.clone(),
+        None => match &opt.category {
+            Category::Fmt { files, check } => return fmt(files, *check),
+            Category::Compat {
+                old,
+                new,
+                search_paths,
+            } => return compat(old, new, search_paths),
+            Category::Lint {
+                wit,
+                languages,
+                search_paths,
+            } => return lint(wit, languages, search_paths),
+            Category::Abi {
+                wit,
+                func,
+                variant,
+                search_paths,
+            } => return abi(wit, func.as_deref(), *variant, search_paths),
+            Category::Json { wit, search_paths } => return json(wit, search_paths),
+            Category::List => return list(),
+            _ => unreachable!(),
+        },
+    };
     let mut files = Files::default();
     match opt.category {
         Category::Guest(GuestGenerator::Rust { opts, world, .. }) => {
@@ -175,6 +387,24 @@ fn main() -> Result<()> {
         Category::Markdown { opts, world, .. } => {
             gen_world(Box::new(opts.build()), world, &mut files)?;
         }
+        Category::Gen {
+            generator,
+            options,
+            world,
+            ..
+        } => {
+            let options = Options::parse(options.iter().map(|s| s.as_str()));
+            match registry().get(&generator) {
+                Some(registration) => gen_world(registration.build(&options)?, world, &mut files)?,
+                None => gen_external(&generator, &options, world, &mut files)?,
+            }
+        }
+        Category::List
+        | Category::Fmt { .. }
+        | Category::Compat { .. }
+        | Category::Lint { .. }
+        | Category::Abi { .. }
+        | Category::Json { .. } => unreachable!(),
     }
 
     for (name, contents) in files.iter() {
@@ -193,18 +423,259 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn fmt(paths: &[PathBuf], check: bool) -> Result<()> {
+    let mut unformatted = Vec::new();
+    for path in paths {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+        let formatted = wit_parser::format(path, &contents)?;
+        if formatted == contents {
+            continue;
+        }
+        if check {
+            unformatted.push(path.display().to_string());
+        } else {
+            std::fs::write(path, formatted)
+                .with_context(|| format!("failed to write {:?}", path))?;
+        }
+    }
+    if !unformatted.is_empty() {
+        bail!("files are not formatted:\n  {}", unformatted.join("\n  "));
+    }
+    Ok(())
+}
+
+fn compat(old: &Path, new: &Path, search_paths: &[PathBuf]) -> Result<()> {
+    // Both versions commonly use the same dependencies, which are only
+    // parsed once.
+    let mut cache = wit_parser::WitCache::new(wit_parser::ParseOptions {
+        search_paths: search_paths.to_vec(),
+        ..wit_parser::ParseOptions::default()
+    });
+    let (old, _) = cache.parse_file(old)?;
+    let (new, _) = cache.parse_file(new)?;
+    let changes = wit_parser::compat::compare(&old, &new);
+    print!("{}", changes);
+    if changes.is_breaking() {
+        bail!("the new interface has breaking changes");
+    }
+    Ok(())
+}
+
+fn lint(wit: &Path, languages: &[Language], search_paths: &[PathBuf]) -> Result<()> {
+    let options = wit_parser::ParseOptions {
+        search_paths: search_paths.to_vec(),
+        ..wit_parser::ParseOptions::default()
+    };
+    let (iface, _) = Interface::parse_file_with(wit, &options)?;
+    let languages = if languages.is_empty() {
+        &[
+            Language::Rust,
+            Language::Python,
+            Language::Java,
+            Language::Js,
+        ][..]
+    } else {
+        languages
+    };
+    let namings = languages.iter().map(|l| l.naming()).collect::<Vec<_>>();
+    let lints = wit_bindgen_core::lint::lint(&iface, &namings);
+    print!("{}", lints);
+    if lints.has_errors() {
+        bail!("some names can't be used in generated bindings");
+    }
+    Ok(())
+}
+
+fn abi(wit: &Path, func: Option<&str>, side: AbiSide, search_paths: &[PathBuf]) -> Result<()> {
+    let options = wit_parser::ParseOptions {
+        search_paths: search_paths.to_vec(),
+        ..wit_parser::ParseOptions::default()
+    };
+    let (iface, _) = Interface::parse_file_with(wit, &options)?;
+    let (variant, lift_lower) = side.abi();
+    match func {
+        Some(name) => {
+            let func = iface
+                .functions
+                .iter()
+                .find(|f| f.name == name)
+                .ok_or_else(|| anyhow!("no function named `{}` in {:?}", name, wit))?;
+            print!(
+                "{}",
+                wit_parser::abi::record(&iface, variant, lift_lower, func)
+            );
+        }
+        None => {
+            for (i, func) in iface.functions.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                println!("// {}", func.name);
+                print!(
+                    "{}",
+                    wit_parser::abi::record(&iface, variant, lift_lower, func)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn json(wit: &Path, search_paths: &[PathBuf]) -> Result<()> {
+    let options = wit_parser::ParseOptions {
+        search_paths: search_paths.to_vec(),
+        ..wit_parser::ParseOptions::default()
+    };
+    let (iface, _) = Interface::parse_file_with(wit, &options)?;
+    println!("{}", serde_json::to_string_pretty(&iface)?);
+    Ok(())
+}
+
+/// Returns every generator built into the CLI.
+///
+/// The JS host generator isn't registered: it generates bindings for a
+/// component binary, instantiating its core modules, rather than implementing
+/// `Generator` over `*.wit` interfaces as registered generators do.
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    registry.register(wit_bindgen_gen_guest_rust::registration());
+    registry.register(wit_bindgen_gen_guest_c::registration());
+    registry.register(wit_bindgen_gen_guest_teavm_java::registration());
+    registry.register(wit_bindgen_gen_host_wasmtime_rust::registration());
+    registry.register(wit_bindgen_gen_host_wasmtime_py::registration());
+    registry.register(wit_bindgen_gen_markdown::registration());
+    registry
+}
+
+fn list() -> Result<()> {
+    for registration in registry().iter() {
+        println!("{}", registration.name());
+        println!("    {}", registration.description());
+        for option in registration.options() {
+            println!("    -O {}", option);
+            println!("        {}", option.description);
+        }
+    }
+    Ok(())
+}
+
 fn gen_world(mut generator: Box<dyn Generator>, world: World, files: &mut Files) -> Result<()> {
-    let imports = world
+    let (imports, exports) = load_world(&world)?;
+    // The interfaces given by path come last, see `load_world`, and are
+    // checked up front so that constructs the generator doesn't support can
+    // be pointed out in their files.
+    let given = world
         .imports
         .iter()
-        .map(|wit| Interface::parse_file(wit))
-        .collect::<Result<Vec<_>>>()?;
-    let exports = world
-        .exports
+        .zip(&imports[imports.len() - world.imports.len()..])
+        .chain(
+            world
+                .exports
+                .iter()
+                .zip(&exports[exports.len() - world.exports.len()..]),
+        );
+    for (path, iface) in given {
+        generator
+            .validate(iface)
+            .map_err(|e| locate_unsupported(e, path))?;
+    }
+    generator.generate_all(&imports, &exports, files)
+}
+
+/// Turns an [`UnsupportedFeature`] error into a diagnostic showing where it
+/// was used in `path`, the file its interface was parsed from, if it has a
+/// span.
+fn locate_unsupported(e: anyhow::Error, path: &Path) -> anyhow::Error {
+    let span = match e.downcast_ref::<UnsupportedFeature>() {
+        Some(UnsupportedFeature {
+            span: Some(span), ..
+        }) => *span,
+        _ => return e,
+    };
+    let mut contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return e,
+    };
+    if path.extension().and_then(|s| s.to_str()) == Some("md") {
+        contents = wit_parser::unwrap_md(&contents);
+    }
+    let diagnostic = wit_parser::Diagnostic::error(path, &contents, span, e.to_string());
+    wit_parser::Diagnostics::from(diagnostic).into()
+}
+
+/// Generates bindings with the executable `wit-bindgen-<name>`, as described
+/// on `Category::Gen`.
+fn gen_external(name: &str, options: &Options, world: World, files: &mut Files) -> Result<()> {
+    let (imports, exports) = load_world(&world)?;
+    let options = options
         .iter()
-        .map(|wit| Interface::parse_file(wit))
-        .collect::<Result<Vec<_>>>()?;
+        .map(|(name, value)| {
+            let value = match value {
+                Some(value) => serde_json::Value::from(value),
+                None => serde_json::Value::from(true),
+            };
+            (name.to_string(), value)
+        })
+        .collect::<serde_json::Map<_, _>>();
+    let input = serde_json::to_vec(&serde_json::json!({
+        "imports": imports,
+        "exports": exports,
+        "options": options,
+    }))?;
 
-    generator.generate_all(&imports, &exports, files);
+    let program = format!("wit-bindgen-{}", name);
+    let mut child = Command::new(&program)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| {
+            format!(
+                "unknown generator `{}`, and failed to run `{}`",
+                name, program
+            )
+        })?;
+    // Write from another thread so a generator which prints before it has
+    // read all of its input can't deadlock.
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!("`{}` failed: {}", program, output.status);
+    }
+    writer
+        .join()
+        .unwrap()
+        .with_context(|| format!("failed to write to `{}`", program))?;
+
+    let generated: BTreeMap<String, String> = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("`{}` didn't print a JSON object of files", program))?;
+    for (name, contents) in generated {
+        files.push(&name, contents.as_bytes());
+    }
     Ok(())
 }
+
+/// Parses the interfaces `world` imports and exports, with those of its
+/// world file, if any, before those given by path.
+fn load_world(world: &World) -> Result<(Vec<Interface>, Vec<Interface>)> {
+    let mut cache = wit_parser::WitCache::new(wit_parser::ParseOptions {
+        search_paths: world.search_paths.clone(),
+        ..wit_parser::ParseOptions::default()
+    });
+    let mut imports = Vec::new();
+    let mut exports = Vec::new();
+    if let Some(path) = &world.world {
+        let (world, _) = cache.parse_world_file(path)?;
+        imports.extend(world.imports.into_values());
+        exports.extend(world.default);
+        exports.extend(world.exports.into_values());
+    }
+    for wit in world.imports.iter() {
+        imports.push(cache.parse_file(wit)?.0);
+    }
+    for wit in world.exports.iter() {
+        exports.push(cache.parse_file(wit)?.0);
+    }
+    Ok((imports, exports))
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Poll, Wake, Waker};
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Engine, Store};
+
+wit_bindgen_host_wasmtime_rust::export!("../../tests/runtime/async_functions/imports.wit");
+
+#[derive(Default)]
+pub struct MyImports {
+    async_calls: u32,
+}
+
+#[wit_bindgen_host_wasmtime_rust::async_trait]
+impl imports::Imports for MyImports {
+    async fn async_add(&mut self, a: u32, b: u32) -> u32 {
+        self.async_calls += 1;
+        a + b
+    }
+
+    fn sync_double(&mut self, a: u32) -> u32 {
+        a * 2
+    }
+}
+
+wit_bindgen_host_wasmtime_rust::import!("../../tests/runtime/async_functions/exports.wit");
+
+fn run(wasm: &str) -> Result<()> {
+    // Async host functions need an async engine, with which every call into
+    // wasm, including instantiation and calls to sync exports, must be async.
+    let mut config = crate::default_config()?;
+    config.async_support(true);
+    let engine = Engine::new(&config)?;
+    let component = Component::from_file(&engine, wasm)?;
+
+    let mut linker = Linker::new(&engine);
+    imports::add_to_linker(
+        &mut linker,
+        |cx: &mut crate::Context<MyImports>| -> &mut MyImports { &mut cx.imports },
+    )?;
+    crate::testwasi::add_to_linker(&mut linker, |cx| &mut cx.testwasi)?;
+    let mut store = Store::new(&engine, crate::Context::default());
+
+    block_on(async {
+        let (exports, _instance) =
+            exports::Exports::instantiate_async(&mut store, &component, &linker).await?;
+
+        assert_eq!(exports.run_async(&mut store, 1).await?, 2);
+        assert_eq!(exports.run_sync(&mut store, 3).await?, 6);
+        assert_eq!(store.data().imports.async_calls, 1);
+        Ok(())
+    })
+}
+
+/// Runs `future` to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(std::thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(result) => break result,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
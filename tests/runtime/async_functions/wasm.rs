@@ -0,0 +1,15 @@
+wit_bindgen_guest_rust::import!("../../tests/runtime/async_functions/imports.wit");
+wit_bindgen_guest_rust::export!("../../tests/runtime/async_functions/exports.wit");
+
+struct Exports;
+
+#[wit_bindgen_guest_rust::async_trait(?Send)]
+impl exports::Exports for Exports {
+    async fn run_async(a: u32) -> u32 {
+        imports::async_add(a, 1)
+    }
+
+    fn run_sync(a: u32) -> u32 {
+        imports::sync_double(a)
+    }
+}
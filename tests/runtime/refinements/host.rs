@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use wasmtime::Trap;
+use wit_bindgen_host_wasmtime_rust::RefinementError;
+
+wit_bindgen_host_wasmtime_rust::export!("../../tests/runtime/refinements/imports.wit");
+
+#[derive(Default)]
+pub struct MyImports {
+    greeted: Vec<String>,
+}
+
+impl imports::Imports for MyImports {
+    fn echo_port(&mut self, p: u16) -> u16 {
+        p
+    }
+
+    fn greet(&mut self, n: String) {
+        self.greeted.push(n);
+    }
+}
+
+wit_bindgen_host_wasmtime_rust::import!("../../tests/runtime/refinements/exports.wit");
+
+fn run(wasm: &str) -> Result<()> {
+    // Each failed call leaves the instance unusable, so a new one is created
+    // for each of them.
+    let instantiate = || {
+        crate::instantiate(
+            wasm,
+            |linker| {
+                imports::add_to_linker(
+                    linker,
+                    |cx: &mut crate::Context<MyImports>| -> &mut MyImports { &mut cx.imports },
+                )
+            },
+            |store, module, linker| exports::Exports::instantiate(store, module, linker),
+        )
+    };
+
+    let (exports, mut store) = instantiate()?;
+    exports.test_imports(&mut store)?;
+    assert_eq!(store.data().imports.greeted, ["wasm"]);
+    assert_eq!(exports.roundtrip_port(&mut store, 443)?, 443);
+
+    // Values which the guest passes to the host are checked by the host...
+    let (exports, mut store) = instantiate()?;
+    let err = exports.long_name(&mut store).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RefinementError>(),
+        Some(&RefinementError {
+            ty: "name",
+            refinement: "len <= 8",
+        })
+    );
+
+    let (exports, mut store) = instantiate()?;
+    assert_err(
+        exports.greet_long_name(&mut store),
+        "value of `name` does not satisfy `where len <= 8`",
+    )?;
+    assert!(store.data().imports.greeted.is_empty());
+
+    // ... while those which the host passes to the guest are checked by the
+    // guest, which traps.
+    let (exports, mut store) = instantiate()?;
+    assert_trap(exports.roundtrip_port(&mut store, 0).map(drop))?;
+
+    let (exports, mut store) = instantiate()?;
+    assert_trap(exports.echo_zero_port(&mut store))?;
+
+    return Ok(());
+
+    fn assert_err(result: Result<()>, err: &str) -> Result<()> {
+        match result {
+            Ok(()) => anyhow::bail!("export didn't fail"),
+            Err(e) if format!("{:?}", e).contains(err) => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("expected error containing \"{}\"", err)),
+        }
+    }
+
+    fn assert_trap(result: Result<()>) -> Result<()> {
+        match result {
+            Ok(()) => anyhow::bail!("export didn't trap"),
+            Err(e) if e.downcast_ref::<Trap>().is_some() => Ok(()),
+            Err(e) => Err(e).context("expected a trap"),
+        }
+    }
+}
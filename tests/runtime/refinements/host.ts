@@ -0,0 +1,44 @@
+import { loadWasm, testwasi } from "./helpers.js";
+import { instantiate, RefinementError } from "./refinements.js";
+
+// @ts-ignore
+import * as assert from 'assert';
+
+async function run() {
+  const greeted: string[] = [];
+
+  // Each failed call leaves the instance unusable, so a new one is created
+  // for each of them.
+  const mk = () => instantiate(loadWasm, {
+    testwasi,
+    imports: {
+      echoPort(p) { return p; },
+      greet(n) { greeted.push(n); },
+    },
+  });
+
+  const wasm = await mk();
+  wasm.testImports();
+  assert.deepStrictEqual(greeted, ['wasm']);
+  assert.strictEqual(wasm.roundtripPort(443), 443);
+
+  // Values which the guest passes to the host are checked by the host...
+  assert.throws(() => wasm.longName(), (e: RefinementError) => {
+    assert.ok(e instanceof RefinementError);
+    assert.strictEqual(e.type, 'name');
+    assert.strictEqual(e.refinement, 'len <= 8');
+    return true;
+  });
+  const greeter = await mk();
+  assert.throws(() => greeter.greetLongName(), /value of `name` does not satisfy `where len <= 8`/);
+  assert.deepStrictEqual(greeted, ['wasm']);
+
+  // ... while those which the host passes to the guest are checked by the
+  // guest, which traps.
+  const roundtripper = await mk();
+  assert.throws(() => roundtripper.roundtripPort(0), WebAssembly.RuntimeError);
+  const echoer = await mk();
+  assert.throws(() => echoer.echoZeroPort(), WebAssembly.RuntimeError);
+}
+
+await run()
@@ -0,0 +1,29 @@
+wit_bindgen_guest_rust::import!("../../tests/runtime/refinements/imports.wit");
+wit_bindgen_guest_rust::export!("../../tests/runtime/refinements/exports.wit");
+
+struct Exports;
+
+impl exports::Exports for Exports {
+    fn test_imports() {
+        assert_eq!(imports::echo_port(80), 80);
+        imports::greet("wasm");
+    }
+
+    fn roundtrip_port(p: u16) -> u16 {
+        p
+    }
+
+    fn long_name() -> String {
+        "much too long".to_string()
+    }
+
+    fn echo_zero_port() {
+        // Lifting the invalid result panics, trapping the guest.
+        imports::echo_port(0);
+        unreachable!();
+    }
+
+    fn greet_long_name() {
+        imports::greet("much too long");
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use std::cell::Cell;
+
+wit_bindgen_host_wasmtime_rust::export!("../../tests/runtime/resources/imports.wit");
+
+#[derive(Default)]
+pub struct MyImports {
+    live_counters: u32,
+}
+
+impl imports::Imports for MyImports {
+    type Counter = Cell<i32>;
+
+    fn counter_drop(&mut self, _state: Cell<i32>) {
+        self.live_counters -= 1;
+    }
+
+    fn counter_new(&mut self, start: i32) -> Cell<i32> {
+        self.live_counters += 1;
+        Cell::new(start)
+    }
+
+    fn counter_get(&mut self, self_: &Cell<i32>) -> i32 {
+        self_.get()
+    }
+
+    fn counter_add(&mut self, self_: &Cell<i32>, amount: i32) {
+        self_.set(self_.get() + amount);
+    }
+
+    fn counter_merge(&mut self, a: Cell<i32>, b: Cell<i32>) -> Cell<i32> {
+        // Both counters are consumed and one is created in their place.
+        self.live_counters -= 1;
+        Cell::new(a.get() + b.get())
+    }
+
+    fn live_counters(&mut self) -> u32 {
+        self.live_counters
+    }
+}
+
+type Imports = (MyImports, imports::ImportsTables<MyImports>);
+
+wit_bindgen_host_wasmtime_rust::import!("../../tests/runtime/resources/exports.wit");
+
+fn run(wasm: &str) -> Result<()> {
+    let (exports, mut store) = crate::instantiate(
+        wasm,
+        |linker| {
+            imports::add_to_linker(linker, |cx: &mut crate::Context<Imports>| {
+                let (host, tables) = &mut cx.imports;
+                (host, tables)
+            })
+        },
+        |store, module, linker| exports::Exports::instantiate(store, module, linker),
+    )?;
+
+    exports.test_imports(&mut store)?;
+    assert_eq!(store.data().imports.0.live_counters, 0);
+
+    let a = exports.point_new(&mut store, 1, 2)?;
+    let b = exports.point_new(&mut store, 10, 20)?;
+    assert_eq!(exports.point_x(&mut store, &a)?, 1);
+    assert_eq!(exports.point_y(&mut store, &b)?, 20);
+
+    let sum = exports.add_points(&mut store, &a, &b)?;
+    assert_eq!(exports.point_x(&mut store, &sum)?, 11);
+    assert_eq!(exports.point_y(&mut store, &sum)?, 22);
+    assert_eq!(exports.consume_point(&mut store, sum)?, 33);
+
+    exports.point_drop(&mut store, a)?;
+    exports.point_drop(&mut store, b)?;
+
+    Ok(())
+}
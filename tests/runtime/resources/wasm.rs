@@ -0,0 +1,53 @@
+wit_bindgen_guest_rust::import!("../../tests/runtime/resources/imports.wit");
+wit_bindgen_guest_rust::export!("../../tests/runtime/resources/exports.wit");
+
+use imports::Counter;
+
+struct Exports;
+
+impl exports::Exports for Exports {
+    fn add_points(a: &Point, b: &Point) -> Point {
+        Point {
+            x: a.x + b.x,
+            y: a.y + b.y,
+        }
+    }
+
+    fn consume_point(p: Point) -> i32 {
+        p.x + p.y
+    }
+
+    fn test_imports() {
+        let counter = Counter::new(1);
+        assert_eq!(counter.get(), 1);
+        counter.add(2);
+        assert_eq!(counter.get(), 3);
+        assert_eq!(imports::live_counters(), 1);
+
+        let merged = Counter::merge(counter, Counter::new(10));
+        assert_eq!(merged.get(), 13);
+        assert_eq!(imports::live_counters(), 1);
+
+        drop(merged);
+        assert_eq!(imports::live_counters(), 0);
+    }
+}
+
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl exports::Point for Point {
+    fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    fn x(&self) -> i32 {
+        self.x
+    }
+
+    fn y(&self) -> i32 {
+        self.y
+    }
+}